@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use liana::miniscript::bitcoin::{
@@ -33,6 +34,8 @@ pub enum Message {
     Recovery(Result<SpendTx, Error>),
     Signed(Fingerprint, Result<Psbt, Error>),
     WalletUpdated(Result<Arc<Wallet>, Error>),
+    BackupCreated(Result<PathBuf, Error>),
+    TransactionsExported(Result<PathBuf, Error>),
     Updated(Result<(), Error>),
     Saved(Result<(), Error>),
     Verified(Fingerprint, Result<(), Error>),
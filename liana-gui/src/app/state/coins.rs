@@ -49,12 +49,13 @@ pub struct CoinsPanel {
     selected: Vec<usize>,
     labels_edited: LabelsEdited,
     warning: Option<Error>,
-    /// timelock value to pass for the heir to consume a coin.
-    timelock: u16,
+    /// timelock value to pass for the heir to consume a coin. None if all the recovery paths are
+    /// behind an absolute timelock.
+    timelock: Option<u16>,
 }
 
 impl CoinsPanel {
-    pub fn new(coins: &[Coin], timelock: u16) -> Self {
+    pub fn new(coins: &[Coin], timelock: Option<u16>) -> Self {
         let mut panel = Self {
             labels_edited: LabelsEdited::default(),
             coins: Coins::default(),
@@ -206,7 +207,7 @@ mod tests {
 
     #[test]
     fn test_coins_panel_update_coins() {
-        let mut panel = CoinsPanel::new(&[], 0);
+        let mut panel = CoinsPanel::new(&[], Some(0));
         let txid = bitcoin::Txid::from_str(
             "f7bd1b2a995b689d326e51eb742eb1088c4a8f110d9cb56128fd553acc9f88e5",
         )
@@ -83,7 +83,7 @@ pub fn redirect(menu: Menu) -> Command<Message> {
 fn coins_summary(
     coins: &[Coin],
     tip_height: u32,
-    timelock: u16,
+    timelock: Option<u16>,
 ) -> (Amount, Amount, Vec<OutPoint>, Option<u32>) {
     let mut balance = Amount::from_sat(0);
     let mut unconfirmed_balance = Amount::from_sat(0);
@@ -99,6 +99,11 @@ fn coins_summary(
                 if coin.block_height.is_none() {
                     continue;
                 }
+                // Coins never expire if all the recovery paths are behind an absolute timelock.
+                let timelock = match timelock {
+                    Some(timelock) => timelock,
+                    None => continue,
+                };
                 let seq = remaining_sequence(coin, tip_height, timelock);
                 // Warn user for coins that are expiring in less than 10 percent of
                 // the timelock.
@@ -411,7 +416,7 @@ mod tests {
         .unwrap();
 
         let tip_height = 800_000;
-        let timelock = 10_000;
+        let timelock = Some(10_000);
         let mut coins = Vec::new();
         // Without coins, all values are 0 / empty / None:
         assert_eq!(
@@ -556,5 +561,15 @@ mod tests {
                 Some(500)
             )
         );
+        // Without a relative timelock, the balances are the same but no coin is expiring.
+        assert_eq!(
+            coins_summary(&coins, tip_height, None),
+            (
+                Amount::from_sat(425),
+                Amount::from_sat(109),
+                Vec::new(),
+                None
+            )
+        );
     }
 }
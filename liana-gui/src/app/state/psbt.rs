@@ -8,7 +8,8 @@ use iced::Subscription;
 use iced::Command;
 use liana::{
     descriptors::LianaPolicy,
-    miniscript::bitcoin::{bip32::Fingerprint, psbt::Psbt, Network, Txid},
+    miniscript::bitcoin::{bip32::Fingerprint, hashes::hex::FromHex, psbt::Psbt, Network, Txid},
+    musig,
 };
 use lianad::commands::CoinStatus;
 
@@ -56,6 +57,7 @@ pub enum PsbtAction {
     Save(SaveAction),
     Sign(SignAction),
     Update(UpdateAction),
+    AddPreimage(PreimageAction),
     Broadcast(BroadcastAction),
     Delete(DeleteAction),
 }
@@ -66,6 +68,7 @@ impl<'a> AsRef<dyn Action + 'a> for PsbtAction {
             Self::Save(a) => a,
             Self::Sign(a) => a,
             Self::Update(a) => a,
+            Self::AddPreimage(a) => a,
             Self::Broadcast(a) => a,
             Self::Delete(a) => a,
         }
@@ -78,6 +81,7 @@ impl<'a> AsMut<dyn Action + 'a> for PsbtAction {
             Self::Save(a) => a,
             Self::Sign(a) => a,
             Self::Update(a) => a,
+            Self::AddPreimage(a) => a,
             Self::Broadcast(a) => a,
             Self::Delete(a) => a,
         }
@@ -168,6 +172,11 @@ impl PsbtState {
                 self.action = Some(PsbtAction::Update(action));
                 return cmd;
             }
+            Message::View(view::Message::Spend(view::SpendTxMessage::AddPreimage)) => {
+                self.action = Some(PsbtAction::AddPreimage(PreimageAction::new(
+                    self.wallet.clone(),
+                )));
+            }
             Message::View(view::Message::Spend(view::SpendTxMessage::Broadcast)) => {
                 let outpoints: Vec<_> = self.tx.coins.keys().cloned().collect();
                 return Command::perform(
@@ -410,6 +419,10 @@ pub struct SignAction {
     signed: HashSet<Fingerprint>,
     is_saved: bool,
     display_modal: bool,
+    /// The password of the hot signer mnemonic, if it is encrypted. It is only used for signing.
+    hot_signer_password: form::Value<String>,
+    /// The BIP39 passphrase of the hot signer, if it requires one. It is only used for signing.
+    hot_signer_passphrase: form::Value<String>,
 }
 
 impl SignAction {
@@ -428,6 +441,8 @@ impl SignAction {
             signed,
             is_saved,
             display_modal: true,
+            hot_signer_password: form::Value::default(),
+            hot_signer_passphrase: form::Value::default(),
         }
     }
 }
@@ -462,13 +477,41 @@ impl Action for SignAction {
                 }
             }
             Message::View(view::Message::Spend(view::SpendTxMessage::SelectHotSigner)) => {
+                let password = self
+                    .wallet
+                    .signer
+                    .as_ref()
+                    .filter(|signer| signer.password_required())
+                    .map(|_| self.hot_signer_password.value.clone());
+                let passphrase = Some(self.hot_signer_passphrase.value.clone())
+                    .filter(|passphrase| !passphrase.is_empty());
                 return Command::perform(
-                    sign_psbt_with_hot_signer(self.wallet.clone(), tx.psbt.clone()),
+                    sign_psbt_with_hot_signer(
+                        self.wallet.clone(),
+                        tx.psbt.clone(),
+                        password,
+                        passphrase,
+                    ),
                     |(fg, res)| Message::Signed(fg, res),
                 );
             }
+            Message::View(view::Message::Spend(view::SpendTxMessage::HotSignerPasswordEdited(
+                password,
+            ))) => {
+                self.hot_signer_password.value = password;
+                self.hot_signer_password.valid = true;
+            }
+            Message::View(view::Message::Spend(
+                view::SpendTxMessage::HotSignerPassphraseEdited(passphrase),
+            )) => {
+                self.hot_signer_passphrase.value = passphrase;
+                self.hot_signer_passphrase.valid = true;
+            }
             Message::Signed(fingerprint, res) => {
                 self.signing.remove(&fingerprint);
+                // Don't keep the password and passphrase around once used.
+                self.hot_signer_password = form::Value::default();
+                self.hot_signer_passphrase = form::Value::default();
                 match res {
                     Err(e) => {
                         if !matches!(e, Error::HardwareWallet(async_hwi::Error::UserRefused)) {
@@ -542,6 +585,16 @@ impl Action for SignAction {
                         .signer
                         .as_ref()
                         .and_then(|signer| self.wallet.keys_aliases.get(&signer.fingerprint)),
+                    self.wallet
+                        .signer
+                        .as_ref()
+                        .filter(|signer| signer.password_required())
+                        .map(|_| &self.hot_signer_password),
+                    self.wallet
+                        .signer
+                        .as_ref()
+                        .filter(|signer| signer.password_required() || signer.passphrase_required())
+                        .map(|_| &self.hot_signer_passphrase),
                     &self.signed,
                     &self.signing,
                 ),
@@ -573,16 +626,26 @@ fn merge_signatures(psbt: &mut Psbt, signed_psbt: &Psbt) {
         if let Some(sig) = signed_psbtin.tap_key_sig {
             psbtin.tap_key_sig = Some(sig);
         }
+        psbtin
+            .sha256_preimages
+            .extend(signed_psbtin.sha256_preimages.clone());
+        psbtin
+            .hash160_preimages
+            .extend(signed_psbtin.hash160_preimages.clone());
+        // The MuSig2 nonces and partial signatures.
+        musig::merge_psbt_in(psbtin, signed_psbtin);
     }
 }
 
 async fn sign_psbt_with_hot_signer(
     wallet: Arc<Wallet>,
     psbt: Psbt,
+    password: Option<String>,
+    passphrase: Option<String>,
 ) -> (Fingerprint, Result<Psbt, Error>) {
     if let Some(signer) = &wallet.signer {
         let res = signer
-            .sign_psbt(psbt)
+            .sign_psbt(psbt, password.as_deref(), passphrase.as_deref())
             .map_err(|e| WalletError::HotSigner(format!("Hot signer failed to sign psbt: {}", e)))
             .map_err(|e| e.into());
         (signer.fingerprint(), res)
@@ -746,6 +809,96 @@ impl Action for UpdateAction {
     }
 }
 
+pub struct PreimageAction {
+    wallet: Arc<Wallet>,
+    preimage: form::Value<String>,
+    processing: bool,
+    error: Option<Error>,
+    success: bool,
+}
+
+impl PreimageAction {
+    pub fn new(wallet: Arc<Wallet>) -> Self {
+        Self {
+            wallet,
+            preimage: form::Value::default(),
+            processing: false,
+            error: None,
+            success: false,
+        }
+    }
+
+    // The PSBT with the preimage set in all its inputs, if it is a valid preimage for one of the
+    // hash locks of our descriptor.
+    fn psbt_with_preimage(&self, psbt: &Psbt) -> Option<Psbt> {
+        let preimage = Vec::<u8>::from_hex(&self.preimage.value).ok()?;
+        let mut psbt = psbt.clone();
+        self.wallet
+            .main_descriptor
+            .add_preimage(&mut psbt, &preimage)
+            .ok()?;
+        Some(psbt)
+    }
+}
+
+impl Action for PreimageAction {
+    fn view<'a>(&'a self, content: Element<'a, view::Message>) -> Element<'a, view::Message> {
+        modal::Modal::new(
+            content,
+            if self.success {
+                view::psbt::add_preimage_success_view()
+            } else {
+                view::psbt::add_preimage_view(&self.preimage, self.error.as_ref(), self.processing)
+            },
+        )
+        .on_blur(Some(view::Message::Spend(view::SpendTxMessage::Cancel)))
+        .into()
+    }
+
+    fn update(
+        &mut self,
+        daemon: Arc<dyn Daemon + Sync + Send>,
+        message: Message,
+        tx: &mut SpendTx,
+    ) -> Command<Message> {
+        match message {
+            Message::Updated(res) => {
+                self.processing = false;
+                match res {
+                    Ok(()) => {
+                        self.success = true;
+                        self.error = None;
+                        tx.psbt = self.psbt_with_preimage(&tx.psbt).expect("Already checked");
+                        tx.sigs = self
+                            .wallet
+                            .main_descriptor
+                            .partial_spend_info(&tx.psbt)
+                            .unwrap();
+                    }
+                    Err(e) => self.error = e.into(),
+                }
+            }
+            Message::View(view::Message::Spend(view::SpendTxMessage::PreimageEdited(s))) => {
+                self.preimage.value = s;
+                self.preimage.valid = self.psbt_with_preimage(&tx.psbt).is_some();
+            }
+            Message::View(view::Message::Spend(view::SpendTxMessage::Confirm)) => {
+                if let Some(psbt) = self.psbt_with_preimage(&tx.psbt) {
+                    self.processing = true;
+                    self.error = None;
+                    return Command::perform(
+                        async move { daemon.update_spend_tx(&psbt).await.map_err(|e| e.into()) },
+                        Message::Updated,
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        Command::none()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,9 +4,12 @@ use std::sync::Arc;
 
 use iced::Command;
 
-use liana::miniscript::bitcoin::{
-    bip32::{DerivationPath, Fingerprint},
-    secp256k1,
+use liana::{
+    descriptors::RecoveryTimelock,
+    miniscript::bitcoin::{
+        bip32::{DerivationPath, Fingerprint},
+        secp256k1,
+    },
 };
 use liana_ui::{component::form, widget::Element};
 use lianad::commands::CoinStatus;
@@ -148,16 +151,26 @@ impl State for RecoveryPanel {
                     let feerate_vb = self.feerate.value.parse::<u64>().expect("Checked before");
                     self.warning = None;
                     let desc = self.wallet.main_descriptor.clone();
-                    let sequence = self
+                    let timelock = self
                         .recovery_paths
                         .get(self.selected_path.expect("A path must be selected"))
-                        .map(|p| p.sequence);
+                        .map(|p| p.timelock);
                     let network = cache.network;
                     return Command::perform(
                         async move {
-                            let psbt = daemon
-                                .create_recovery(address, feerate_vb, sequence)
-                                .await?;
+                            let psbt = match timelock {
+                                Some(RecoveryTimelock::RelativeTime(timelock)) => {
+                                    daemon
+                                        .create_time_recovery(address, feerate_vb, timelock)
+                                        .await?
+                                }
+                                Some(RecoveryTimelock::Relative(sequence)) => {
+                                    daemon
+                                        .create_recovery(address, feerate_vb, Some(sequence))
+                                        .await?
+                                }
+                                _ => daemon.create_recovery(address, feerate_vb, None).await?,
+                            };
                             let outpoints: Vec<_> = psbt
                                 .unsigned_tx
                                 .input
@@ -228,19 +241,36 @@ impl From<RecoveryPanel> for Box<dyn State> {
 
 pub struct RecoveryPath {
     threshold: usize,
-    sequence: u16,
+    timelock: RecoveryTimelock,
     origins: Vec<(Fingerprint, HashSet<DerivationPath>)>,
     total_amount: Amount,
     number_of_coins: usize,
 }
 
+// The number of blocks expected to be mined during this time-based relative timelock, in
+// 512-second intervals, with a block every 10 minutes. We only know at which height the coins were
+// confirmed so this is used to tell which are available, the daemon checks the actual timelock
+// when creating the recovery transaction.
+fn expected_blocks(timelock: u16) -> u16 {
+    (timelock as u32 * 512).div_ceil(600) as u16
+}
+
 fn recovery_paths(wallet: &Wallet, coins: &[Coin], blockheight: i32) -> Vec<RecoveryPath> {
-    wallet
-        .main_descriptor
-        .policy()
+    let policy = wallet.main_descriptor.policy();
+    policy
         .recovery_paths()
         .iter()
-        .map(|(&sequence, path)| {
+        .map(|(&sequence, path)| (RecoveryTimelock::Relative(sequence), sequence, path))
+        .chain(
+            policy
+                .time_recovery_paths()
+                .iter()
+                .map(|(&timelock, path)| {
+                    let blocks = expected_blocks(timelock);
+                    (RecoveryTimelock::RelativeTime(timelock), blocks, path)
+                }),
+        )
+        .map(|(timelock, sequence, path)| {
             let (number_of_coins, total_amount) = coins
                 .iter()
                 .filter(|coin| {
@@ -258,7 +288,7 @@ fn recovery_paths(wallet: &Wallet, coins: &[Coin], blockheight: i32) -> Vec<Reco
             RecoveryPath {
                 total_amount,
                 number_of_coins,
-                sequence,
+                timelock,
                 threshold,
                 origins: origins.into_iter().collect(),
             }
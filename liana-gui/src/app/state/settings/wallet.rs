@@ -2,6 +2,7 @@ use std::collections::HashSet;
 use std::convert::From;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use iced::{Command, Subscription};
 
@@ -14,6 +15,7 @@ use liana_ui::{
     component::{form, modal},
     widget::Element,
 };
+use lianad::commands::backup::{BackupHardwareWallet, BackupKeyAlias, WalletBackup};
 
 use crate::{
     app::{
@@ -32,6 +34,20 @@ pub struct WalletSettingsState {
     modal: Option<RegisterWalletModal>,
     processing: bool,
     updated: bool,
+    backup: BackupState,
+}
+
+/// State of the backup section of the wallet settings.
+#[derive(Default)]
+struct BackupState {
+    /// Whether the daemon backend supports backups.
+    supported: bool,
+    /// Path of the backup file to restore from.
+    restore_path: form::Value<String>,
+    /// Path of the last created backup file.
+    created: Option<PathBuf>,
+    restored: bool,
+    processing: bool,
 }
 
 impl WalletSettingsState {
@@ -45,6 +61,7 @@ impl WalletSettingsState {
             modal: None,
             processing: false,
             updated: false,
+            backup: BackupState::default(),
         }
     }
 
@@ -84,6 +101,14 @@ impl State for WalletSettingsState {
             &self.keys_aliases,
             self.processing,
             self.updated,
+            self.backup
+                .supported
+                .then_some(view::settings::BackupSection {
+                    restore_path: &self.backup.restore_path,
+                    created: self.backup.created.as_ref(),
+                    restored: self.backup.restored,
+                    processing: self.backup.processing,
+                }),
         );
         if let Some(m) = &self.modal {
             modal::Modal::new(content, m.view())
@@ -118,13 +143,63 @@ impl State for WalletSettingsState {
                         Ok(wallet) => {
                             self.keys_aliases = Self::keys_aliases(&wallet);
                             self.wallet = wallet;
-                            self.updated = true;
+                            if self.backup.processing {
+                                self.backup.restored = true;
+                            } else {
+                                self.updated = true;
+                            }
                         }
                         Err(e) => self.warning = Some(e),
                     };
+                    self.backup.processing = false;
                     Command::none()
                 }
             }
+            Message::BackupCreated(res) => {
+                self.backup.processing = false;
+                match res {
+                    Ok(path) => self.backup.created = Some(path),
+                    Err(e) => self.warning = Some(e),
+                }
+                Command::none()
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::CreateBackup)) => {
+                self.warning = None;
+                self.backup.processing = true;
+                self.backup.created = None;
+                Command::perform(
+                    create_backup(
+                        self.data_dir.clone(),
+                        cache.network,
+                        self.wallet.clone(),
+                        daemon,
+                    ),
+                    Message::BackupCreated,
+                )
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::BackupPathEdited(
+                path,
+            ))) => {
+                self.backup.restore_path.valid = !path.is_empty();
+                self.backup.restore_path.value = path;
+                self.backup.restored = false;
+                Command::none()
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::RestoreBackup)) => {
+                self.warning = None;
+                self.backup.processing = true;
+                self.backup.restored = false;
+                Command::perform(
+                    restore_backup(
+                        self.data_dir.clone(),
+                        cache.network,
+                        self.wallet.clone(),
+                        PathBuf::from(&self.backup.restore_path.value),
+                        daemon,
+                    ),
+                    Message::WalletUpdated,
+                )
+            }
             Message::View(view::Message::Settings(
                 view::SettingsMessage::FingerprintAliasEdited(fg, value),
             )) => {
@@ -183,6 +258,7 @@ impl State for WalletSettingsState {
         self.descriptor = wallet.main_descriptor.clone();
         self.keys_aliases = Self::keys_aliases(&wallet);
         self.wallet = wallet;
+        self.backup.supported = daemon.backend() != DaemonBackend::RemoteBackend;
         Command::perform(
             async move { daemon.get_info().await.map_err(|e| e.into()) },
             Message::Info,
@@ -405,3 +481,120 @@ async fn update_keys_aliases(
 
     Ok(Arc::new(wallet))
 }
+
+/// Create a backup of the wallet, including the metadata only known to the GUI, and write it to
+/// a file in the network data directory. Returns the path of the file.
+async fn create_backup(
+    data_dir: PathBuf,
+    network: Network,
+    wallet: Arc<Wallet>,
+    daemon: Arc<dyn Daemon + Sync + Send>,
+) -> Result<PathBuf, Error> {
+    let mut backup = daemon.create_backup().await?;
+
+    let mut key_aliases: Vec<BackupKeyAlias> = wallet
+        .keys_aliases
+        .iter()
+        .map(|(fingerprint, alias)| BackupKeyAlias {
+            fingerprint: *fingerprint,
+            alias: alias.clone(),
+        })
+        .collect();
+    key_aliases.sort_by(|a1, a2| a1.fingerprint.cmp(&a2.fingerprint));
+    backup.key_aliases = key_aliases;
+    backup.hardware_wallets = wallet
+        .hardware_wallets
+        .iter()
+        .map(|hw| BackupHardwareWallet {
+            kind: hw.kind.clone(),
+            fingerprint: hw.fingerprint,
+            token: hw.token.clone(),
+        })
+        .collect();
+
+    let content = serde_json::to_string_pretty(&backup)
+        .map_err(|e| Error::Unexpected(format!("Failed to serialize backup: {}", e)))?;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut path = data_dir;
+    path.push(network.to_string());
+    path.push(format!(
+        "liana-backup-{}-{}.json",
+        wallet.descriptor_checksum(),
+        now
+    ));
+    std::fs::write(&path, content)
+        .map_err(|e| Error::Unexpected(format!("Failed to write backup file: {}", e)))?;
+
+    Ok(path)
+}
+
+/// Restore a wallet backup from the given file. The daemon restores the wallet data, while the
+/// keys aliases and the hardware wallets registrations are restored in the GUI settings.
+async fn restore_backup(
+    data_dir: PathBuf,
+    network: Network,
+    wallet: Arc<Wallet>,
+    path: PathBuf,
+    daemon: Arc<dyn Daemon + Sync + Send>,
+) -> Result<Arc<Wallet>, Error> {
+    let content = std::fs::read(&path)
+        .map_err(|e| Error::Unexpected(format!("Failed to read backup file: {}", e)))?;
+    let backup: WalletBackup = serde_json::from_slice(&content)
+        .map_err(|e| Error::Unexpected(format!("Failed to parse backup file: {}", e)))?;
+
+    daemon.restore_backup(&backup).await?;
+
+    let mut wallet = wallet.as_ref().clone();
+    for key_alias in backup.key_aliases {
+        wallet
+            .keys_aliases
+            .insert(key_alias.fingerprint, key_alias.alias);
+    }
+    for hw in backup.hardware_wallets {
+        let hw_cfg = HardwareWalletConfig {
+            kind: hw.kind,
+            fingerprint: hw.fingerprint,
+            token: hw.token,
+        };
+        if let Some(hw_config) = wallet
+            .hardware_wallets
+            .iter_mut()
+            .find(|cfg| cfg.kind == hw_cfg.kind && cfg.fingerprint == hw_cfg.fingerprint)
+        {
+            *hw_config = hw_cfg;
+        } else {
+            wallet.hardware_wallets.push(hw_cfg);
+        }
+    }
+
+    if daemon.backend() != DaemonBackend::RemoteBackend {
+        let mut settings = settings::Settings::from_file(data_dir.clone(), network)?;
+        let checksum = wallet.descriptor_checksum();
+        if let Some(wallet_setting) = settings
+            .wallets
+            .iter_mut()
+            .find(|w| w.descriptor_checksum == checksum)
+        {
+            wallet_setting.keys = wallet
+                .keys_aliases
+                .iter()
+                .map(|(master_fingerprint, name)| settings::KeySetting {
+                    master_fingerprint: *master_fingerprint,
+                    name: name.clone(),
+                })
+                .collect();
+            wallet_setting.hardware_wallets = wallet.hardware_wallets.clone();
+        }
+
+        settings.to_file(data_dir, network)?;
+    }
+
+    daemon
+        .update_wallet_metadata(&wallet.keys_aliases, &wallet.hardware_wallets)
+        .await?;
+
+    Ok(Arc::new(wallet))
+}
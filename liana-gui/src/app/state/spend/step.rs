@@ -82,7 +82,7 @@ pub struct DefineSpend {
     network: Network,
     descriptor: LianaDescriptor,
     curve: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
-    timelock: u16,
+    timelock: Option<u16>,
     coins: Vec<(Coin, bool)>,
     coins_labels: HashMap<String, String>,
     batch_label: form::Value<String>,
@@ -97,7 +97,7 @@ impl DefineSpend {
         network: Network,
         descriptor: LianaDescriptor,
         coins: &[Coin],
-        timelock: u16,
+        timelock: Option<u16>,
     ) -> Self {
         let coins: Vec<(Coin, bool)> = coins
             .iter()
@@ -147,8 +147,8 @@ impl DefineSpend {
         self.coins.sort_by(|(a, a_selected), (b, b_selected)| {
             if *a_selected && !b_selected || !a_selected && *b_selected {
                 b_selected.cmp(a_selected)
-            } else if remaining_sequence(a, blockheight, timelock)
-                == remaining_sequence(b, blockheight, timelock)
+            } else if timelock.map(|tl| remaining_sequence(a, blockheight, tl))
+                == timelock.map(|tl| remaining_sequence(b, blockheight, tl))
             {
                 // bigger amount first
                 b.amount.cmp(&a.amount)
@@ -490,7 +490,7 @@ impl Step for DefineSpend {
                                     .await
                                     .map_err(|e| e.into())
                                     .and_then(|res| match res {
-                                        CreateSpendResult::Success { psbt, warnings } => {
+                                        CreateSpendResult::Success { psbt, warnings, .. } => {
                                             Ok((psbt, warnings))
                                         }
                                         CreateSpendResult::InsufficientFunds { missing } => {
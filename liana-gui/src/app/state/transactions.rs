@@ -1,13 +1,15 @@
 use std::{
     collections::{HashMap, HashSet},
     convert::TryInto,
+    path::PathBuf,
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use chrono::NaiveDate;
 use iced::Command;
 use liana::{
-    miniscript::bitcoin::{OutPoint, Txid},
+    miniscript::bitcoin::{Network, OutPoint, Txid},
     spend::{SpendCreationError, MAX_FEERATE},
 };
 use liana_ui::{
@@ -32,7 +34,7 @@ use crate::{
 
 use crate::daemon::{
     model::{CreateSpendResult, HistoryTransaction, LabelItem, Labelled},
-    Daemon,
+    Daemon, DaemonBackend,
 };
 
 pub struct TransactionsPanel {
@@ -44,6 +46,21 @@ pub struct TransactionsPanel {
     create_rbf_modal: Option<CreateRbfModal>,
     is_last_page: bool,
     processing: bool,
+    export: ExportState,
+}
+
+/// State of the export of the transactions history to a CSV file.
+#[derive(Default)]
+struct ExportState {
+    /// Whether the daemon backend supports exporting transactions.
+    supported: bool,
+    /// First day of the exported date range, as YYYY-MM-DD. No lower bound if empty.
+    start: form::Value<String>,
+    /// Last day of the exported date range, as YYYY-MM-DD. No upper bound if empty.
+    end: form::Value<String>,
+    /// Path of the last exported file.
+    exported: Option<PathBuf>,
+    processing: bool,
 }
 
 impl TransactionsPanel {
@@ -57,6 +74,7 @@ impl TransactionsPanel {
             create_rbf_modal: None,
             is_last_page: false,
             processing: false,
+            export: ExportState::default(),
         }
     }
 
@@ -88,6 +106,14 @@ impl State for TransactionsPanel {
                 self.warning.as_ref(),
                 self.is_last_page,
                 self.processing,
+                self.export
+                    .supported
+                    .then_some(view::transactions::ExportSection {
+                        start: &self.export.start,
+                        end: &self.export.end,
+                        exported: self.export.exported.as_ref(),
+                        processing: self.export.processing,
+                    }),
             )
         }
     }
@@ -95,7 +121,7 @@ impl State for TransactionsPanel {
     fn update(
         &mut self,
         daemon: Arc<dyn Daemon + Sync + Send>,
-        _cache: &Cache,
+        cache: &Cache,
         message: Message,
     ) -> Command<Message> {
         match message {
@@ -157,6 +183,49 @@ impl State for TransactionsPanel {
             Message::View(view::Message::CreateRbf(view::CreateRbfMessage::Cancel)) => {
                 self.create_rbf_modal = None;
             }
+            Message::View(view::Message::ExportTransactions(msg)) => match msg {
+                view::ExportTransactionsMessage::StartEdited(date) => {
+                    self.export.start.valid = date.is_empty() || parse_date(&date).is_some();
+                    self.export.start.value = date;
+                    self.export.exported = None;
+                }
+                view::ExportTransactionsMessage::EndEdited(date) => {
+                    self.export.end.valid = date.is_empty() || parse_date(&date).is_some();
+                    self.export.end.value = date;
+                    self.export.exported = None;
+                }
+                view::ExportTransactionsMessage::Export => {
+                    let start = parse_date(&self.export.start.value).unwrap_or(0);
+                    // Include the whole last day of the range.
+                    let end = parse_date(&self.export.end.value)
+                        .map(|t| t.saturating_add(24 * 60 * 60 - 1))
+                        .unwrap_or(u32::MAX);
+                    if start > end {
+                        self.export.end.valid = false;
+                        return Command::none();
+                    }
+                    self.warning = None;
+                    self.export.processing = true;
+                    self.export.exported = None;
+                    return Command::perform(
+                        export_transactions(
+                            cache.datadir_path.clone(),
+                            cache.network,
+                            start,
+                            end,
+                            daemon,
+                        ),
+                        Message::TransactionsExported,
+                    );
+                }
+            },
+            Message::TransactionsExported(res) => {
+                self.export.processing = false;
+                match res {
+                    Ok(path) => self.export.exported = Some(path),
+                    Err(e) => self.warning = Some(e),
+                }
+            }
             Message::View(view::Message::CreateRbf(view::CreateRbfMessage::New(is_cancel))) => {
                 if let Some(tx) = &self.selected_tx {
                     if tx.fee_amount.is_some() {
@@ -248,7 +317,7 @@ impl State for TransactionsPanel {
             }
             _ => {
                 if let Some(modal) = &mut self.create_rbf_modal {
-                    return modal.update(daemon, _cache, message);
+                    return modal.update(daemon, cache, message);
                 }
             }
         };
@@ -261,6 +330,7 @@ impl State for TransactionsPanel {
         _wallet: Arc<Wallet>,
     ) -> Command<Message> {
         self.selected_tx = None;
+        self.export.supported = daemon.backend() != DaemonBackend::RemoteBackend;
         let now: u32 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -283,6 +353,40 @@ impl State for TransactionsPanel {
     }
 }
 
+/// Parse a YYYY-MM-DD date into the timestamp of its first second, UTC.
+fn parse_date(date: &str) -> Option<u32> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?
+        .and_utc()
+        .timestamp()
+        .try_into()
+        .ok()
+}
+
+/// Export the confirmed transactions between the two given timestamps to a CSV file in the
+/// network directory, and return its path.
+async fn export_transactions(
+    data_dir: PathBuf,
+    network: Network,
+    start: u32,
+    end: u32,
+    daemon: Arc<dyn Daemon + Sync + Send>,
+) -> Result<PathBuf, Error> {
+    let csv = daemon.export_txs(start, end).await?.csv;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut path = data_dir;
+    path.push(network.to_string());
+    path.push(format!("liana-transactions-{}.csv", now));
+    std::fs::write(&path, csv)
+        .map_err(|e| Error::Unexpected(format!("Failed to write transactions file: {}", e)))?;
+
+    Ok(path)
+}
+
 impl From<TransactionsPanel> for Box<dyn State> {
     fn from(s: TransactionsPanel) -> Box<dyn State> {
         Box::new(s)
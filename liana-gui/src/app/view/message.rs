@@ -19,6 +19,7 @@ pub enum Message {
     SelectHardwareWallet(usize),
     CreateRbf(CreateRbfMessage),
     ShowQrCode(usize),
+    ExportTransactions(ExportTransactionsMessage),
 }
 
 #[derive(Debug, Clone)]
@@ -58,8 +59,12 @@ pub enum SpendTxMessage {
     Confirm,
     Cancel,
     SelectHotSigner,
+    HotSignerPasswordEdited(String),
+    HotSignerPassphraseEdited(String),
     EditPsbt,
     PsbtEdited(String),
+    AddPreimage,
+    PreimageEdited(String),
     Next,
 }
 
@@ -76,6 +81,9 @@ pub enum SettingsMessage {
     RegisterWallet,
     FingerprintAliasEdited(Fingerprint, String),
     Save,
+    CreateBackup,
+    BackupPathEdited(String),
+    RestoreBackup,
 }
 
 #[derive(Debug, Clone)]
@@ -100,3 +108,10 @@ pub enum CreateRbfMessage {
     Cancel,
     Confirm,
 }
+
+#[derive(Debug, Clone)]
+pub enum ExportTransactionsMessage {
+    StartEdited(String),
+    EndEdited(String),
+    Export,
+}
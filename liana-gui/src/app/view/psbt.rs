@@ -378,6 +378,11 @@ pub fn spend_overview_view<'a>(
             Some(
                 Row::new()
                     .push(Space::with_width(Length::Fill))
+                    .push_maybe(tx.missing_preimage().then(|| {
+                        button::secondary(None, "Add preimage")
+                            .on_press(Message::Spend(SpendTxMessage::AddPreimage))
+                            .width(Length::Fixed(150.0))
+                    }))
                     .push_maybe(if tx.path_ready().is_none() {
                         Some(
                             button::secondary(None, "Sign")
@@ -492,17 +497,32 @@ pub fn signatures<'a>(
                             .padding(15)
                             .spacing(10)
                             .push(text("Finalizing this transaction requires:"))
-                            .push_maybe(if tx.sigs.recovery_paths().is_empty() {
-                                Some(path_view(
-                                    desc_info.primary_path(),
-                                    tx.sigs.primary_path(),
-                                    keys_aliases,
-                                ))
-                            } else {
-                                tx.sigs.recovery_paths().iter().last().map(|(seq, path)| {
-                                    let keys = &desc_info.recovery_paths()[seq];
-                                    path_view(keys, path, keys_aliases)
-                                })
+                            .push({
+                                // The recovery path with the longest timelock available, if any.
+                                let (keys, path) = tx
+                                    .sigs
+                                    .recovery_paths()
+                                    .iter()
+                                    .last()
+                                    .map(|(seq, path)| (&desc_info.recovery_paths()[seq], path))
+                                    .or_else(|| {
+                                        tx.sigs.time_recovery_paths().iter().last().map(
+                                            |(tl, path)| {
+                                                (&desc_info.time_recovery_paths()[tl], path)
+                                            },
+                                        )
+                                    })
+                                    .or_else(|| {
+                                        tx.sigs.absolute_recovery_paths().iter().last().map(
+                                            |(tl, path)| {
+                                                (&desc_info.absolute_recovery_paths()[tl], path)
+                                            },
+                                        )
+                                    })
+                                    .unwrap_or_else(|| {
+                                        (desc_info.primary_path(), tx.sigs.primary_path())
+                                    });
+                                path_view(keys, path, keys_aliases)
                             }),
                     )
                 },
@@ -594,7 +614,15 @@ pub fn path_view<'a>(
                 (!sigs.signed_pubkeys.is_empty())
                     .then_some(p1_regular(", already signed by ").style(color::GREY_3)),
             )
-            .push(row_signed),
+            .push(row_signed)
+            .push_maybe(
+                path.hash_lock()
+                    .filter(|_| sigs.missing_preimage)
+                    .map(|hash_lock| {
+                        p1_regular(format!(", and the preimage of {}", hash_lock))
+                            .style(color::GREY_3)
+                    }),
+            ),
     )
     .direction(scrollable::Direction::Horizontal(
         scrollable::Properties::new().width(2).scroller_width(2),
@@ -1013,6 +1041,8 @@ pub fn sign_action<'a>(
     hws: &'a [HardwareWallet],
     signer: Option<Fingerprint>,
     signer_alias: Option<&'a String>,
+    signer_password: Option<&form::Value<String>>,
+    signer_passphrase: Option<&form::Value<String>>,
     signed: &HashSet<Fingerprint>,
     signing: &HashSet<Fingerprint>,
 ) -> Element<'a, Message> {
@@ -1049,11 +1079,38 @@ pub fn sign_action<'a>(
                             } else {
                                 hw::hot_signer(fingerprint, signer_alias)
                             })
-                            .on_press(Message::Spend(SpendTxMessage::SelectHotSigner))
+                            .on_press_maybe(match (signer_password, signer_passphrase) {
+                                // The passphrase is optional for an encrypted mnemonic, as whether
+                                // it is needed is only known once decrypted.
+                                (Some(password), _) if password.value.is_empty() => None,
+                                (None, Some(passphrase)) if passphrase.value.is_empty() => None,
+                                _ => Some(Message::Spend(SpendTxMessage::SelectHotSigner)),
+                            })
                             .padding(10)
                             .style(theme::Button::Border)
                             .width(Length::Fill)
                         }))
+                        .push_maybe(signer_password.map(|password| {
+                            form::Form::new("Mnemonic password", password, |msg| {
+                                Message::Spend(SpendTxMessage::HotSignerPasswordEdited(msg))
+                            })
+                            .secure(true)
+                            .size(text::P1_SIZE)
+                            .padding(10)
+                        }))
+                        .push_maybe(signer_passphrase.map(|passphrase| {
+                            let placeholder = if signer_password.is_some() {
+                                "BIP39 passphrase, if any"
+                            } else {
+                                "BIP39 passphrase"
+                            };
+                            form::Form::new(placeholder, passphrase, |msg| {
+                                Message::Spend(SpendTxMessage::HotSignerPassphraseEdited(msg))
+                            })
+                            .secure(true)
+                            .size(text::P1_SIZE)
+                            .padding(10)
+                        }))
                         .width(Length::Fill),
                 )
                 .spacing(20)
@@ -1174,3 +1231,54 @@ pub fn update_spend_success_view<'a>() -> Element<'a, Message> {
         .align_items(Alignment::Center)
         .into()
 }
+
+pub fn add_preimage_view<'a>(
+    preimage: &form::Value<String>,
+    error: Option<&Error>,
+    processing: bool,
+) -> Element<'a, Message> {
+    Column::new()
+        .push(warn(error))
+        .push(card::simple(
+            Column::new()
+                .spacing(10)
+                .push(text("Insert the preimage:").bold())
+                .push(
+                    text("Spending through this path requires revealing a secret.")
+                        .style(color::GREY_3),
+                )
+                .push(
+                    form::Form::new_trimmed("Preimage", preimage, move |msg| {
+                        Message::Spend(SpendTxMessage::PreimageEdited(msg))
+                    })
+                    .warning("Please enter the hex encoded 32 bytes preimage of the hash")
+                    .size(P1_SIZE)
+                    .padding(10),
+                )
+                .push(Row::new().push(Space::with_width(Length::Fill)).push(
+                    if preimage.valid && !preimage.value.is_empty() && !processing {
+                        button::secondary(None, "Add")
+                            .on_press(Message::Spend(SpendTxMessage::Confirm))
+                    } else if processing {
+                        button::secondary(None, "Processing...")
+                    } else {
+                        button::secondary(None, "Add")
+                    },
+                )),
+        ))
+        .max_width(400)
+        .into()
+}
+
+pub fn add_preimage_success_view<'a>() -> Element<'a, Message> {
+    Column::new()
+        .push(
+            card::simple(Container::new(
+                text("Preimage is added to the transaction").style(color::GREEN),
+            ))
+            .padding(50),
+        )
+        .width(Length::Fixed(400.0))
+        .align_items(Alignment::Center)
+        .into()
+}
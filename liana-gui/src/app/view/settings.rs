@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use iced::{
@@ -874,6 +875,49 @@ fn is_ok_and<T, E>(res: &Result<T, E>, f: impl FnOnce(&T) -> bool) -> bool {
     }
 }
 
+/// State of the backup section of the wallet settings.
+pub struct BackupSection<'a> {
+    /// Path of the backup file to restore from.
+    pub restore_path: &'a form::Value<String>,
+    /// Path of the last created backup file.
+    pub created: Option<&'a PathBuf>,
+    pub restored: bool,
+    pub processing: bool,
+}
+
+// The BIP388 wallet policy of the descriptor, for registering the wallet on signing devices which
+// support wallet policies or sharing the wallet setup in a compact form.
+fn wallet_policy<'a>(descriptor: &LianaDescriptor) -> Column<'a, Message> {
+    let policy = match descriptor.to_wallet_policy() {
+        Ok(policy) => policy.to_string(),
+        Err(e) => {
+            return Column::new()
+                .spacing(10)
+                .push(text("Wallet policy (BIP388):").bold())
+                .push(text(e.to_string()).small().style(color::GREY_3));
+        }
+    };
+    Column::new()
+        .spacing(10)
+        .push(text("Wallet policy (BIP388):").bold())
+        .push(
+            scrollable(
+                Column::new()
+                    .push(text(policy.clone()).small())
+                    .push(Space::with_height(Length::Fixed(5.0))),
+            )
+            .direction(scrollable::Direction::Horizontal(
+                scrollable::Properties::new().width(5).scroller_width(5),
+            )),
+        )
+        .push(
+            Row::new().push(Column::new().width(Length::Fill)).push(
+                button::secondary(Some(icon::clipboard_icon()), "Copy")
+                    .on_press(Message::Clipboard(policy)),
+            ),
+        )
+}
+
 pub fn wallet_settings<'a>(
     cache: &'a Cache,
     warning: Option<&Error>,
@@ -881,6 +925,7 @@ pub fn wallet_settings<'a>(
     keys_aliases: &'a [(Fingerprint, form::Value<String>)],
     processing: bool,
     updated: bool,
+    backup: Option<BackupSection<'a>>,
 ) -> Element<'a, Message> {
     dashboard(
         &Menu::Settings,
@@ -940,6 +985,7 @@ pub fn wallet_settings<'a>(
                 )
                 .width(Length::Fill),
             )
+            .push(card::simple(wallet_policy(descriptor)).width(Length::Fill))
             .push(
                 card::simple(display_policy(descriptor.policy(), keys_aliases)).width(Length::Fill),
             )
@@ -997,8 +1043,85 @@ pub fn wallet_settings<'a>(
                         .spacing(10),
                 )
                 .width(Length::Fill),
+            )
+            .push_maybe(backup.map(wallet_backup)),
+    )
+}
+
+fn wallet_backup(backup: BackupSection) -> Element<Message> {
+    card::simple(
+        Column::new()
+            .spacing(10)
+            .push(text("Backup:").bold())
+            .push(
+                text(
+                    "Save the descriptor, the labels, the PSBTs, the fingerprint aliases and the \
+                    hardware wallets registrations of this wallet to a file, in order to restore \
+                    it without losing any information.",
+                )
+                .small(),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(Space::with_width(Length::Fill))
+                    .push_maybe(backup.created.map(|path| {
+                        Row::new()
+                            .spacing(5)
+                            .align_items(Alignment::Center)
+                            .push(icon::circle_check_icon().style(color::GREEN))
+                            .push(
+                                text(format!("Backup saved to {}", path.to_string_lossy()))
+                                    .style(color::GREEN),
+                            )
+                    }))
+                    .push(if !backup.processing {
+                        button::secondary(Some(icon::export_icon()), "Create backup")
+                            .on_press(Message::Settings(SettingsMessage::CreateBackup))
+                    } else {
+                        button::secondary(Some(icon::export_icon()), "Create backup")
+                    }),
+            )
+            .push(text("Restore from a backup file:").bold())
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(
+                        form::Form::new_trimmed(
+                            "Path to the backup file",
+                            backup.restore_path,
+                            |msg| Message::Settings(SettingsMessage::BackupPathEdited(msg)),
+                        )
+                        .size(P1_SIZE)
+                        .padding(10),
+                    )
+                    .push_maybe(if backup.restored {
+                        Some(
+                            Row::new()
+                                .align_items(Alignment::Center)
+                                .push(icon::circle_check_icon().style(color::GREEN))
+                                .push(text("Restored").style(color::GREEN)),
+                        )
+                    } else {
+                        None
+                    })
+                    .push(
+                        if backup.restore_path.valid
+                            && !backup.restore_path.value.is_empty()
+                            && !backup.processing
+                        {
+                            button::secondary(Some(icon::import_icon()), "Restore")
+                                .on_press(Message::Settings(SettingsMessage::RestoreBackup))
+                        } else {
+                            button::secondary(Some(icon::import_icon()), "Restore")
+                        },
+                    ),
             ),
     )
+    .width(Length::Fill)
+    .into()
 }
 
 fn display_policy(
@@ -1006,7 +1129,30 @@ fn display_policy(
     keys_aliases: &[(Fingerprint, form::Value<String>)],
 ) -> Element<'_, Message> {
     let (primary_threshold, primary_keys) = policy.primary_path().thresh_origins();
-    let recovery_paths = policy.recovery_paths();
+    // The recovery paths behind a relative timelock in blocks, then those behind a time-based one,
+    // along with a description of their timelock.
+    let recovery_paths = policy
+        .recovery_paths()
+        .iter()
+        .map(|(sequence, path)| {
+            (
+                format!(
+                    "{} blocks (~{})",
+                    sequence,
+                    expire_message_units(*sequence as u32).join(",")
+                ),
+                path,
+            )
+        })
+        .chain(policy.time_recovery_paths().iter().map(|(timelock, path)| {
+            (
+                format!(
+                    "~{}",
+                    duration_message_units(*timelock as u32 * 512 / 60).join(",")
+                ),
+                path,
+            )
+        }));
 
     // The iteration over an HashMap keys can have a different order at each refresh
     let mut primary_keys: Vec<Fingerprint> = primary_keys.into_keys().collect();
@@ -1060,7 +1206,7 @@ fn display_policy(
             )
             .push(text("can always spend this wallet's funds (Primary path)")),
     );
-    for (i, (sequence, recovery_path)) in recovery_paths.iter().enumerate() {
+    for (i, (timelock, recovery_path)) in recovery_paths.enumerate() {
         let (threshold, recovery_keys) = recovery_path.thresh_origins();
 
         // The iteration over an HashMap keys can have a different order at each refresh
@@ -1112,14 +1258,7 @@ fn display_policy(
                     },
                 ))
                 .push(text("can spend coins inactive for"))
-                .push(
-                    text(format!(
-                        "{} blocks (~{})",
-                        sequence,
-                        expire_message_units(*sequence as u32).join(",")
-                    ))
-                    .bold(),
-                )
+                .push(text(timelock).bold())
                 .push(text(format!("(Recovery path #{})", i + 1))),
         );
     }
@@ -1134,7 +1273,11 @@ fn display_policy(
 
 /// returns y,m,d
 fn expire_message_units(sequence: u32) -> Vec<String> {
-    let mut n_minutes = sequence * 10;
+    duration_message_units(sequence * 10)
+}
+
+/// returns y,m,d or h,m for a duration in minutes
+fn duration_message_units(mut n_minutes: u32) -> Vec<String> {
     let n_years = n_minutes / 525960;
     n_minutes -= n_years * 525960;
     let n_months = n_minutes / 43830;
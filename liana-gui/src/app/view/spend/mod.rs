@@ -114,7 +114,7 @@ pub fn create_spend_tx<'a>(
     recipients: Vec<Element<'a, Message>>,
     is_valid: bool,
     duplicate: bool,
-    timelock: u16,
+    timelock: Option<u16>,
     coins: &[(Coin, bool)],
     coins_labels: &'a HashMap<String, String>,
     batch_label: &form::Value<String>,
@@ -422,7 +422,7 @@ fn coin_list_view<'a>(
     i: usize,
     coin: &Coin,
     coins_labels: &'a HashMap<String, String>,
-    timelock: u16,
+    timelock: Option<u16>,
     blockheight: u32,
     selected: bool,
 ) -> Element<'a, Message> {
@@ -458,9 +458,11 @@ fn coin_list_view<'a>(
                     badge::spent()
                 } else if coin.block_height.is_none() {
                     badge::unconfirmed()
-                } else {
+                } else if let Some(timelock) = timelock {
                     let seq = remaining_sequence(coin, blockheight, timelock);
                     coins::coin_sequence_label(seq, timelock as u32)
+                } else {
+                    Container::new(Space::with_width(Length::Shrink))
                 })
                 .spacing(10)
                 .align_items(Alignment::Center)
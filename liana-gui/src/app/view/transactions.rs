@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
 use chrono::{DateTime, Local, Utc};
 use iced::{alignment, widget::tooltip, Alignment, Length};
@@ -15,17 +16,33 @@ use crate::{
         cache::Cache,
         error::Error,
         menu::Menu,
-        view::{dashboard, label, message::CreateRbfMessage, message::Message, warning::warn},
+        view::{
+            dashboard, label,
+            message::{CreateRbfMessage, ExportTransactionsMessage, Message},
+            warning::warn,
+        },
     },
     daemon::model::{HistoryTransaction, Txid},
 };
 
+/// State of the export of the transactions history.
+pub struct ExportSection<'a> {
+    /// First day of the exported date range.
+    pub start: &'a form::Value<String>,
+    /// Last day of the exported date range.
+    pub end: &'a form::Value<String>,
+    /// Path of the last exported file.
+    pub exported: Option<&'a PathBuf>,
+    pub processing: bool,
+}
+
 pub fn transactions_view<'a>(
     cache: &'a Cache,
     txs: &'a [HistoryTransaction],
     warning: Option<&'a Error>,
     is_last_page: bool,
     processing: bool,
+    export: Option<ExportSection<'a>>,
 ) -> Element<'a, Message> {
     dashboard(
         &Menu::Transactions,
@@ -33,6 +50,7 @@ pub fn transactions_view<'a>(
         warning,
         Column::new()
             .push(Container::new(h3("Transactions")).width(Length::Fill))
+            .push_maybe(export.map(export_transactions))
             .push(
                 Column::new()
                     .spacing(10)
@@ -76,6 +94,59 @@ pub fn transactions_view<'a>(
     )
 }
 
+fn export_transactions(export: ExportSection) -> Element<Message> {
+    card::simple(
+        Column::new()
+            .spacing(10)
+            .push(text("Export the confirmed transactions to a CSV file:").bold())
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(
+                        form::Form::new_trimmed("From (YYYY-MM-DD)", export.start, |msg| {
+                            Message::ExportTransactions(ExportTransactionsMessage::StartEdited(msg))
+                        })
+                        .warning("Invalid date")
+                        .size(P1_SIZE)
+                        .padding(10),
+                    )
+                    .push(
+                        form::Form::new_trimmed("To (YYYY-MM-DD)", export.end, |msg| {
+                            Message::ExportTransactions(ExportTransactionsMessage::EndEdited(msg))
+                        })
+                        .warning("Invalid date")
+                        .size(P1_SIZE)
+                        .padding(10),
+                    )
+                    .push(
+                        if export.start.valid && export.end.valid && !export.processing {
+                            button::secondary(Some(icon::export_icon()), "Export").on_press(
+                                Message::ExportTransactions(ExportTransactionsMessage::Export),
+                            )
+                        } else {
+                            button::secondary(Some(icon::export_icon()), "Export")
+                        },
+                    ),
+            )
+            .push_maybe(export.exported.map(|path| {
+                Row::new()
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(icon::circle_check_icon().style(color::GREEN))
+                    .push(
+                        text(format!(
+                            "Transactions exported to {}",
+                            path.to_string_lossy()
+                        ))
+                        .style(color::GREEN),
+                    )
+            })),
+    )
+    .width(Length::Fill)
+    .into()
+}
+
 fn tx_list_view(i: usize, tx: &HistoryTransaction) -> Element<'_, Message> {
     Container::new(
         Button::new(
@@ -73,7 +73,12 @@ impl Wallet {
         for (fingerprint, _) in info.primary_path().thresh_origins().1.iter() {
             descriptor_keys.insert(*fingerprint);
         }
-        for path in info.recovery_paths().values() {
+        for path in info
+            .recovery_paths()
+            .values()
+            .chain(info.time_recovery_paths().values())
+            .chain(info.absolute_recovery_paths().values())
+        {
             for (fingerprint, _) in path.thresh_origins().1.iter() {
                 descriptor_keys.insert(*fingerprint);
             }
@@ -141,7 +146,7 @@ impl Wallet {
         datadir_path: &Path,
         network: bitcoin::Network,
     ) -> Result<Self, WalletError> {
-        let hot_signers = match HotSigner::from_datadir(datadir_path, network) {
+        let hot_signers = match HotSigner::stored_mnemonics(datadir_path, network) {
             Ok(signers) => signers,
             Err(e) => match e {
                 liana::signer::SignerError::MnemonicStorage(e) => {
@@ -155,13 +160,16 @@ impl Wallet {
             },
         };
 
-        let curve = bitcoin::secp256k1::Secp256k1::signing_only();
         let keys = self.descriptor_keys();
-        if let Some(hot_signer) = hot_signers
+        if let Some(stored) = hot_signers
             .into_iter()
-            .find(|s| keys.contains(&s.fingerprint(&curve)))
+            .find(|stored| keys.contains(&stored.fingerprint))
         {
-            Ok(self.with_signer(Signer::new(hot_signer)))
+            // The mnemonic may be encrypted, or stored for a master key derived using a BIP39
+            // passphrase. Those will be asked for when signing.
+            let signer = Signer::from_stored(stored, network)
+                .map_err(|e| WalletError::HotSigner(e.to_string()))?;
+            Ok(self.with_signer(signer))
         } else {
             Ok(self)
         }
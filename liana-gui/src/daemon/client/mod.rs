@@ -14,7 +14,7 @@ pub mod jsonrpc;
 
 use liana::miniscript::bitcoin::{address, psbt::Psbt, Address, Network, OutPoint, Txid};
 use lianad::{
-    commands::{CoinStatus, CreateRecoveryResult, LabelItem},
+    commands::{backup::WalletBackup, CoinStatus, CreateRecoveryResult, LabelItem},
     config::Config,
 };
 
@@ -182,6 +182,25 @@ impl<C: Client + Send + Sync + Debug> Daemon for Lianad<C> {
         Ok(res.psbt)
     }
 
+    async fn create_time_recovery(
+        &self,
+        address: Address<address::NetworkUnchecked>,
+        feerate_vb: u64,
+        timelock: u16,
+    ) -> Result<Psbt, DaemonError> {
+        let res: CreateRecoveryResult = self.call(
+            "createrecovery",
+            Some(vec![
+                json!(address),
+                json!(feerate_vb),
+                json!(null),
+                json!(null),
+                json!(timelock),
+            ]),
+        )?;
+        Ok(res.psbt)
+    }
+
     async fn get_labels(
         &self,
         items: &HashSet<LabelItem>,
@@ -205,6 +224,23 @@ impl<C: Client + Send + Sync + Debug> Daemon for Lianad<C> {
         let _res: serde_json::value::Value = self.call("updatelabels", Some(vec![labels]))?;
         Ok(())
     }
+
+    async fn create_backup(&self) -> Result<WalletBackup, DaemonError> {
+        self.call("createbackup", Option::<Request>::None)
+    }
+
+    async fn restore_backup(&self, backup: &WalletBackup) -> Result<(), DaemonError> {
+        let _res: serde_json::value::Value = self.call("restorebackup", Some(vec![backup]))?;
+        Ok(())
+    }
+
+    async fn export_txs(
+        &self,
+        start: u32,
+        end: u32,
+    ) -> Result<ExportTransactionsResult, DaemonError> {
+        self.call("exporttransactions", Some(vec![json!(start), json!(end)]))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
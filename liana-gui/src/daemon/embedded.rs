@@ -6,7 +6,7 @@ use super::{model::*, node, Daemon, DaemonBackend, DaemonError};
 use async_trait::async_trait;
 use liana::miniscript::bitcoin::{address, psbt::Psbt, Address, Network, OutPoint, Txid};
 use lianad::{
-    commands::{CoinStatus, LabelItem},
+    commands::{backup::WalletBackup, CoinStatus, LabelItem},
     config::Config,
     DaemonControl, DaemonHandle,
 };
@@ -209,6 +209,21 @@ impl Daemon for EmbeddedDaemon {
         .await
     }
 
+    async fn create_time_recovery(
+        &self,
+        address: Address<address::NetworkUnchecked>,
+        feerate_vb: u64,
+        timelock: u16,
+    ) -> Result<Psbt, DaemonError> {
+        self.command(|daemon| {
+            daemon
+                .create_time_recovery(address, feerate_vb, timelock)
+                .map(|res| res.psbt)
+                .map_err(|e| DaemonError::Unexpected(e.to_string()))
+        })
+        .await
+    }
+
     async fn get_labels(
         &self,
         items: &HashSet<LabelItem>,
@@ -227,4 +242,26 @@ impl Daemon for EmbeddedDaemon {
         })
         .await
     }
+
+    async fn create_backup(&self) -> Result<WalletBackup, DaemonError> {
+        self.command(|daemon| Ok(daemon.create_backup())).await
+    }
+
+    async fn restore_backup(&self, backup: &WalletBackup) -> Result<(), DaemonError> {
+        self.command(|daemon| {
+            daemon
+                .restore_backup(backup)
+                .map_err(|e| DaemonError::Unexpected(e.to_string()))
+        })
+        .await
+    }
+
+    async fn export_txs(
+        &self,
+        start: u32,
+        end: u32,
+    ) -> Result<ExportTransactionsResult, DaemonError> {
+        self.command(|daemon| Ok(daemon.export_transactions(start, end)))
+            .await
+    }
 }
@@ -15,7 +15,7 @@ use liana::miniscript::bitcoin::{
     address, bip32::Fingerprint, psbt::Psbt, secp256k1, Address, Network, OutPoint, Txid,
 };
 use lianad::{
-    commands::{CoinStatus, LabelItem, TransactionInfo},
+    commands::{backup::WalletBackup, CoinStatus, LabelItem, TransactionInfo},
     config::Config,
     StartupError,
 };
@@ -128,6 +128,32 @@ pub trait Daemon: Debug {
     async fn send_wallet_invitation(&self, _email: &str) -> Result<(), DaemonError> {
         Ok(())
     }
+    /// Create a recovery transaction through a recovery path behind a time-based relative
+    /// timelock, in 512-second intervals. Not implemented by the LianaLite backend.
+    async fn create_time_recovery(
+        &self,
+        _address: Address<address::NetworkUnchecked>,
+        _feerate_vb: u64,
+        _timelock: u16,
+    ) -> Result<Psbt, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    /// Not implemented by the LianaLite backend.
+    async fn create_backup(&self) -> Result<WalletBackup, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    /// Not implemented by the LianaLite backend.
+    async fn restore_backup(&self, _backup: &WalletBackup) -> Result<(), DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    /// Not implemented by the LianaLite backend.
+    async fn export_txs(
+        &self,
+        _start: u32,
+        _end: u32,
+    ) -> Result<model::ExportTransactionsResult, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
 
     // List spend transactions, optionally filtered to the specified `txids`.
     // Set `txids` to `None` for no filter (passing an empty slice returns no transactions).
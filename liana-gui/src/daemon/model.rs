@@ -11,8 +11,9 @@ pub use liana::{
     },
 };
 pub use lianad::commands::{
-    CreateSpendResult, GetAddressResult, GetInfoResult, GetLabelsResult, LabelItem, ListCoinsEntry,
-    ListCoinsResult, ListSpendEntry, ListSpendResult, ListTransactionsResult, TransactionInfo,
+    CreateSpendResult, ExportTransactionsResult, GetAddressResult, GetInfoResult, GetLabelsResult,
+    LabelItem, ListCoinsEntry, ListCoinsResult, ListSpendEntry, ListSpendResult,
+    ListTransactionsResult, TransactionInfo,
 };
 
 pub type Coin = ListCoinsEntry;
@@ -188,13 +189,28 @@ impl SpendTx {
     /// Returns the path ready if it exists.
     pub fn path_ready(&self) -> Option<&PathSpendInfo> {
         let path = self.sigs.primary_path();
-        if path.sigs_count >= path.threshold {
+        if path.sigs_count >= path.threshold && !path.missing_preimage {
             return Some(path);
         }
         self.sigs
             .recovery_paths()
             .values()
-            .find(|&path| path.sigs_count >= path.threshold)
+            .chain(self.sigs.time_recovery_paths().values())
+            .chain(self.sigs.absolute_recovery_paths().values())
+            .find(|&path| path.sigs_count >= path.threshold && !path.missing_preimage)
+    }
+
+    /// Whether the latest available spending path requires the preimage of a hash which wasn't
+    /// provided yet.
+    pub fn missing_preimage(&self) -> bool {
+        self.sigs
+            .recovery_paths()
+            .values()
+            .last()
+            .or_else(|| self.sigs.time_recovery_paths().values().last())
+            .or_else(|| self.sigs.absolute_recovery_paths().values().last())
+            .unwrap_or_else(|| self.sigs.primary_path())
+            .missing_preimage
     }
 
     pub fn signers(&self) -> HashSet<Fingerprint> {
@@ -203,7 +219,13 @@ impl SpendTx {
             signers.insert(*fg);
         }
 
-        for path in self.sigs.recovery_paths().values() {
+        for path in self
+            .sigs
+            .recovery_paths()
+            .values()
+            .chain(self.sigs.time_recovery_paths().values())
+            .chain(self.sigs.absolute_recovery_paths().values())
+        {
             for fg in path.signed_pubkeys.keys() {
                 signers.insert(*fg);
             }
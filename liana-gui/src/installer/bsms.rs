@@ -0,0 +1,33 @@
+//! Exchange of BIP129 (BSMS) records as files between the coordinator and the signers.
+
+use std::path::{Path, PathBuf};
+
+use liana::miniscript::bitcoin::Network;
+
+use super::{create_and_write_file, Error};
+
+/// Get a record from a user input, which may either be the record itself or the path to a file
+/// containing it.
+pub fn read_record(content_or_path: &str) -> Result<String, Error> {
+    let path = Path::new(content_or_path.trim());
+    if path.is_file() {
+        std::fs::read_to_string(path)
+            .map_err(|e| Error::Unexpected(format!("Failed to read record file: {}", e)))
+    } else {
+        Ok(content_or_path.to_string())
+    }
+}
+
+/// Write a record to a file in the network data directory, and return its path.
+pub fn write_record(
+    data_dir: &Path,
+    network: Network,
+    file_name: &str,
+    record: &str,
+) -> Result<PathBuf, Error> {
+    let mut network_datadir = data_dir.to_path_buf();
+    network_datadir.push(network.to_string());
+    std::fs::create_dir_all(&network_datadir)
+        .map_err(|e| Error::CannotCreateDatadir(e.to_string()))?;
+    create_and_write_file(network_datadir, file_name, record.as_bytes())
+}
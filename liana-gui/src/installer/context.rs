@@ -9,7 +9,7 @@ use crate::{
     signer::Signer,
 };
 use async_hwi::DeviceKind;
-use liana::{descriptors::LianaDescriptor, miniscript::bitcoin};
+use liana::{bsms, descriptors::LianaDescriptor, miniscript::bitcoin};
 use lianad::config::{BitcoinBackend, BitcoinConfig};
 
 #[derive(Debug, Clone)]
@@ -64,10 +64,15 @@ pub struct Context {
     // In case a user entered a mnemonic,
     // we dont want to override the generated signer with it.
     pub recovered_signer: Option<Arc<Signer>>,
+    // The passwords to encrypt the stored mnemonics of the generated and the recovered signers.
+    pub hot_signer_password: Option<String>,
+    pub recovered_signer_password: Option<String>,
     pub bitcoind_is_external: bool,
     pub internal_bitcoind_config: Option<InternalBitcoindConfig>,
     pub internal_bitcoind: Option<Bitcoind>,
     pub remote_backend: RemoteBackend,
+    // The BSMS session if some keys were imported from BSMS key records.
+    pub bsms_coordinator: Option<bsms::Coordinator>,
 }
 
 impl Context {
@@ -90,10 +95,13 @@ impl Context {
             network,
             hw_is_used: false,
             recovered_signer: None,
+            hot_signer_password: None,
+            recovered_signer_password: None,
             bitcoind_is_external: true,
             internal_bitcoind_config: None,
             internal_bitcoind: None,
             remote_backend,
+            bsms_coordinator: None,
         }
     }
 }
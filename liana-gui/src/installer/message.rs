@@ -2,6 +2,7 @@ use liana::miniscript::{
     bitcoin::{bip32::Fingerprint, Network},
     DescriptorPublicKey,
 };
+use liana::signer::MnemonicLength;
 use std::path::PathBuf;
 
 use super::{context, Error};
@@ -43,7 +44,37 @@ pub enum Message {
     HardwareWallets(HardwareWalletMessage),
     WalletRegistered(Result<(Fingerprint, Option<[u8; 32]>), Error>),
     MnemonicWord(usize, String),
+    MnemonicPassphrase(String),
+    MnemonicPassword(String),
+    MnemonicLength(MnemonicLength),
+    MnemonicEntropy(String),
     ImportMnemonic(bool),
+    Bsms(Bsms),
+    Slip39(Slip39),
+}
+
+#[derive(Debug, Clone)]
+pub enum Bsms {
+    // Coordinator messages.
+    SaveDescriptorRecord,
+    // Signer messages.
+    TokenEdited(String),
+    DescriptionEdited(String),
+    CreateKeyRecord,
+    DescriptorRecordEdited(String),
+    VerifyDescriptorRecord,
+}
+
+#[derive(Debug, Clone)]
+pub enum Slip39 {
+    // Use SLIP39 shares instead of the BIP39 mnemonic.
+    Use(bool),
+    // Backup messages.
+    Threshold(u8),
+    ShareCount(u8),
+    // Recovery messages.
+    ShareEdited(usize, String),
+    AddShare,
 }
 
 #[derive(Debug, Clone)]
@@ -127,7 +158,9 @@ pub enum DefinePath {
     AddKey,
     Key(usize, DefineKey),
     ThresholdEdited(usize),
-    SequenceEdited(u16),
+    /// The sequence, and whether it is a time-based one (in 512-second intervals) instead of a
+    /// number of blocks.
+    SequenceEdited(u16, bool),
     EditSequence,
     EditThreshold,
 }
@@ -154,5 +187,6 @@ pub enum ImportKeyModal {
 pub enum ThresholdSequenceModal {
     ThresholdEdited(usize),
     SequenceEdited(String),
+    TimeSequenceToggled(bool),
     Confirm,
 }
@@ -1,3 +1,4 @@
+mod bsms;
 mod context;
 mod message;
 mod prompt;
@@ -388,6 +389,7 @@ pub async fn install_local_wallet(
             .store(
                 &cfg.data_dir().expect("Already checked"),
                 cfg.bitcoin_config.network,
+                ctx.hot_signer_password.as_deref(),
             )
             .map_err(|e| Error::Unexpected(format!("Failed to store mnemonic: {}", e)))?;
 
@@ -399,6 +401,7 @@ pub async fn install_local_wallet(
             .store(
                 &cfg.data_dir().expect("Already checked"),
                 cfg.bitcoin_config.network,
+                ctx.recovered_signer_password.as_deref(),
             )
             .map_err(|e| Error::Unexpected(format!("Failed to store mnemonic: {}", e)))?;
 
@@ -464,7 +467,7 @@ pub async fn create_remote_wallet(
         signer
             .lock()
             .unwrap()
-            .store(&data_dir, ctx.network)
+            .store(&data_dir, ctx.network, ctx.hot_signer_password.as_deref())
             .map_err(|e| Error::Unexpected(format!("Failed to store mnemonic: {}", e)))?;
 
         info!("Hot signer mnemonic stored");
@@ -472,7 +475,11 @@ pub async fn create_remote_wallet(
 
     if let Some(signer) = &ctx.recovered_signer {
         signer
-            .store(&data_dir, ctx.network)
+            .store(
+                &data_dir,
+                ctx.network,
+                ctx.recovered_signer_password.as_deref(),
+            )
             .map_err(|e| Error::Unexpected(format!("Failed to store mnemonic: {}", e)))?;
 
         info!("Recovered signer mnemonic stored");
@@ -559,7 +566,11 @@ pub async fn import_remote_wallet(
 
     if let Some(signer) = &ctx.recovered_signer {
         signer
-            .store(&data_dir, ctx.network)
+            .store(
+                &data_dir,
+                ctx.network,
+                ctx.recovered_signer_password.as_deref(),
+            )
             .map_err(|e| Error::Unexpected(format!("Failed to store mnemonic: {}", e)))?;
 
         info!("Recovered signer mnemonic stored");
@@ -688,9 +699,13 @@ pub fn extract_daemon_config(ctx: &Context) -> Config {
             .descriptor
             .clone()
             .expect("Context must have a descriptor at this point"),
+        enable_signspend: false,
+        external_signer: None,
         data_dir: Some(ctx.data_dir.clone()),
         bitcoin_config: ctx.bitcoin_config.clone(),
         bitcoin_backend: ctx.bitcoin_backend.clone(),
+        wallets: Vec::new(),
+        expiry_watch: None,
     }
 }
 
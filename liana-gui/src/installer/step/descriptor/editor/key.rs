@@ -3,14 +3,16 @@ use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
 use iced::{Command, Subscription};
+use liana::bsms::{self, KeyRecord, Token};
 use liana::miniscript::bitcoin::bip32::Xpub;
 use liana::miniscript::{
     bitcoin::{
         bip32::{DerivationPath, Fingerprint},
-        Network,
+        secp256k1, Network,
     },
     descriptor::{DerivPaths, DescriptorMultiXKey, DescriptorPublicKey, DescriptorXKey, Wildcard},
 };
+use liana::signer::MnemonicLength;
 
 use liana_ui::{component::form, widget::Element};
 
@@ -19,6 +21,7 @@ use async_hwi::{DeviceKind, Version};
 use crate::{
     hw::{is_compatible_with_tapminiscript, HardwareWallet, HardwareWallets},
     installer::{
+        bsms::read_record,
         message::{self, Message},
         view, Error,
     },
@@ -50,6 +53,8 @@ pub struct Key {
     pub fingerprint: Fingerprint,
     pub key: DescriptorPublicKey,
     pub is_compatible_taproot: bool,
+    /// The verified BSMS key record the key was imported from, if any.
+    pub bsms_key_record: Option<KeyRecord>,
 }
 
 pub fn check_key_network(key: &DescriptorPublicKey, network: Network) -> bool {
@@ -89,7 +94,16 @@ pub struct EditXpubModal {
     keys: Vec<Key>,
     hot_signer: Arc<Mutex<Signer>>,
     hot_signer_fingerprint: Fingerprint,
+    // The BIP39 passphrase of the hot signer. It may only be set as long as the hot signer's key
+    // isn't used in the descriptor.
+    hot_signer_passphrase: Option<form::Value<String>>,
+    // The length of the hot signer's mnemonic and the entropy provided by the user to generate
+    // it. They may only be changed as long as the passphrase may.
+    hot_signer_length: MnemonicLength,
+    hot_signer_entropy: form::Value<String>,
     chosen_signer: Option<Key>,
+    // The token of the BSMS session, to import keys from BSMS key records.
+    bsms_token: Option<Token>,
 }
 
 impl EditXpubModal {
@@ -101,14 +115,24 @@ impl EditXpubModal {
         keys_coordinate: Vec<(usize, usize)>,
         network: Network,
         hot_signer: Arc<Mutex<Signer>>,
-        hot_signer_fingerprint: Fingerprint,
         keys: Vec<Key>,
+        bsms_token: Option<Token>,
     ) -> Self {
         // The xpub is manually imported if the key is neither from a device or the hot signer.
         let manually_imported_xpub = key
             .as_ref()
             .map(|k| !k.is_hot_signer && k.device_kind.is_none())
             .unwrap_or(false);
+        // The passphrase form starts empty, so make sure the hot signer doesn't use a passphrase
+        // set in a previous modal for a key which wasn't kept.
+        let hot_signer_passphrase = (!keys.iter().any(|k| k.is_hot_signer)).then(|| form::Value {
+            valid: hot_signer.lock().unwrap().set_passphrase("").is_ok(),
+            value: String::new(),
+        });
+        let hot_signer_fingerprint = hot_signer.lock().unwrap().fingerprint();
+        let hot_signer_length =
+            MnemonicLength::from_word_count(hot_signer.lock().unwrap().mnemonic().len())
+                .unwrap_or_default();
         Self {
             device_must_support_tapminiscript,
             other_path_keys,
@@ -132,8 +156,93 @@ impl EditXpubModal {
             network,
             chosen_signer: key,
             hot_signer_fingerprint,
+            hot_signer_passphrase,
+            hot_signer_length,
+            hot_signer_entropy: form::Value {
+                valid: true,
+                value: String::new(),
+            },
             hot_signer,
             duplicate_master_fg: false,
+            bsms_token,
+        }
+    }
+
+    fn hot_signer_key(&self) -> Key {
+        let signer = self.hot_signer.lock().unwrap();
+        let fingerprint = signer.fingerprint();
+        let derivation_path = default_derivation_path(self.network);
+        let key_str = format!(
+            "[{}{}]{}",
+            fingerprint,
+            derivation_path.to_string().trim_start_matches('m'),
+            signer.get_extended_pubkey(&derivation_path)
+        );
+        Key {
+            is_hot_signer: true,
+            fingerprint,
+            name: "".to_string(),
+            key: DescriptorPublicKey::from_str(&key_str).unwrap(),
+            is_compatible_taproot: true,
+            device_kind: None,
+            device_version: None,
+            bsms_key_record: None,
+        }
+    }
+
+    // Update the chosen key after the hot signer changed.
+    fn refresh_hot_signer_key(&mut self) {
+        self.hot_signer_fingerprint = self.hot_signer.lock().unwrap().fingerprint();
+        let key = self.hot_signer_key();
+        if let Some(chosen) = self.chosen_signer.as_mut().filter(|k| k.is_hot_signer) {
+            *chosen = Key {
+                name: std::mem::take(&mut chosen.name),
+                ..key
+            };
+        }
+    }
+
+    // Generate a new hot signer with the chosen mnemonic length and user entropy, keeping its
+    // passphrase.
+    fn regenerate_hot_signer(&mut self) {
+        let passphrase = match &self.hot_signer_passphrase {
+            Some(passphrase) => passphrase.value.clone(),
+            None => return,
+        };
+        let entropy = match user_entropy(&self.hot_signer_entropy.value) {
+            Some(entropy) => entropy,
+            None => {
+                self.hot_signer_entropy.valid = false;
+                return;
+            }
+        };
+        self.hot_signer_entropy.valid = true;
+        let signer = Signer::generate_with(self.network, self.hot_signer_length, &entropy)
+            .and_then(|mut signer| signer.set_passphrase(&passphrase).map(|_| signer));
+        match signer {
+            Ok(signer) => {
+                *self.hot_signer.lock().unwrap() = signer;
+                self.refresh_hot_signer_key();
+            }
+            Err(e) => self.error = Some(Error::Unexpected(e.to_string())),
+        }
+    }
+
+    // Read and verify a BSMS key record from the content of the xpub form, which may be the
+    // record itself or the path to a file containing it. Returns `None` if it isn't a key record
+    // for this session.
+    fn bsms_key_record(&mut self, s: &str) -> Option<KeyRecord> {
+        let token = self.bsms_token.as_ref()?;
+        let content = read_record(s).ok()?;
+        let secp = secp256k1::Secp256k1::verification_only();
+        match bsms::read_key_record(token, &content, &secp) {
+            Ok(record) => Some(record),
+            Err(e @ bsms::BsmsError::InvalidSignature)
+            | Err(e @ bsms::BsmsError::TokenMismatch) => {
+                self.error = Some(Error::Unexpected(e.to_string()));
+                None
+            }
+            Err(_) => None,
         }
     }
 
@@ -188,6 +297,7 @@ impl super::DescriptorEditModal for EditXpubModal {
                                                     ),
                                                 device_kind: Some(device_kind),
                                                 device_version,
+                                                bsms_key_record: None,
                                             })
                                         } else {
                                             Err(Error::Unexpected(
@@ -205,28 +315,32 @@ impl super::DescriptorEditModal for EditXpubModal {
             Message::Reload => {
                 return self.load();
             }
+            Message::MnemonicPassphrase(passphrase) => {
+                if let Some(form) = &mut self.hot_signer_passphrase {
+                    let mut signer = self.hot_signer.lock().unwrap();
+                    form.valid = signer.set_passphrase(&passphrase).is_ok();
+                    form.value = passphrase;
+                    drop(signer);
+                    self.refresh_hot_signer_key();
+                }
+            }
+            Message::MnemonicLength(length) => {
+                if self.hot_signer_passphrase.is_some() && length != self.hot_signer_length {
+                    self.hot_signer_length = length;
+                    self.regenerate_hot_signer();
+                }
+            }
+            Message::MnemonicEntropy(entropy) => {
+                if self.hot_signer_passphrase.is_some() {
+                    self.hot_signer_entropy.value = entropy;
+                    self.regenerate_hot_signer();
+                }
+            }
             Message::UseHotSigner => {
                 self.manually_imported_xpub = false;
-                let fingerprint = self.hot_signer.lock().unwrap().fingerprint();
-                let derivation_path = default_derivation_path(self.network);
-                let key_str = format!(
-                    "[{}{}]{}",
-                    fingerprint,
-                    derivation_path.to_string().trim_start_matches('m'),
-                    self.hot_signer
-                        .lock()
-                        .unwrap()
-                        .get_extended_pubkey(&derivation_path)
-                );
-                self.chosen_signer = Some(Key {
-                    is_hot_signer: true,
-                    fingerprint,
-                    name: "".to_string(),
-                    key: DescriptorPublicKey::from_str(&key_str).unwrap(),
-                    is_compatible_taproot: true,
-                    device_kind: None,
-                    device_version: None,
-                });
+                let key = self.hot_signer_key();
+                let fingerprint = key.fingerprint;
+                self.chosen_signer = Some(key);
                 self.form_name.value = self
                     .keys
                     .iter()
@@ -287,6 +401,7 @@ impl super::DescriptorEditModal for EditXpubModal {
                                     is_compatible_taproot: true,
                                     device_kind: None,
                                     device_version: None,
+                                    bsms_key_record: None,
                                 });
                                 self.form_name.value = "".to_string();
                                 self.form_name.valid = true;
@@ -294,6 +409,38 @@ impl super::DescriptorEditModal for EditXpubModal {
                         } else {
                             self.form_xpub.valid = false;
                         }
+                    } else if let Some(record) = self.bsms_key_record(&s) {
+                        self.error = None;
+                        self.chosen_signer = None;
+                        self.form_xpub.valid = if self.network == Network::Bitcoin {
+                            record.xpub.network == Network::Bitcoin
+                        } else {
+                            record.xpub.network == Network::Testnet
+                        };
+                        if self.form_xpub.valid {
+                            // The signer's description is a sensible default name.
+                            self.form_name.valid =
+                                !self.keys.iter().any(|k| k.name == record.description);
+                            self.form_name.value.clone_from(&record.description);
+                            self.chosen_signer = Some(Key {
+                                is_hot_signer: false,
+                                fingerprint: record.fingerprint,
+                                name: "".to_string(),
+                                key: DescriptorPublicKey::XPub(DescriptorXKey {
+                                    origin: Some((
+                                        record.fingerprint,
+                                        record.derivation_path.clone(),
+                                    )),
+                                    derivation_path: DerivationPath::master(),
+                                    wildcard: Wildcard::None,
+                                    xkey: record.xpub,
+                                }),
+                                is_compatible_taproot: true,
+                                device_kind: None,
+                                device_version: None,
+                                bsms_key_record: Some(record),
+                            });
+                        }
                     } else {
                         self.form_xpub.valid = false;
                     }
@@ -393,10 +540,31 @@ impl super::DescriptorEditModal for EditXpubModal {
             &self.form_xpub,
             self.manually_imported_xpub,
             self.duplicate_master_fg,
+            self.bsms_token.as_ref(),
+            self.hot_signer_length,
+            &self.hot_signer_entropy,
+            self.hot_signer_passphrase.as_ref(),
         )
     }
 }
 
+// The entropy from dice rolls (1 to 6) or coin flips (H or T), ignoring whitespaces. Returns
+// `None` if both are mixed or if there are other characters.
+fn user_entropy(s: &str) -> Option<Vec<u8>> {
+    let entropy: Vec<u8> = s
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if entropy.iter().all(|c| (b'1'..=b'6').contains(c))
+        || entropy.iter().all(|c| *c == b'H' || *c == b'T')
+    {
+        Some(entropy)
+    } else {
+        None
+    }
+}
+
 pub fn default_derivation_path(network: Network) -> DerivationPath {
     DerivationPath::from_str({
         if network == Network::Bitcoin {
@@ -8,9 +8,10 @@ use std::sync::{Arc, Mutex};
 
 use iced::{Command, Subscription};
 use liana::{
+    bsms,
     descriptors::{LianaDescriptor, LianaPolicy, PathInfo},
     miniscript::{
-        bitcoin::{bip32::Fingerprint, Network},
+        bitcoin::{bip32::Fingerprint, secp256k1, Network},
         descriptor::DescriptorPublicKey,
     },
 };
@@ -52,6 +53,8 @@ pub struct Path {
     threshold: usize,
     // sequence is 0 if it is a primary path.
     sequence: u16,
+    // Whether the sequence is a number of 512-second intervals instead of a number of blocks.
+    time_sequence: bool,
     duplicate_sequence: bool,
 }
 
@@ -61,6 +64,7 @@ impl Path {
             keys: vec![None],
             threshold: 1,
             sequence: 0,
+            time_sequence: false,
             duplicate_sequence: false,
         }
     }
@@ -70,6 +74,7 @@ impl Path {
             keys: vec![None],
             threshold: 1,
             sequence: u16::MAX,
+            time_sequence: false,
             duplicate_sequence: false,
         }
     }
@@ -102,29 +107,28 @@ pub struct DefineDescriptor {
 
     modal: Option<Box<dyn DescriptorEditModal>>,
     signer: Arc<Mutex<Signer>>,
-    signer_fingerprint: Fingerprint,
 
     keys: HashMap<Fingerprint, Key>,
     paths: Vec<Path>,
     descriptor_template: DescriptorTemplate,
+    // The token of the BSMS session, shared with the signers who send us BSMS key records.
+    bsms_token: Option<bsms::Token>,
 
     error: Option<String>,
 }
 
 impl DefineDescriptor {
     pub fn new(network: Network, signer: Arc<Mutex<Signer>>) -> Self {
-        let signer_fingerprint = signer.lock().unwrap().fingerprint();
         Self {
             network,
             use_taproot: false,
             modal: None,
-            signer_fingerprint,
-
             signer,
             error: None,
             keys: HashMap::new(),
             descriptor_template: DescriptorTemplate::default(),
             paths: Vec::new(),
+            bsms_token: bsms::Token::generate(bsms::TokenLength::Bits64).ok(),
         }
     }
 
@@ -145,15 +149,17 @@ impl DefineDescriptor {
         let mut all_sequence = HashSet::new();
         let mut duplicate_sequences = HashSet::new();
         for path in &mut self.paths {
-            if all_sequence.contains(&path.sequence) {
-                duplicate_sequences.insert(path.sequence);
+            let sequence = (path.sequence, path.time_sequence);
+            if all_sequence.contains(&sequence) {
+                duplicate_sequences.insert(sequence);
             } else {
-                all_sequence.insert(path.sequence);
+                all_sequence.insert(sequence);
             }
         }
 
         for path in &mut self.paths {
-            path.duplicate_sequence = duplicate_sequences.contains(&path.sequence);
+            path.duplicate_sequence =
+                duplicate_sequences.contains(&(path.sequence, path.time_sequence));
         }
     }
 
@@ -251,18 +257,19 @@ impl Step for DefineDescriptor {
                     coordinate,
                     self.network,
                     self.signer.clone(),
-                    self.signer_fingerprint,
                     self.keys.values().cloned().collect(),
+                    self.bsms_token.clone(),
                 );
                 let cmd = modal.load();
                 self.modal = Some(Box::new(modal));
                 return cmd;
             }
             Message::DefineDescriptor(message::DefineDescriptor::Path(i, msg)) => match msg {
-                message::DefinePath::SequenceEdited(seq) => {
+                message::DefinePath::SequenceEdited(seq, time_sequence) => {
                     self.modal = None;
                     if let Some(path) = self.paths.get_mut(i) {
                         path.sequence = seq;
+                        path.time_sequence = time_sequence;
                     }
                     self.check_for_duplicate();
                 }
@@ -274,7 +281,11 @@ impl Step for DefineDescriptor {
                 }
                 message::DefinePath::EditSequence => {
                     if let Some(path) = self.paths.get(i) {
-                        self.modal = Some(Box::new(EditSequenceModal::new(i, path.sequence)));
+                        self.modal = Some(Box::new(EditSequenceModal::new(
+                            i,
+                            path.sequence,
+                            path.time_sequence,
+                        )));
                     }
                 }
                 message::DefinePath::EditThreshold => {
@@ -313,8 +324,8 @@ impl Step for DefineDescriptor {
                             vec![(i, j)],
                             self.network,
                             self.signer.clone(),
-                            self.signer_fingerprint,
                             self.keys.values().cloned().collect(),
+                            self.bsms_token.clone(),
                         );
                         let cmd = modal.load();
                         self.modal = Some(Box::new(modal));
@@ -394,6 +405,7 @@ impl Step for DefineDescriptor {
         }
 
         let mut recovery_paths = BTreeMap::new();
+        let mut time_recovery_paths = BTreeMap::new();
 
         for path in &self.paths[1..] {
             let mut recovery_keys: Vec<DescriptorPublicKey> = Vec::new();
@@ -429,7 +441,11 @@ impl Step for DefineDescriptor {
                 PathInfo::Multi(path.threshold, recovery_keys)
             };
 
-            recovery_paths.insert(path.sequence, recovery_keys);
+            if path.time_sequence {
+                time_recovery_paths.insert(path.sequence, recovery_keys);
+            } else {
+                recovery_paths.insert(path.sequence, recovery_keys);
+            }
         }
 
         if spending_keys.is_empty() {
@@ -443,9 +459,17 @@ impl Step for DefineDescriptor {
         };
 
         let policy = match if self.use_taproot {
-            LianaPolicy::new(spending_keys, recovery_paths)
+            LianaPolicy::new_with_time_recovery_paths(
+                spending_keys,
+                recovery_paths,
+                time_recovery_paths,
+            )
         } else {
-            LianaPolicy::new_legacy(spending_keys, recovery_paths)
+            LianaPolicy::new_legacy_with_time_recovery_paths(
+                spending_keys,
+                recovery_paths,
+                time_recovery_paths,
+            )
         } {
             Ok(policy) => policy,
             Err(e) => {
@@ -454,6 +478,31 @@ impl Step for DefineDescriptor {
             }
         };
 
+        // The keys imported from BSMS key records make up a BSMS session, to which we'll have to
+        // distribute the descriptor record.
+        ctx.bsms_coordinator = None;
+        if let Some(token) = &self.bsms_token {
+            let mut coordinator = bsms::Coordinator::new(token.clone());
+            let secp = secp256k1::Secp256k1::verification_only();
+            let used_keys: HashSet<&Fingerprint> = self
+                .paths
+                .iter()
+                .flat_map(|p| p.keys.iter().flatten())
+                .collect();
+            for record in used_keys
+                .into_iter()
+                .filter_map(|fg| self.keys.get(fg)?.bsms_key_record.clone())
+            {
+                if let Err(e) = coordinator.add_key_record(record, &secp) {
+                    self.error = Some(e.to_string());
+                    return false;
+                }
+            }
+            if !coordinator.key_records().is_empty() {
+                ctx.bsms_coordinator = Some(coordinator);
+            }
+        }
+
         ctx.descriptor = Some(LianaDescriptor::new(policy));
         ctx.hw_is_used = hw_is_used;
         true
@@ -477,6 +526,7 @@ impl Step for DefineDescriptor {
                         .as_ref()
                         .and_then(|f| self.keys.get(f)),
                     self.paths[1].sequence,
+                    self.paths[1].time_sequence,
                     self.valid(),
                 )
             }
@@ -487,6 +537,7 @@ impl Step for DefineDescriptor {
                     self.path_keys(&self.paths[0]),
                     self.path_keys(&self.paths[1]),
                     self.paths[1].sequence,
+                    self.paths[1].time_sequence,
                     self.paths[1].threshold,
                     self.valid(),
                 )
@@ -497,6 +548,7 @@ impl Step for DefineDescriptor {
                 view::editor::template::custom::Path {
                     keys: self.path_keys(&self.paths[0]),
                     sequence: self.paths[0].sequence,
+                    time_sequence: self.paths[0].time_sequence,
                     duplicate_sequence: self.paths[0].duplicate_sequence,
                     threshold: self.paths[0].threshold,
                 },
@@ -504,6 +556,7 @@ impl Step for DefineDescriptor {
                     .iter()
                     .map(|p| view::editor::template::custom::Path {
                         sequence: p.sequence,
+                        time_sequence: p.time_sequence,
                         duplicate_sequence: p.duplicate_sequence,
                         threshold: p.threshold,
                         keys: self.path_keys(p),
@@ -531,19 +584,58 @@ impl From<DefineDescriptor> for Box<dyn Step> {
     }
 }
 
+/// Number of 512-second intervals in a day.
+const INTERVALS_PER_DAY: f64 = 86_400.0 / 512.0;
+
+/// Convert a number of days to the smallest number of 512-second intervals covering it, if it
+/// fits in a time-based relative timelock.
+pub fn days_to_time_sequence(days: u16) -> Option<u16> {
+    let intervals = (f64::from(days) * INTERVALS_PER_DAY).ceil();
+    if intervals > f64::from(u16::MAX) {
+        None
+    } else {
+        Some(intervals as u16)
+    }
+}
+
+/// Convert a number of 512-second intervals to the nearest number of days.
+pub fn time_sequence_to_days(sequence: u16) -> u16 {
+    (f64::from(sequence) / INTERVALS_PER_DAY).round() as u16
+}
+
 pub struct EditSequenceModal {
     path_index: usize,
+    // A number of blocks, or a number of days if the sequence is time-based.
     sequence: form::Value<String>,
+    time_sequence: bool,
 }
 
 impl EditSequenceModal {
-    pub fn new(path_index: usize, sequence: u16) -> Self {
+    pub fn new(path_index: usize, sequence: u16, time_sequence: bool) -> Self {
+        let value = if time_sequence {
+            time_sequence_to_days(sequence)
+        } else {
+            sequence
+        };
         Self {
             path_index,
             sequence: form::Value {
-                value: sequence.to_string(),
+                value: value.to_string(),
                 valid: true,
             },
+            time_sequence,
+        }
+    }
+
+    // The sequence to be set on the path, in blocks or in 512-second intervals.
+    fn sequence(&self) -> Option<u16> {
+        let value = u16::from_str(&self.sequence.value).ok()?;
+        if value == 0 {
+            None
+        } else if self.time_sequence {
+            days_to_time_sequence(value)
+        } else {
+            Some(value)
         }
     }
 }
@@ -559,23 +651,36 @@ impl DescriptorEditModal for EditSequenceModal {
         {
             match msg {
                 message::ThresholdSequenceModal::SequenceEdited(seq) => {
-                    if let Ok(s) = u16::from_str(&seq) {
-                        self.sequence.valid = s != 0
-                    } else {
-                        self.sequence.valid = false;
-                    }
                     self.sequence.value = seq;
+                    self.sequence.valid = self.sequence().is_some();
+                }
+                message::ThresholdSequenceModal::TimeSequenceToggled(time_sequence) => {
+                    // Keep the same duration when switching units, assuming 10-minute blocks.
+                    if let Ok(value) = u16::from_str(&self.sequence.value) {
+                        let value = if time_sequence {
+                            (u32::from(value) * 10).div_ceil(24 * 60)
+                        } else {
+                            u32::from(value) * 24 * 60 / 10
+                        };
+                        self.sequence.value = value.min(u16::MAX.into()).to_string();
+                    }
+                    self.time_sequence = time_sequence;
+                    self.sequence.valid = self.sequence().is_some();
                 }
                 message::ThresholdSequenceModal::Confirm => {
                     if self.sequence.valid {
-                        if let Ok(sequence) = u16::from_str(&self.sequence.value) {
+                        if let Some(sequence) = self.sequence() {
                             let path_index = self.path_index;
+                            let time_sequence = self.time_sequence;
                             return Command::perform(
                                 async move { (path_index, sequence) },
-                                |(path_index, sequence)| {
+                                move |(path_index, sequence)| {
                                     message::DefineDescriptor::Path(
                                         path_index,
-                                        message::DefinePath::SequenceEdited(sequence),
+                                        message::DefinePath::SequenceEdited(
+                                            sequence,
+                                            time_sequence,
+                                        ),
                                     )
                                 },
                             )
@@ -590,7 +695,7 @@ impl DescriptorEditModal for EditSequenceModal {
     }
 
     fn view(&self, _hws: &HardwareWallets) -> Element<Message> {
-        view::editor::edit_sequence_modal(&self.sequence)
+        view::editor::edit_sequence_modal(&self.sequence, self.time_sequence)
     }
 }
 
@@ -726,7 +831,7 @@ mod tests {
         sandbox
             .update(Message::DefineDescriptor(message::DefineDescriptor::Path(
                 1,
-                message::DefinePath::SequenceEdited(1000),
+                message::DefinePath::SequenceEdited(1000, false),
             )))
             .await;
 
@@ -789,6 +894,7 @@ mod tests {
             device_version: None,
             is_compatible_taproot: false,
             is_hot_signer: false,
+            bsms_key_record: None,
         };
 
         // Use Specter device for primary key
@@ -867,4 +973,75 @@ mod tests {
             assert!(ctx.hw_is_used);
         });
     }
+
+    #[tokio::test]
+    async fn test_define_descriptor_time_sequence() {
+        let mut ctx = Context::new(
+            Network::Testnet,
+            PathBuf::from_str("/").unwrap(),
+            crate::installer::context::RemoteBackend::None,
+        );
+        let sandbox: Sandbox<DefineDescriptor> = Sandbox::new(DefineDescriptor::new(
+            Network::Testnet,
+            Arc::new(Mutex::new(Signer::generate(Network::Testnet).unwrap())),
+        ));
+        sandbox.load(&ctx).await;
+
+        let key = |name: &str, xpub: &str| {
+            let key = DescriptorPublicKey::from_str(xpub).unwrap();
+            Key {
+                name: name.to_string(),
+                fingerprint: key.master_fingerprint(),
+                key,
+                device_kind: None,
+                device_version: None,
+                is_compatible_taproot: true,
+                is_hot_signer: false,
+                bsms_key_record: None,
+            }
+        };
+        sandbox
+            .update(Message::DefineDescriptor(
+                message::DefineDescriptor::KeysEdited(vec![(0, 0)], key("Primary key", "[4df3f0e3/84'/0'/0']tpubDDRs9DnRUiJc4hq92PSJKhfzQBgHJUrDo7T2i48smsDfLsQcm3Vh7JhuGqJv8zozVkNFin8YPgpmn2NWNmpRaE3GW2pSxbmAzYf2juy7LeW")),
+            ))
+            .await;
+        sandbox
+            .update(Message::DefineDescriptor(
+                message::DefineDescriptor::KeysEdited(vec![(1, 0)], key("Inheritance key", "[f5acc2fd/48'/1'/0'/2']tpubDFAqEGNyad35aBCKUAXbQGDjdVhNueno5ZZVEn3sQbW5ci457gLR7HyTmHBg93oourBssgUxuWz1jX5uhc1qaqFo9VsybY1J5FuedLfm4dK")),
+            ))
+            .await;
+
+        // Set the recovery path to be available after 90 days, using the sequence modal.
+        sandbox
+            .update(Message::DefineDescriptor(message::DefineDescriptor::Path(
+                1,
+                message::DefinePath::EditSequence,
+            )))
+            .await;
+        for msg in [
+            message::ThresholdSequenceModal::TimeSequenceToggled(true),
+            message::ThresholdSequenceModal::SequenceEdited("90".to_string()),
+            message::ThresholdSequenceModal::Confirm,
+        ] {
+            sandbox
+                .update(Message::DefineDescriptor(
+                    message::DefineDescriptor::ThresholdSequenceModal(msg),
+                ))
+                .await;
+        }
+        sandbox.check(|step| {
+            assert!(step.modal.is_none());
+            assert_eq!(step.paths[1].sequence, 15_188);
+            assert!(step.paths[1].time_sequence);
+            assert!((step).apply(&mut ctx));
+            let policy = ctx.descriptor.as_ref().unwrap().policy();
+            assert!(policy.recovery_paths().is_empty());
+            assert!(policy.time_recovery_paths().contains_key(&15_188));
+        });
+
+        assert_eq!(days_to_time_sequence(90), Some(15_188));
+        assert_eq!(time_sequence_to_days(15_188), 90);
+        assert_eq!(days_to_time_sequence(388), Some(65_475));
+        assert_eq!(days_to_time_sequence(389), None);
+    }
 }
@@ -2,12 +2,13 @@ pub mod editor;
 
 use std::{
     collections::{HashMap, HashSet},
-    str::FromStr,
+    path::PathBuf,
 };
 
 use iced::{Command, Subscription};
 use liana::{
-    descriptors::LianaDescriptor,
+    bsms,
+    descriptors::{import::import_wallet, LianaDescriptor},
     miniscript::bitcoin::{bip32::Fingerprint, Network},
 };
 
@@ -19,6 +20,7 @@ use crate::{
     app::wallet::wallet_name,
     hw::{HardwareWallet, HardwareWallets},
     installer::{
+        bsms::write_record,
         message::{self, Message},
         step::{Context, Step},
         view, Error,
@@ -43,24 +45,30 @@ impl ImportDescriptor {
     }
 
     fn check_descriptor(&mut self, network: Network) -> Option<LianaDescriptor> {
+        self.error = None;
         if !self.imported_descriptor.value.is_empty() {
-            if let Ok(desc) = LianaDescriptor::from_str(&self.imported_descriptor.value) {
-                if network == Network::Bitcoin {
-                    self.imported_descriptor.valid = desc.all_xpubs_net_is(network);
-                } else {
-                    self.imported_descriptor.valid = desc.all_xpubs_net_is(Network::Testnet);
+            // The user may also paste a wallet exported by another coordinator.
+            match import_wallet(&self.imported_descriptor.value) {
+                Ok(desc) => {
+                    if network == Network::Bitcoin {
+                        self.imported_descriptor.valid = desc.all_xpubs_net_is(network);
+                    } else {
+                        self.imported_descriptor.valid = desc.all_xpubs_net_is(Network::Testnet);
+                    }
+                    if self.imported_descriptor.valid {
+                        self.wrong_network = false;
+                        Some(desc)
+                    } else {
+                        self.wrong_network = true;
+                        None
+                    }
                 }
-                if self.imported_descriptor.valid {
+                Err(e) => {
+                    self.imported_descriptor.valid = false;
                     self.wrong_network = false;
-                    Some(desc)
-                } else {
-                    self.wrong_network = true;
+                    self.error = Some(e.to_string());
                     None
                 }
-            } else {
-                self.imported_descriptor.valid = false;
-                self.wrong_network = false;
-                None
             }
         } else {
             self.wrong_network = false;
@@ -292,19 +300,62 @@ pub struct BackupDescriptor {
     done: bool,
     descriptor: Option<LianaDescriptor>,
     key_aliases: HashMap<Fingerprint, String>,
+    warnings: Vec<String>,
+    data_dir: PathBuf,
+    network: Option<Network>,
+    bsms_coordinator: Option<bsms::Coordinator>,
+    // The path of the BSMS descriptor record file, once saved.
+    bsms_record: Option<Result<PathBuf, String>>,
+}
+
+impl BackupDescriptor {
+    fn save_bsms_descriptor_record(&self) -> Result<PathBuf, String> {
+        let (coordinator, descriptor, network) =
+            match (&self.bsms_coordinator, &self.descriptor, self.network) {
+                (Some(coordinator), Some(descriptor), Some(network)) => {
+                    (coordinator, descriptor, network)
+                }
+                _ => return Err("No BSMS session".to_string()),
+            };
+        let record = coordinator
+            .descriptor_record(descriptor, network)
+            .map_err(|e| e.to_string())?;
+        write_record(
+            &self.data_dir,
+            network,
+            "bsms-descriptor-record.txt",
+            &record,
+        )
+        .map_err(|e| e.to_string())
+    }
 }
 
 impl Step for BackupDescriptor {
     fn update(&mut self, _hws: &mut HardwareWallets, message: Message) -> Command<Message> {
-        if let Message::UserActionDone(done) = message {
-            self.done = done;
+        match message {
+            Message::UserActionDone(done) => {
+                self.done = done;
+            }
+            Message::Bsms(message::Bsms::SaveDescriptorRecord) => {
+                self.bsms_record = Some(self.save_bsms_descriptor_record());
+            }
+            _ => {}
         }
         Command::none()
     }
     fn load_context(&mut self, ctx: &Context) {
+        self.data_dir.clone_from(&ctx.data_dir);
+        self.network = Some(ctx.bitcoin_config.network);
+        self.bsms_coordinator.clone_from(&ctx.bsms_coordinator);
         if self.descriptor != ctx.descriptor {
             self.descriptor.clone_from(&ctx.descriptor);
+            self.warnings = self
+                .descriptor
+                .as_ref()
+                .map(|desc| desc.lint().iter().map(|w| w.to_string()).collect())
+                .unwrap_or_default();
             self.done = false;
+            self.bsms_record = None;
         }
         self.key_aliases = ctx
             .keys
@@ -324,6 +375,9 @@ impl Step for BackupDescriptor {
             email,
             self.descriptor.as_ref().expect("Must be a descriptor"),
             &self.key_aliases,
+            &self.warnings,
+            self.bsms_coordinator.is_some(),
+            self.bsms_record.as_ref(),
             self.done,
         )
     }
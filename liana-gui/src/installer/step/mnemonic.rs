@@ -2,19 +2,38 @@ use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 
 use iced::Command;
-use liana::{bip39, signer::HotSigner};
+use liana::{
+    bip39,
+    signer::{HotSigner, MnemonicLength},
+};
 
-use liana_ui::widget::Element;
+use liana_ui::{component::form, widget::Element};
 
 use crate::{
     hw::HardwareWallets,
-    installer::{context::Context, message::Message, step::Step, view},
+    installer::{
+        context::Context,
+        message::{Message, Slip39},
+        step::Step,
+        view,
+    },
     signer::Signer,
 };
 
+/// The SLIP39 share set to back up instead of the mnemonic.
+pub struct Slip39Backup {
+    pub threshold: u8,
+    pub share_count: u8,
+    pub shares: Vec<String>,
+}
+
 pub struct BackupMnemonic {
-    words: [&'static str; 12],
+    words: Vec<&'static str>,
+    slip39: Option<Slip39Backup>,
+    // The optional password to encrypt the stored mnemonic.
+    password: form::Value<String>,
     done: bool,
+    error: Option<String>,
     signer: Arc<Mutex<Signer>>,
 }
 
@@ -24,9 +43,32 @@ impl BackupMnemonic {
         Self {
             done: false,
             words,
+            slip39: None,
+            password: form::Value::default(),
+            error: None,
             signer,
         }
     }
+
+    fn update_slip39_shares(&mut self) {
+        if let Some(slip39) = &mut self.slip39 {
+            slip39.threshold = slip39.threshold.min(slip39.share_count);
+            match self
+                .signer
+                .lock()
+                .unwrap()
+                .slip39_shares(slip39.threshold, slip39.share_count)
+            {
+                Ok(shares) => {
+                    slip39.shares = shares;
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        // The shares must be backed up again.
+        self.done = false;
+    }
 }
 
 impl From<BackupMnemonic> for Box<dyn Step> {
@@ -36,12 +78,48 @@ impl From<BackupMnemonic> for Box<dyn Step> {
 }
 
 impl Step for BackupMnemonic {
+    // The hot signer may have been generated again when defining the descriptor.
+    fn load_context(&mut self, _ctx: &Context) {
+        let words = self.signer.lock().unwrap().mnemonic();
+        if words != self.words {
+            self.words = words;
+            self.update_slip39_shares();
+        }
+    }
     fn update(&mut self, _hws: &mut HardwareWallets, message: Message) -> Command<Message> {
-        if let Message::UserActionDone(done) = message {
-            self.done = done;
+        match message {
+            Message::UserActionDone(done) => self.done = done,
+            Message::MnemonicPassword(password) => self.password.value = password,
+            Message::Slip39(Slip39::Use(use_slip39)) => {
+                self.slip39 = use_slip39.then(|| Slip39Backup {
+                    threshold: 2,
+                    share_count: 3,
+                    shares: Vec::new(),
+                });
+                self.error = None;
+                self.update_slip39_shares();
+            }
+            Message::Slip39(Slip39::Threshold(threshold)) => {
+                if let Some(slip39) = &mut self.slip39 {
+                    slip39.threshold = threshold;
+                    self.update_slip39_shares();
+                }
+            }
+            Message::Slip39(Slip39::ShareCount(share_count)) => {
+                if let Some(slip39) = &mut self.slip39 {
+                    slip39.share_count = share_count;
+                    self.update_slip39_shares();
+                }
+            }
+            _ => {}
         }
         Command::none()
     }
+    fn apply(&mut self, ctx: &mut Context) -> bool {
+        ctx.hot_signer_password =
+            Some(self.password.value.clone()).filter(|password| !password.is_empty());
+        true
+    }
     fn skip(&self, ctx: &Context) -> bool {
         if let Some(descriptor) = &ctx.descriptor {
             !descriptor
@@ -57,13 +135,28 @@ impl Step for BackupMnemonic {
         progress: (usize, usize),
         email: Option<&'a str>,
     ) -> Element<Message> {
-        view::backup_mnemonic(progress, email, &self.words, self.done)
+        view::backup_mnemonic(
+            progress,
+            email,
+            &self.words,
+            self.slip39.as_ref(),
+            &self.password,
+            self.done,
+            self.error.as_ref(),
+        )
     }
 }
 
 pub struct RecoverMnemonic {
     language: bip39::Language,
-    words: [(String, bool); 12],
+    length: MnemonicLength,
+    words: Vec<(String, bool)>,
+    // The SLIP39 shares to recover the mnemonic from, if the user chose so.
+    slip39_shares: Option<Vec<String>>,
+    // The optional BIP39 passphrase. It is never stored.
+    passphrase: form::Value<String>,
+    // The optional password to encrypt the stored mnemonic.
+    password: form::Value<String>,
     current: usize,
     suggestions: Vec<String>,
     error: Option<String>,
@@ -75,7 +168,11 @@ impl Default for RecoverMnemonic {
     fn default() -> Self {
         Self {
             language: bip39::Language::English,
-            words: Default::default(),
+            length: MnemonicLength::default(),
+            words: vec![Default::default(); MnemonicLength::default().word_count()],
+            slip39_shares: None,
+            passphrase: form::Value::default(),
+            password: form::Value::default(),
             current: 0,
             suggestions: Vec::new(),
             error: None,
@@ -113,6 +210,37 @@ impl Step for RecoverMnemonic {
                     *word = value;
                 }
             }
+            Message::MnemonicPassphrase(passphrase) => {
+                self.passphrase.value = passphrase;
+                self.error = None;
+            }
+            Message::MnemonicPassword(password) => self.password.value = password,
+            Message::MnemonicLength(length) => {
+                self.length = length;
+                self.words.resize(length.word_count(), Default::default());
+                self.current = self.current.min(length.word_count() - 1);
+                self.error = None;
+            }
+            Message::Slip39(Slip39::Use(use_slip39)) => {
+                // A share set has at least 2 shares.
+                self.slip39_shares = use_slip39.then(|| vec![String::new(); 2]);
+                self.error = None;
+            }
+            Message::Slip39(Slip39::ShareEdited(i, share)) => {
+                if let Some(s) = self
+                    .slip39_shares
+                    .as_mut()
+                    .and_then(|shares| shares.get_mut(i))
+                {
+                    *s = share;
+                    self.error = None;
+                }
+            }
+            Message::Slip39(Slip39::AddShare) => {
+                if let Some(shares) = &mut self.slip39_shares {
+                    shares.push(String::new());
+                }
+            }
             Message::ImportMnemonic(recover) => self.recover = recover,
             Message::Skip => {
                 self.skip = true;
@@ -130,13 +258,23 @@ impl Step for RecoverMnemonic {
             return true;
         }
 
-        let words: Vec<String> = self
-            .words
-            .iter()
-            .filter_map(|(s, valid)| if *valid { Some(s.clone()) } else { None })
-            .collect();
-
-        let seed = match HotSigner::from_str(ctx.bitcoin_config.network, &words.join(" ")) {
+        let network = ctx.bitcoin_config.network;
+        let seed = if let Some(shares) = &self.slip39_shares {
+            let shares: Vec<String> = shares
+                .iter()
+                .filter(|share| !share.trim().is_empty())
+                .cloned()
+                .collect();
+            HotSigner::from_slip39_shares(network, &shares)
+        } else {
+            let words: Vec<String> = self
+                .words
+                .iter()
+                .filter_map(|(s, valid)| if *valid { Some(s.clone()) } else { None })
+                .collect();
+            HotSigner::from_str(network, &words.join(" "))
+        };
+        let seed = match seed.and_then(|seed| seed.with_passphrase(&self.passphrase.value)) {
             Ok(seed) => seed,
             Err(e) => {
                 self.error = Some(e.to_string());
@@ -153,7 +291,12 @@ impl Step for RecoverMnemonic {
             for (fingerprint, _) in info.primary_path().thresh_origins().1.iter() {
                 descriptor_keys.insert(*fingerprint);
             }
-            for (_, path) in info.recovery_paths().iter() {
+            for path in info
+                .recovery_paths()
+                .values()
+                .chain(info.time_recovery_paths().values())
+                .chain(info.absolute_recovery_paths().values())
+            {
                 for (fingerprint, _) in path.thresh_origins().1.iter() {
                     descriptor_keys.insert(*fingerprint);
                 }
@@ -166,6 +309,8 @@ impl Step for RecoverMnemonic {
         }
 
         ctx.recovered_signer = Some(Arc::new(signer));
+        ctx.recovered_signer_password =
+            Some(self.password.value.clone()).filter(|password| !password.is_empty());
         true
     }
     fn view<'a>(
@@ -177,7 +322,11 @@ impl Step for RecoverMnemonic {
         view::recover_mnemonic(
             progress,
             email,
+            self.length,
             &self.words,
+            self.slip39_shares.as_deref(),
+            &self.passphrase,
+            &self.password,
             self.current,
             &self.suggestions,
             self.recover,
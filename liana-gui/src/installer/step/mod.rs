@@ -17,7 +17,7 @@ pub use descriptor::{
 };
 
 pub use backend::{ChooseBackend, ImportRemoteWallet, RemoteBackendLogin};
-pub use mnemonic::{BackupMnemonic, RecoverMnemonic};
+pub use mnemonic::{BackupMnemonic, RecoverMnemonic, Slip39Backup};
 pub use share_xpubs::ShareXpubs;
 
 use std::path::PathBuf;
@@ -1,17 +1,28 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use iced::{Command, Subscription};
-use liana::miniscript::bitcoin::{
-    bip32::{ChildNumber, Fingerprint},
-    Network,
+use liana::{
+    bsms::{self, KeyRecord, Token},
+    miniscript::bitcoin::{
+        bip32::{ChildNumber, Fingerprint},
+        Network,
+    },
 };
 
-use liana_ui::widget::Element;
+use liana_ui::{
+    component::form,
+    widget::{Column, Element},
+};
 
 use crate::{
     hw::{HardwareWallet, HardwareWallets},
     installer::{
-        message::Message,
+        bsms::{read_record, write_record},
+        message::{self, Message},
         step::{
             descriptor::editor::key::{default_derivation_path, get_extended_pubkey},
             Context, Step,
@@ -32,8 +43,23 @@ pub struct SignerXpubs {
     signer: Arc<Mutex<Signer>>,
     xpubs: Vec<String>,
     next_account: ChildNumber,
-    words: [&'static str; 12],
+    words: Vec<&'static str>,
+    // The optional BIP39 passphrase. It is never stored.
+    passphrase: form::Value<String>,
     did_backup: bool,
+    bsms: BsmsSigner,
+}
+
+/// The hot signer's side of a BSMS session: it sends a key record to the coordinator, and
+/// verifies the descriptor record it receives in return.
+#[derive(Default)]
+struct BsmsSigner {
+    token: form::Value<String>,
+    description: form::Value<String>,
+    key_record: Option<KeyRecord>,
+    key_record_file: Option<Result<PathBuf, String>>,
+    descriptor_record: form::Value<String>,
+    verified_descriptor: Option<Result<String, String>>,
 }
 
 impl SignerXpubs {
@@ -44,12 +70,70 @@ impl SignerXpubs {
             signer,
             xpubs: Vec::new(),
             next_account: ChildNumber::from_hardened_idx(0).unwrap(),
+            passphrase: form::Value::default(),
             did_backup: false,
+            bsms: BsmsSigner::default(),
         }
     }
 
+    // Create a key record for our key, encrypted with the coordinator's token, and save it to a
+    // file to be sent to the coordinator.
+    fn create_bsms_key_record(
+        &mut self,
+        data_dir: &std::path::Path,
+        network: Network,
+    ) -> Result<PathBuf, String> {
+        let token = Token::from_str(&self.bsms.token.value).map_err(|e| e.to_string())?;
+        let signer = self.signer.lock().unwrap();
+        let record = signer.bsms_key_record(
+            token.clone(),
+            default_derivation_path(network),
+            self.bsms.description.value.clone(),
+        );
+        let path = write_record(
+            data_dir,
+            network,
+            &format!("bsms-key-record-{}.txt", signer.fingerprint()),
+            &token.encrypt(&record.to_string()),
+        )
+        .map_err(|e| e.to_string())?;
+        self.bsms.key_record = Some(record);
+        Ok(path)
+    }
+
+    // Decrypt and verify the descriptor record sent by the coordinator. Returns the descriptor.
+    fn verify_bsms_descriptor_record(&self) -> Result<String, String> {
+        let key_record = self
+            .bsms
+            .key_record
+            .as_ref()
+            .ok_or_else(|| "No key record was created".to_string())?;
+        let content = read_record(&self.bsms.descriptor_record.value).map_err(|e| e.to_string())?;
+        bsms::verify_descriptor_record(key_record, &content)
+            .map(|desc| desc.to_string())
+            .map_err(|e| e.to_string())
+    }
+
     fn select(&mut self, network: Network) {
         self.next_account = self.next_account.increment().unwrap();
+        self.update_xpubs(network);
+    }
+
+    // Derive the master key using the new passphrase, and update the xpubs if they were shown.
+    fn set_passphrase(&mut self, passphrase: String, network: Network) {
+        self.passphrase.valid = self
+            .signer
+            .lock()
+            .unwrap()
+            .set_passphrase(&passphrase)
+            .is_ok();
+        self.passphrase.value = passphrase;
+        if !self.xpubs.is_empty() {
+            self.update_xpubs(network);
+        }
+    }
+
+    fn update_xpubs(&mut self, network: Network) {
         let signer = self.signer.lock().unwrap();
         let derivation_path = default_derivation_path(network);
         // We keep only one for the moment.
@@ -62,12 +146,32 @@ impl SignerXpubs {
     }
 
     pub fn view(&self) -> Element<Message> {
-        view::signer_xpubs(&self.xpubs, &self.words, self.did_backup)
+        Column::new()
+            .spacing(10)
+            .push(view::signer_xpubs(
+                &self.xpubs,
+                &self.words,
+                &self.passphrase,
+                self.did_backup,
+            ))
+            .push_maybe(if !self.xpubs.is_empty() && self.did_backup {
+                Some(view::signer_bsms(
+                    &self.bsms.token,
+                    &self.bsms.description,
+                    self.bsms.key_record_file.as_ref(),
+                    &self.bsms.descriptor_record,
+                    self.bsms.verified_descriptor.as_ref(),
+                ))
+            } else {
+                None
+            })
+            .into()
     }
 }
 
 pub struct ShareXpubs {
     network: Network,
+    data_dir: PathBuf,
     hw_xpubs: Vec<HardwareWalletXpubs>,
     xpubs_signer: SignerXpubs,
 }
@@ -76,6 +180,7 @@ impl ShareXpubs {
     pub fn new(network: Network, signer: Arc<Mutex<Signer>>) -> Self {
         Self {
             network,
+            data_dir: PathBuf::new(),
             hw_xpubs: Vec::new(),
             xpubs_signer: SignerXpubs::new(signer),
         }
@@ -108,6 +213,37 @@ impl Step for ShareXpubs {
             Message::UserActionDone(done) => {
                 self.xpubs_signer.did_backup = done;
             }
+            Message::MnemonicPassphrase(passphrase) => {
+                self.xpubs_signer.set_passphrase(passphrase, self.network);
+            }
+            Message::Bsms(msg) => {
+                let bsms = &mut self.xpubs_signer.bsms;
+                match msg {
+                    message::Bsms::TokenEdited(token) => {
+                        bsms.token.valid = Token::from_str(&token).is_ok();
+                        bsms.token.value = token;
+                    }
+                    message::Bsms::DescriptionEdited(description) => {
+                        bsms.description.value = description;
+                    }
+                    message::Bsms::CreateKeyRecord => {
+                        let res = self
+                            .xpubs_signer
+                            .create_bsms_key_record(&self.data_dir, self.network);
+                        self.xpubs_signer.bsms.key_record_file = Some(res);
+                        self.xpubs_signer.bsms.verified_descriptor = None;
+                    }
+                    message::Bsms::DescriptorRecordEdited(record) => {
+                        bsms.descriptor_record.value = record;
+                        bsms.verified_descriptor = None;
+                    }
+                    message::Bsms::VerifyDescriptorRecord => {
+                        let res = self.xpubs_signer.verify_bsms_descriptor_record();
+                        self.xpubs_signer.bsms.verified_descriptor = Some(res);
+                    }
+                    message::Bsms::SaveDescriptorRecord => {}
+                }
+            }
             Message::Select(i) => {
                 if let Some(HardwareWallet::Supported {
                     device,
@@ -153,6 +289,10 @@ impl Step for ShareXpubs {
         hws.refresh().map(Message::HardwareWallets)
     }
 
+    fn load_context(&mut self, ctx: &Context) {
+        self.data_dir.clone_from(&ctx.data_dir);
+    }
+
     fn apply(&mut self, ctx: &mut Context) -> bool {
         ctx.bitcoin_config.network = self.network;
         // Drop connections to hardware wallets.
@@ -1,9 +1,9 @@
 pub mod template;
 
-use iced::widget::{container, pick_list, scrollable, slider, Button, Space};
+use iced::widget::{checkbox, container, pick_list, scrollable, slider, Button, Space};
 use iced::{Alignment, Length};
 
-use liana::miniscript::bitcoin::Network;
+use liana::{bsms::Token, miniscript::bitcoin::Network, signer::MnemonicLength};
 use liana_ui::component::text::{self, h3, p1_bold, p2_regular, H3_SIZE};
 use liana_ui::image;
 use std::borrow::Cow;
@@ -88,6 +88,7 @@ pub fn path(
     color: iced::Color,
     title: Option<String>,
     sequence: u16,
+    time_sequence: bool,
     duplicate_sequence: bool,
     threshold: usize,
     keys: Vec<Element<message::DefinePath>>,
@@ -98,7 +99,11 @@ pub fn path(
         Column::new()
             .spacing(10)
             .push_maybe(title.map(|t| Row::new().push(Space::with_width(10)).push(p1_bold(t))))
-            .push(defined_sequence(sequence, duplicate_sequence))
+            .push(defined_sequence(
+                sequence,
+                time_sequence,
+                duplicate_sequence,
+            ))
             .push(
                 Column::new()
                     .spacing(5)
@@ -263,6 +268,10 @@ pub fn edit_key_modal<'a>(
     form_xpub: &form::Value<String>,
     manually_imported_xpub: bool,
     duplicate_master_fg: bool,
+    bsms_token: Option<&Token>,
+    hot_signer_length: MnemonicLength,
+    hot_signer_entropy: &form::Value<String>,
+    hot_signer_passphrase: Option<&form::Value<String>>,
 ) -> Element<'a, Message> {
     let content = Column::new()
         .padding(25)
@@ -296,6 +305,17 @@ pub fn edit_key_modal<'a>(
                             .on_press(Message::UseHotSigner)
                             .style(theme::Button::Border),
                         )
+                        .push_maybe(hot_signer_passphrase
+                            .filter(|_| Some(*hot_signer_fingerprint) == chosen_signer)
+                            .map(|passphrase| Column::new()
+                                .spacing(20)
+                                .push(super::mnemonic_generation(
+                                    hot_signer_length,
+                                    hot_signer_entropy,
+                                ))
+                                .push(super::bip39_passphrase(passphrase))
+                            )
+                        )
                         .push(if manually_imported_xpub {
                                 card::simple(Column::new()
                                     .spacing(10)
@@ -324,7 +344,15 @@ pub fn edit_key_modal<'a>(
                                                     .padding(10),
                                             )
                                             .spacing(10)
-                                    ))
+                                    )
+                                    .push_maybe(bsms_token.map(|token| {
+                                        p2_regular(format!(
+                                            "You can also enter a BSMS key record, or the path to its file. \
+                                            Share the token {} with the signer to create it.",
+                                            token
+                                        ))
+                                        .style(color::GREY_2)
+                                    })))
                                     } else {
                                     Container::new(
                                             Button::new(
@@ -403,8 +431,13 @@ fn example_xpub(network: Network) -> String {
 }
 
 /// returns y,m,d,h,m
-pub fn duration_from_sequence(sequence: u16) -> (u32, u32, u32, u32, u32) {
-    let mut n_minutes = sequence as u32 * 10;
+/// The duration of a sequence, in blocks (assuming 10-minute blocks) or in 512-second intervals.
+pub fn duration_from_sequence(sequence: u16, time_sequence: bool) -> (u32, u32, u32, u32, u32) {
+    let mut n_minutes = if time_sequence {
+        sequence as u32 * 512 / 60
+    } else {
+        sequence as u32 * 10
+    };
     let n_years = n_minutes / 525960;
     n_minutes -= n_years * 525960;
     let n_months = n_minutes / 43830;
@@ -417,7 +450,10 @@ pub fn duration_from_sequence(sequence: u16) -> (u32, u32, u32, u32, u32) {
     (n_years, n_months, n_days, n_hours, n_minutes)
 }
 
-pub fn edit_sequence_modal<'a>(sequence: &form::Value<String>) -> Element<'a, Message> {
+pub fn edit_sequence_modal<'a>(
+    sequence: &form::Value<String>,
+    time_sequence: bool,
+) -> Element<'a, Message> {
     let mut col = Column::new()
         .width(Length::Fill)
         .spacing(20)
@@ -434,17 +470,49 @@ pub fn edit_sequence_modal<'a>(sequence: &form::Value<String>) -> Element<'a, Me
                                 ),
                             )
                         })
-                        .warning("Sequence must be superior to 0 and inferior to 65535"),
+                        .warning(if time_sequence {
+                            "Duration must be superior to 0 and inferior to 389 days"
+                        } else {
+                            "Sequence must be superior to 0 and inferior to 65535"
+                        }),
                     )
                     .width(Length::Fixed(200.0)),
                 )
                 .spacing(10)
-                .push(text("blocks").bold()),
+                .push(text(if time_sequence { "days" } else { "blocks" }).bold()),
+        )
+        .push(
+            checkbox(
+                "Use a time-based timelock instead of a number of blocks",
+                time_sequence,
+            )
+            .on_toggle(|v| {
+                Message::DefineDescriptor(message::DefineDescriptor::ThresholdSequenceModal(
+                    message::ThresholdSequenceModal::TimeSequenceToggled(v),
+                ))
+            }),
         );
 
-    if sequence.valid {
+    if sequence.valid && time_sequence {
+        if let Ok(days) = u16::from_str(&sequence.value) {
+            col = col.push(
+                Container::new(
+                    slider(1..=388_u16, days, |v| {
+                        Message::DefineDescriptor(
+                            message::DefineDescriptor::ThresholdSequenceModal(
+                                message::ThresholdSequenceModal::SequenceEdited(v.to_string()),
+                            ),
+                        )
+                    })
+                    .step(1_u16),
+                )
+                .width(Length::Fixed(500.0)),
+            );
+        }
+    } else if sequence.valid {
         if let Ok(sequence) = u16::from_str(&sequence.value) {
-            let (n_years, n_months, n_days, n_hours, n_minutes) = duration_from_sequence(sequence);
+            let (n_years, n_months, n_days, n_hours, n_minutes) =
+                duration_from_sequence(sequence, false);
             col = col
                 .push(
                     [
@@ -50,6 +50,7 @@ pub fn custom_template_description(progress: (usize, usize)) -> Element<'static,
 pub struct Path<'a> {
     pub keys: Vec<Option<&'a Key>>,
     pub sequence: u16,
+    pub time_sequence: bool,
     pub duplicate_sequence: bool,
     pub threshold: usize,
 }
@@ -96,6 +97,7 @@ pub fn custom_template<'a>(
                     color::GREEN,
                     Some("Primary spending option:".to_string()),
                     primary_path.sequence,
+                    primary_path.time_sequence,
                     primary_path.duplicate_sequence,
                     primary_path.threshold,
                     primary_path
@@ -138,6 +140,7 @@ pub fn custom_template<'a>(
                             color::ORANGE,
                             Some(format!("Recovery option #{}:", i + 1)),
                             p.sequence,
+                            p.time_sequence,
                             p.duplicate_sequence,
                             p.threshold,
                             p.keys
@@ -70,6 +70,7 @@ pub fn inheritance_template<'a>(
     primary_key: Option<&'a Key>,
     recovery_key: Option<&'a Key>,
     sequence: u16,
+    time_sequence: bool,
     valid: bool,
 ) -> Element<'a, Message> {
     layout(
@@ -108,6 +109,7 @@ pub fn inheritance_template<'a>(
                     None,
                     0,
                     false,
+                    false,
                     1,
                     vec![if let Some(key) = primary_key {
                         defined_key(
@@ -134,6 +136,7 @@ pub fn inheritance_template<'a>(
                     color::WHITE,
                     None,
                     sequence,
+                    time_sequence,
                     false,
                     1,
                     vec![if let Some(key) = recovery_key {
@@ -80,6 +80,7 @@ pub fn multisig_security_template<'a>(
     primary_keys: Vec<Option<&'a Key>>,
     recovery_keys: Vec<Option<&'a Key>>,
     sequence: u16,
+    time_sequence: bool,
     threshold: usize,
     valid: bool,
 ) -> Element<'a, Message> {
@@ -119,6 +120,7 @@ pub fn multisig_security_template<'a>(
                     None,
                     0,
                     false,
+                    false,
                     primary_keys.len(),
                     primary_keys
                         .iter()
@@ -165,6 +167,7 @@ pub fn multisig_security_template<'a>(
                     color::ORANGE,
                     None,
                     sequence,
+                    time_sequence,
                     false,
                     threshold,
                     recovery_keys
@@ -1,7 +1,9 @@
 pub mod editor;
 
 use async_hwi::utils::extract_keys_and_template;
-use iced::widget::{checkbox, radio, scrollable, scrollable::Properties, Button, Space, TextInput};
+use iced::widget::{
+    checkbox, pick_list, radio, scrollable, scrollable::Properties, Button, Space, TextInput,
+};
 use iced::{
     alignment,
     widget::{progress_bar, tooltip as iced_tooltip},
@@ -18,6 +20,7 @@ use std::{collections::HashSet, str::FromStr};
 use liana::{
     descriptors::{LianaDescriptor, LianaPolicy},
     miniscript::bitcoin::{self, bip32::Fingerprint},
+    signer::{MnemonicLength, SLIP39_MAX_SHARES},
 };
 use liana_ui::{
     color,
@@ -34,7 +37,7 @@ use crate::{
     installer::{
         message::{self, DefineBitcoind, DefineNode, Message},
         prompt,
-        step::{DownloadState, InstallState},
+        step::{DownloadState, InstallState, Slip39Backup},
         view::editor::duration_from_sequence,
         Error,
     },
@@ -262,6 +265,14 @@ pub fn import_descriptor<'a>(
 ) -> Element<'a, Message> {
     let col_descriptor = Column::new()
         .push(text("Descriptor:").bold())
+        .push(
+            text(
+                "You can also paste a Sparrow or Specter wallet export, \
+                a Coldcard multisig configuration file, a BSMS descriptor record \
+                or a BIP388 wallet policy.",
+            )
+            .small(),
+        )
         .push(
             form::Form::new_trimmed("Descriptor", imported_descriptor, |msg| {
                 Message::DefineDescriptor(message::DefineDescriptor::ImportDescriptor(msg))
@@ -304,12 +315,42 @@ pub fn import_descriptor<'a>(
     )
 }
 
+fn bsms_descriptor_record(record: Option<&Result<PathBuf, String>>) -> Element<Message> {
+    card::simple(
+        Column::new()
+            .spacing(10)
+            .push(text("BSMS descriptor record").small().bold())
+            .push(text(
+                "Some keys were imported from BSMS key records. Save the descriptor record and \
+                send it to their signers, so they can verify the descriptor.",
+            ))
+            .push(
+                button::secondary(Some(icon::import_icon()), "Save the descriptor record")
+                    .on_press(Message::Bsms(message::Bsms::SaveDescriptorRecord)),
+            )
+            .push_maybe(record.map(|res| {
+                match res {
+                    Ok(path) => text(format!(
+                        "Descriptor record saved to {}",
+                        path.to_string_lossy()
+                    ))
+                    .style(color::GREEN),
+                    Err(e) => text(format!("Failed to save the descriptor record: {}", e))
+                        .style(color::RED),
+                }
+            })),
+    )
+    .max_width(1500)
+    .into()
+}
+
 const BACKUP_WARNING: &str =
     "Beware to back up the mnemonic as it will NOT be stored on the computer.";
 
 pub fn signer_xpubs<'a>(
     xpubs: &'a [String],
-    words: &'a [&'static str; 12],
+    words: &'a [&'static str],
+    passphrase: &form::Value<String>,
     did_backup: bool,
 ) -> Element<'a, Message> {
     Container::new(
@@ -355,6 +396,11 @@ pub fn signer_xpubs<'a>(
                     .padding(15),
                 )
             })
+            .push_maybe(if xpubs.is_empty() {
+                None
+            } else {
+                Some(Container::new(bip39_passphrase(passphrase)).padding(15))
+            })
             .push_maybe(if !xpubs.is_empty() {
                 Some(
                     Container::new(
@@ -402,6 +448,92 @@ pub fn signer_xpubs<'a>(
     .into()
 }
 
+pub fn signer_bsms<'a>(
+    token: &form::Value<String>,
+    description: &form::Value<String>,
+    key_record_file: Option<&'a Result<PathBuf, String>>,
+    descriptor_record: &form::Value<String>,
+    verified_descriptor: Option<&'a Result<String, String>>,
+) -> Element<'a, Message> {
+    card::simple(
+        Column::new()
+            .spacing(10)
+            .push(text("BSMS signer").small().bold())
+            .push(text(
+                "If the wallet is set up by a BSMS coordinator, enter the token they shared to \
+                create a key record for this key and send them the saved file.",
+            ))
+            .push(
+                form::Form::new_trimmed("Token", token, |msg| {
+                    Message::Bsms(message::Bsms::TokenEdited(msg))
+                })
+                .warning("Token must be 00 or 16 or 32 hexadecimal characters")
+                .size(text::P1_SIZE)
+                .padding(10),
+            )
+            .push(
+                form::Form::new("Key description (optional)", description, |msg| {
+                    Message::Bsms(message::Bsms::DescriptionEdited(msg))
+                })
+                .size(text::P1_SIZE)
+                .padding(10),
+            )
+            .push(button::secondary(None, "Create key record").on_press_maybe(
+                if token.valid && !token.value.is_empty() {
+                    Some(Message::Bsms(message::Bsms::CreateKeyRecord))
+                } else {
+                    None
+                },
+            ))
+            .push_maybe(key_record_file.map(|res| {
+                match res {
+                    Ok(path) => text(format!("Key record saved to {}", path.to_string_lossy()))
+                        .style(color::GREEN),
+                    Err(e) => {
+                        text(format!("Failed to create the key record: {}", e)).style(color::RED)
+                    }
+                }
+            }))
+            .push_maybe(key_record_file.filter(|res| res.is_ok()).map(|_| {
+                Column::new()
+                    .spacing(10)
+                    .push(text(
+                        "Once the coordinator sent back the descriptor record, paste it or \
+                                the path to its file to verify it contains this key.",
+                    ))
+                    .push(
+                        form::Form::new_trimmed("Descriptor record", descriptor_record, |msg| {
+                            Message::Bsms(message::Bsms::DescriptorRecordEdited(msg))
+                        })
+                        .size(text::P1_SIZE)
+                        .padding(10),
+                    )
+                    .push(button::secondary(None, "Verify").on_press_maybe(
+                        if descriptor_record.value.is_empty() {
+                            None
+                        } else {
+                            Some(Message::Bsms(message::Bsms::VerifyDescriptorRecord))
+                        },
+                    ))
+                    .push_maybe(verified_descriptor.map(|res| {
+                        match res {
+                            Ok(desc) => Column::new()
+                                .spacing(5)
+                                .push(
+                                    text("The descriptor record is valid and contains this key:")
+                                        .style(color::GREEN),
+                                )
+                                .push(text(desc.clone()).small()),
+                            Err(e) => Column::new().push(
+                                text(format!("Invalid descriptor record: {}", e)).style(color::RED),
+                            ),
+                        }
+                    }))
+            })),
+    )
+    .into()
+}
+
 pub fn hardware_wallet_xpubs<'a>(
     i: usize,
     hw: &'a HardwareWallet,
@@ -683,6 +815,9 @@ pub fn backup_descriptor<'a>(
     email: Option<&'a str>,
     descriptor: &'a LianaDescriptor,
     keys_aliases: &'a HashMap<Fingerprint, String>,
+    warnings: &'a [String],
+    bsms_session: bool,
+    bsms_record: Option<&'a Result<PathBuf, String>>,
     done: bool,
 ) -> Element<'a, Message> {
     layout(
@@ -749,6 +884,26 @@ pub fn backup_descriptor<'a>(
                     .width(Length::Fill)
                     .max_width(1500),
             )
+            .push_maybe(if warnings.is_empty() {
+                None
+            } else {
+                Some(warnings.iter().fold(
+                    Column::new().spacing(5).max_width(1500),
+                    |col, warning| {
+                        col.push(
+                            Row::new()
+                                .spacing(5)
+                                .push(icon::warning_icon().style(color::ORANGE))
+                                .push(text(warning).style(color::ORANGE)),
+                        )
+                    },
+                ))
+            })
+            .push_maybe(if bsms_session {
+                Some(bsms_descriptor_record(bsms_record))
+            } else {
+                None
+            })
             .push(
                 checkbox("I have backed up my descriptor", done).on_toggle(Message::UserActionDone),
             )
@@ -1478,9 +1633,11 @@ pub fn defined_threshold<'a>(
 
 pub fn defined_sequence<'a>(
     sequence: u16,
+    time_sequence: bool,
     duplicate_sequence: bool,
 ) -> Element<'a, message::DefinePath> {
-    let (n_years, n_months, n_days, n_hours, n_minutes) = duration_from_sequence(sequence);
+    let (n_years, n_months, n_days, n_hours, n_minutes) =
+        duration_from_sequence(sequence, time_sequence);
     Container::new(
         Column::new()
             .spacing(5)
@@ -1680,8 +1837,11 @@ pub fn key_list_view<'a>(
 pub fn backup_mnemonic<'a>(
     progress: (usize, usize),
     email: Option<&'a str>,
-    words: &'a [&'static str; 12],
+    words: &'a [&'static str],
+    slip39: Option<&'a Slip39Backup>,
+    password: &form::Value<String>,
     done: bool,
+    error: Option<&'a String>,
 ) -> Element<'a, Message> {
     layout(
         progress,
@@ -1690,6 +1850,12 @@ pub fn backup_mnemonic<'a>(
         Column::new()
             .push(text(prompt::MNEMONIC_HELP))
             .push(
+                checkbox("Back up as SLIP39 shares", slip39.is_some())
+                    .on_toggle(|b| Message::Slip39(message::Slip39::Use(b))),
+            )
+            .push(if let Some(slip39) = slip39 {
+                slip39_backup(slip39)
+            } else {
                 words
                     .iter()
                     .enumerate()
@@ -1703,8 +1869,11 @@ pub fn backup_mnemonic<'a>(
                                 )
                                 .push(text(*w).bold()),
                         )
-                    }),
-            )
+                    })
+                    .into()
+            })
+            .push_maybe(error.map(|e| card::invalid(text(e).style(color::RED))))
+            .push(mnemonic_password(password))
             .push(checkbox("I have backed up my mnemonic", done).on_toggle(Message::UserActionDone))
             .push(if done {
                 button::secondary(None, "Next")
@@ -1720,10 +1889,251 @@ pub fn backup_mnemonic<'a>(
     )
 }
 
+// The form for the optional BIP39 passphrase of the hot signer.
+fn bip39_passphrase<'a>(passphrase: &form::Value<String>) -> Element<'a, Message> {
+    Column::new()
+        .spacing(5)
+        .push(text("BIP39 passphrase (optional)").bold())
+        .push(
+            text(
+                "The passphrase is never stored on this computer: \
+                it will be asked for each time this key signs.",
+            )
+            .small(),
+        )
+        .push(
+            form::Form::new("Passphrase", passphrase, Message::MnemonicPassphrase)
+                .secure(true)
+                .size(text::P1_SIZE)
+                .padding(10),
+        )
+        .max_width(500)
+        .into()
+}
+
+// The SLIP39 share set to back up, along with the choice of the threshold and the number of
+// shares.
+fn slip39_backup<'a>(slip39: &'a Slip39Backup) -> Element<'a, Message> {
+    Column::new()
+        .spacing(10)
+        .push(text(prompt::SLIP39_BACKUP_HELP).small())
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(text("Shares needed:"))
+                .push(
+                    pick_list(
+                        (2..=slip39.share_count).collect::<Vec<_>>(),
+                        Some(slip39.threshold),
+                        |t| Message::Slip39(message::Slip39::Threshold(t)),
+                    )
+                    .style(theme::PickList::Secondary)
+                    .padding(10),
+                )
+                .push(text("out of"))
+                .push(
+                    pick_list(
+                        (2..=SLIP39_MAX_SHARES).collect::<Vec<_>>(),
+                        Some(slip39.share_count),
+                        |c| Message::Slip39(message::Slip39::ShareCount(c)),
+                    )
+                    .style(theme::PickList::Secondary)
+                    .padding(10),
+                ),
+        )
+        .push(slip39.shares.iter().enumerate().fold(
+            Column::new().spacing(10),
+            |col, (i, share)| {
+                col.push(
+                    Row::new()
+                        .spacing(10)
+                        .push(
+                            Container::new(text(format!("Share #{}", i + 1)).small())
+                                .width(Length::Fixed(80.0)),
+                        )
+                        .push(text(share).bold()),
+                )
+            },
+        ))
+        .max_width(800)
+        .into()
+}
+
+// The forms for the length of the generated mnemonic and the optional entropy provided by the
+// user.
+fn mnemonic_generation<'a>(
+    length: MnemonicLength,
+    entropy: &form::Value<String>,
+) -> Element<'a, Message> {
+    Column::new()
+        .spacing(5)
+        .push(text("Mnemonic length").bold())
+        .push(
+            pick_list(
+                &MnemonicLength::ALL[..],
+                Some(length),
+                Message::MnemonicLength,
+            )
+            .style(theme::PickList::Secondary)
+            .padding(10),
+        )
+        .push(Space::with_height(Length::Fixed(10.0)))
+        .push(text("Additional entropy (optional)").bold())
+        .push(
+            text(
+                "Dice rolls or coin flips to mix in with the randomness of this computer, \
+                if you don't trust it.",
+            )
+            .small(),
+        )
+        .push(
+            form::Form::new(
+                "Dice rolls (1 to 6) or coin flips (H or T)",
+                entropy,
+                |msg| Message::MnemonicEntropy(msg),
+            )
+            .warning("Please enter only dice rolls (1 to 6) or only coin flips (H or T)")
+            .size(text::P1_SIZE)
+            .padding(10),
+        )
+        .max_width(500)
+        .into()
+}
+
+// The form for the optional password encrypting the mnemonic stored on this computer.
+fn mnemonic_password<'a>(password: &form::Value<String>) -> Element<'a, Message> {
+    Column::new()
+        .spacing(5)
+        .push(text("Mnemonic password (optional)").bold())
+        .push(
+            text(
+                "The mnemonic will be stored encrypted on this computer: the password \
+                will be asked for each time this key signs. If it is lost, the key can only \
+                be recovered from the mnemonic backup.",
+            )
+            .small(),
+        )
+        .push(
+            form::Form::new("Password", password, Message::MnemonicPassword)
+                .secure(true)
+                .size(text::P1_SIZE)
+                .padding(10),
+        )
+        .max_width(500)
+        .into()
+}
+
+// The inputs for the words of the mnemonic to recover, with suggestions for the current one.
+fn mnemonic_words_input<'a>(
+    length: MnemonicLength,
+    words: &'a [(String, bool)],
+    current: usize,
+    suggestions: &'a [String],
+) -> Element<'a, Message> {
+    Column::new()
+        .align_items(Alignment::Center)
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(text("Number of words:"))
+                .push(
+                    pick_list(
+                        &MnemonicLength::ALL[..],
+                        Some(length),
+                        Message::MnemonicLength,
+                    )
+                    .style(theme::PickList::Secondary)
+                    .padding(10),
+                ),
+        )
+        .push(
+            Container::new(if !suggestions.is_empty() {
+                suggestions.iter().fold(Row::new().spacing(5), |row, sugg| {
+                    row.push(
+                        Button::new(text(sugg))
+                            .style(theme::Button::Secondary)
+                            .on_press(Message::MnemonicWord(current, sugg.to_string())),
+                    )
+                })
+            } else {
+                Row::new()
+            })
+            // Fixed height in order to not move words list
+            .height(Length::Fixed(50.0)),
+        )
+        .push(
+            words
+                .iter()
+                .enumerate()
+                .fold(Column::new().spacing(5), |acc, (i, (word, valid))| {
+                    acc.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(
+                                Container::new(text(format!("#{}", i + 1)).small())
+                                    .width(Length::Fixed(50.0)),
+                            )
+                            .push(
+                                Container::new(
+                                    TextInput::new("", word)
+                                        .on_input(move |msg| Message::MnemonicWord(i, msg)),
+                                )
+                                .width(Length::Fixed(100.0)),
+                            )
+                            .push_maybe(if *valid {
+                                Some(icon::circle_check_icon().style(color::GREEN))
+                            } else {
+                                None
+                            }),
+                    )
+                }),
+        )
+        .into()
+}
+
+// The inputs for the SLIP39 shares to recover the mnemonic from, one share per input.
+fn slip39_shares_input<'a>(shares: &'a [String]) -> Element<'a, Message> {
+    shares
+        .iter()
+        .enumerate()
+        .fold(Column::new().spacing(10), |col, (i, share)| {
+            col.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(
+                        Container::new(text(format!("#{}", i + 1)).small())
+                            .width(Length::Fixed(50.0)),
+                    )
+                    .push(
+                        TextInput::new("Share words", share)
+                            .on_input(move |msg| {
+                                Message::Slip39(message::Slip39::ShareEdited(i, msg))
+                            })
+                            .padding(10),
+                    ),
+            )
+        })
+        .push(
+            button::secondary(Some(icon::plus_icon()), "Add share")
+                .on_press(Message::Slip39(message::Slip39::AddShare))
+                .width(Length::Fixed(200.0)),
+        )
+        .max_width(800)
+        .into()
+}
+
 pub fn recover_mnemonic<'a>(
     progress: (usize, usize),
     email: Option<&'a str>,
-    words: &'a [(String, bool); 12],
+    length: MnemonicLength,
+    words: &'a [(String, bool)],
+    slip39_shares: Option<&'a [String]>,
+    passphrase: &form::Value<String>,
+    password: &form::Value<String>,
     current: usize,
     suggestions: &'a [String],
     recover: bool,
@@ -1740,49 +2150,21 @@ pub fn recover_mnemonic<'a>(
                     Column::new()
                         .align_items(Alignment::Center)
                         .push(
-                            Container::new(if !suggestions.is_empty() {
-                                suggestions.iter().fold(Row::new().spacing(5), |row, sugg| {
-                                    row.push(
-                                        Button::new(text(sugg))
-                                            .style(theme::Button::Secondary)
-                                            .on_press(Message::MnemonicWord(
-                                                current,
-                                                sugg.to_string(),
-                                            )),
-                                    )
-                                })
-                            } else {
-                                Row::new()
-                            })
-                            // Fixed height in order to not move words list
-                            .height(Length::Fixed(50.0)),
+                            checkbox("Recover from SLIP39 shares", slip39_shares.is_some())
+                                .on_toggle(|b| Message::Slip39(message::Slip39::Use(b))),
                         )
-                        .push(words.iter().enumerate().fold(
-                            Column::new().spacing(5),
-                            |acc, (i, (word, valid))| {
-                                acc.push(
-                                    Row::new()
-                                        .spacing(10)
-                                        .align_items(Alignment::Center)
-                                        .push(
-                                            Container::new(text(format!("#{}", i + 1)).small())
-                                                .width(Length::Fixed(50.0)),
-                                        )
-                                        .push(
-                                            Container::new(TextInput::new("", word).on_input(
-                                                move |msg| Message::MnemonicWord(i, msg),
-                                            ))
-                                            .width(Length::Fixed(100.0)),
-                                        )
-                                        .push_maybe(if *valid {
-                                            Some(icon::circle_check_icon().style(color::GREEN))
-                                        } else {
-                                            None
-                                        }),
-                                )
-                            },
-                        ))
-                        .push(Space::with_height(Length::Fixed(50.0)))
+                        .push(Space::with_height(Length::Fixed(20.0)))
+                        .push_maybe(slip39_shares.map(slip39_shares_input))
+                        .push_maybe(
+                            slip39_shares
+                                .is_none()
+                                .then(|| mnemonic_words_input(length, words, current, suggestions)),
+                        )
+                        .push(Space::with_height(Length::Fixed(20.0)))
+                        .push(bip39_passphrase(passphrase))
+                        .push(Space::with_height(Length::Fixed(20.0)))
+                        .push(mnemonic_password(password))
+                        .push(Space::with_height(Length::Fixed(30.0)))
                         .push_maybe(error.map(|e| card::invalid(text(e).style(color::RED)))),
                 )
             } else {
@@ -1810,7 +2192,12 @@ pub fn recover_mnemonic<'a>(
                             .width(Length::Fixed(200.0)),
                     )
                     .push(
-                        if words.iter().any(|(_, valid)| !valid) || error.is_some() {
+                        if error.is_some()
+                            || match slip39_shares {
+                                Some(shares) => shares.iter().all(|s| s.trim().is_empty()),
+                                None => words.iter().any(|(_, valid)| !valid),
+                            }
+                        {
                             button::secondary(None, "Next").width(Length::Fixed(200.0))
                         } else {
                             button::secondary(None, "Next")
@@ -613,10 +613,7 @@ impl Daemon for BackendWalletClient {
         }
 
         let res: api::Address = response.json().await?;
-        Ok(GetAddressResult {
-            address: res.address,
-            derivation_index: res.derivation_index,
-        })
+        Ok(GetAddressResult::new(res.address, res.derivation_index))
     }
 
     /// Spent coins are not returned if statuses is empty, unless their outpoints are specified.
@@ -754,6 +751,7 @@ impl Daemon for BackendWalletClient {
             api::DraftPsbtResult::Success(draft) => Ok(CreateSpendResult::Success {
                 psbt: draft.raw,
                 warnings: draft.warnings,
+                payjoin_endpoint: None,
             }),
             api::DraftPsbtResult::InsufficientFunds(api::InsufficientFundsInfo { missing }) => {
                 Ok(CreateSpendResult::InsufficientFunds { missing })
@@ -794,6 +792,7 @@ impl Daemon for BackendWalletClient {
             api::DraftPsbtResult::Success(draft) => Ok(CreateSpendResult::Success {
                 psbt: draft.raw,
                 warnings: draft.warnings,
+                payjoin_endpoint: None,
             }),
             api::DraftPsbtResult::InsufficientFunds(api::InsufficientFundsInfo { missing }) => {
                 Ok(CreateSpendResult::InsufficientFunds { missing })
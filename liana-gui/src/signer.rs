@@ -1,18 +1,28 @@
 pub use liana::signer::SignerError;
 
 use liana::{
+    bsms::{KeyRecord, Token},
     miniscript::bitcoin::{
         bip32::{DerivationPath, Fingerprint, Xpub},
         psbt::Psbt,
         secp256k1, Network,
     },
-    signer::HotSigner,
+    signer::{HotSigner, MnemonicLength, StoredMnemonic},
 };
 
+enum Key {
+    Hot(HotSigner),
+    /// The mnemonic is encrypted in the datadir. It is decrypted for each signature and never
+    /// kept in memory.
+    Encrypted(StoredMnemonic, Network),
+}
+
 pub struct Signer {
     curve: secp256k1::Secp256k1<secp256k1::All>,
-    key: HotSigner,
+    key: Key,
     pub fingerprint: Fingerprint,
+    /// Whether the master key is derived using a BIP39 passphrase which must be given for signing.
+    passphrase_required: bool,
 }
 
 impl std::fmt::Debug for Signer {
@@ -26,41 +36,161 @@ impl Signer {
         let curve = secp256k1::Secp256k1::new();
         let fingerprint = key.fingerprint(&curve);
         Self {
-            key,
+            key: Key::Hot(key),
             curve,
             fingerprint,
+            passphrase_required: false,
+        }
+    }
+
+    /// A signer for a mnemonic read from the datadir. If it is encrypted, the password is asked
+    /// for each signature. If its master key is derived using a BIP39 passphrase, so is the
+    /// passphrase. Neither is ever kept.
+    pub fn from_stored(stored: StoredMnemonic, network: Network) -> Result<Self, SignerError> {
+        if stored.is_encrypted() {
+            Ok(Self {
+                curve: secp256k1::Secp256k1::new(),
+                fingerprint: stored.fingerprint,
+                key: Key::Encrypted(stored, network),
+                passphrase_required: false,
+            })
+        } else {
+            let key = stored.signer(network, None)?;
+            let signer = Self::new(key);
+            Ok(Self {
+                passphrase_required: signer.fingerprint != stored.fingerprint,
+                fingerprint: stored.fingerprint,
+                ..signer
+            })
+        }
+    }
+
+    /// Whether the mnemonic is encrypted, and a password must be given for signing.
+    pub fn password_required(&self) -> bool {
+        matches!(self.key, Key::Encrypted(..))
+    }
+
+    /// Whether a BIP39 passphrase must be given for signing. For an encrypted mnemonic, this is
+    /// only known once decrypted.
+    pub fn passphrase_required(&self) -> bool {
+        self.passphrase_required
+    }
+
+    // The installer only uses the signers it generates or recovers, which are never encrypted.
+    fn hot_key(&self) -> &HotSigner {
+        match &self.key {
+            Key::Hot(key) => key,
+            Key::Encrypted(..) => panic!("Encrypted signers are only used for signing."),
         }
     }
 
+    /// Derive the master key using this BIP39 passphrase, an empty one meaning no passphrase.
+    pub fn set_passphrase(&mut self, passphrase: &str) -> Result<(), SignerError> {
+        let key = self.hot_key().with_passphrase(passphrase)?;
+        self.fingerprint = key.fingerprint(&self.curve);
+        self.key = Key::Hot(key);
+        Ok(())
+    }
+
     pub fn set_network(&mut self, network: Network) {
-        self.key.set_network(network)
+        match &mut self.key {
+            Key::Hot(key) => key.set_network(network),
+            Key::Encrypted(_, key_network) => *key_network = network,
+        }
+    }
+
+    pub fn mnemonic(&self) -> Vec<&'static str> {
+        self.hot_key().words()
     }
 
-    pub fn mnemonic(&self) -> [&'static str; 12] {
-        self.key.words()
+    pub fn slip39_shares(
+        &self,
+        threshold: u8,
+        share_count: u8,
+    ) -> Result<Vec<String>, SignerError> {
+        self.hot_key().slip39_shares(threshold, share_count)
     }
 
     pub fn generate(network: Network) -> Result<Self, SignerError> {
         Ok(Self::new(HotSigner::generate(network)?))
     }
 
+    /// Generate a signer using a mnemonic of this length, mixing in the entropy provided by the
+    /// user if any.
+    pub fn generate_with(
+        network: Network,
+        length: MnemonicLength,
+        user_entropy: &[u8],
+    ) -> Result<Self, SignerError> {
+        Ok(Self::new(HotSigner::generate_with(
+            network,
+            length,
+            user_entropy,
+        )?))
+    }
+
     pub fn fingerprint(&self) -> Fingerprint {
         self.fingerprint
     }
 
     pub fn get_extended_pubkey(&self, path: &DerivationPath) -> Xpub {
-        self.key.xpub_at(path, &self.curve)
+        self.hot_key().xpub_at(path, &self.curve)
     }
 
-    pub fn sign_psbt(&self, psbt: Psbt) -> Result<Psbt, SignerError> {
-        self.key.sign_psbt(psbt, &self.curve)
+    pub fn bsms_key_record(
+        &self,
+        token: Token,
+        path: DerivationPath,
+        description: String,
+    ) -> KeyRecord {
+        self.hot_key()
+            .bsms_key_record(token, path, description, &self.curve)
+    }
+
+    /// Sign the PSBT. The password is needed if the mnemonic is encrypted, and the BIP39
+    /// passphrase if the master key is derived using one. The master key must match the
+    /// fingerprint of the signer.
+    pub fn sign_psbt(
+        &self,
+        psbt: Psbt,
+        password: Option<&str>,
+        passphrase: Option<&str>,
+    ) -> Result<Psbt, SignerError> {
+        let decrypted;
+        let key = match &self.key {
+            Key::Hot(key) => key,
+            Key::Encrypted(stored, network) => {
+                decrypted = stored.signer(*network, password)?;
+                &decrypted
+            }
+        };
+        let derived;
+        let key = match passphrase {
+            Some(passphrase) => {
+                derived = key.with_passphrase(passphrase)?;
+                &derived
+            }
+            None => key,
+        };
+        if key.fingerprint(&self.curve) != self.fingerprint {
+            return Err(SignerError::PassphraseMismatch(self.fingerprint));
+        }
+        key.sign_psbt(psbt, &self.curve)
     }
 
+    /// Store the mnemonic in the datadir, encrypted if a password is given.
     pub fn store(
         &self,
         datadir_root: &std::path::Path,
         network: Network,
+        password: Option<&str>,
     ) -> Result<(), SignerError> {
-        self.key.store(datadir_root, network, &self.curve)
+        match password {
+            Some(password) => {
+                self.hot_key()
+                    .store_encrypted(datadir_root, network, password, &self.curve)
+            }
+            None => self.hot_key().store(datadir_root, network, &self.curve),
+        }
     }
 }
@@ -103,6 +103,12 @@ where
         self.input = self.input.size(size);
         self
     }
+
+    /// Hides the value of the [`Form`], for passwords.
+    pub fn secure(mut self, is_secure: bool) -> Self {
+        self.input = self.input.secure(is_secure);
+        self
+    }
 }
 
 impl<'a, Message: 'a + Clone> From<Form<'a, Message>> for Element<'a, Message> {
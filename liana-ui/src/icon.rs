@@ -47,6 +47,10 @@ pub fn import_icon() -> Text<'static> {
     bootstrap_icon('\u{F30A}')
 }
 
+pub fn export_icon() -> Text<'static> {
+    bootstrap_icon('\u{F603}')
+}
+
 pub fn wallet_icon() -> Text<'static> {
     bootstrap_icon('\u{F615}')
 }
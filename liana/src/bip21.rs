@@ -0,0 +1,285 @@
+//! BIP21
+//!
+//! Parsing and generation of `bitcoin:` payment URIs, including the BIP78 payjoin parameters.
+
+use std::{error, fmt, str::FromStr};
+
+use miniscript::bitcoin::{self, address::NetworkUnchecked, Denomination};
+
+const URI_SCHEME: &str = "bitcoin:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentUriError {
+    InvalidScheme,
+    InvalidAddress(String),
+    InvalidAmount(String),
+    InvalidEncoding(String),
+    DuplicateParameter(String),
+    /// A parameter prefixed with `req-` that we don't know about.
+    UnknownRequiredParameter(String),
+}
+
+impl fmt::Display for PaymentUriError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidScheme => write!(f, "Payment URI must start with '{}'.", URI_SCHEME),
+            Self::InvalidAddress(e) => write!(f, "Invalid address in payment URI: {}", e),
+            Self::InvalidAmount(a) => write!(f, "Invalid amount in payment URI: '{}'.", a),
+            Self::InvalidEncoding(s) => {
+                write!(f, "Invalid percent-encoding in payment URI: '{}'.", s)
+            }
+            Self::DuplicateParameter(p) => {
+                write!(
+                    f,
+                    "Parameter '{}' is present more than once in payment URI.",
+                    p
+                )
+            }
+            Self::UnknownRequiredParameter(p) => {
+                write!(f, "Unknown required parameter '{}' in payment URI.", p)
+            }
+        }
+    }
+}
+
+impl error::Error for PaymentUriError {}
+
+/// A BIP21 payment URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentUri {
+    pub address: bitcoin::Address<NetworkUnchecked>,
+    pub amount: Option<bitcoin::Amount>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    /// The BIP78 endpoint of the receiver, if it supports payjoin.
+    pub payjoin_endpoint: Option<String>,
+    /// Whether the receiver allows the sender to substitute its output in a payjoin.
+    pub payjoin_output_substitution: bool,
+}
+
+impl PaymentUri {
+    /// A payment URI for this address, without any parameter.
+    pub fn new(address: bitcoin::Address<NetworkUnchecked>) -> Self {
+        Self {
+            address,
+            amount: None,
+            label: None,
+            message: None,
+            payjoin_endpoint: None,
+            payjoin_output_substitution: true,
+        }
+    }
+
+    /// Whether this string looks like a payment URI, as opposed to a bare address.
+    pub fn is_uri(s: &str) -> bool {
+        s.get(..URI_SCHEME.len())
+            .map_or(false, |scheme| scheme.eq_ignore_ascii_case(URI_SCHEME))
+    }
+}
+
+// Decode a percent-encoded URI component.
+fn percent_decode(s: &str) -> Result<String, PaymentUriError> {
+    let invalid = || PaymentUriError::InvalidEncoding(s.to_string());
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3).ok_or_else(invalid)?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+// Percent-encode a URI component, leaving only the unreserved characters as is.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// Set this parameter, making sure it wasn't already.
+fn set_once<T>(param: &mut Option<T>, key: &str, value: T) -> Result<(), PaymentUriError> {
+    if param.replace(value).is_some() {
+        return Err(PaymentUriError::DuplicateParameter(key.to_string()));
+    }
+    Ok(())
+}
+
+impl FromStr for PaymentUri {
+    type Err = PaymentUriError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !Self::is_uri(s) {
+            return Err(PaymentUriError::InvalidScheme);
+        }
+        let s = &s[URI_SCHEME.len()..];
+        let (address, query) = s.split_once('?').unwrap_or((s, ""));
+        let address = bitcoin::Address::from_str(address)
+            .map_err(|e| PaymentUriError::InvalidAddress(e.to_string()))?;
+
+        let (mut amount, mut label, mut message, mut payjoin_endpoint, mut pjos) =
+            (None, None, None, None, None);
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            match key {
+                "amount" => {
+                    let value = bitcoin::Amount::from_str_in(value, Denomination::Bitcoin)
+                        .map_err(|_| PaymentUriError::InvalidAmount(value.to_string()))?;
+                    set_once(&mut amount, key, value)?;
+                }
+                "label" => set_once(&mut label, key, percent_decode(value)?)?,
+                "message" => set_once(&mut message, key, percent_decode(value)?)?,
+                "pj" => set_once(&mut payjoin_endpoint, key, percent_decode(value)?)?,
+                "pjos" => set_once(&mut pjos, key, value != "0")?,
+                key if key.starts_with("req-") => {
+                    return Err(PaymentUriError::UnknownRequiredParameter(key.to_string()));
+                }
+                // Unknown optional parameters must be ignored.
+                _ => {}
+            }
+        }
+
+        Ok(PaymentUri {
+            address,
+            amount,
+            label,
+            message,
+            payjoin_endpoint,
+            payjoin_output_substitution: pjos.unwrap_or(true),
+        })
+    }
+}
+
+impl fmt::Display for PaymentUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", URI_SCHEME, self.address.clone().assume_checked())?;
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(format!(
+                "amount={}",
+                amount.to_string_in(Denomination::Bitcoin)
+            ));
+        }
+        if let Some(label) = &self.label {
+            params.push(format!("label={}", percent_encode(label)));
+        }
+        if let Some(message) = &self.message {
+            params.push(format!("message={}", percent_encode(message)));
+        }
+        if let Some(endpoint) = &self.payjoin_endpoint {
+            params.push(format!("pj={}", percent_encode(endpoint)));
+            if !self.payjoin_output_substitution {
+                params.push("pjos=0".to_string());
+            }
+        }
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_payment_uri() {
+        let addr = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+        let uri = PaymentUri::from_str(&format!("bitcoin:{}", addr)).unwrap();
+        assert_eq!(uri.address.assume_checked().to_string(), addr);
+        assert_eq!(uri.amount, None);
+        assert_eq!(uri.payjoin_endpoint, None);
+        assert!(uri.payjoin_output_substitution);
+
+        let uri = PaymentUri::from_str(&format!(
+            "BITCOIN:{}?amount=0.0005&label=Luke%20Jr&message=Donation%20for%20project%20xyz&unknown=1",
+            addr
+        ))
+        .unwrap();
+        assert_eq!(uri.amount, Some(bitcoin::Amount::from_sat(50_000)));
+        assert_eq!(uri.label.as_deref(), Some("Luke Jr"));
+        assert_eq!(uri.message.as_deref(), Some("Donation for project xyz"));
+
+        let uri = PaymentUri::from_str(&format!(
+            "bitcoin:{}?amount=1&pj=https://example.com/pj%3Fid%3D1&pjos=0",
+            addr
+        ))
+        .unwrap();
+        assert_eq!(uri.amount, Some(bitcoin::Amount::ONE_BTC));
+        assert_eq!(
+            uri.payjoin_endpoint.as_deref(),
+            Some("https://example.com/pj?id=1")
+        );
+        assert!(!uri.payjoin_output_substitution);
+
+        assert!(!PaymentUri::is_uri(addr));
+        assert_eq!(
+            PaymentUri::from_str(addr),
+            Err(PaymentUriError::InvalidScheme)
+        );
+        assert!(matches!(
+            PaymentUri::from_str("bitcoin:notanaddress"),
+            Err(PaymentUriError::InvalidAddress(..))
+        ));
+        assert_eq!(
+            PaymentUri::from_str(&format!("bitcoin:{}?amount=1,5", addr)),
+            Err(PaymentUriError::InvalidAmount("1,5".to_string()))
+        );
+        assert_eq!(
+            PaymentUri::from_str(&format!("bitcoin:{}?amount=1&amount=2", addr)),
+            Err(PaymentUriError::DuplicateParameter("amount".to_string()))
+        );
+        assert_eq!(
+            PaymentUri::from_str(&format!("bitcoin:{}?label=%E", addr)),
+            Err(PaymentUriError::InvalidEncoding("%E".to_string()))
+        );
+        assert_eq!(
+            PaymentUri::from_str(&format!(
+                "bitcoin:{}?req-somethingyoudontunderstand=50",
+                addr
+            )),
+            Err(PaymentUriError::UnknownRequiredParameter(
+                "req-somethingyoudontunderstand".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn payment_uri_roundtrip() {
+        let addr =
+            bitcoin::Address::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").unwrap();
+
+        let mut uri = PaymentUri::new(addr);
+        assert_eq!(
+            uri.to_string(),
+            "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"
+        );
+
+        uri.amount = Some(bitcoin::Amount::from_sat(50_000));
+        uri.label = Some("Luke Jr".to_string());
+        uri.message = Some("Donation for project xyz & co".to_string());
+        assert_eq!(
+            uri.to_string(),
+            "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq?amount=0.0005&label=Luke%20Jr&message=Donation%20for%20project%20xyz%20%26%20co"
+        );
+        assert_eq!(PaymentUri::from_str(&uri.to_string()).unwrap(), uri);
+
+        uri.payjoin_endpoint = Some("https://example.com/pj?id=1".to_string());
+        uri.payjoin_output_substitution = false;
+        assert_eq!(PaymentUri::from_str(&uri.to_string()).unwrap(), uri);
+    }
+}
@@ -0,0 +1,663 @@
+//! BSMS
+//!
+//! The coordinator and signer roles of a BIP129 (Bitcoin Secure Multisig Setup) ceremony. The
+//! coordinator generates a token and shares it with every signer. In the first round, each signer
+//! returns a key record: its key and a description, signed with this key and encrypted with the
+//! token. In the second round, once the coordinator created the descriptor from the key records,
+//! it distributes it to signers as an encrypted descriptor record that each of them verifies.
+
+use crate::{
+    descriptors::{
+        import::{
+            from_bsms_record, ImportError, BSMS_NO_PATH_RESTRICTIONS, BSMS_PATH_RESTRICTIONS,
+        },
+        LianaDescriptor,
+    },
+    random,
+};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use miniscript::{
+    bitcoin::{
+        self, bip32,
+        hashes::{sha256, sha512, Hash, HashEngine, Hmac, HmacEngine},
+        hex::{DisplayHex, FromHex},
+        secp256k1,
+        sign_message::{signed_msg_hash, MessageSignature},
+    },
+    descriptor,
+};
+
+use std::{error, fmt, str::FromStr};
+
+pub const BSMS_VERSION: &str = "BSMS 1.0";
+
+// Parameters of the derivation of the encryption key from the token.
+const ENCRYPTION_SALT: &[u8] = b"No SPOF";
+const PBKDF2_ITERATIONS: u32 = 2048;
+// The size of the MAC prepended to the ciphertext.
+const MAC_SIZE: usize = 32;
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+#[derive(Debug)]
+pub enum BsmsError {
+    InvalidToken(String),
+    Randomness(random::RandomnessError),
+    /// The record can't be decrypted with this token, or was tampered with.
+    Decryption,
+    InvalidKeyRecord(String),
+    /// The signature of the key record isn't valid for its key.
+    InvalidSignature,
+    /// The record was created for another session.
+    TokenMismatch,
+    DuplicateKey(bip32::Fingerprint),
+    /// The key of a signer isn't part of the descriptor.
+    MissingKey(bip32::Fingerprint),
+    DescriptorRecord(ImportError),
+}
+
+impl fmt::Display for BsmsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidToken(e) => write!(f, "Invalid BSMS token: {}", e),
+            Self::Randomness(e) => write!(f, "{}", e),
+            Self::Decryption => write!(
+                f,
+                "Failed to decrypt the record. Is it for this session's token?"
+            ),
+            Self::InvalidKeyRecord(e) => write!(f, "Invalid key record: {}", e),
+            Self::InvalidSignature => write!(f, "The signature of the key record is invalid."),
+            Self::TokenMismatch => write!(f, "The record is for another session's token."),
+            Self::DuplicateKey(fg) => write!(f, "A key record for key '{}' already exists.", fg),
+            Self::MissingKey(fg) => write!(f, "Key '{}' isn't part of the descriptor.", fg),
+            Self::DescriptorRecord(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for BsmsError {}
+
+/// The length of a randomly generated token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenLength {
+    Bits64,
+    Bits128,
+}
+
+/// The secret shared by the coordinator with the signers at the beginning of the session. It is
+/// used to authenticate and encrypt the records. An empty token means no encryption, and is
+/// represented as `00`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token(Vec<u8>);
+
+impl Token {
+    pub fn no_encryption() -> Token {
+        Token(Vec::new())
+    }
+
+    pub fn generate(length: TokenLength) -> Result<Token, BsmsError> {
+        let bytes = random::random_bytes().map_err(BsmsError::Randomness)?;
+        let len = match length {
+            TokenLength::Bits64 => 8,
+            TokenLength::Bits128 => 16,
+        };
+        Ok(Token(bytes[..len].to_vec()))
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn encryption_key(&self) -> [u8; 32] {
+        pbkdf2_sha512(&self.0, ENCRYPTION_SALT, PBKDF2_ITERATIONS)
+    }
+
+    fn mac(&self, encryption_key: &[u8; 32], data: &[u8]) -> [u8; MAC_SIZE] {
+        let hmac_key = sha256::Hash::hash(encryption_key);
+        let mut engine = HmacEngine::<sha256::Hash>::new(hmac_key.as_byte_array());
+        engine.input(&self.0);
+        engine.input(data);
+        Hmac::from_engine(engine).to_byte_array()
+    }
+
+    /// Encrypt a record with this token. The result is the hex encoding of the MAC followed by
+    /// the ciphertext. Records are not encrypted with the `00` token.
+    pub fn encrypt(&self, record: &str) -> String {
+        if !self.is_encrypted() {
+            return record.to_string();
+        }
+        let key = self.encryption_key();
+        let mac = self.mac(&key, record.as_bytes());
+        let mut data = record.as_bytes().to_vec();
+        Aes256Ctr::new_from_slices(&key, &mac[..16])
+            .expect("Valid key and IV lengths")
+            .apply_keystream(&mut data);
+        let mut encrypted = mac.to_vec();
+        encrypted.extend_from_slice(&data);
+        encrypted.to_lower_hex_string()
+    }
+
+    /// Decrypt a record encrypted with this token, checking its integrity.
+    pub fn decrypt(&self, content: &str) -> Result<String, BsmsError> {
+        if !self.is_encrypted() {
+            return Ok(content.to_string());
+        }
+        let encrypted = Vec::<u8>::from_hex(content.trim()).map_err(|_| BsmsError::Decryption)?;
+        if encrypted.len() < MAC_SIZE {
+            return Err(BsmsError::Decryption);
+        }
+        let (mac, data) = encrypted.split_at(MAC_SIZE);
+        let key = self.encryption_key();
+        let mut data = data.to_vec();
+        Aes256Ctr::new_from_slices(&key, &mac[..16])
+            .expect("Valid key and IV lengths")
+            .apply_keystream(&mut data);
+        if self.mac(&key, &data)[..] != *mac {
+            return Err(BsmsError::Decryption);
+        }
+        String::from_utf8(data).map_err(|_| BsmsError::Decryption)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_encrypted() {
+            write!(f, "{}", self.0.to_lower_hex_string())
+        } else {
+            write!(f, "00")
+        }
+    }
+}
+
+impl FromStr for Token {
+    type Err = BsmsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "00" {
+            return Ok(Token::no_encryption());
+        }
+        let bytes = Vec::<u8>::from_hex(s).map_err(|e| BsmsError::InvalidToken(e.to_string()))?;
+        if bytes.len() != 8 && bytes.len() != 16 {
+            return Err(BsmsError::InvalidToken(
+                "must be 64 or 128 bits, or '00' for no encryption".to_string(),
+            ));
+        }
+        Ok(Token(bytes))
+    }
+}
+
+// PBKDF2 with HMAC-SHA512, for a 32-byte output. This is shorter than the HMAC output so a single
+// block is needed.
+fn pbkdf2_sha512(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha512::Hash>::new(password);
+    engine.input(salt);
+    engine.input(&1u32.to_be_bytes());
+    let mut u = Hmac::from_engine(engine).to_byte_array();
+    let mut block = u;
+    for _ in 1..iterations {
+        let mut engine = HmacEngine::<sha512::Hash>::new(password);
+        engine.input(&u);
+        u = Hmac::from_engine(engine).to_byte_array();
+        for (b, u) in block.iter_mut().zip(u.iter()) {
+            *b ^= u;
+        }
+    }
+    let mut key = [0; 32];
+    key.copy_from_slice(&block[..32]);
+    key
+}
+
+/// The record a signer sends to the coordinator in the first round: its key, along with a
+/// description of the signer, signed with this key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRecord {
+    pub token: Token,
+    pub fingerprint: bip32::Fingerprint,
+    pub derivation_path: bip32::DerivationPath,
+    pub xpub: bip32::Xpub,
+    pub description: String,
+    signature: MessageSignature,
+}
+
+impl KeyRecord {
+    /// Create a key record for the extended key at the given derivation path from the master key
+    /// with the given fingerprint.
+    pub fn sign(
+        token: Token,
+        fingerprint: bip32::Fingerprint,
+        derivation_path: bip32::DerivationPath,
+        xpriv: &bip32::Xpriv,
+        description: String,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    ) -> KeyRecord {
+        let xpub = bip32::Xpub::from_priv(secp, xpriv);
+        let msg = signed_content(&token, fingerprint, &derivation_path, &xpub, &description);
+        let msg_hash = secp256k1::Message::from_digest(signed_msg_hash(&msg).to_byte_array());
+        let sig = secp.sign_ecdsa_recoverable(&msg_hash, &xpriv.private_key);
+        KeyRecord {
+            token,
+            fingerprint,
+            derivation_path,
+            xpub,
+            description,
+            signature: MessageSignature::new(sig, true),
+        }
+    }
+
+    /// Check the record was signed with its key.
+    pub fn verify(
+        &self,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Result<(), BsmsError> {
+        let msg = signed_content(
+            &self.token,
+            self.fingerprint,
+            &self.derivation_path,
+            &self.xpub,
+            &self.description,
+        );
+        match self.signature.recover_pubkey(secp, signed_msg_hash(&msg)) {
+            Ok(pubkey) if pubkey.inner == self.xpub.public_key => Ok(()),
+            _ => Err(BsmsError::InvalidSignature),
+        }
+    }
+
+    /// The key as a descriptor key expression, without the derivation steps.
+    pub fn key(&self) -> String {
+        key_expression(self.fingerprint, &self.derivation_path, &self.xpub)
+    }
+}
+
+fn key_expression(
+    fingerprint: bip32::Fingerprint,
+    derivation_path: &bip32::DerivationPath,
+    xpub: &bip32::Xpub,
+) -> String {
+    format!(
+        "[{}{}]{}",
+        fingerprint,
+        derivation_path.to_string().trim_start_matches('m'),
+        xpub
+    )
+}
+
+// The first four lines of a key record, which are signed.
+fn signed_content(
+    token: &Token,
+    fingerprint: bip32::Fingerprint,
+    derivation_path: &bip32::DerivationPath,
+    xpub: &bip32::Xpub,
+    description: &str,
+) -> String {
+    format!(
+        "{}\n{}\n{}\n{}",
+        BSMS_VERSION,
+        token,
+        key_expression(fingerprint, derivation_path, xpub),
+        description
+    )
+}
+
+impl fmt::Display for KeyRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\n{}",
+            signed_content(
+                &self.token,
+                self.fingerprint,
+                &self.derivation_path,
+                &self.xpub,
+                &self.description
+            ),
+            self.signature.to_base64()
+        )
+    }
+}
+
+impl FromStr for KeyRecord {
+    type Err = BsmsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |e: &str| BsmsError::InvalidKeyRecord(e.to_string());
+
+        let lines: Vec<&str> = s.trim().lines().map(|l| l.trim()).collect();
+        if lines.len() != 5 {
+            return Err(invalid("a key record must have 5 lines"));
+        }
+        if lines[0] != BSMS_VERSION {
+            return Err(invalid("unsupported version, only 'BSMS 1.0' is supported"));
+        }
+        let token = Token::from_str(lines[1])?;
+        let (fingerprint, derivation_path, xpub) =
+            match descriptor::DescriptorPublicKey::from_str(lines[2]) {
+                Ok(descriptor::DescriptorPublicKey::XPub(xkey))
+                    if xkey.derivation_path.is_empty()
+                        && xkey.wildcard == descriptor::Wildcard::None =>
+                {
+                    let (fg, der_path) = xkey
+                        .origin
+                        .ok_or_else(|| invalid("the key must have an origin"))?;
+                    (fg, der_path, xkey.xkey)
+                }
+                _ => return Err(invalid("the key must be an extended public key")),
+            };
+        let signature = MessageSignature::from_base64(lines[4])
+            .map_err(|e| BsmsError::InvalidKeyRecord(e.to_string()))?;
+        Ok(KeyRecord {
+            token,
+            fingerprint,
+            derivation_path,
+            xpub,
+            description: lines[3].to_string(),
+            signature,
+        })
+    }
+}
+
+/// The unencrypted descriptor record for this descriptor, with the first receive address for
+/// the given network.
+///
+/// A BIP129 descriptor template can only express the standard receive and change paths of the
+/// keys (`/**`, restricted to `/0/*,/1/*`). If a key is reused with other derivation paths (for
+/// instance `/<2;3>/*`), the descriptor itself is shared instead, without path restrictions.
+pub fn descriptor_record(desc: &LianaDescriptor, network: bitcoin::Network) -> String {
+    let secp = secp256k1::Secp256k1::verification_only();
+    let desc_str = desc.to_string();
+    let template = desc_str
+        .split('#')
+        .next()
+        .expect("Split always yields an item")
+        .replace("/<0;1>/*", "/**");
+    let (template, path_restrictions) = if template.contains('<') {
+        (desc_str, BSMS_NO_PATH_RESTRICTIONS)
+    } else {
+        (template, BSMS_PATH_RESTRICTIONS)
+    };
+    let first_address = desc
+        .receive_descriptor()
+        .derive(0.into(), &secp)
+        .address(network);
+    format!(
+        "{}\n{}\n{}\n{}",
+        BSMS_VERSION, template, path_restrictions, first_address
+    )
+}
+
+/// The coordinator of a BSMS session. It gathers the key records of the signers, and distributes
+/// the descriptor record.
+#[derive(Debug, Clone)]
+pub struct Coordinator {
+    token: Token,
+    key_records: Vec<KeyRecord>,
+}
+
+impl Coordinator {
+    pub fn new(token: Token) -> Coordinator {
+        Coordinator {
+            token,
+            key_records: Vec::new(),
+        }
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn key_records(&self) -> &[KeyRecord] {
+        &self.key_records
+    }
+
+    /// Add a key record received from a signer to the session, after verifying it. See
+    /// [`read_key_record`].
+    pub fn add_key_record(
+        &mut self,
+        record: KeyRecord,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Result<(), BsmsError> {
+        if record.token != self.token {
+            return Err(BsmsError::TokenMismatch);
+        }
+        record.verify(secp)?;
+        if self.key_records.iter().any(|r| r.xpub == record.xpub) {
+            return Err(BsmsError::DuplicateKey(record.fingerprint));
+        }
+        self.key_records.push(record);
+        Ok(())
+    }
+
+    /// The encrypted descriptor record to distribute to the signers. All the keys of the session
+    /// must be part of the descriptor.
+    pub fn descriptor_record(
+        &self,
+        desc: &LianaDescriptor,
+        network: bitcoin::Network,
+    ) -> Result<String, BsmsError> {
+        if let Some(record) = self
+            .key_records
+            .iter()
+            .find(|r| !desc.contains_xpub(&r.xpub))
+        {
+            return Err(BsmsError::MissingKey(record.fingerprint));
+        }
+        Ok(self.token.encrypt(&descriptor_record(desc, network)))
+    }
+}
+
+/// As a coordinator, decrypt and verify a key record received from a signer.
+pub fn read_key_record(
+    token: &Token,
+    content: &str,
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+) -> Result<KeyRecord, BsmsError> {
+    let record = KeyRecord::from_str(&token.decrypt(content)?)?;
+    if record.token != *token {
+        return Err(BsmsError::TokenMismatch);
+    }
+    record.verify(secp)?;
+    Ok(record)
+}
+
+/// As a signer, decrypt and verify the descriptor record received from the coordinator. The key
+/// we shared in the first round must be part of the descriptor.
+pub fn verify_descriptor_record(
+    key_record: &KeyRecord,
+    content: &str,
+) -> Result<LianaDescriptor, BsmsError> {
+    let record = key_record.token.decrypt(content)?;
+    let desc = from_bsms_record(&record).map_err(BsmsError::DescriptorRecord)?;
+    if !desc.contains_xpub(&key_record.xpub) {
+        return Err(BsmsError::MissingKey(key_record.fingerprint));
+    }
+    Ok(desc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::HotSigner;
+
+    const MNEMONIC_A: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    const MNEMONIC_B: &str =
+        "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    #[test]
+    fn token_encryption() {
+        let token = Token::from_str("a54044308ceac9b7").unwrap();
+        assert!(token.is_encrypted());
+        assert_eq!(token.to_string(), "a54044308ceac9b7");
+        assert_eq!(Token::from_str("00").unwrap(), Token::no_encryption());
+        assert_eq!(Token::no_encryption().to_string(), "00");
+        Token::from_str("a54044").unwrap_err();
+        Token::from_str("not hex").unwrap_err();
+        for length in [TokenLength::Bits64, TokenLength::Bits128].iter().copied() {
+            let token = Token::generate(length).unwrap();
+            assert_eq!(Token::from_str(&token.to_string()).unwrap(), token);
+        }
+
+        let record = "BSMS 1.0\nsome content";
+        let encrypted = token.encrypt(record);
+        assert_ne!(encrypted, record);
+        assert_eq!(token.decrypt(&encrypted).unwrap(), record);
+        // Not with another token.
+        let other_token = Token::from_str("a54044308ceac9b8").unwrap();
+        assert!(matches!(
+            other_token.decrypt(&encrypted),
+            Err(BsmsError::Decryption)
+        ));
+        // Tampering with the ciphertext is detected.
+        let mut tampered = Vec::<u8>::from_hex(&encrypted).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            token.decrypt(&tampered.to_lower_hex_string()),
+            Err(BsmsError::Decryption)
+        ));
+        // No encryption with the `00` token.
+        assert_eq!(Token::no_encryption().encrypt(record), record);
+
+        // The signer 1 key record from BIP129, encrypted with the token of its test vector.
+        let record = "BSMS 1.0\na54044308ceac9b7\n[b7868815/48'/0'/0'/2']xpub6FA5rfxJc94K1kNtxRby1hoHwi7YDyTWwx1KUR3FwskaF6HzCbZMz3zQwGnCqdiFeMTPV3YneTGS2YQPiuNYsSvtggWWMQpEJD4jXU7ZzEh\nSigner 1 key\nH6DDz4zp+vMntb/VxcimMN/SvgWg4x+KBYIpxcgHj1Q8M6Zmq3g1zzzi7cOQiNOOYEnOtLP/sTk/3bHUMKnUwns=";
+        let encrypted = token.encrypt(record);
+        let (mac, ciphertext) = encrypted.split_at(64);
+        assert_eq!(
+            mac,
+            "4527b2c731744950b49114c07dfddf32fc6865f8fc1ce9484b6e695db393ad0c"
+        );
+        assert_eq!(ciphertext, "1cda7d916dba94668a3665d0f583e684926ea239fc7395f458889bb0d626bce773ae8f77d3ce12a89a8c74d68244076984d03785558b8194aa814e48e9da857901eea03a8eb58324123aec8863f7c3623a38a53ba741f9a08a509c16a56ea3f985761cfceff63dea059bccdcdcf4f03d02ace9852535bcbebb77b84f288496fbc42348a633aebe7914df65ab00003b7709d17dcc899378d44472f2f0dec8d8d4fcc90cbf674ebf755555dacb7d87413e269f3a5f0cb37034042a28657c39401aecaad642597d47b8208d9a191c5f023a9df5192583e7cd90f5125cf233167b286ccda9893a85df31e85a934c7aca436bcd4e955effe3291b8a273a7e65eb07196260404e9de6");
+        assert_eq!(token.decrypt(&encrypted).unwrap(), record);
+    }
+
+    #[test]
+    fn bsms_session() {
+        let secp = secp256k1::Secp256k1::new();
+        let der_path = bip32::DerivationPath::from_str("m/48'/1'/0'/2'").unwrap();
+        let token = Token::generate(TokenLength::Bits64).unwrap();
+        let mut coordinator = Coordinator::new(token.clone());
+
+        // Round 1: each signer creates a key record, which the coordinator verifies.
+        let signers: Vec<HotSigner> = [MNEMONIC_A, MNEMONIC_B]
+            .iter()
+            .map(|m| HotSigner::from_str(bitcoin::Network::Testnet, m).unwrap())
+            .collect();
+        let key_records: Vec<KeyRecord> = signers
+            .iter()
+            .enumerate()
+            .map(|(i, signer)| {
+                signer.bsms_key_record(
+                    token.clone(),
+                    der_path.clone(),
+                    format!("Signer {}", i),
+                    &secp,
+                )
+            })
+            .collect();
+        for record in &key_records {
+            let content = token.encrypt(&record.to_string());
+            let read_record = read_key_record(&token, &content, &secp).unwrap();
+            assert_eq!(read_record, *record);
+            coordinator.add_key_record(read_record, &secp).unwrap();
+        }
+        assert!(matches!(
+            coordinator.add_key_record(key_records[0].clone(), &secp),
+            Err(BsmsError::DuplicateKey(_))
+        ));
+
+        // A record with an invalid signature or for another session is rejected.
+        let mut forged = key_records[1].clone();
+        forged.description = "Not signer 1".to_string();
+        forged.verify(&secp).unwrap_err();
+        assert!(matches!(
+            read_key_record(&token, &token.encrypt(&forged.to_string()), &secp),
+            Err(BsmsError::InvalidSignature)
+        ));
+        let other_token = Token::from_str("a54044308ceac9b7").unwrap();
+        let other_record = signers[0].bsms_key_record(
+            other_token.clone(),
+            der_path.clone(),
+            "Signer 0".to_string(),
+            &secp,
+        );
+        assert!(matches!(
+            read_key_record(
+                &token,
+                &other_token.encrypt(&other_record.to_string()),
+                &secp
+            ),
+            Err(BsmsError::Decryption)
+        ));
+        assert!(matches!(
+            coordinator.add_key_record(other_record.clone(), &secp),
+            Err(BsmsError::TokenMismatch)
+        ));
+
+        // Round 2: the coordinator distributes the descriptor which the signers verify.
+        let keys: Vec<String> = coordinator
+            .key_records()
+            .iter()
+            .map(|r| format!("{}/<0;1>/*", r.key()))
+            .collect();
+        let desc = LianaDescriptor::from_str(&format!(
+            "wsh(or_d(pk({}),and_v(v:pkh({}),older(52560))))",
+            keys[0], keys[1]
+        ))
+        .unwrap();
+        let record = coordinator
+            .descriptor_record(&desc, bitcoin::Network::Testnet)
+            .unwrap();
+        for key_record in &key_records {
+            assert_eq!(verify_descriptor_record(key_record, &record).unwrap(), desc);
+        }
+        assert!(matches!(
+            verify_descriptor_record(&other_record, &record),
+            Err(BsmsError::Decryption)
+        ));
+        let plain_record = descriptor_record(&desc, bitcoin::Network::Testnet);
+        let lines: Vec<_> = plain_record.lines().collect();
+        assert!(lines[1].contains(&format!("{}/**", key_records[0].key())));
+        assert!(!lines[1].contains('<'));
+        assert_eq!(lines[2], "/0/*,/1/*");
+
+        // A key reused with other derivation paths can't be expressed in a descriptor template.
+        // The descriptor itself is distributed instead.
+        let reused_desc = LianaDescriptor::from_str(&format!(
+            "wsh(or_d(pk({}),and_v(v:multi(1,{},{}),older(52560))))",
+            keys[0],
+            keys[1],
+            keys[0].replace("<0;1>", "<2;3>")
+        ))
+        .unwrap();
+        let plain_record = descriptor_record(&reused_desc, bitcoin::Network::Testnet);
+        let lines: Vec<_> = plain_record.lines().collect();
+        assert_eq!(lines[1], reused_desc.to_string());
+        assert_eq!(lines[2], "No path restrictions");
+        let record = coordinator
+            .descriptor_record(&reused_desc, bitcoin::Network::Testnet)
+            .unwrap();
+        for key_record in &key_records {
+            assert_eq!(
+                verify_descriptor_record(key_record, &record).unwrap(),
+                reused_desc
+            );
+        }
+
+        // The coordinator can't distribute a descriptor without all the keys.
+        let other_path = bip32::DerivationPath::from_str("m/48'/1'/1'/2'").unwrap();
+        let other_key = key_expression(
+            signers[0].fingerprint(&secp),
+            &other_path,
+            &signers[0].xpub_at(&other_path, &secp),
+        );
+        let desc = LianaDescriptor::from_str(&format!(
+            "wsh(or_d(pk({}),and_v(v:pkh({}/<0;1>/*),older(52560))))",
+            keys[0], other_key
+        ))
+        .unwrap();
+        assert!(matches!(
+            coordinator.descriptor_record(&desc, bitcoin::Network::Testnet),
+            Err(BsmsError::MissingKey(_))
+        ));
+    }
+}
@@ -1,14 +1,19 @@
+use crate::musig;
+
 use miniscript::{
     bitcoin::{
-        self, bip32,
-        hashes::{sha256, Hash},
+        self, absolute, bip32,
+        hashes::{hash160, sha256, Hash},
+        psbt::Input as PsbtIn,
         secp256k1, Sequence,
     },
     descriptor,
     policy::{Concrete as ConcretePolicy, Liftable, Semantic as SemanticPolicy},
-    ScriptContext,
+    AbsLockTime, ScriptContext,
 };
 
+use serde::{Serialize, Serializer};
+
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
     convert::TryFrom,
@@ -26,6 +31,10 @@ pub enum LianaPolicyError {
     DuplicateOriginSamePath(Box<descriptor::DescriptorPublicKey>),
     InvalidMultiThresh(usize),
     InvalidMultiKeys(usize),
+    /// A spending path may only be behind a single hash lock.
+    NestedHashLock,
+    /// A MuSig2 aggregate key may only be used for the primary path of a Taproot descriptor.
+    InvalidMusig,
     IncompatibleDesc,
     PolicyAnalysis(miniscript::Error),
     /// The spending policy is not a valid Miniscript policy: it may for instance be malleable, or
@@ -55,6 +64,14 @@ impl std::fmt::Display for LianaPolicyError {
             Self::DuplicateOriginSamePath(key) => {
                 write!(f, "Key '{}' is derived from the same origin as another key present in the same spending path. It is not possible to use a signer more than once within a single spending path.", key)
             }
+            Self::NestedHashLock => write!(
+                f,
+                "A spending path may only require the preimage of a single hash."
+            ),
+            Self::InvalidMusig => write!(
+                f,
+                "A MuSig2 aggregate key must have at least two participants with the same derivation paths, and may only be used as the primary path of a Taproot descriptor."
+            ),
             Self::IncompatibleDesc => write!(
                 f,
                 "Descriptor is not compatible with a Liana spending policy."
@@ -144,6 +161,30 @@ fn csv_check(csv_value: u32) -> Result<u16, LianaPolicyError> {
     }
 }
 
+// Same as above for a time-based relative locktime, except the type flag must be set. Returns the
+// number of 512-second intervals.
+fn csv_time_check(csv_value: u32) -> Result<u16, LianaPolicyError> {
+    let intervals = csv_value & !SEQUENCE_LOCKTIME_TYPE_FLAG;
+    if csv_value & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+        csv_check(intervals).map_err(|_| LianaPolicyError::InsaneTimelock(csv_value))
+    } else {
+        Err(LianaPolicyError::InsaneTimelock(csv_value))
+    }
+}
+
+// We require the absolute locktime to:
+//  - be positive (Miniscript requires it not to be 0)
+//  - be below 2**31, the maximum value Miniscript allows
+//
+// It may either be a block height or a UNIX timestamp, as per the nLockTime semantic.
+fn cltv_check(cltv_value: u32) -> Result<u32, LianaPolicyError> {
+    if cltv_value > 0 && cltv_value < (1 << 31) {
+        Ok(cltv_value)
+    } else {
+        Err(LianaPolicyError::InsaneTimelock(cltv_value))
+    }
+}
+
 // Get the fingerprint and the full derivation paths (path from the master fingerprint in the
 // origin, with the xpub derivation path appended) for a multipath xpub.
 fn key_origins(
@@ -169,91 +210,264 @@ fn key_origins(
     }
 }
 
+// The bit of a relative locktime which signals it is time-based, as per BIP68.
+const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+
+/// The timelock behind which a recovery path is available.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Ord, PartialOrd, Hash, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum RecoveryTimelock {
+    /// A relative timelock, in number of blocks since the coin was confirmed.
+    Relative(u16),
+    /// A relative timelock, in number of 512-second intervals since the coin was confirmed.
+    RelativeTime(u16),
+    /// An absolute timelock, with the nLockTime semantic: a block height if below 500'000'000,
+    /// a UNIX timestamp otherwise.
+    Absolute(u32),
+}
+
+impl RecoveryTimelock {
+    // Get the timelock out of a Miniscript policy node, if it is one.
+    fn from_policy(
+        policy: &SemanticPolicy<descriptor::DescriptorPublicKey>,
+    ) -> Option<Result<Self, LianaPolicyError>> {
+        match policy {
+            SemanticPolicy::Older(val) if val.0 & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 => {
+                Some(csv_time_check(val.0).map(Self::RelativeTime))
+            }
+            SemanticPolicy::Older(val) => Some(csv_check(val.0).map(Self::Relative)),
+            SemanticPolicy::After(val) => {
+                Some(cltv_check(val.to_consensus_u32()).map(Self::Absolute))
+            }
+            _ => None,
+        }
+    }
+}
+
+// An approximate number of days for this duration in seconds.
+fn approx_days(secs: u32) -> u32 {
+    (secs + 43_200) / 86_400
+}
+
+impl fmt::Display for RecoveryTimelock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Relative(blocks) => write!(
+                f,
+                "{} blocks (about {} days) after the coin was confirmed",
+                blocks,
+                approx_days(u32::from(blocks) * 600)
+            ),
+            Self::RelativeTime(intervals) => {
+                let secs = u32::from(intervals) * 512;
+                write!(
+                    f,
+                    "{} seconds (about {} days) after the coin was confirmed",
+                    secs,
+                    approx_days(secs)
+                )
+            }
+            Self::Absolute(height) if height < absolute::LOCK_TIME_THRESHOLD => {
+                write!(f, "from block height {}", height)
+            }
+            Self::Absolute(timestamp) => write!(f, "from UNIX timestamp {}", timestamp),
+        }
+    }
+}
+
+/// A hash whose preimage must be revealed to spend through a path. For instance a recovery path
+/// may require a secret held by a third party, along with the signatures of the heirs.
+///
+/// As per Miniscript, the preimage must always be 32 bytes long.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Ord, PartialOrd, Hash)]
+pub enum HashLock {
+    Sha256(sha256::Hash),
+    Hash160(hash160::Hash),
+}
+
+impl fmt::Display for HashLock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sha256(h) => write!(f, "sha256({})", h),
+            Self::Hash160(h) => write!(f, "hash160({})", h),
+        }
+    }
+}
+
+impl Serialize for HashLock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl HashLock {
+    // Get the hash lock out of a Miniscript policy node, if it is one.
+    fn from_policy(policy: &SemanticPolicy<descriptor::DescriptorPublicKey>) -> Option<Self> {
+        match policy {
+            SemanticPolicy::Sha256(h) => Some(Self::Sha256(*h)),
+            SemanticPolicy::Hash160(h) => Some(Self::Hash160(*h)),
+            _ => None,
+        }
+    }
+
+    fn into_ms_policy(self) -> ConcretePolicy<descriptor::DescriptorPublicKey> {
+        match self {
+            Self::Sha256(h) => ConcretePolicy::Sha256(h),
+            Self::Hash160(h) => ConcretePolicy::Hash160(h),
+        }
+    }
+
+    /// Whether this is a valid preimage for this hash lock.
+    pub fn is_unlocked_by(&self, preimage: &[u8]) -> bool {
+        preimage.len() == 32
+            && match self {
+                Self::Sha256(h) => sha256::Hash::hash(preimage) == *h,
+                Self::Hash160(h) => hash160::Hash::hash(preimage) == *h,
+            }
+    }
+
+    /// Whether the preimage for this hash lock is present in this PSBT input.
+    pub fn preimage_in(&self, psbt_in: &PsbtIn) -> bool {
+        match self {
+            Self::Sha256(h) => psbt_in.sha256_preimages.contains_key(h),
+            Self::Hash160(h) => psbt_in.hash160_preimages.contains_key(h),
+        }
+    }
+
+    /// Set the preimage for this hash lock in this PSBT input. The caller is expected to have
+    /// checked the preimage is valid using [HashLock::is_unlocked_by].
+    pub fn set_preimage(&self, psbt_in: &mut PsbtIn, preimage: Vec<u8>) {
+        match self {
+            Self::Sha256(h) => psbt_in.sha256_preimages.insert(*h, preimage),
+            Self::Hash160(h) => psbt_in.hash160_preimages.insert(*h, preimage),
+        };
+    }
+}
+
 /// Information about a single spending path in the descriptor.
 #[derive(Debug, Eq, PartialEq, Clone, Ord, PartialOrd, Hash)]
 pub enum PathInfo {
     Single(descriptor::DescriptorPublicKey),
     Multi(usize, Vec<descriptor::DescriptorPublicKey>),
+    /// The keys of this path may only sign along with the preimage of a hash.
+    HashLocked(HashLock, Box<PathInfo>),
+    /// All the keys of this path must sign, through a MuSig2 aggregate key used as the Taproot
+    /// internal key. Only available for the primary path of a Taproot descriptor.
+    Musig(Vec<descriptor::DescriptorPublicKey>),
 }
 
 impl PathInfo {
     /// Get the information about the primary spending path.
     /// Returns None if the policy does not describe the primary spending path of a Liana
-    /// descriptor (that is, a set of keys).
+    /// descriptor (that is, a set of keys, optionally along with a hash lock).
     pub fn from_primary_path(
         policy: SemanticPolicy<descriptor::DescriptorPublicKey>,
     ) -> Result<PathInfo, LianaPolicyError> {
         match policy {
             SemanticPolicy::Key(key) => Ok(PathInfo::Single(key)),
-            SemanticPolicy::Threshold(k, subs) => {
-                let keys: Result<_, LianaPolicyError> = subs
+            SemanticPolicy::Threshold(k, subs)
+                if subs.iter().all(|sub| matches!(sub, SemanticPolicy::Key(_))) =>
+            {
+                let keys = subs
                     .into_iter()
-                    .map(|sub| match sub {
-                        SemanticPolicy::Key(key) => Ok(key),
-                        _ => Err(LianaPolicyError::IncompatibleDesc),
+                    .filter_map(|sub| match sub {
+                        SemanticPolicy::Key(key) => Some(key),
+                        _ => None,
                     })
                     .collect();
-                Ok(PathInfo::Multi(k, keys?))
+                Ok(PathInfo::Multi(k, keys))
             }
-            _ => Err(LianaPolicyError::IncompatibleDesc),
+            policy => match PathInfo::from_locked_path(policy)? {
+                (None, path_info) => Ok(path_info),
+                (Some(_), _) => Err(LianaPolicyError::IncompatibleDesc),
+            },
         }
     }
 
     /// Get the information about the recovery spending path.
     /// Returns None if the policy does not describe the recovery spending path of a Liana
-    /// descriptor (that is, a set of keys after a timelock).
+    /// descriptor (that is, a set of keys after a timelock, optionally along with a hash lock).
     pub fn from_recovery_path(
         policy: SemanticPolicy<descriptor::DescriptorPublicKey>,
-    ) -> Result<(u16, PathInfo), LianaPolicyError> {
-        // The recovery spending path must always be a policy of type `thresh(2, older(x), thresh(n, key1,
-        // key2, ..))`. In the special case n == 1, it is only `thresh(2, older(x), key)`. In the
-        // special case n == len(keys) (i.e. it's an N-of-N multisig), it is normalized as
-        // `thresh(n+1, older(x), key1, key2, ...)`.
-        let (k, subs) = match policy {
-            SemanticPolicy::Threshold(k, subs) => (k, subs),
+    ) -> Result<(RecoveryTimelock, PathInfo), LianaPolicyError> {
+        match PathInfo::from_locked_path(policy)? {
+            (Some(timelock), path_info) => Ok((timelock, path_info)),
+            (None, _) => Err(LianaPolicyError::IncompatibleDesc),
+        }
+    }
+
+    // Get the information about a spending path which is a set of keys along with a timelock
+    // and/or a hash lock.
+    fn from_locked_path(
+        policy: SemanticPolicy<descriptor::DescriptorPublicKey>,
+    ) -> Result<(Option<RecoveryTimelock>, PathInfo), LianaPolicyError> {
+        // Such a spending path must always be a policy of type `thresh(2, older(x), thresh(n,
+        // key1, key2, ..))`. In the special case n == 1, it is only `thresh(2, older(x), key)`. In
+        // the special case n == len(keys) (i.e. it's an N-of-N multisig), it is normalized as
+        // `thresh(n+1, older(x), key1, key2, ...)`. The relative timelock `older(x)` may also be
+        // an absolute one, `after(x)`. A hash lock (`sha256(h)` or `hash160(h)`) may be present
+        // as an additional sub, in which case the timelock may be absent.
+        // In any case all subs are mandatory: if the threshold is not equal to the number of
+        // subs, the conditions can't be mandatory.
+        let subs = match policy {
+            SemanticPolicy::Threshold(k, subs) if k == subs.len() => subs,
             _ => return Err(LianaPolicyError::IncompatibleDesc),
         };
-        if k == 2 && subs.len() == 2 {
-            // The general case (as well as the n == 1 case). The sub that is not the timelock is
-            // of the same form as a primary path.
-            let tl_value = subs
-                .iter()
-                .find_map(|s| match s {
-                    SemanticPolicy::Older(val) => Some(csv_check(val.0)),
-                    _ => None,
-                })
-                .ok_or(LianaPolicyError::IncompatibleDesc)??;
-            let keys_sub = subs
-                .into_iter()
-                .find(is_single_key_or_multisig)
-                .ok_or(LianaPolicyError::IncompatibleDesc)?;
-            PathInfo::from_primary_path(keys_sub).map(|info| (tl_value, info))
-        } else if k == subs.len() && subs.len() > 2 {
-            // The N-of-N case. All subs but the threshold must be keys (if one had been thresh()
-            // of keys it would have been normalized).
-            let mut tl_value = None;
-            let mut keys = Vec::with_capacity(subs.len());
-            for sub in subs {
-                match sub {
-                    SemanticPolicy::Key(key) => keys.push(key),
-                    SemanticPolicy::Older(val) => {
-                        if tl_value.is_some() {
-                            return Err(LianaPolicyError::IncompatibleDesc);
-                        }
-                        tl_value = Some(csv_check(val.0)?);
-                    }
-                    _ => return Err(LianaPolicyError::IncompatibleDesc),
+        let (mut timelock, mut hash_lock, mut keys_sub) = (None, None, None);
+        let mut keys = Vec::with_capacity(subs.len());
+        for sub in subs {
+            if let Some(tl) = RecoveryTimelock::from_policy(&sub) {
+                if timelock.replace(tl?).is_some() {
+                    return Err(LianaPolicyError::IncompatibleDesc);
+                }
+            } else if let Some(lock) = HashLock::from_policy(&sub) {
+                if hash_lock.replace(lock).is_some() {
+                    return Err(LianaPolicyError::IncompatibleDesc);
                 }
+            } else if let SemanticPolicy::Key(key) = sub {
+                keys.push(key);
+            } else if is_single_key_or_multisig(&sub) && keys_sub.is_none() {
+                keys_sub = Some(sub);
+            } else {
+                return Err(LianaPolicyError::IncompatibleDesc);
             }
-            assert!(keys.len() > 1); // At least 3 subs, only one of which may be older().
-            Ok((
-                tl_value.ok_or(LianaPolicyError::IncompatibleDesc)?,
-                PathInfo::Multi(k - 1, keys),
-            ))
-        } else {
-            // If there is less than 2 subs, there can't be both a timelock and keys. If the
-            // threshold is not equal to the number of subs, the timelock can't be mandatory.
-            Err(LianaPolicyError::IncompatibleDesc)
+        }
+
+        // The keys are either a single sub of the same form as a primary path, or directly
+        // part of the subs if it's a single key or an N-of-N multisig (if they had been part of
+        // a thresh() of keys it would have been normalized).
+        let path_info = match (keys_sub, keys.len()) {
+            (Some(sub), 0) => PathInfo::from_primary_path(sub)?,
+            (None, 1) => PathInfo::Single(keys.pop().expect("Length is 1")),
+            (None, n) if n > 1 => PathInfo::Multi(n, keys),
+            _ => return Err(LianaPolicyError::IncompatibleDesc),
+        };
+        let path_info = match hash_lock {
+            Some(hash_lock) => path_info.with_hash_lock(hash_lock),
+            None => path_info,
+        };
+        Ok((timelock, path_info))
+    }
+
+    /// Require the preimage of this hash to spend through this path, in addition to the
+    /// signatures.
+    pub fn with_hash_lock(self, hash_lock: HashLock) -> Self {
+        Self::HashLocked(hash_lock, Box::new(self))
+    }
+
+    /// The hash whose preimage is required to spend through this path, if any.
+    pub fn hash_lock(&self) -> Option<&HashLock> {
+        match self {
+            Self::HashLocked(hash_lock, _) => Some(hash_lock),
+            _ => None,
+        }
+    }
+
+    /// The keys of this path, without the hash lock.
+    pub fn without_hash_lock(&self) -> &PathInfo {
+        match self {
+            Self::HashLocked(_, path_info) => path_info,
+            path_info => path_info,
         }
     }
 
@@ -261,10 +475,13 @@ impl PathInfo {
     pub fn with_added_key(mut self, key: descriptor::DescriptorPublicKey) -> Self {
         match self {
             Self::Single(curr_key) => Self::Multi(1, vec![curr_key, key]),
-            Self::Multi(_, ref mut keys) => {
+            Self::Multi(_, ref mut keys) | Self::Musig(ref mut keys) => {
                 keys.push(key);
                 self
             }
+            Self::HashLocked(hash_lock, path_info) => {
+                Self::HashLocked(hash_lock, Box::new(path_info.with_added_key(key)))
+            }
         }
     }
 
@@ -286,24 +503,14 @@ impl PathInfo {
                 all_origins.insert(fg, der_path);
                 (1, all_origins)
             }
-            PathInfo::Multi(k, keys) => {
-                let mut all_origins: HashMap<_, HashSet<_>> = HashMap::with_capacity(keys.len());
-                for key in keys {
-                    let (fg, der_paths) =
-                        key_origins(key).expect("Must be a multixpub with an origin.");
-                    if let Some(existing_der_paths) = all_origins.get_mut(&fg) {
-                        existing_der_paths.extend(der_paths)
-                    } else {
-                        all_origins.insert(fg, der_paths);
-                    }
-                }
-                (*k, all_origins)
-            }
+            PathInfo::Multi(k, keys) => (*k, keys_origins(keys)),
+            PathInfo::HashLocked(_, path_info) => path_info.thresh_origins(),
+            PathInfo::Musig(keys) => (keys.len(), keys_origins(keys)),
         }
     }
 
     /// Get the spend information for this descriptor based from the list of all pubkeys that
-    /// signed the transaction.
+    /// signed the transaction. If this path is hash-locked, the preimage is assumed missing.
     pub fn spend_info<'a>(
         &self,
         all_pubkeys_signed: impl Iterator<Item = &'a (bip32::Fingerprint, bip32::DerivationPath)>,
@@ -344,6 +551,7 @@ impl PathInfo {
             threshold,
             sigs_count,
             signed_pubkeys,
+            missing_preimage: self.hash_lock().is_some(),
         }
     }
 
@@ -357,10 +565,145 @@ impl PathInfo {
                     .map(|key| ConcretePolicy::Key(key).into())
                     .collect(),
             ),
+            PathInfo::HashLocked(hash_lock, path_info) => ConcretePolicy::And(vec![
+                path_info.into_ms_policy().into(),
+                hash_lock.into_ms_policy().into(),
+            ]),
+            PathInfo::Musig(keys) => ConcretePolicy::Key(
+                musig_aggregate_key(&keys).expect("Checked when creating the policy."),
+            ),
+        }
+    }
+
+    /// The aggregate key of the participants of a MuSig2 path. This is the key used in the
+    /// descriptor. Returns `None` if this isn't a (valid) MuSig2 path.
+    pub fn musig_aggregate_key(&self) -> Option<descriptor::DescriptorPublicKey> {
+        match self {
+            PathInfo::Musig(keys) => musig_aggregate_key(keys),
+            _ => None,
+        }
+    }
+
+    // All the keys of this path, in the order they appear in the policy.
+    pub(super) fn keys(&self) -> Vec<&descriptor::DescriptorPublicKey> {
+        match self {
+            PathInfo::Single(key) => vec![key],
+            PathInfo::Multi(_, keys) | PathInfo::Musig(keys) => keys.iter().collect(),
+            PathInfo::HashLocked(_, path_info) => path_info.keys(),
+        }
+    }
+
+    /// Get a human-readable explanation of this spending path, available behind this timelock
+    /// (or at any time if `None`).
+    pub fn explain(&self, timelock: Option<RecoveryTimelock>) -> PathExplanation {
+        let keys: Vec<_> = self
+            .keys()
+            .into_iter()
+            .map(|key| {
+                let (fingerprint, der_paths) =
+                    key_origins(key).expect("Must be a multixpub with an origin.");
+                let mut derivation_paths: Vec<_> = der_paths.into_iter().collect();
+                derivation_paths.sort();
+                KeyExplanation {
+                    fingerprint,
+                    derivation_paths,
+                }
+            })
+            .collect();
+        let (threshold, _) = self.thresh_origins();
+        let is_musig = matches!(self.without_hash_lock(), PathInfo::Musig(_));
+        let hash_lock = self.hash_lock().copied();
+
+        let fingerprints = keys
+            .iter()
+            .map(|key| key.fingerprint.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut description = if keys.len() == 1 {
+            format!("The key {}", fingerprints)
+        } else if is_musig {
+            format!(
+                "All of the {} keys {}, through a MuSig2 aggregate key,",
+                keys.len(),
+                fingerprints
+            )
+        } else if threshold == keys.len() {
+            format!("All of the {} keys {}", keys.len(), fingerprints)
+        } else {
+            format!("{} of the {} keys {}", threshold, keys.len(), fingerprints)
+        };
+        if let Some(hash_lock) = hash_lock {
+            description += &format!(", along with the preimage of {},", hash_lock);
+        }
+        match timelock {
+            Some(timelock) => description += &format!(" can spend {}.", timelock),
+            None => description += " can spend at any time.",
+        }
+
+        PathExplanation {
+            is_primary: timelock.is_none(),
+            timelock,
+            threshold,
+            keys,
+            is_musig,
+            hash_lock,
+            description,
+            satisfaction: None,
         }
     }
 }
 
+// Get the set of origins of these keys, as a mapping from fingerprint to derivation paths.
+fn keys_origins(
+    keys: &[descriptor::DescriptorPublicKey],
+) -> HashMap<bip32::Fingerprint, HashSet<bip32::DerivationPath>> {
+    let mut all_origins: HashMap<_, HashSet<_>> = HashMap::with_capacity(keys.len());
+    for key in keys {
+        let (fg, der_paths) = key_origins(key).expect("Must be a multixpub with an origin.");
+        if let Some(existing_der_paths) = all_origins.get_mut(&fg) {
+            existing_der_paths.extend(der_paths)
+        } else {
+            all_origins.insert(fg, der_paths);
+        }
+    }
+    all_origins
+}
+
+// Compute the BIP328 aggregate extended key of these MuSig2 participants. The participants' xpubs
+// are aggregated and the multipath derivation, which must be the same for all of them, is applied
+// to the aggregate xpub. The aggregate xpub is given its own fingerprint as origin, to mark it as
+// a key we can't sign for with any signer.
+fn musig_aggregate_key(
+    keys: &[descriptor::DescriptorPublicKey],
+) -> Option<descriptor::DescriptorPublicKey> {
+    let first_key = match keys.first()? {
+        descriptor::DescriptorPublicKey::MultiXPub(xpub) => xpub,
+        _ => return None,
+    };
+    let pubkeys = keys
+        .iter()
+        .map(|key| match key {
+            descriptor::DescriptorPublicKey::MultiXPub(xpub)
+                if xpub.derivation_paths == first_key.derivation_paths
+                    && xpub.wildcard == first_key.wildcard =>
+            {
+                Some(xpub.xkey.public_key)
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let secp = secp256k1::Secp256k1::verification_only();
+    let xkey = musig::aggregate_xpub(&secp, pubkeys, first_key.xkey.network).ok()?;
+    Some(descriptor::DescriptorPublicKey::MultiXPub(
+        descriptor::DescriptorMultiXKey {
+            origin: Some((xkey.fingerprint(), bip32::DerivationPath::master())),
+            xkey,
+            derivation_paths: first_key.derivation_paths.clone(),
+            wildcard: first_key.wildcard,
+        },
+    ))
+}
+
 // See
 // https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#constructing-and-spending-taproot-outputs:
 // > One example of such a point is H =
@@ -448,8 +791,15 @@ fn unspendable_internal_key(
 
 /// A Liana spending policy is one composed of at least two spending paths:
 ///     - A directly available path with any number of keys checks; or
-///     - One or more recovery paths with any number of keys checks, behind increasing relative
-///     timelocks. No two recovery paths may have the same timelock.
+///     - Any number of recovery paths with any number of keys checks, behind increasing relative
+///     timelocks. No two recovery paths may have the same timelock. The relative timelocks may be
+///     expressed in blocks or in 512-second intervals (as per BIP68).
+///     - Any number of recovery paths with any number of keys checks, behind absolute timelocks (a
+///     date or a block height). No two of them may have the same timelock.
+/// There must be at least one recovery path, be it behind a relative or an absolute timelock.
+/// Any of these paths may additionally require the preimage of a hash to be revealed.
+/// Under Taproot, the primary path may instead be a MuSig2 aggregate of several keys, used as the
+/// internal key.
 /// A Liana policy can be created from some settings (the primary and recovery keys, the
 /// timelock(s)) and be used to derive a descriptor. It can also be inferred from a descriptor and
 /// be used to retrieve the settings.
@@ -459,6 +809,8 @@ fn unspendable_internal_key(
 pub struct LianaPolicy {
     pub(super) primary_path: PathInfo,
     pub(super) recovery_paths: BTreeMap<u16, PathInfo>,
+    pub(super) time_recovery_paths: BTreeMap<u16, PathInfo>,
+    pub(super) absolute_recovery_paths: BTreeMap<u32, PathInfo>,
     is_taproot: bool,
 }
 
@@ -467,15 +819,20 @@ impl LianaPolicy {
     fn _new(
         primary_path: PathInfo,
         recovery_paths: BTreeMap<u16, PathInfo>,
+        time_recovery_paths: BTreeMap<u16, PathInfo>,
+        absolute_recovery_paths: BTreeMap<u32, PathInfo>,
         is_taproot: bool,
     ) -> Result<LianaPolicy, LianaPolicyError> {
-        if recovery_paths.is_empty() {
+        if recovery_paths.is_empty()
+            && time_recovery_paths.is_empty()
+            && absolute_recovery_paths.is_empty()
+        {
             return Err(LianaPolicyError::MissingRecoveryPath);
         }
 
         // We require the locktime to:
         //  - not be disabled
-        //  - be in number of blocks
+        //  - be in number of blocks, or of 512-second intervals
         //  - be 'clean' / minimal, ie all bits without consensus meaning should be 0
         //  - be positive (Miniscript requires it not to be 0)
         //
@@ -483,6 +840,32 @@ impl LianaPolicy {
         if recovery_paths.contains_key(&0) {
             return Err(LianaPolicyError::InsaneTimelock(0));
         }
+        if time_recovery_paths.contains_key(&0) {
+            return Err(LianaPolicyError::InsaneTimelock(
+                SEQUENCE_LOCKTIME_TYPE_FLAG,
+            ));
+        }
+        for timelock in absolute_recovery_paths.keys() {
+            cltv_check(*timelock)?;
+        }
+
+        // A MuSig2 aggregate key is used as the internal key of a Taproot descriptor. It may only
+        // be used for the primary path, without a hash lock.
+        let is_musig = |path: &PathInfo| matches!(path.without_hash_lock(), PathInfo::Musig(..));
+        if recovery_paths
+            .values()
+            .chain(time_recovery_paths.values())
+            .chain(absolute_recovery_paths.values())
+            .any(is_musig)
+            || (primary_path.hash_lock().is_some() && is_musig(&primary_path))
+        {
+            return Err(LianaPolicyError::InvalidMusig);
+        }
+        if let PathInfo::Musig(ref keys) = primary_path {
+            if !is_taproot || keys.len() < 2 || primary_path.musig_aggregate_key().is_none() {
+                return Err(LianaPolicyError::InvalidMusig);
+            }
+        }
 
         // Check all keys are valid according to our standard (this checks all are multipath keys).
         // Note while the Miniscript compiler does check for duplicate, it does so at the
@@ -490,14 +873,16 @@ impl LianaPolicy {
         // ourselves here.
         let spending_paths = recovery_paths
             .values()
+            .chain(time_recovery_paths.values())
+            .chain(absolute_recovery_paths.values())
             .chain(std::iter::once(&primary_path));
         let mut key_checker = DescKeyChecker::new();
         for path in spending_paths {
-            match path {
+            match path.without_hash_lock() {
                 PathInfo::Single(ref key) => {
                     let _ = key_checker.check(key)?;
                 }
-                PathInfo::Multi(_, ref keys) => {
+                PathInfo::Multi(_, ref keys) | PathInfo::Musig(ref keys) => {
                     // Record the origins of the keys for this spending path. If any two keys share
                     // the same origin, they are from the same signer. We restrict using a signer
                     // more than once within a single spending path as it can lead to surprising
@@ -514,6 +899,7 @@ impl LianaPolicy {
                         origin_fingerprints.insert(fg);
                     }
                 }
+                PathInfo::HashLocked(..) => return Err(LianaPolicyError::NestedHashLock),
             }
         }
 
@@ -521,9 +907,19 @@ impl LianaPolicy {
         let policy = LianaPolicy {
             primary_path,
             recovery_paths,
+            time_recovery_paths,
+            absolute_recovery_paths,
             is_taproot,
         };
-        policy.clone().into_multipath_descriptor_fallible()?;
+        let desc = policy.clone().into_multipath_descriptor_fallible()?;
+        // The compiler must have picked the MuSig2 aggregate key as the internal key.
+        if let (Some(agg_key), descriptor::Descriptor::Tr(tr_desc)) =
+            (policy.primary_path.musig_aggregate_key(), desc)
+        {
+            if *tr_desc.internal_key() != agg_key {
+                return Err(LianaPolicyError::InvalidMusig);
+            }
+        }
         Ok(policy)
     }
 
@@ -532,7 +928,13 @@ impl LianaPolicy {
         primary_path: PathInfo,
         recovery_paths: BTreeMap<u16, PathInfo>,
     ) -> Result<LianaPolicy, LianaPolicyError> {
-        Self::_new(primary_path, recovery_paths, /* is_taproot = */ true)
+        Self::_new(
+            primary_path,
+            recovery_paths,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            /* is_taproot = */ true,
+        )
     }
 
     /// Create a new Liana policy for use under a P2WSH context.
@@ -540,7 +942,91 @@ impl LianaPolicy {
         primary_path: PathInfo,
         recovery_paths: BTreeMap<u16, PathInfo>,
     ) -> Result<LianaPolicy, LianaPolicyError> {
-        Self::_new(primary_path, recovery_paths, /* is_taproot = */ false)
+        Self::_new(
+            primary_path,
+            recovery_paths,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            /* is_taproot = */ false,
+        )
+    }
+
+    /// Create a new Liana policy for use under a Taproot context, with recovery paths behind
+    /// relative timelocks in blocks and recovery paths behind relative timelocks in 512-second
+    /// intervals (BIP68 time-based relative locktimes). Either mapping may be empty, but not both.
+    pub fn new_with_time_recovery_paths(
+        primary_path: PathInfo,
+        recovery_paths: BTreeMap<u16, PathInfo>,
+        time_recovery_paths: BTreeMap<u16, PathInfo>,
+    ) -> Result<LianaPolicy, LianaPolicyError> {
+        Self::_new(
+            primary_path,
+            recovery_paths,
+            time_recovery_paths,
+            BTreeMap::new(),
+            /* is_taproot = */ true,
+        )
+    }
+
+    /// Same as [LianaPolicy::new_with_time_recovery_paths] for use under a P2WSH context.
+    pub fn new_legacy_with_time_recovery_paths(
+        primary_path: PathInfo,
+        recovery_paths: BTreeMap<u16, PathInfo>,
+        time_recovery_paths: BTreeMap<u16, PathInfo>,
+    ) -> Result<LianaPolicy, LianaPolicyError> {
+        Self::_new(
+            primary_path,
+            recovery_paths,
+            time_recovery_paths,
+            BTreeMap::new(),
+            /* is_taproot = */ false,
+        )
+    }
+
+    /// Create a new Liana policy for use under a Taproot context, whose recovery paths are all
+    /// behind absolute timelocks. See [LianaPolicy::with_absolute_recovery_paths] for the format of
+    /// the mapping.
+    pub fn new_with_absolute_recovery_paths(
+        primary_path: PathInfo,
+        absolute_recovery_paths: BTreeMap<u32, PathInfo>,
+    ) -> Result<LianaPolicy, LianaPolicyError> {
+        Self::_new(
+            primary_path,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            absolute_recovery_paths,
+            /* is_taproot = */ true,
+        )
+    }
+
+    /// Same as [LianaPolicy::new_with_absolute_recovery_paths] for use under a P2WSH context.
+    pub fn new_legacy_with_absolute_recovery_paths(
+        primary_path: PathInfo,
+        absolute_recovery_paths: BTreeMap<u32, PathInfo>,
+    ) -> Result<LianaPolicy, LianaPolicyError> {
+        Self::_new(
+            primary_path,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            absolute_recovery_paths,
+            /* is_taproot = */ false,
+        )
+    }
+
+    /// Add recovery paths behind absolute timelocks to this policy. They are given as a mapping
+    /// from the nLockTime value (a block height if below 500'000'000, a UNIX timestamp otherwise)
+    /// to the keys which can spend after it.
+    pub fn with_absolute_recovery_paths(
+        self,
+        absolute_recovery_paths: BTreeMap<u32, PathInfo>,
+    ) -> Result<LianaPolicy, LianaPolicyError> {
+        Self::_new(
+            self.primary_path,
+            self.recovery_paths,
+            self.time_recovery_paths,
+            absolute_recovery_paths,
+            self.is_taproot,
+        )
     }
 
     /// Create a Liana policy from a descriptor. This will check the descriptor is correctly formed
@@ -600,7 +1086,9 @@ impl LianaPolicy {
 
         // Fetch all spending paths' semantic policies. The primary path is identified as the only
         // one that isn't timelocked.
-        let (mut primary_path, mut recovery_paths) = (None::<PathInfo>, BTreeMap::new());
+        let (mut primary_path, mut recovery_paths, mut time_recovery_paths) =
+            (None::<PathInfo>, BTreeMap::new(), BTreeMap::new());
+        let mut absolute_recovery_paths = BTreeMap::new();
         for sub in subs {
             // This is a (multi)key check. It must be the primary path.
             if is_single_key_or_multisig(&sub) {
@@ -609,46 +1097,219 @@ impl LianaPolicy {
                 // pk(A), pk(B)), thresh(2, older(42), pk(C)))` to `thresh(1, pk(A), pk(B),
                 // thresh(2, older(42), pk(C)))`.
                 if let Some(prim_path) = primary_path {
-                    if let SemanticPolicy::Key(key) = sub {
-                        primary_path = Some(prim_path.with_added_key(key));
-                    } else {
-                        return Err(LianaPolicyError::IncompatibleDesc);
+                    match sub {
+                        SemanticPolicy::Key(key) if prim_path.hash_lock().is_none() => {
+                            primary_path = Some(prim_path.with_added_key(key));
+                        }
+                        _ => return Err(LianaPolicyError::IncompatibleDesc),
                     }
                 } else {
                     primary_path = Some(PathInfo::from_primary_path(sub)?);
                 }
             } else {
                 // If it's not a simple (multi)key check, it must be (one of) the timelocked
-                // recovery path(s).
-                let (timelock, path_info) = PathInfo::from_recovery_path(sub)?;
-                if recovery_paths.contains_key(&timelock) {
+                // recovery path(s), or a primary path behind a hash lock.
+                let previous = match PathInfo::from_locked_path(sub)? {
+                    (None, path_info) => primary_path.replace(path_info),
+                    (Some(RecoveryTimelock::Relative(timelock)), path_info) => {
+                        recovery_paths.insert(timelock, path_info)
+                    }
+                    (Some(RecoveryTimelock::RelativeTime(timelock)), path_info) => {
+                        time_recovery_paths.insert(timelock, path_info)
+                    }
+                    (Some(RecoveryTimelock::Absolute(timelock)), path_info) => {
+                        absolute_recovery_paths.insert(timelock, path_info)
+                    }
+                };
+                if previous.is_some() {
                     return Err(LianaPolicyError::IncompatibleDesc);
                 }
-                recovery_paths.insert(timelock, path_info);
             }
         }
 
         // Use the constructor for sanity checking the keys and the Miniscript policy. Note this
         // makes sure the recovery paths mapping isn't empty, too.
         let prim_path = primary_path.ok_or(LianaPolicyError::IncompatibleDesc)?;
-        LianaPolicy::_new(prim_path, recovery_paths, is_taproot)
+        LianaPolicy::_new(
+            prim_path,
+            recovery_paths,
+            time_recovery_paths,
+            absolute_recovery_paths,
+            is_taproot,
+        )
+    }
+
+    /// Use a MuSig2 aggregate of the keys of these participants as the primary path. This is
+    /// necessary when inferring the policy from a descriptor, which only contains the aggregate
+    /// key.
+    pub fn with_musig_primary_path(
+        self,
+        participants: Vec<descriptor::DescriptorPublicKey>,
+    ) -> Result<LianaPolicy, LianaPolicyError> {
+        let primary_path = PathInfo::Musig(participants);
+        let agg_key = primary_path
+            .musig_aggregate_key()
+            .ok_or(LianaPolicyError::IncompatibleDesc)?;
+        if PathInfo::Single(agg_key) != self.primary_path {
+            return Err(LianaPolicyError::IncompatibleDesc);
+        }
+        Self::_new(
+            primary_path,
+            self.recovery_paths,
+            self.time_recovery_paths,
+            self.absolute_recovery_paths,
+            self.is_taproot,
+        )
     }
 
     pub fn primary_path(&self) -> &PathInfo {
         &self.primary_path
     }
 
-    /// Timelocks and path info of the recovery paths. Note we guarantee this mapping is never
-    /// empty, as there is always at least one recovery path.
+    /// Timelocks (in blocks) and path info of the recovery paths. Note this mapping may only be
+    /// empty if there is at least one recovery path behind a time-based relative timelock or an
+    /// absolute timelock.
     pub fn recovery_paths(&self) -> &BTreeMap<u16, PathInfo> {
-        assert!(!self.recovery_paths.is_empty());
         &self.recovery_paths
     }
 
+    /// Timelocks (in 512-second intervals) and path info of the recovery paths behind a
+    /// time-based relative timelock. This mapping may be empty.
+    pub fn time_recovery_paths(&self) -> &BTreeMap<u16, PathInfo> {
+        &self.time_recovery_paths
+    }
+
+    /// Absolute timelocks and path info of the recovery paths available after a given date or
+    /// block height. This mapping may be empty.
+    pub fn absolute_recovery_paths(&self) -> &BTreeMap<u32, PathInfo> {
+        &self.absolute_recovery_paths
+    }
+
+    /// All the spending paths of this policy along with their timelock: the primary path first,
+    /// then the recovery paths behind a relative timelock in blocks, then those behind a relative
+    /// time-based timelock and finally those behind an absolute timelock.
+    pub fn all_paths(&self) -> impl Iterator<Item = (Option<RecoveryTimelock>, &PathInfo)> {
+        std::iter::once((None, &self.primary_path))
+            .chain(
+                self.recovery_paths
+                    .iter()
+                    .map(|(tl, path)| (Some(RecoveryTimelock::Relative(*tl)), path)),
+            )
+            .chain(
+                self.time_recovery_paths
+                    .iter()
+                    .map(|(tl, path)| (Some(RecoveryTimelock::RelativeTime(*tl)), path)),
+            )
+            .chain(
+                self.absolute_recovery_paths
+                    .iter()
+                    .map(|(tl, path)| (Some(RecoveryTimelock::Absolute(*tl)), path)),
+            )
+    }
+
+    /// Get advisory warnings about risky, but valid, settings in this policy. See
+    /// [PolicyWarning] for the checks performed.
+    pub fn lint(&self) -> Vec<PolicyWarning> {
+        let mut warnings = Vec::new();
+
+        // Check the timelocks of the recovery paths.
+        for timelock in self.all_paths().filter_map(|(timelock, _)| timelock) {
+            let is_short = match timelock {
+                RecoveryTimelock::Relative(blocks) => blocks < MIN_RECOMMENDED_BLOCKS,
+                RecoveryTimelock::RelativeTime(intervals) => {
+                    u32::from(intervals) * 512 < MIN_RECOMMENDED_SECS
+                }
+                // This can't be checked without knowing the current date.
+                RecoveryTimelock::Absolute(_) => false,
+            };
+            if is_short {
+                warnings.push(PolicyWarning::ShortTimelock { timelock });
+            }
+        }
+
+        // Check whether the same devices are used both in the primary path and a recovery path.
+        let primary_fgs: HashSet<_> = self
+            .primary_path
+            .keys()
+            .into_iter()
+            .filter_map(|key| key_origins(key).map(|(fg, _)| fg))
+            .collect();
+        for (timelock, path) in self.all_paths() {
+            if let Some(timelock) = timelock {
+                let mut shared: Vec<_> = path
+                    .keys()
+                    .into_iter()
+                    .filter_map(|key| key_origins(key).map(|(fg, _)| fg))
+                    .filter(|fg| primary_fgs.contains(fg))
+                    .collect();
+                shared.sort();
+                shared.dedup();
+                warnings.extend(shared.into_iter().map(|fingerprint| {
+                    PolicyWarning::SharedDevice {
+                        fingerprint,
+                        timelock,
+                    }
+                }));
+            }
+        }
+
+        // Check the multisig paths which require all their keys.
+        for (timelock, path) in self.all_paths() {
+            let (threshold, _) = path.thresh_origins();
+            if threshold > 1 && threshold == path.keys().len() {
+                warnings.push(PolicyWarning::NoRedundancy {
+                    timelock,
+                    threshold,
+                });
+            }
+        }
+
+        // Check the derivation paths of the keys, and that they are all for the same network.
+        let mut networks = HashSet::new();
+        let mut checked_keys = HashSet::new();
+        for (_, path) in self.all_paths() {
+            for key in path.keys() {
+                if let descriptor::DescriptorPublicKey::MultiXPub(xpub) = key {
+                    networks.insert(xpub.xkey.network);
+                    if let Some((fingerprint, der_path)) = &xpub.origin {
+                        if !is_standard_origin(der_path, xpub.xkey.network)
+                            && checked_keys.insert((*fingerprint, der_path.clone()))
+                        {
+                            warnings.push(PolicyWarning::UnusualDerivationPath {
+                                fingerprint: *fingerprint,
+                                derivation_path: der_path.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        if networks.len() > 1 {
+            warnings.push(PolicyWarning::MixedNetworks);
+        }
+
+        warnings
+    }
+
+    /// Get a human-readable explanation of all the spending paths of this policy, in the order
+    /// of [LianaPolicy::all_paths]. The cost of satisfying each path depends on the compiled
+    /// descriptor and is therefore not set. See `LianaDescriptor::explain`.
+    pub fn explain(&self) -> PolicyExplanation {
+        PolicyExplanation {
+            is_taproot: self.is_taproot,
+            paths: self
+                .all_paths()
+                .map(|(timelock, path)| path.explain(timelock))
+                .collect(),
+        }
+    }
+
     fn into_policy(self) -> miniscript::policy::Concrete<descriptor::DescriptorPublicKey> {
         let LianaPolicy {
             primary_path,
             recovery_paths,
+            time_recovery_paths,
+            absolute_recovery_paths,
             ..
         } = self;
 
@@ -656,16 +1317,40 @@ impl LianaPolicy {
         let primary_keys = primary_path.into_ms_policy();
 
         // Incrementally create the top-level policy using all recovery paths.
-        assert!(!recovery_paths.is_empty());
-        recovery_paths
+        assert!(
+            !recovery_paths.is_empty()
+                || !time_recovery_paths.is_empty()
+                || !absolute_recovery_paths.is_empty()
+        );
+        let relative_paths = recovery_paths.into_iter().map(|(timelock, path_info)| {
+            (
+                ConcretePolicy::Older(Sequence::from_height(timelock)),
+                path_info,
+            )
+        });
+        let time_paths = time_recovery_paths
+            .into_iter()
+            .map(|(timelock, path_info)| {
+                (
+                    ConcretePolicy::Older(Sequence::from_512_second_intervals(timelock)),
+                    path_info,
+                )
+            });
+        let absolute_paths = absolute_recovery_paths
             .into_iter()
-            .fold(primary_keys, |tl_policy, (timelock, path_info)| {
-                let timelock = ConcretePolicy::Older(Sequence::from_height(timelock));
+            .map(|(timelock, path_info)| {
+                let timelock = AbsLockTime::from(absolute::LockTime::from_consensus(timelock));
+                (ConcretePolicy::After(timelock), path_info)
+            });
+        relative_paths.chain(time_paths).chain(absolute_paths).fold(
+            primary_keys,
+            |tl_policy, (timelock, path_info)| {
                 let keys = path_info.into_ms_policy();
                 let recovery_branch = ConcretePolicy::And(vec![keys.into(), timelock.into()]);
                 // We assume the larger the timelock the less likely a branch would be used.
                 ConcretePolicy::Or(vec![(99, tl_policy.into()), (1, recovery_branch.into())])
-            })
+            },
+        )
     }
 
     fn into_multipath_descriptor_fallible(
@@ -751,6 +1436,8 @@ pub struct PathSpendInfo {
     /// The keys for which a signature was provided and the number (always >=1) of
     /// signatures provided for this key.
     pub signed_pubkeys: HashMap<bip32::Fingerprint, usize>,
+    /// Whether this path requires the preimage of a hash which wasn't provided.
+    pub missing_preimage: bool,
 }
 
 /// Information about a partial spend of Liana coins
@@ -761,6 +1448,12 @@ pub struct PartialSpendInfo {
     /// Number of signatures present for the recovery path, only present for the recovery paths
     /// that are available.
     pub(super) recovery_paths: BTreeMap<u16, PathSpendInfo>,
+    /// Number of signatures present for the recovery paths behind a time-based relative
+    /// timelock, only present for those that are available.
+    pub(super) time_recovery_paths: BTreeMap<u16, PathSpendInfo>,
+    /// Number of signatures present for the recovery paths behind an absolute timelock, only
+    /// present for those that are available.
+    pub(super) absolute_recovery_paths: BTreeMap<u32, PathSpendInfo>,
 }
 
 impl PartialSpendInfo {
@@ -774,6 +1467,178 @@ impl PartialSpendInfo {
     pub fn recovery_paths(&self) -> &BTreeMap<u16, PathSpendInfo> {
         &self.recovery_paths
     }
+
+    /// Get the number of signatures present for each recovery path behind a time-based relative
+    /// timelock. Only present for available paths.
+    pub fn time_recovery_paths(&self) -> &BTreeMap<u16, PathSpendInfo> {
+        &self.time_recovery_paths
+    }
+
+    /// Get the number of signatures present for each recovery path behind an absolute timelock.
+    /// Only present for available paths.
+    pub fn absolute_recovery_paths(&self) -> &BTreeMap<u32, PathSpendInfo> {
+        &self.absolute_recovery_paths
+    }
+}
+
+// Under this number of blocks (about a month) a relative timelock is considered short.
+const MIN_RECOMMENDED_BLOCKS: u16 = 30 * 144;
+// Under this number of seconds (a month) a time-based relative timelock is considered short.
+const MIN_RECOMMENDED_SECS: u32 = 30 * 24 * 3600;
+
+// Whether this origin derivation path is one we'd expect for a key in a Liana descriptor. That is
+// a BIP48 (script type 2' or 3') or a BIP87 account, for the coin type of this network.
+fn is_standard_origin(der_path: &bip32::DerivationPath, network: bitcoin::Network) -> bool {
+    let coin_type = if network == bitcoin::Network::Bitcoin {
+        0
+    } else {
+        1
+    };
+    let hardened = |index: &bip32::ChildNumber| index.is_hardened();
+    match der_path.as_ref() {
+        [purpose, coin, account, script_type]
+            if *purpose == bip32::ChildNumber::Hardened { index: 48 } =>
+        {
+            *coin == bip32::ChildNumber::Hardened { index: coin_type }
+                && hardened(account)
+                && (*script_type == bip32::ChildNumber::Hardened { index: 2 }
+                    || *script_type == bip32::ChildNumber::Hardened { index: 3 })
+        }
+        [purpose, coin, account] if *purpose == bip32::ChildNumber::Hardened { index: 87 } => {
+            *coin == bip32::ChildNumber::Hardened { index: coin_type } && hardened(account)
+        }
+        _ => false,
+    }
+}
+
+/// An advisory warning about a valid, but risky, Liana policy.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PolicyWarning {
+    /// A recovery path becomes available less than a month after a coin is received. The coins
+    /// must be refreshed very often to prevent it from becoming available.
+    ShortTimelock { timelock: RecoveryTimelock },
+    /// Keys from the same device are used in both the primary path and a recovery path. Losing
+    /// this device would affect both.
+    SharedDevice {
+        fingerprint: bip32::Fingerprint,
+        timelock: RecoveryTimelock,
+    },
+    /// All the keys of a multisig path are required to spend through it, losing any of them
+    /// makes the path unusable. `timelock` is `None` for the primary path.
+    NoRedundancy {
+        timelock: Option<RecoveryTimelock>,
+        threshold: usize,
+    },
+    /// A key was derived from its master key at a non-standard path, which may make it hard to
+    /// recover with another wallet.
+    UnusualDerivationPath {
+        fingerprint: bip32::Fingerprint,
+        derivation_path: bip32::DerivationPath,
+    },
+    /// The extended keys aren't all for the same network.
+    MixedNetworks,
+}
+
+impl fmt::Display for PolicyWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ShortTimelock { timelock } => write!(
+                f,
+                "A recovery path is available {}, less than a month after receiving coins. \
+                 They will need to be refreshed very often.",
+                timelock
+            ),
+            Self::SharedDevice {
+                fingerprint,
+                timelock,
+            } => write!(
+                f,
+                "Keys from the device {} are used both in the primary path and in the recovery \
+                 path available {}.",
+                fingerprint, timelock
+            ),
+            Self::NoRedundancy {
+                timelock: None,
+                threshold,
+            } => write!(
+                f,
+                "All {} keys of the primary path are needed to spend: losing any of them \
+                 makes it unusable.",
+                threshold
+            ),
+            Self::NoRedundancy {
+                timelock: Some(timelock),
+                threshold,
+            } => write!(
+                f,
+                "All {} keys of the recovery path available {} are needed to spend: losing any \
+                 of them makes it unusable.",
+                threshold, timelock
+            ),
+            Self::UnusualDerivationPath {
+                fingerprint,
+                derivation_path,
+            } => write!(
+                f,
+                "The key from the device {} is at the unusual derivation path {}.",
+                fingerprint, derivation_path
+            ),
+            Self::MixedNetworks => write!(f, "The keys aren't all for the same network."),
+        }
+    }
+}
+
+/// A key which may sign for a spending path.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+pub struct KeyExplanation {
+    /// Fingerprint of the master extended key.
+    pub fingerprint: bip32::Fingerprint,
+    /// Derivation paths from the master key, for receive and change addresses (without the
+    /// wildcard step).
+    pub derivation_paths: Vec<bip32::DerivationPath>,
+}
+
+/// The cost of spending a coin through a spending path.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize)]
+pub struct SatisfactionCost {
+    /// Maximum size of the witness satisfying this path, in weight units.
+    pub sat_weight: usize,
+    /// Maximum size of a whole transaction input spending through this path, in virtual bytes.
+    pub input_vbytes: usize,
+    /// The feerate used to compute the fee, in sats/vb.
+    pub feerate: u64,
+    /// The fee for this input at this feerate, in satoshis.
+    pub fee: u64,
+}
+
+/// A human-readable explanation of a spending path.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+pub struct PathExplanation {
+    /// Whether this is the primary path, available at any time.
+    pub is_primary: bool,
+    /// The timelock behind which this path is available, `None` for the primary path.
+    pub timelock: Option<RecoveryTimelock>,
+    /// The number of signatures required to spend through this path.
+    pub threshold: usize,
+    /// The keys which may sign for this path.
+    pub keys: Vec<KeyExplanation>,
+    /// Whether the keys sign through a MuSig2 aggregate key.
+    pub is_musig: bool,
+    /// The hash whose preimage must be revealed to spend through this path, if any.
+    pub hash_lock: Option<HashLock>,
+    /// A sentence describing this path.
+    pub description: String,
+    /// The cost of spending a coin through this path, if known.
+    pub satisfaction: Option<SatisfactionCost>,
+}
+
+/// A human-readable explanation of a Liana policy.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+pub struct PolicyExplanation {
+    pub is_taproot: bool,
+    /// All the spending paths, the primary path first.
+    pub paths: Vec<PathExplanation>,
 }
 
 #[cfg(test)]
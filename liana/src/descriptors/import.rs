@@ -0,0 +1,501 @@
+//! Import
+//!
+//! Parsing of wallet configurations exported by other coordinators: raw descriptors (as exported
+//! by Sparrow), Sparrow/Specter JSON wallet exports, Coldcard multisig configuration files,
+//! BIP129 (BSMS) descriptor records and BIP388 wallet policies.
+
+use crate::descriptors::{LianaDescError, LianaDescriptor, WalletPolicy, WalletPolicyError};
+
+use miniscript::bitcoin::{address::NetworkUnchecked, bip32, secp256k1, Address};
+
+use std::{error, fmt, str::FromStr};
+
+/// The path restrictions of a BSMS descriptor record compatible with Liana descriptors.
+pub(crate) const BSMS_PATH_RESTRICTIONS: &str = "/0/*,/1/*";
+pub(crate) const BSMS_NO_PATH_RESTRICTIONS: &str = "No path restrictions";
+
+/// The format of an imported wallet configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A descriptor string, possibly along with comments (as in Sparrow's export).
+    Descriptor,
+    /// A JSON wallet export from Sparrow or Specter.
+    WalletJson,
+    /// A Coldcard multisig configuration text file.
+    ColdcardMultisig,
+    /// A BIP129 descriptor record.
+    Bsms,
+    /// A BIP388 wallet policy, serialized as JSON.
+    WalletPolicy,
+}
+
+impl ImportFormat {
+    /// Guess the format of a wallet configuration from its content.
+    pub fn detect(content: &str) -> ImportFormat {
+        let content = content.trim();
+        if content.starts_with('{') && content.contains("\"descriptor_template\"") {
+            ImportFormat::WalletPolicy
+        } else if content.starts_with('{') {
+            ImportFormat::WalletJson
+        } else if content.starts_with("BSMS") {
+            ImportFormat::Bsms
+        } else if content
+            .lines()
+            .any(|l| l.trim().to_lowercase().starts_with("policy:"))
+        {
+            ImportFormat::ColdcardMultisig
+        } else {
+            ImportFormat::Descriptor
+        }
+    }
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Descriptor => write!(f, "descriptor"),
+            Self::WalletJson => write!(f, "Sparrow/Specter wallet export"),
+            Self::ColdcardMultisig => write!(f, "Coldcard multisig configuration"),
+            Self::Bsms => write!(f, "BSMS descriptor record"),
+            Self::WalletPolicy => write!(f, "BIP388 wallet policy"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Json(String),
+    /// The content doesn't follow the detected format.
+    Malformed(ImportFormat, String),
+    /// The parts of the wallet configuration which can't be expressed as a Liana policy.
+    Unsupported(Vec<String>),
+    Descriptor(LianaDescError),
+    WalletPolicy(WalletPolicyError),
+    /// The first address of a BSMS record doesn't match the descriptor.
+    AddressMismatch {
+        expected: String,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "Invalid JSON wallet export: {}", e),
+            Self::Malformed(format, e) => write!(f, "Invalid {}: {}", format, e),
+            Self::Unsupported(parts) => write!(
+                f,
+                "This wallet can't be used with Liana. Unsupported: {}.",
+                parts.join("; ")
+            ),
+            Self::Descriptor(e) => write!(f, "{}", e),
+            Self::WalletPolicy(e) => write!(f, "{}", e),
+            Self::AddressMismatch { expected } => write!(
+                f,
+                "The first address of the descriptor record ({}) doesn't match the descriptor.",
+                expected
+            ),
+        }
+    }
+}
+
+impl error::Error for ImportError {}
+
+/// Import a wallet configuration in any of the supported formats. See [`ImportFormat`].
+pub fn import_wallet(content: &str) -> Result<LianaDescriptor, ImportError> {
+    match ImportFormat::detect(content) {
+        ImportFormat::Descriptor => from_descriptor_text(content),
+        ImportFormat::WalletJson => from_wallet_json(content),
+        ImportFormat::ColdcardMultisig => from_coldcard_config(content),
+        ImportFormat::Bsms => from_bsms_record(content),
+        ImportFormat::WalletPolicy => from_wallet_policy(content),
+    }
+}
+
+/// Import a BIP388 wallet policy serialized as JSON. See [`WalletPolicy`].
+pub fn from_wallet_policy(content: &str) -> Result<LianaDescriptor, ImportError> {
+    let policy = WalletPolicy::from_str(content)
+        .map_err(|e| ImportError::Malformed(ImportFormat::WalletPolicy, e.to_string()))?;
+    LianaDescriptor::from_wallet_policy(&policy).map_err(|e| match e {
+        WalletPolicyError::Descriptor(e) => ImportError::Descriptor(e),
+        e => ImportError::WalletPolicy(e),
+    })
+}
+
+/// Import a descriptor from a text file. Empty lines and comments are ignored, and the first
+/// descriptor is used.
+pub fn from_descriptor_text(content: &str) -> Result<LianaDescriptor, ImportError> {
+    let desc_str = content
+        .lines()
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or_else(|| {
+            ImportError::Malformed(ImportFormat::Descriptor, "no descriptor found".to_string())
+        })?;
+    parse_descriptor(&normalize_descriptor(desc_str))
+}
+
+/// Import a JSON wallet export from Sparrow or Specter. Only the `descriptor` field is used.
+pub fn from_wallet_json(content: &str) -> Result<LianaDescriptor, ImportError> {
+    let json: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| ImportError::Json(e.to_string()))?;
+    let desc_str = json
+        .get("descriptor")
+        .and_then(|d| d.as_str())
+        .ok_or_else(|| ImportError::Json("missing 'descriptor' field".to_string()))?;
+    parse_descriptor(&normalize_descriptor(desc_str))
+}
+
+/// Import a Coldcard multisig configuration file. Those describe plain multisig wallets, which
+/// always lack a recovery path, but we still parse them to tell the user what isn't supported.
+pub fn from_coldcard_config(content: &str) -> Result<LianaDescriptor, ImportError> {
+    let malformed = |e: String| ImportError::Malformed(ImportFormat::ColdcardMultisig, e);
+
+    let (mut policy, mut format, mut derivation) = (None, None, None);
+    // A derivation path may be specified for the next key only, as a comment.
+    let mut next_derivation = None;
+    let mut keys = Vec::new();
+    for line in content.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some((k, v)) = comment.split_once(':') {
+                if k.trim().eq_ignore_ascii_case("derivation") {
+                    next_derivation = Some(parse_coldcard_path(v.trim()).map_err(malformed)?);
+                }
+            }
+            continue;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| malformed(format!("unexpected line '{}'", line)))?;
+        let (key, value) = (key.trim(), value.trim());
+        match key.to_lowercase().as_str() {
+            "name" => {}
+            "policy" => policy = Some(value.to_string()),
+            "format" => format = Some(value.to_uppercase()),
+            "derivation" => derivation = Some(parse_coldcard_path(value).map_err(malformed)?),
+            _ => {
+                let fingerprint = bip32::Fingerprint::from_str(key)
+                    .map_err(|_| malformed(format!("unexpected line '{}'", line)))?;
+                let path = next_derivation
+                    .take()
+                    .or_else(|| derivation.clone())
+                    .unwrap_or_default();
+                keys.push(format!("[{}{}]{}/<0;1>/*", fingerprint, path, value));
+            }
+        }
+    }
+
+    let policy = policy.ok_or_else(|| malformed("missing 'Policy' line".to_string()))?;
+    let (thresh, n_keys) = policy
+        .split_once(" of ")
+        .or_else(|| policy.split_once('/'))
+        .and_then(|(m, n)| {
+            Some((
+                m.trim().parse::<usize>().ok()?,
+                n.trim().parse::<usize>().ok()?,
+            ))
+        })
+        .ok_or_else(|| malformed(format!("invalid policy '{}'", policy)))?;
+    if n_keys != keys.len() {
+        return Err(malformed(format!(
+            "the policy has {} keys but {} are specified",
+            n_keys,
+            keys.len()
+        )));
+    }
+
+    let multi = format!("sortedmulti({},{})", thresh, keys.join(","));
+    // The default address format of Coldcard multisig wallets is P2SH.
+    let desc_str = match format.as_deref().unwrap_or("P2SH") {
+        "P2WSH" => format!("wsh({})", multi),
+        "P2SH-P2WSH" | "P2WSH-P2SH" => format!("sh(wsh({}))", multi),
+        "P2SH" => format!("sh({})", multi),
+        f => return Err(malformed(format!("unknown address format '{}'", f))),
+    };
+    parse_descriptor(&desc_str)
+}
+
+/// Import a BIP129 descriptor record (the output of the second round of a BSMS setup). The first
+/// address of the record is checked against the descriptor.
+pub fn from_bsms_record(content: &str) -> Result<LianaDescriptor, ImportError> {
+    let malformed = |e: &str| ImportError::Malformed(ImportFormat::Bsms, e.to_string());
+
+    let mut lines = content.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+    match lines.next() {
+        Some("BSMS 1.0") => {}
+        _ => {
+            return Err(malformed(
+                "unsupported version, only 'BSMS 1.0' is supported",
+            ))
+        }
+    }
+    let desc_template = lines
+        .next()
+        .ok_or_else(|| malformed("missing descriptor"))?;
+    let restrictions = lines
+        .next()
+        .ok_or_else(|| malformed("missing path restrictions"))?;
+    let first_addr = lines
+        .next()
+        .ok_or_else(|| malformed("missing first address"))?;
+    if lines.next().is_some() {
+        return Err(malformed("unexpected trailing content"));
+    }
+
+    if restrictions != BSMS_PATH_RESTRICTIONS && restrictions != BSMS_NO_PATH_RESTRICTIONS {
+        return Err(ImportError::Unsupported(vec![format!(
+            "path restrictions '{}' (only '{}' is supported)",
+            restrictions, BSMS_PATH_RESTRICTIONS
+        )]));
+    }
+    let desc = parse_descriptor(&normalize_descriptor(desc_template))?;
+
+    let secp = secp256k1::Secp256k1::verification_only();
+    let derived_spk = desc
+        .receive_descriptor()
+        .derive(0.into(), &secp)
+        .script_pubkey();
+    let expected = || ImportError::AddressMismatch {
+        expected: first_addr.to_string(),
+    };
+    let addr = Address::<NetworkUnchecked>::from_str(first_addr).map_err(|_| expected())?;
+    if addr.assume_checked().script_pubkey() != derived_spk {
+        return Err(expected());
+    }
+
+    Ok(desc)
+}
+
+// Convert the derivation path of a Coldcard configuration ("m/48'/0'/0'/2'") to the format of a
+// key origin in a descriptor ("/48'/0'/0'/2'").
+fn parse_coldcard_path(path: &str) -> Result<String, String> {
+    let path = bip32::DerivationPath::from_str(path)
+        .map_err(|e| format!("invalid derivation path '{}': {}", path, e))?;
+    Ok(path.to_string().trim_start_matches('m').to_string())
+}
+
+// Other coordinators may use a BIP129 `/**` suffix or a single receive descriptor. Turn those
+// into a multipath descriptor with receive and change paths. The checksum is dropped if the
+// descriptor was modified, since it would be invalid.
+fn normalize_descriptor(desc_str: &str) -> String {
+    let desc_str = desc_str.trim();
+    let normalized = if desc_str.contains("/**") {
+        desc_str.replace("/**", "/<0;1>/*")
+    } else if !desc_str.contains('<') && desc_str.contains("/0/*") {
+        desc_str.replace("/0/*", "/<0;1>/*")
+    } else {
+        return desc_str.to_string();
+    };
+    normalized
+        .split('#')
+        .next()
+        .expect("Split always yields an item")
+        .to_string()
+}
+
+// Parse the descriptor, listing the unsupported parts if it isn't a valid Liana descriptor.
+fn parse_descriptor(desc_str: &str) -> Result<LianaDescriptor, ImportError> {
+    LianaDescriptor::from_str(desc_str).map_err(|e| {
+        let unsupported = unsupported_parts(desc_str);
+        if unsupported.is_empty() {
+            ImportError::Descriptor(e)
+        } else {
+            ImportError::Unsupported(unsupported)
+        }
+    })
+}
+
+fn unsupported_parts(desc_str: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    if desc_str.starts_with("sh(") {
+        parts.push("P2SH addresses (only P2WSH and Taproot are supported)".to_string());
+    } else if ["pkh(", "wpkh(", "combo("]
+        .iter()
+        .any(|prefix| desc_str.starts_with(prefix))
+    {
+        parts.push("single-key wallets".to_string());
+    }
+    if desc_str.contains("sortedmulti(") {
+        parts.push("sorted multisig ('sortedmulti')".to_string());
+    }
+    if !desc_str.contains("older(") && !desc_str.contains("after(") {
+        parts.push("no timelocked recovery path".to_string());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::Network;
+
+    const PRIM_KEY: &str = "[92162c45]tpubD6NzVbkrYhZ4WzTf9SsD6h7AH7oQEippXK2KP8qvhMMqFoNeN5YFVi7vRyeRSDGtgd2bPyMxUNmHui8t5yCgszxPPxMafu1VVzDpg9aruYW";
+    const RECO_KEY: &str = "[abcdef01]tpubD6NzVbkrYhZ4Wdgu2yfdmrce5g4fiH1ZLmKhewsnNKupbi4sxjH1ZVAorkBLWSkhsjhg8kiq8C4BrBjMy3SjAKDyDdbuvUa1ToAHbiR98js";
+
+    fn liana_desc(suffix: &str) -> String {
+        format!(
+            "wsh(or_d(pk({}{}),and_v(v:pkh({}{}),older(2))))",
+            PRIM_KEY, suffix, RECO_KEY, suffix
+        )
+    }
+
+    #[test]
+    fn import_descriptor_text() {
+        let expected = LianaDescriptor::from_str(&liana_desc("/<0;1>/*")).unwrap();
+
+        // Sparrow-like export with comments.
+        let content = format!(
+            "# Receive and change descriptor (BIP389):\n{}\n\n# Receive descriptor (Bitcoin Core):\n{}\n",
+            liana_desc("/<0;1>/*"),
+            liana_desc("/0/*")
+        );
+        assert_eq!(ImportFormat::detect(&content), ImportFormat::Descriptor);
+        assert_eq!(import_wallet(&content).unwrap(), expected);
+
+        // A single receive descriptor with a (now invalid) checksum.
+        let content = format!("{}#abcdefgh", liana_desc("/0/*"));
+        assert_eq!(import_wallet(&content).unwrap(), expected);
+
+        assert!(matches!(
+            import_wallet("# Nothing here\n"),
+            Err(ImportError::Malformed(ImportFormat::Descriptor, _))
+        ));
+        let err = import_wallet(&format!("wsh(pk({}/<0;1>/*))", PRIM_KEY)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "This wallet can't be used with Liana. Unsupported: no timelocked recovery path."
+        );
+    }
+
+    #[test]
+    fn import_wallet_json() {
+        let expected = LianaDescriptor::from_str(&liana_desc("/<0;1>/*")).unwrap();
+        let content = format!(
+            "{{\"label\": \"Inheritance\", \"blockheight\": 2500000, \"descriptor\": \"{}\", \"devices\": []}}",
+            liana_desc("/0/*")
+        );
+        assert_eq!(ImportFormat::detect(&content), ImportFormat::WalletJson);
+        assert_eq!(import_wallet(&content).unwrap(), expected);
+
+        assert!(matches!(
+            import_wallet("{\"label\": \"Inheritance\"}"),
+            Err(ImportError::Json(_))
+        ));
+        assert!(matches!(import_wallet("{"), Err(ImportError::Json(_))));
+    }
+
+    #[test]
+    fn import_wallet_policy() {
+        let expected = LianaDescriptor::from_str(&liana_desc("/<0;1>/*")).unwrap();
+        let content = expected.to_wallet_policy().unwrap().to_string();
+        assert_eq!(ImportFormat::detect(&content), ImportFormat::WalletPolicy);
+        assert_eq!(import_wallet(&content).unwrap(), expected);
+
+        let content = content.replace("@1", "@2");
+        assert!(matches!(
+            import_wallet(&content),
+            Err(ImportError::WalletPolicy(_))
+        ));
+    }
+
+    #[test]
+    fn import_coldcard_config() {
+        let (prim_xpub, reco_xpub) = (&PRIM_KEY[10..], &RECO_KEY[10..]);
+        let content = format!(
+            "# Coldcard Multisig setup file (created on 92162C45)\n\
+             #\n\
+             Name: Inheritance\n\
+             Policy: 2 of 2\n\
+             Derivation: m/48'/1'/0'/2'\n\
+             Format: P2WSH\n\
+             \n\
+             92162C45: {}\n\
+             # derivation: m/48'/1'/1'/2'\n\
+             ABCDEF01: {}\n",
+            prim_xpub, reco_xpub
+        );
+        assert_eq!(
+            ImportFormat::detect(&content),
+            ImportFormat::ColdcardMultisig
+        );
+        match import_wallet(&content) {
+            Err(ImportError::Unsupported(parts)) => assert_eq!(
+                parts,
+                vec![
+                    "sorted multisig ('sortedmulti')".to_string(),
+                    "no timelocked recovery path".to_string()
+                ]
+            ),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+
+        // The default format is P2SH.
+        let content = content.replace("Format: P2WSH\n", "");
+        match import_wallet(&content) {
+            Err(ImportError::Unsupported(parts)) => assert_eq!(parts.len(), 3),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+
+        // The "M/N" form of the policy is accepted too.
+        let content = content.replace("Policy: 2 of 2", "Policy: 2/2");
+        match import_wallet(&content) {
+            Err(ImportError::Unsupported(parts)) => assert_eq!(parts.len(), 3),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+
+        // The number of keys in the policy must match the keys specified.
+        let content = content.replace("Policy: 2/2", "Policy: 2 of 3");
+        match import_wallet(&content) {
+            Err(ImportError::Malformed(ImportFormat::ColdcardMultisig, e)) => {
+                assert_eq!(e, "the policy has 3 keys but 2 are specified")
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        let content = content.replace("Policy: 2 of 3", "Policy: 2 of three");
+        match import_wallet(&content) {
+            Err(ImportError::Malformed(ImportFormat::ColdcardMultisig, e)) => {
+                assert_eq!(e, "invalid policy '2 of three'")
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn import_bsms_record() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let expected = LianaDescriptor::from_str(&liana_desc("/<0;1>/*")).unwrap();
+        let first_addr = expected
+            .receive_descriptor()
+            .derive(0.into(), &secp)
+            .address(Network::Testnet);
+        let content = format!(
+            "BSMS 1.0\n{}\n/0/*,/1/*\n{}\n",
+            liana_desc("/**"),
+            first_addr
+        );
+        assert_eq!(ImportFormat::detect(&content), ImportFormat::Bsms);
+        assert_eq!(import_wallet(&content).unwrap(), expected);
+
+        // The first address must match.
+        let other_addr = expected
+            .receive_descriptor()
+            .derive(1.into(), &secp)
+            .address(Network::Testnet);
+        let wrong_content = content.replace(&first_addr.to_string(), &other_addr.to_string());
+        assert!(matches!(
+            import_wallet(&wrong_content),
+            Err(ImportError::AddressMismatch { .. })
+        ));
+
+        // Only the receive and change paths are supported.
+        let wrong_content = content.replace("/0/*,/1/*", "/0/*,/1/*,/2/*");
+        assert!(matches!(
+            import_wallet(&wrong_content),
+            Err(ImportError::Unsupported(_))
+        ));
+        let wrong_content = content.replace("BSMS 1.0", "BSMS 2.0");
+        assert!(matches!(
+            import_wallet(&wrong_content),
+            Err(ImportError::Malformed(ImportFormat::Bsms, _))
+        ));
+    }
+}
@@ -1,9 +1,12 @@
+use crate::musig;
+
 use miniscript::{
     bitcoin::{
-        self, bip32,
+        self, absolute, bip32,
         constants::WITNESS_SCALE_FACTOR,
+        hashes::Hash,
         psbt::{Input as PsbtIn, Output as PsbtOut, Psbt},
-        secp256k1,
+        relative, secp256k1, sighash, taproot,
     },
     descriptor,
     miniscript::satisfy::Placeholder,
@@ -19,7 +22,7 @@ use std::{
     str::{self, FromStr},
 };
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 pub mod keys;
 pub use keys::*;
@@ -27,6 +30,11 @@ pub use keys::*;
 pub mod analysis;
 pub use analysis::*;
 
+pub mod import;
+
+pub mod wallet_policy;
+pub use wallet_policy::{WalletPolicy, WalletPolicyError};
+
 #[derive(Debug)]
 pub enum LianaDescError {
     Miniscript(miniscript::Error),
@@ -36,6 +44,9 @@ pub enum LianaDescError {
     InsanePsbt,
     /// Not all inputs' sequence the same, not all inputs signed with the same key, ..
     InconsistentPsbt,
+    /// The given preimage isn't for any of the hash locks in the descriptor.
+    UnknownPreimage,
+    Musig(musig::MusigError),
 }
 
 impl std::fmt::Display for LianaDescError {
@@ -46,6 +57,11 @@ impl std::fmt::Display for LianaDescError {
             Self::Policy(e) => write!(f, "{}", e),
             Self::InsanePsbt => write!(f, "Analyzed PSBT is empty or malformed."),
             Self::InconsistentPsbt => write!(f, "Analyzed PSBT is inconsistent across inputs."),
+            Self::UnknownPreimage => write!(
+                f,
+                "Preimage doesn't unlock any of the hash locks in the descriptor."
+            ),
+            Self::Musig(e) => write!(f, "{}", e),
         }
     }
 }
@@ -62,6 +78,180 @@ fn varint_len(n: usize) -> usize {
     bitcoin::VarInt(n as u64).size()
 }
 
+// Compute the BIP380 checksum of a descriptor string. rust-miniscript can't do it for us for
+// descriptors containing a BIP390 `musig()` key expression, since it doesn't support them.
+fn desc_checksum(desc: &str) -> Option<String> {
+    const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+    const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const GENERATORS: [u64; 5] = [
+        0xf5dee51989,
+        0xa9fdca3312,
+        0x1bab10e32d,
+        0x3706b1677a,
+        0x644d626ffd,
+    ];
+
+    fn poly_mod(c: u64, val: u64) -> u64 {
+        let c0 = c >> 35;
+        GENERATORS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| c0 & (1 << i) != 0)
+            .fold(((c & 0x7ffffffff) << 5) ^ val, |c, (_, gen)| c ^ gen)
+    }
+
+    let (mut c, mut cls, mut cls_count) = (1, 0, 0);
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET.find(ch)? as u64;
+        c = poly_mod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        cls_count += 1;
+        if cls_count == 3 {
+            c = poly_mod(c, cls);
+            cls = 0;
+            cls_count = 0;
+        }
+    }
+    if cls_count > 0 {
+        c = poly_mod(c, cls);
+    }
+    for _ in 0..8 {
+        c = poly_mod(c, 0);
+    }
+    c ^= 1;
+
+    Some(
+        (0..8)
+            .map(|i| CHECKSUM_CHARSET[((c >> (5 * (7 - i))) & 31) as usize] as char)
+            .collect(),
+    )
+}
+
+// Replace the BIP390 `musig()` key expression in this descriptor string, if there is one, by the
+// aggregate key it stands for. The checksum, if present, is checked and removed. Returns the
+// participants' keys along with the resulting descriptor string. The participants' keys are
+// given the derivation paths of the aggregate key, like all other keys in a Liana descriptor.
+fn replace_musig_expr(
+    s: &str,
+) -> Result<Option<(String, Vec<descriptor::DescriptorPublicKey>)>, LianaDescError> {
+    let start = match s.find("musig(") {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let s = match s.rsplit_once('#') {
+        Some((desc_str, checksum)) => {
+            if desc_checksum(desc_str).as_deref() != Some(checksum) {
+                return Err(LianaDescError::Miniscript(
+                    miniscript::Error::BadDescriptor(format!("Invalid checksum '{}'", checksum)),
+                ));
+            }
+            desc_str
+        }
+        None => s,
+    };
+
+    // The participants are listed within the parentheses, the derivation steps for the aggregate
+    // key follow them until the end of the key expression.
+    let args_start = start + "musig(".len();
+    let invalid_expr = || {
+        let end = s[start..].find(')').map_or(s.len(), |i| start + i + 1);
+        LianaDescError::Musig(musig::MusigError::InvalidDescriptorKey(
+            s[start..end].to_string(),
+        ))
+    };
+    let args_end = s[args_start..]
+        .find(')')
+        .map(|i| args_start + i)
+        .ok_or_else(invalid_expr)?;
+    let deriv_start = args_end + 1;
+    let expr_end = s[deriv_start..]
+        .find(&[',', ')'][..])
+        .map(|i| deriv_start + i)
+        .ok_or_else(invalid_expr)?;
+    let derivation = &s[deriv_start..expr_end];
+    let participants = s[args_start..args_end]
+        .split(',')
+        .map(|participant| {
+            // BIP390 disallows derivation steps for the participants when the aggregate key is
+            // itself derived.
+            if participant
+                .rsplit(']')
+                .next()
+                .map_or(true, |xpub| xpub.contains('/'))
+            {
+                return Err(invalid_expr());
+            }
+            descriptor::DescriptorPublicKey::from_str(&format!("{}{}", participant, derivation))
+                .map_err(|_| invalid_expr())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let agg_key = PathInfo::Musig(participants.clone())
+        .musig_aggregate_key()
+        .ok_or_else(invalid_expr)?;
+
+    Ok(Some((
+        format!("{}{}{}", &s[..start], agg_key, &s[expr_end..]),
+        participants,
+    )))
+}
+
+// The BIP390 `musig()` key expression for this aggregate key.
+fn musig_expr(
+    participants: &[descriptor::DescriptorPublicKey],
+    agg_key: &descriptor::DescriptorPublicKey,
+) -> String {
+    let participants: Vec<_> = participants
+        .iter()
+        .filter_map(|key| match key {
+            descriptor::DescriptorPublicKey::MultiXPub(xpub) => Some(
+                descriptor::DescriptorPublicKey::XPub(descriptor::DescriptorXKey {
+                    origin: xpub.origin.clone(),
+                    xkey: xpub.xkey,
+                    derivation_path: bip32::DerivationPath::master(),
+                    wildcard: descriptor::Wildcard::None,
+                })
+                .to_string(),
+            ),
+            _ => None,
+        })
+        .collect();
+    // The derivation steps of the aggregate key follow its xpub in the key expression.
+    let agg_key = agg_key.to_string();
+    let derivation = match agg_key.find(']') {
+        Some(i) => agg_key[i..].find('/').map_or("", |j| &agg_key[i + j..]),
+        None => "",
+    };
+    format!("musig({}){}", participants.join(","), derivation)
+}
+
+// (De)serialize the keys of the MuSig2 participants as strings.
+mod serde_participants {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        keys: &Option<Vec<descriptor::DescriptorPublicKey>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        keys.as_ref()
+            .map(|keys| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<descriptor::DescriptorPublicKey>>, D::Error> {
+        Option::<Vec<String>>::deserialize(deserializer)?
+            .map(|keys| {
+                keys.iter()
+                    .map(|k| {
+                        descriptor::DescriptorPublicKey::from_str(k).map_err(de::Error::custom)
+                    })
+                    .collect()
+            })
+            .transpose()
+    }
+}
+
 // Whether the key identified by its fingerprint+derivation path was derived from one of the xpubs
 // for this spending path.
 fn key_is_for_path(
@@ -92,6 +282,14 @@ pub struct LianaDescriptor {
     multi_desc: descriptor::Descriptor<descriptor::DescriptorPublicKey>,
     receive_desc: SinglePathLianaDesc,
     change_desc: SinglePathLianaDesc,
+    /// The participants of the MuSig2 aggregate key used for the primary path, if any. The
+    /// descriptors above only contain the aggregate key.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "serde_participants"
+    )]
+    musig_participants: Option<Vec<descriptor::DescriptorPublicKey>>,
 }
 
 /// A Miniscript descriptor with a main, unencombered, branch (the main owner of the coins)
@@ -105,7 +303,22 @@ pub struct DerivedSinglePathLianaDesc(descriptor::Descriptor<DerivedPublicKey>);
 
 impl fmt::Display for LianaDescriptor {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.multi_desc)
+        if let Some(ref participants) = self.musig_participants {
+            // Display the aggregate key as a BIP390 musig() key expression.
+            let agg_key = PathInfo::Musig(participants.clone())
+                .musig_aggregate_key()
+                .expect("Checked at creation.");
+            let desc_str = self.multi_desc.to_string();
+            let desc_str = desc_str
+                .split('#')
+                .next()
+                .expect("Always at least one element.")
+                .replacen(&agg_key.to_string(), &musig_expr(participants, &agg_key), 1);
+            let checksum = desc_checksum(&desc_str).expect("Only valid characters.");
+            write!(f, "{}#{}", desc_str, checksum)
+        } else {
+            write!(f, "{}", self.multi_desc)
+        }
     }
 }
 
@@ -114,10 +327,22 @@ impl str::FromStr for LianaDescriptor {
 
     fn from_str(s: &str) -> Result<LianaDescriptor, Self::Err> {
         // Parse a descriptor and check it is a multipath descriptor corresponding to a valid Liana
-        // spending policy.
-        let desc = descriptor::Descriptor::<descriptor::DescriptorPublicKey>::from_str(s)
-            .map_err(LianaDescError::Miniscript)?;
-        LianaPolicy::from_multipath_descriptor(&desc)?;
+        // spending policy. If it contains a MuSig2 aggregate key, it must be the primary path.
+        let (desc, musig_participants) = match replace_musig_expr(s)? {
+            Some((desc_str, participants)) => (
+                descriptor::Descriptor::<descriptor::DescriptorPublicKey>::from_str(&desc_str),
+                Some(participants),
+            ),
+            None => (
+                descriptor::Descriptor::<descriptor::DescriptorPublicKey>::from_str(s),
+                None,
+            ),
+        };
+        let desc = desc.map_err(LianaDescError::Miniscript)?;
+        let policy = LianaPolicy::from_multipath_descriptor(&desc)?;
+        if let Some(ref participants) = musig_participants {
+            policy.with_musig_primary_path(participants.clone())?;
+        }
 
         // Compute the receive and change "sub" descriptors right away. According to our pubkey
         // check above, there must be only two of those, 0 and 1.
@@ -136,6 +361,7 @@ impl str::FromStr for LianaDescriptor {
             multi_desc: desc,
             receive_desc,
             change_desc,
+            musig_participants,
         })
     }
 }
@@ -174,6 +400,10 @@ impl ChangeOutput {
 impl LianaDescriptor {
     pub fn new(spending_policy: LianaPolicy) -> LianaDescriptor {
         // Get the descriptor from the chosen spending policy.
+        let musig_participants = match spending_policy.primary_path {
+            PathInfo::Musig(ref participants) => Some(participants.clone()),
+            _ => None,
+        };
         let multi_desc = spending_policy.into_multipath_descriptor();
 
         // Compute the receive and change "sub" descriptors right away. According to our pubkey
@@ -193,6 +423,7 @@ impl LianaDescriptor {
             multi_desc,
             receive_desc,
             change_desc,
+            musig_participants,
         }
     }
 
@@ -207,6 +438,17 @@ impl LianaDescriptor {
         })
     }
 
+    /// Whether this extended public key is used in this descriptor, including as a participant
+    /// of a MuSig2 aggregate key.
+    pub fn contains_xpub(&self, xpub: &bip32::Xpub) -> bool {
+        let is_xpub = |key: &descriptor::DescriptorPublicKey| match key {
+            descriptor::DescriptorPublicKey::MultiXPub(k) => k.xkey == *xpub,
+            _ => false,
+        };
+        !self.multi_desc.for_each_key(|key| !is_xpub(key))
+            || self.musig_participants.iter().flatten().any(is_xpub)
+    }
+
     /// Get the descriptor for receiving addresses.
     pub fn receive_descriptor(&self) -> &SinglePathLianaDesc {
         &self.receive_desc
@@ -219,19 +461,57 @@ impl LianaDescriptor {
 
     /// Get the spending policy of this descriptor.
     pub fn policy(&self) -> LianaPolicy {
-        LianaPolicy::from_multipath_descriptor(&self.multi_desc)
-            .expect("We never create a Liana descriptor with an invalid Liana policy.")
+        let policy = LianaPolicy::from_multipath_descriptor(&self.multi_desc)
+            .expect("We never create a Liana descriptor with an invalid Liana policy.");
+        if let Some(ref participants) = self.musig_participants {
+            policy
+                .with_musig_primary_path(participants.clone())
+                .expect("We never create a Liana descriptor with an invalid MuSig2 primary path.")
+        } else {
+            policy
+        }
+    }
+
+    /// Get the value (in blocks) of the smallest relative timelock of the recovery paths. A
+    /// time-based relative timelock is converted to an estimated number of blocks, assuming
+    /// 10-minute blocks. Use [LianaDescriptor::first_relative_timelock] for the exact value.
+    /// Returns `None` if all the recovery paths are behind an absolute timelock.
+    pub fn first_timelock_value(&self) -> Option<u16> {
+        Some(match self.first_relative_timelock()? {
+            relative::LockTime::Blocks(height) => height.value(),
+            relative::LockTime::Time(time) => {
+                let blocks = (u32::from(time.value()) * 512 + 599) / 600;
+                blocks.try_into().unwrap_or(u16::MAX)
+            }
+        })
     }
 
-    /// Get the value (in blocks) of the smallest relative timelock of the recovery paths.
-    pub fn first_timelock_value(&self) -> u16 {
-        *self
-            .policy()
+    /// Get the relative timelock of the recovery path which becomes available first. A
+    /// time-based timelock is considered to come first if it is shorter than the height-based
+    /// one, assuming 10-minute blocks. Returns `None` if all the recovery paths are behind an
+    /// absolute timelock.
+    pub fn first_relative_timelock(&self) -> Option<relative::LockTime> {
+        let policy = self.policy();
+        let height = policy
             .recovery_paths
-            .iter()
+            .keys()
             .next()
-            .expect("There is always at least one recovery path")
-            .0
+            .map(|tl| relative::Height::from(*tl));
+        let time = policy
+            .time_recovery_paths
+            .keys()
+            .next()
+            .map(|tl| relative::Time::from_512_second_intervals(*tl));
+        match (height, time) {
+            (Some(height), Some(time))
+                if u32::from(time.value()) * 512 < u32::from(height.value()) * 600 =>
+            {
+                Some(time.into())
+            }
+            (Some(height), _) => Some(height.into()),
+            (None, Some(time)) => Some(time.into()),
+            (None, None) => None,
+        }
     }
 
     /// Get the maximum size difference of a transaction input spending a Script derived from this
@@ -240,55 +520,7 @@ impl LianaDescriptor {
     /// size of the witness stack length varint.
     pub fn max_sat_weight(&self, use_primary_path: bool) -> usize {
         if use_primary_path {
-            // Get the keys from the primary path, to get a satisfaction size estimation only
-            // considering those.
-            let keys = self
-                .policy()
-                .primary_path
-                .thresh_origins()
-                .1
-                .into_iter()
-                .fold(BTreeSet::new(), |mut keys, (fg, der_paths)| {
-                    for der_path in der_paths {
-                        keys.insert(((fg, der_path), CanSign::default()));
-                    }
-                    keys
-                });
-            let assets = Assets {
-                keys,
-                ..Default::default()
-            };
-
-            // Unfortunately rust-miniscript satisfaction size estimation is inconsistent. For
-            // Taproot it considers the whole witness (except the control block size + the
-            // script size), while under P2WSH it does not consider the witscript! Therefore we
-            // manually add the size of the witscript under P2WSH by means of the
-            // `explicit_script()` helper, which gives an error for Taproot, and for Taproot
-            // we add the sizes of the control block and script.
-            let der_desc = self
-                .receive_desc
-                .0
-                .at_derivation_index(0)
-                .expect("unhardened index");
-            let witscript_size = der_desc
-                .explicit_script()
-                .map(|s| varint_len(s.len()) + s.len());
-
-            // Finally, compute the satisfaction template for the primary path and get its size.
-            let plan = der_desc.plan(&assets).expect("Always satisfiable");
-            plan.witness_size()
-                + witscript_size.unwrap_or_else(|_| {
-                    plan.witness_template()
-                        .iter()
-                        .map(|elem| match elem {
-                            // We need to calculate the size manually before calculating the varint length.
-                            // See https://docs.rs/miniscript/11.0.0/src/miniscript/util.rs.html#35-36.
-                            Placeholder::TapScript(s) => varint_len(s.len()),
-                            Placeholder::TapControlBlock(cb) => varint_len(cb.serialize().len()),
-                            _ => 0,
-                        })
-                        .sum()
-                })
+            self.path_max_sat_weight(&self.policy().primary_path, None)
         } else {
             // We add one to account for the witness stack size, as the values above give the
             // difference in size for a satisfied input that was *already* in a transaction
@@ -303,6 +535,116 @@ impl LianaDescriptor {
         }
     }
 
+    /// Get the maximum size difference of a transaction input spending a Script derived from this
+    /// descriptor through the given spending path, available behind the given timelock, before
+    /// and after satisfaction. Same as [LianaDescriptor::max_sat_weight] otherwise.
+    pub fn path_max_sat_weight(
+        &self,
+        path_info: &PathInfo,
+        timelock: Option<RecoveryTimelock>,
+    ) -> usize {
+        // Get the keys from this path, to get a satisfaction size estimation only considering
+        // those. For a MuSig2 primary path, that's only the aggregate key.
+        let path_info = path_info
+            .musig_aggregate_key()
+            .map(PathInfo::Single)
+            .unwrap_or_else(|| path_info.clone());
+        let keys = path_info.thresh_origins().1.into_iter().fold(
+            BTreeSet::new(),
+            |mut keys, (fg, der_paths)| {
+                for der_path in der_paths {
+                    keys.insert(((fg, der_path), CanSign::default()));
+                }
+                keys
+            },
+        );
+        let mut assets = Assets {
+            keys,
+            ..Default::default()
+        };
+        // If the path is behind a hash lock, also consider its preimage.
+        match path_info.hash_lock() {
+            Some(HashLock::Sha256(h)) => {
+                assets.sha256_preimages.insert(*h);
+            }
+            Some(HashLock::Hash160(h)) => {
+                assets.hash160_preimages.insert(*h);
+            }
+            None => {}
+        }
+        // And if it's a recovery path, its timelock.
+        match timelock {
+            Some(RecoveryTimelock::Relative(blocks)) => {
+                assets.relative_timelock = Some(bitcoin::Sequence::from_height(blocks));
+            }
+            Some(RecoveryTimelock::RelativeTime(intervals)) => {
+                assets.relative_timelock =
+                    Some(bitcoin::Sequence::from_512_second_intervals(intervals));
+            }
+            Some(RecoveryTimelock::Absolute(timelock)) => {
+                assets.absolute_timelock = Some(absolute::LockTime::from_consensus(timelock));
+            }
+            None => {}
+        }
+
+        // Unfortunately rust-miniscript satisfaction size estimation is inconsistent. For
+        // Taproot it considers the whole witness (except the control block size + the
+        // script size), while under P2WSH it does not consider the witscript! Therefore we
+        // manually add the size of the witscript under P2WSH by means of the
+        // `explicit_script()` helper, which gives an error for Taproot, and for Taproot
+        // we add the sizes of the control block and script.
+        let der_desc = self
+            .receive_desc
+            .0
+            .at_derivation_index(0)
+            .expect("unhardened index");
+        let witscript_size = der_desc
+            .explicit_script()
+            .map(|s| varint_len(s.len()) + s.len());
+
+        // Finally, compute the satisfaction template for this path and get its size.
+        let plan = der_desc.plan(&assets).expect("Always satisfiable");
+        plan.witness_size()
+            + witscript_size.unwrap_or_else(|_| {
+                plan.witness_template()
+                    .iter()
+                    .map(|elem| match elem {
+                        // We need to calculate the size manually before calculating the varint length.
+                        // See https://docs.rs/miniscript/11.0.0/src/miniscript/util.rs.html#35-36.
+                        Placeholder::TapScript(s) => varint_len(s.len()),
+                        Placeholder::TapControlBlock(cb) => varint_len(cb.serialize().len()),
+                        _ => 0,
+                    })
+                    .sum()
+            })
+    }
+
+    /// Get a human-readable explanation of all the spending paths of this descriptor, along with
+    /// the cost of spending a coin through each of them at the given feerate (in sats/vb).
+    pub fn explain(&self, feerate_vb: u64) -> PolicyExplanation {
+        let policy = self.policy();
+        let mut explanation = policy.explain();
+        for (path_expl, (timelock, path_info)) in
+            explanation.paths.iter_mut().zip(policy.all_paths())
+        {
+            let sat_weight = self.path_max_sat_weight(path_info, timelock);
+            // txid + vout + nSequence + empty scriptSig + witness
+            let input_vbytes = 32 + 4 + 4 + 1 + sat_weight.div_ceil(WITNESS_SCALE_FACTOR);
+            path_expl.satisfaction = Some(SatisfactionCost {
+                sat_weight,
+                input_vbytes,
+                feerate: feerate_vb,
+                fee: (input_vbytes as u64).saturating_mul(feerate_vb),
+            });
+        }
+        explanation
+    }
+
+    /// Get advisory warnings about risky, but valid, settings in this descriptor.
+    pub fn lint(&self) -> Vec<PolicyWarning> {
+        self.policy().lint()
+    }
+
     /// Get the maximum size difference of a transaction input spending a Script derived from this
     /// descriptor before and after satisfaction. The returned value is in (rounded up) virtual
     /// bytes.
@@ -328,7 +670,8 @@ impl LianaDescriptor {
         matches!(self.multi_desc, descriptor::Descriptor::Tr(..))
     }
 
-    /// Get some information about a PSBT input spending Liana coins.
+    /// Get some information about a PSBT input spending Liana coins, in a transaction with the
+    /// given nLockTime.
     /// This analysis assumes that:
     /// - The PSBT input actually spend a Liana coin for this descriptor. Otherwise the analysis will be off.
     /// - The signatures contained in the PSBT input are valid for this script.
@@ -336,6 +679,7 @@ impl LianaDescriptor {
         &self,
         psbt_in: &PsbtIn,
         txin: &bitcoin::TxIn,
+        lock_time: absolute::LockTime,
     ) -> PartialSpendInfo {
         let is_taproot = self.is_taproot();
         // Get the origin ECDSA or Schnorr signatures, depending on the descriptor type.
@@ -372,15 +716,58 @@ impl LianaDescriptor {
 
         // Determine the structure of the descriptor. Then compute the spend info for the primary
         // and recovery paths. Only provide the spend info for the recovery path if it is available
-        // (ie if the nSequence is >= to the chosen CSV value).
+        // (ie if the nSequence is >= to the chosen CSV value, or if the nLockTime is >= to the
+        // chosen CLTV value and enabled by the nSequence).
+        // For hash-locked paths, also check whether the preimage was provided.
+        let path_spend_info = |path_info: &PathInfo| {
+            let mut spend_info = match path_info {
+                PathInfo::Musig(keys) => musig_spend_info(keys, psbt_in),
+                path_info => path_info.spend_info(pubkeys_signed.clone()),
+            };
+            if let Some(hash_lock) = path_info.hash_lock() {
+                spend_info.missing_preimage = !hash_lock.preimage_in(psbt_in);
+            }
+            spend_info
+        };
         let desc_info = self.policy();
-        let primary_path = desc_info.primary_path.spend_info(pubkeys_signed.clone());
+        let primary_path = path_spend_info(&desc_info.primary_path);
         let recovery_paths = desc_info
             .recovery_paths
             .iter()
             .filter_map(|(timelock, path_info)| {
                 if txin.sequence.is_height_locked() && txin.sequence.0 >= *timelock as u32 {
-                    Some((*timelock, path_info.spend_info(pubkeys_signed.clone())))
+                    Some((*timelock, path_spend_info(path_info)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let time_recovery_paths = desc_info
+            .time_recovery_paths
+            .iter()
+            .filter_map(|(timelock, path_info)| {
+                let lock_time =
+                    relative::LockTime::from(relative::Time::from_512_second_intervals(*timelock));
+                if txin
+                    .sequence
+                    .to_relative_lock_time()
+                    .map_or(false, |seq_lock_time| {
+                        lock_time.is_implied_by(seq_lock_time)
+                    })
+                {
+                    Some((*timelock, path_spend_info(path_info)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let absolute_recovery_paths = desc_info
+            .absolute_recovery_paths
+            .iter()
+            .filter_map(|(timelock, path_info)| {
+                let timelock = absolute::LockTime::from_consensus(*timelock);
+                if txin.sequence.enables_absolute_lock_time() && timelock.is_implied_by(lock_time) {
+                    Some((timelock.to_consensus_u32(), path_spend_info(path_info)))
                 } else {
                     None
                 }
@@ -390,6 +777,8 @@ impl LianaDescriptor {
         PartialSpendInfo {
             primary_path,
             recovery_paths,
+            time_recovery_paths,
+            absolute_recovery_paths,
         }
     }
 
@@ -424,12 +813,13 @@ impl LianaDescriptor {
                 .expect("We checked at least one is present."),
             txins.next().expect("We checked at least one is present."),
         );
-        let spend_info = self.partial_spend_info_txin(first_psbt_in, first_txin);
+        let lock_time = psbt.unsigned_tx.lock_time;
+        let spend_info = self.partial_spend_info_txin(first_psbt_in, first_txin, lock_time);
         for (psbt_in, txin) in psbt_ins.zip(txins) {
             // TODO: maybe it's better to not error if one of the input has more, or different
             // signatures? Instead of erroring we could ignore the superfluous data?
             if txin.sequence != first_txin.sequence
-                || spend_info != self.partial_spend_info_txin(psbt_in, txin)
+                || spend_info != self.partial_spend_info_txin(psbt_in, txin, lock_time)
             {
                 return Err(LianaDescError::InconsistentPsbt);
             }
@@ -503,7 +893,27 @@ impl LianaDescriptor {
     /// one.
     pub fn prune_bip32_derivs(&self, mut psbt: Psbt, spending_path: &PathInfo) -> Psbt {
         // (Fingerprint, derivation path) pairs uniquely identify a key used in this spending path.
-        let (_, path_origins) = spending_path.thresh_origins();
+        let (_, mut path_origins) = spending_path.thresh_origins();
+
+        // For a MuSig2 path, the key in the descriptor is the aggregate key. Also keep the origins
+        // of the participants' xpubs, which they sign with.
+        if let Some(agg_key) = spending_path.musig_aggregate_key() {
+            path_origins.extend(PathInfo::Single(agg_key).thresh_origins().1);
+        }
+        let participants_origins: HashSet<_> = match spending_path {
+            PathInfo::Musig(keys) => keys
+                .iter()
+                .filter_map(|key| match key {
+                    descriptor::DescriptorPublicKey::MultiXPub(xpub) => xpub.origin.clone(),
+                    _ => None,
+                })
+                .collect(),
+            _ => HashSet::new(),
+        };
+        let keep = |fg: &bip32::Fingerprint, der_path: &bip32::DerivationPath| {
+            key_is_for_path(&path_origins, fg, der_path)
+                || participants_origins.contains(&(*fg, der_path.clone()))
+        };
 
         // Go through all the PSBT inputs and drop the BIP32 derivations for keys that are not from
         // this spending path.
@@ -512,10 +922,10 @@ impl LianaDescriptor {
             // empty so it's a noop.
             psbt_in
                 .bip32_derivation
-                .retain(|_, (fg, der_path)| key_is_for_path(&path_origins, fg, der_path));
+                .retain(|_, (fg, der_path)| keep(fg, der_path));
             psbt_in
                 .tap_key_origins
-                .retain(|_, (_, (fg, der_path))| key_is_for_path(&path_origins, fg, der_path));
+                .retain(|_, (_, (fg, der_path))| keep(fg, der_path));
         }
 
         psbt
@@ -528,6 +938,10 @@ impl LianaDescriptor {
     /// - If there is two recovery paths, and the PSBT's first input nSequence is set to unlock the
     ///     first one, prune all but the first recovery path's bip32 derivations.
     /// - Etc..
+    ///
+    /// Recovery paths behind a relative timelock take precedence over those behind an absolute
+    /// timelock. A transaction input may only enable either height-based or time-based relative
+    /// timelocks.
     pub fn prune_bip32_derivs_last_avail(&self, psbt: Psbt) -> Result<Psbt, LianaDescError> {
         let spend_info = self.partial_spend_info(&psbt)?;
         let policy = self.policy();
@@ -541,10 +955,131 @@ impl LianaDescriptor {
                     .get(tl)
                     .expect("Same timelocks must be keys in both mappings.")
             })
+            .or_else(|| {
+                spend_info.time_recovery_paths.iter().last().map(|(tl, _)| {
+                    policy
+                        .time_recovery_paths
+                        .get(tl)
+                        .expect("Same timelocks must be keys in both mappings.")
+                })
+            })
+            .or_else(|| {
+                spend_info
+                    .absolute_recovery_paths
+                    .iter()
+                    .last()
+                    .map(|(tl, _)| {
+                        policy
+                            .absolute_recovery_paths
+                            .get(tl)
+                            .expect("Same timelocks must be keys in both mappings.")
+                    })
+            })
             .unwrap_or(&policy.primary_path);
         Ok(self.prune_bip32_derivs(psbt, path_info))
     }
 
+    /// Set the preimage of one of the hash locks of this descriptor in all the PSBT inputs.
+    /// Returns the hash lock this preimage unlocks.
+    pub fn add_preimage(
+        &self,
+        psbt: &mut Psbt,
+        preimage: &[u8],
+    ) -> Result<HashLock, LianaDescError> {
+        let policy = self.policy();
+        let hash_lock = *policy
+            .recovery_paths
+            .values()
+            .chain(policy.time_recovery_paths.values())
+            .chain(policy.absolute_recovery_paths.values())
+            .chain(std::iter::once(&policy.primary_path))
+            .filter_map(|path_info| path_info.hash_lock())
+            .find(|hash_lock| hash_lock.is_unlocked_by(preimage))
+            .ok_or(LianaDescError::UnknownPreimage)?;
+        for psbt_in in psbt.inputs.iter_mut() {
+            hash_lock.set_preimage(psbt_in, preimage.to_vec());
+        }
+        Ok(hash_lock)
+    }
+
+    /// Add the participants of the MuSig2 aggregate internal key to this PSBT input, along with
+    /// the origins of their xpubs, for them to be able to sign through the key path. This is a
+    /// noop if the primary path isn't a MuSig2 aggregate key.
+    pub fn update_musig_psbt_in(&self, psbt_in: &mut PsbtIn) {
+        let participants = match self.musig_participants {
+            Some(ref participants) => participants,
+            None => return,
+        };
+        let agg_key = match PathInfo::Musig(participants.clone()).musig_aggregate_key() {
+            Some(descriptor::DescriptorPublicKey::MultiXPub(xpub)) => xpub.xkey.public_key,
+            _ => unreachable!("Checked at creation."),
+        };
+        let mut keys = Vec::with_capacity(participants.len());
+        for key in participants {
+            if let descriptor::DescriptorPublicKey::MultiXPub(xpub) = key {
+                keys.push(xpub.xkey.public_key);
+                if let Some(ref origin) = xpub.origin {
+                    psbt_in.tap_key_origins.insert(
+                        xpub.xkey.public_key.x_only_public_key().0,
+                        (Vec::new(), origin.clone()),
+                    );
+                }
+            }
+        }
+        musig::set_participants(psbt_in, &agg_key, keys);
+    }
+
+    /// Aggregate the MuSig2 partial signatures of the participants into a signature for the key
+    /// path of each input. Inputs for which some partial signatures are still missing are left
+    /// untouched.
+    pub fn aggregate_musig_sigs(
+        &self,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+        psbt: &mut Psbt,
+    ) -> Result<(), LianaDescError> {
+        if self.musig_participants.is_none() {
+            return Ok(());
+        }
+
+        let prevouts: Vec<_> = psbt
+            .inputs
+            .iter()
+            .filter_map(|psbt_in| psbt_in.witness_utxo.clone())
+            .collect();
+        if prevouts.len() != psbt.inputs.len() {
+            return Err(LianaDescError::InsanePsbt);
+        }
+        let prevouts = sighash::Prevouts::All(&prevouts);
+        let mut sighash_cache = sighash::SighashCache::new(&psbt.unsigned_tx);
+        let sig_type = sighash::TapSighashType::Default;
+
+        for (i, psbt_in) in psbt.inputs.iter_mut().enumerate() {
+            if psbt_in.tap_key_sig.is_some() {
+                continue;
+            }
+            let session = match musig::KeyPathSession::from_psbt_in(secp, psbt_in)
+                .map_err(LianaDescError::Musig)?
+            {
+                Some(session) => session,
+                None => continue,
+            };
+            let sighash = sighash_cache
+                .taproot_key_spend_signature_hash(i, &prevouts, sig_type)
+                .map_err(|_| LianaDescError::InsanePsbt)?;
+            if let Some(sig) = session
+                .aggregate(secp, psbt_in, sighash.as_byte_array())
+                .map_err(LianaDescError::Musig)?
+            {
+                psbt_in.tap_key_sig = Some(taproot::Signature {
+                    sig,
+                    hash_ty: sig_type,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Maximum possible weight in weight units of an unsigned transaction, `tx`,
     /// after satisfaction, assuming all inputs of `tx` are from this
     /// descriptor.
@@ -583,6 +1118,36 @@ impl LianaDescriptor {
     }
 }
 
+// Get the spend information for a MuSig2 path: the participants which provided a partial
+// signature, or all of them once the partial signatures were aggregated.
+fn musig_spend_info(keys: &[descriptor::DescriptorPublicKey], psbt_in: &PsbtIn) -> PathSpendInfo {
+    let secp = secp256k1::Secp256k1::verification_only();
+    let signers: Vec<secp256k1::PublicKey> =
+        match musig::KeyPathSession::from_psbt_in(&secp, psbt_in) {
+            Ok(Some(session)) if psbt_in.tap_key_sig.is_some() => session.participants().to_vec(),
+            Ok(Some(session)) => session
+                .partial_sigs(psbt_in)
+                .map(|sigs| sigs.into_keys().collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+    let mut signed_pubkeys = HashMap::new();
+    for key in keys {
+        if let descriptor::DescriptorPublicKey::MultiXPub(xpub) = key {
+            if signers.contains(&xpub.xkey.public_key) {
+                *signed_pubkeys.entry(key.master_fingerprint()).or_insert(0) += 1;
+            }
+        }
+    }
+    PathSpendInfo {
+        threshold: keys.len(),
+        sigs_count: signed_pubkeys.values().sum(),
+        signed_pubkeys,
+        missing_preimage: false,
+    }
+}
+
 impl SinglePathLianaDesc {
     /// Derive this descriptor at a given index for a receiving address.
     ///
@@ -1123,13 +1688,13 @@ mod tests {
         LianaDescriptor::from_str("wsh(or_i(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))").unwrap_err();
 
         let desc = LianaDescriptor::from_str("wsh(andor(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(1),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))").unwrap();
-        assert_eq!(desc.first_timelock_value(), 1);
+        assert_eq!(desc.first_timelock_value(), Some(1));
 
         let desc = LianaDescriptor::from_str("wsh(andor(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(42000),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))").unwrap();
-        assert_eq!(desc.first_timelock_value(), 42000);
+        assert_eq!(desc.first_timelock_value(), Some(42000));
 
         let desc = LianaDescriptor::from_str("wsh(andor(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(65535),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))").unwrap();
-        assert_eq!(desc.first_timelock_value(), 0xffff);
+        assert_eq!(desc.first_timelock_value(), Some(0xffff));
     }
 
     #[test]
@@ -1239,6 +1804,181 @@ mod tests {
         assert_eq!(desc.max_sat_weight(false), desc.max_sat_weight(true) + 2);
     }
 
+    #[test]
+    fn explain_descriptor() {
+        let desc = LianaDescriptor::from_str("tr(tpubD6NzVbkrYhZ4WUdbVsXDYBCXS8EPSYG1cAN9g4uP6uLQHMHXRvHSFkQBXy7MBeAvV8PDVJJ4o3AwYMKJHp45ci2g69UCAKteVSAJ61CnGEV/<0;1>/*,{and_v(v:pk([9e1c1983/48'/1'/0'/2']tpubDEWCLCMncbStq4BLXkQUAPqzzrh2tQUgYeQPt4NrB5D7gRraMyGbRqzPTmQGvqfdaFsXDVGSQBRgfXuNjDyfU626pxSjpQZszFNY6CzogxK/<2;3>/*),older(65535)),multi_a(2,[9e1c1983/48'/1'/0'/2']tpubDEWCLCMncbStq4BLXkQUAPqzzrh2tQUgYeQPt4NrB5D7gRraMyGbRqzPTmQGvqfdaFsXDVGSQBRgfXuNjDyfU626pxSjpQZszFNY6CzogxK/<0;1>/*,[3b1913e1/48'/1'/0'/2']tpubDFeZ2ezf4VUuTnjdhxJ1DKhLa2t6vzXZNz8NnEgeT2PN4pPqTCTeWUcaxKHPJcf1C8WzkLA71zSjDwuo4zqu4kkiL91ZUmJydC8f1gx89wM/<0;1>/*)})#ee0r4tw5").unwrap();
+        let explanation = desc.explain(2);
+        assert!(explanation.is_taproot);
+        assert_eq!(explanation.paths.len(), 2);
+
+        // The primary path is the 2-of-2 multisig.
+        let primary = &explanation.paths[0];
+        assert!(primary.is_primary && primary.timelock.is_none());
+        assert_eq!(primary.threshold, 2);
+        let fingerprints: HashSet<_> = primary.keys.iter().map(|k| k.fingerprint).collect();
+        assert_eq!(
+            fingerprints,
+            [
+                bip32::Fingerprint::from_str("9e1c1983").unwrap(),
+                bip32::Fingerprint::from_str("3b1913e1").unwrap()
+            ]
+            .iter()
+            .copied()
+            .collect()
+        );
+        assert!(primary.description.starts_with("All of the 2 keys "));
+        assert!(primary.description.ends_with(" can spend at any time."));
+        assert_eq!(
+            primary.satisfaction.unwrap().sat_weight,
+            desc.max_sat_weight(true)
+        );
+
+        // The recovery path only needs a single signature.
+        let recovery = &explanation.paths[1];
+        assert!(!recovery.is_primary);
+        assert_eq!(recovery.timelock, Some(RecoveryTimelock::Relative(65535)));
+        assert_eq!(recovery.threshold, 1);
+        assert_eq!(
+            recovery.keys,
+            vec![KeyExplanation {
+                fingerprint: bip32::Fingerprint::from_str("9e1c1983").unwrap(),
+                derivation_paths: vec![
+                    bip32::DerivationPath::from_str("m/48'/1'/0'/2'/2").unwrap(),
+                    bip32::DerivationPath::from_str("m/48'/1'/0'/2'/3").unwrap(),
+                ],
+            }]
+        );
+        assert_eq!(
+            recovery.description,
+            "The key 9e1c1983 can spend 65535 blocks (about 455 days) after the coin was confirmed."
+        );
+        // varint_len(num witness elements) = 1
+        // varint_len(signature) + signature = 1 + 64
+        // varint_len(script) + script = 1 + 39
+        // varint_len(control block) + control block = 1 + 65
+        let sat_weight = 1 + (1 + 64) + (1 + 39) + (1 + 65);
+        assert_eq!(
+            recovery.satisfaction,
+            Some(SatisfactionCost {
+                sat_weight,
+                input_vbytes: 41 + 43,
+                feerate: 2,
+                fee: 2 * (41 + 43),
+            })
+        );
+    }
+
+    #[test]
+    fn lint_descriptor() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        // A key at the standard BIP48 derivation path for this signer and account.
+        let std_key = |signer: &HotSigner, account: u32| {
+            let coin_type = if signer
+                .xpub_at(&bip32::DerivationPath::master(), &secp)
+                .network
+                == bitcoin::Network::Bitcoin
+            {
+                0
+            } else {
+                1
+            };
+            let origin = format!("48'/{}'/{}'/2'", coin_type, account);
+            let xpub_str = format!(
+                "[{}/{}]{}/<0;1>/*",
+                signer.fingerprint(&secp),
+                origin,
+                signer.xpub_at(
+                    &bip32::DerivationPath::from_str(&format!("m/{}", origin)).unwrap(),
+                    &secp
+                )
+            );
+            descriptor::DescriptorPublicKey::from_str(&xpub_str).unwrap()
+        };
+        let owner = HotSigner::generate(bitcoin::Network::Bitcoin).unwrap();
+        let heir = HotSigner::generate(bitcoin::Network::Bitcoin).unwrap();
+        let owner_fg = owner.fingerprint(&secp);
+
+        // A simple inheritance setup with a one-year timelock is fine.
+        let policy = LianaPolicy::new_legacy(
+            PathInfo::Single(std_key(&owner, 0)),
+            [(52560, PathInfo::Single(std_key(&heir, 0)))]
+                .iter()
+                .cloned()
+                .collect(),
+        )
+        .unwrap();
+        assert!(LianaDescriptor::new(policy).lint().is_empty());
+
+        // A 2-of-2 primary path with a short timelocked recovery path using one of its devices.
+        let policy = LianaPolicy::new_legacy(
+            PathInfo::Multi(2, vec![std_key(&owner, 0), std_key(&heir, 0)]),
+            [(1000, PathInfo::Single(std_key(&owner, 1)))]
+                .iter()
+                .cloned()
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(
+            LianaDescriptor::new(policy).lint(),
+            vec![
+                PolicyWarning::ShortTimelock {
+                    timelock: RecoveryTimelock::Relative(1000)
+                },
+                PolicyWarning::SharedDevice {
+                    fingerprint: owner_fg,
+                    timelock: RecoveryTimelock::Relative(1000)
+                },
+                PolicyWarning::NoRedundancy {
+                    timelock: None,
+                    threshold: 2
+                },
+            ]
+        );
+
+        // 90 days is long enough, 4000 intervals (about 24 days) isn't.
+        let policy = LianaPolicy::new_legacy_with_time_recovery_paths(
+            PathInfo::Single(std_key(&owner, 0)),
+            BTreeMap::new(),
+            [
+                (15_188, PathInfo::Single(std_key(&heir, 0))),
+                (4000, PathInfo::Single(std_key(&heir, 1))),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        )
+        .unwrap();
+        assert_eq!(
+            LianaDescriptor::new(policy).lint(),
+            vec![PolicyWarning::ShortTimelock {
+                timelock: RecoveryTimelock::RelativeTime(4000)
+            }]
+        );
+
+        // A key without the standard origin, and a key for another network.
+        let random_key = random_desc_key(&secp);
+        let random_fg = random_key.master_fingerprint();
+        let testnet_signer = HotSigner::generate(bitcoin::Network::Testnet).unwrap();
+        let policy = LianaPolicy::new_legacy(
+            PathInfo::Single(random_key),
+            [(52560, PathInfo::Single(std_key(&testnet_signer, 0)))]
+                .iter()
+                .cloned()
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(
+            LianaDescriptor::new(policy).lint(),
+            vec![
+                PolicyWarning::UnusualDerivationPath {
+                    fingerprint: random_fg,
+                    derivation_path: bip32::DerivationPath::master(),
+                },
+                PolicyWarning::MixedNetworks,
+            ]
+        );
+    }
+
     #[test]
     fn liana_desc_keys() {
         let secp = secp256k1::Secp256k1::signing_only();
@@ -1374,6 +2114,406 @@ mod tests {
         Psbt::from_str(psbt_str).unwrap()
     }
 
+    #[test]
+    fn absolute_recovery_paths() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let owner_key = PathInfo::Single(random_desc_key(&secp));
+        let heir_key = PathInfo::Single(random_desc_key(&secp));
+        let lawyer_key = PathInfo::Single(random_desc_key(&secp));
+        let relative_paths: BTreeMap<u16, PathInfo> =
+            [(52560, heir_key.clone())].iter().cloned().collect();
+        // The lawyer can spend after 2030-01-01, or after block 1'000'000.
+        let date_paths: BTreeMap<u32, PathInfo> = [(1_893_456_000, lawyer_key.clone())]
+            .iter()
+            .cloned()
+            .collect();
+        let height_paths: BTreeMap<u32, PathInfo> =
+            [(1_000_000, lawyer_key.clone())].iter().cloned().collect();
+
+        for is_taproot in [false, true] {
+            let policy = if is_taproot {
+                LianaPolicy::new(owner_key.clone(), relative_paths.clone())
+            } else {
+                LianaPolicy::new_legacy(owner_key.clone(), relative_paths.clone())
+            }
+            .unwrap();
+            assert!(policy.absolute_recovery_paths().is_empty());
+
+            // The absolute timelocks must be sane.
+            for insane_tl in [0, 1 << 31, u32::MAX] {
+                let paths = [(insane_tl, lawyer_key.clone())].iter().cloned().collect();
+                assert!(matches!(
+                    policy.clone().with_absolute_recovery_paths(paths),
+                    Err(LianaPolicyError::InsaneTimelock(tl)) if tl == insane_tl
+                ));
+            }
+
+            for abs_paths in [&date_paths, &height_paths] {
+                let policy = policy
+                    .clone()
+                    .with_absolute_recovery_paths(abs_paths.clone())
+                    .unwrap();
+
+                // The absolute recovery paths survive a roundtrip through the descriptor string.
+                let desc = LianaDescriptor::new(policy);
+                assert!(desc
+                    .to_string()
+                    .contains(&format!("after({})", abs_paths.keys().next().unwrap())));
+                let desc = LianaDescriptor::from_str(&desc.to_string()).unwrap();
+                let desc_policy = desc.policy();
+                assert_eq!(desc_policy.recovery_paths(), &relative_paths);
+                assert_eq!(desc_policy.absolute_recovery_paths(), abs_paths);
+                assert_eq!(desc.first_timelock_value(), Some(52560));
+
+                // The absolute recovery path is only available if the nLockTime is set to a value
+                // at least as high, of the same unit, and enabled by the nSequence.
+                let timelock = *abs_paths.keys().next().unwrap();
+                let mut txin = bitcoin::TxIn {
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    ..bitcoin::TxIn::default()
+                };
+                let psbt_in = PsbtIn::default();
+                let info = desc.partial_spend_info_txin(
+                    &psbt_in,
+                    &txin,
+                    absolute::LockTime::from_consensus(timelock - 1),
+                );
+                assert!(info.absolute_recovery_paths().is_empty());
+                for lock_time in [timelock, timelock + 42] {
+                    let lock_time = absolute::LockTime::from_consensus(lock_time);
+                    let info = desc.partial_spend_info_txin(&psbt_in, &txin, lock_time);
+                    let recov_info = &info.absolute_recovery_paths()[&timelock];
+                    assert_eq!(recov_info.threshold, 1);
+                    assert_eq!(recov_info.sigs_count, 0);
+                    assert!(info.recovery_paths().is_empty());
+                }
+                let other_unit = if timelock < 500_000_000 {
+                    1_500_000_000
+                } else {
+                    499_999_999
+                };
+                let info = desc.partial_spend_info_txin(
+                    &psbt_in,
+                    &txin,
+                    absolute::LockTime::from_consensus(other_unit),
+                );
+                assert!(info.absolute_recovery_paths().is_empty());
+                txin.sequence = Sequence::MAX;
+                let info = desc.partial_spend_info_txin(
+                    &psbt_in,
+                    &txin,
+                    absolute::LockTime::from_consensus(timelock),
+                );
+                assert!(info.absolute_recovery_paths().is_empty());
+            }
+
+            // All the recovery paths may be behind an absolute timelock, but there must be one.
+            let new_abs_policy = |abs_paths| {
+                if is_taproot {
+                    LianaPolicy::new_with_absolute_recovery_paths(owner_key.clone(), abs_paths)
+                } else {
+                    LianaPolicy::new_legacy_with_absolute_recovery_paths(
+                        owner_key.clone(),
+                        abs_paths,
+                    )
+                }
+            };
+            assert!(matches!(
+                new_abs_policy(BTreeMap::new()),
+                Err(LianaPolicyError::MissingRecoveryPath)
+            ));
+            let mut abs_paths = date_paths.clone();
+            abs_paths.insert(1_000_000, heir_key.clone());
+            let policy = new_abs_policy(abs_paths.clone()).unwrap();
+            let desc = LianaDescriptor::new(policy.clone());
+            let desc = LianaDescriptor::from_str(&desc.to_string()).unwrap();
+            assert_eq!(desc.policy(), policy);
+            assert!(desc.policy().recovery_paths().is_empty());
+            assert!(desc.policy().time_recovery_paths().is_empty());
+            assert_eq!(desc.first_relative_timelock(), None);
+            assert_eq!(desc.first_timelock_value(), None);
+            let info = desc.partial_spend_info_txin(
+                &PsbtIn::default(),
+                &bitcoin::TxIn {
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    ..bitcoin::TxIn::default()
+                },
+                absolute::LockTime::from_consensus(1_000_000),
+            );
+            assert_eq!(
+                info.absolute_recovery_paths().keys().collect::<Vec<_>>(),
+                vec![&1_000_000]
+            );
+        }
+    }
+
+    #[test]
+    fn time_recovery_paths() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let owner_key = PathInfo::Single(random_desc_key(&secp));
+        let heir_key = PathInfo::Single(random_desc_key(&secp));
+        let lawyer_key = PathInfo::Single(random_desc_key(&secp));
+        // The heir can spend after 90 days, the lawyer after 52560 blocks.
+        let time_paths: BTreeMap<u16, PathInfo> =
+            [(15_188, heir_key.clone())].iter().cloned().collect();
+        let relative_paths: BTreeMap<u16, PathInfo> =
+            [(52560, lawyer_key.clone())].iter().cloned().collect();
+
+        for is_taproot in [false, true] {
+            let new_policy = |recovery_paths, time_recovery_paths| {
+                if is_taproot {
+                    LianaPolicy::new_with_time_recovery_paths(
+                        owner_key.clone(),
+                        recovery_paths,
+                        time_recovery_paths,
+                    )
+                } else {
+                    LianaPolicy::new_legacy_with_time_recovery_paths(
+                        owner_key.clone(),
+                        recovery_paths,
+                        time_recovery_paths,
+                    )
+                }
+            };
+
+            // There must be at least one relative recovery path, and the timelocks must be sane.
+            assert!(matches!(
+                new_policy(BTreeMap::new(), BTreeMap::new()),
+                Err(LianaPolicyError::MissingRecoveryPath)
+            ));
+            assert!(matches!(
+                new_policy(
+                    BTreeMap::new(),
+                    [(0, heir_key.clone())].iter().cloned().collect()
+                ),
+                Err(LianaPolicyError::InsaneTimelock(_))
+            ));
+
+            for recovery_paths in [BTreeMap::new(), relative_paths.clone()] {
+                let policy = new_policy(recovery_paths.clone(), time_paths.clone()).unwrap();
+
+                // The time-based recovery paths survive a roundtrip through the descriptor string.
+                let desc = LianaDescriptor::new(policy);
+                assert!(desc
+                    .to_string()
+                    .contains(&format!("older({})", (1 << 22) | 15_188)));
+                let desc = LianaDescriptor::from_str(&desc.to_string()).unwrap();
+                let desc_policy = desc.policy();
+                assert_eq!(desc_policy.recovery_paths(), &recovery_paths);
+                assert_eq!(desc_policy.time_recovery_paths(), &time_paths);
+
+                // 90 days is about 12960 blocks, less than the height-based timelock.
+                assert_eq!(
+                    desc.first_relative_timelock(),
+                    Some(relative::Time::from_512_second_intervals(15_188).into())
+                );
+                assert_eq!(desc.first_timelock_value(), Some(12_961));
+
+                // The time-based recovery path is only available if the nSequence is set to a
+                // time-based relative locktime at least as high.
+                let psbt_in = PsbtIn::default();
+                let lock_time = absolute::LockTime::ZERO;
+                let mut txin = bitcoin::TxIn {
+                    sequence: Sequence::from_512_second_intervals(15_187),
+                    ..bitcoin::TxIn::default()
+                };
+                let info = desc.partial_spend_info_txin(&psbt_in, &txin, lock_time);
+                assert!(info.time_recovery_paths().is_empty());
+                for intervals in [15_188, 15_189] {
+                    txin.sequence = Sequence::from_512_second_intervals(intervals);
+                    let info = desc.partial_spend_info_txin(&psbt_in, &txin, lock_time);
+                    assert_eq!(info.time_recovery_paths()[&15_188].threshold, 1);
+                    assert!(info.recovery_paths().is_empty());
+                }
+                txin.sequence = Sequence::from_height(60_000);
+                let info = desc.partial_spend_info_txin(&psbt_in, &txin, lock_time);
+                assert!(info.time_recovery_paths().is_empty());
+                assert_eq!(info.recovery_paths().len(), recovery_paths.len());
+            }
+        }
+    }
+
+    #[test]
+    fn hash_locked_paths() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let owner_key = PathInfo::Single(random_desc_key(&secp));
+        let heir_keys = PathInfo::Multi(2, vec![random_desc_key(&secp), random_desc_key(&secp)]);
+        let preimage = [42; 32];
+        let sha_lock = HashLock::Sha256(bitcoin::hashes::sha256::Hash::hash(&preimage));
+        let hash160_lock = HashLock::Hash160(bitcoin::hashes::hash160::Hash::hash(&preimage));
+        assert!(sha_lock.is_unlocked_by(&preimage));
+        assert!(hash160_lock.is_unlocked_by(&preimage));
+        assert!(!sha_lock.is_unlocked_by(&[43; 32]));
+        // The preimage must be 32 bytes long.
+        let short_preimage = [42; 31];
+        let short_lock = HashLock::Sha256(bitcoin::hashes::sha256::Hash::hash(&short_preimage));
+        assert!(!short_lock.is_unlocked_by(&short_preimage));
+
+        for is_taproot in [false, true] {
+            let new_policy = |primary_path: PathInfo, recovery_path: PathInfo| {
+                let recovery_paths = [(52560, recovery_path)].iter().cloned().collect();
+                if is_taproot {
+                    LianaPolicy::new(primary_path, recovery_paths)
+                } else {
+                    LianaPolicy::new_legacy(primary_path, recovery_paths)
+                }
+            };
+
+            // A single hash lock per spending path.
+            let nested = heir_keys
+                .clone()
+                .with_hash_lock(sha_lock)
+                .with_hash_lock(hash160_lock);
+            assert!(matches!(
+                new_policy(owner_key.clone(), nested),
+                Err(LianaPolicyError::NestedHashLock)
+            ));
+
+            // Both the primary path and the recovery path may be hash-locked. The hash locks
+            // survive a roundtrip through the descriptor string.
+            for (primary_path, recovery_path) in [
+                (
+                    owner_key.clone(),
+                    heir_keys.clone().with_hash_lock(sha_lock),
+                ),
+                (
+                    owner_key.clone().with_hash_lock(hash160_lock),
+                    heir_keys.clone(),
+                ),
+            ] {
+                let policy = new_policy(primary_path.clone(), recovery_path.clone()).unwrap();
+                let desc = LianaDescriptor::new(policy);
+                let desc = LianaDescriptor::from_str(&desc.to_string()).unwrap();
+                let desc_policy = desc.policy();
+                for (desc_path, path) in [
+                    (desc_policy.primary_path(), &primary_path),
+                    (&desc_policy.recovery_paths()[&52560], &recovery_path),
+                ] {
+                    assert_eq!(desc_path.hash_lock(), path.hash_lock());
+                    assert_eq!(desc_path.thresh_origins(), path.thresh_origins());
+                }
+            }
+
+            // The preimage is accounted for when estimating the satisfaction size of the primary
+            // path.
+            let desc =
+                LianaDescriptor::new(new_policy(owner_key.clone(), heir_keys.clone()).unwrap());
+            let locked_desc = LianaDescriptor::new(
+                new_policy(
+                    owner_key.clone().with_hash_lock(sha_lock),
+                    heir_keys.clone(),
+                )
+                .unwrap(),
+            );
+            assert!(locked_desc.max_sat_weight(true) >= desc.max_sat_weight(true) + 33);
+
+            // The spend info reports the missing preimage until it's set in the PSBT.
+            let desc = LianaDescriptor::new(
+                new_policy(
+                    owner_key.clone(),
+                    heir_keys.clone().with_hash_lock(sha_lock),
+                )
+                .unwrap(),
+            );
+            let txin = bitcoin::TxIn {
+                sequence: Sequence::from_height(52560),
+                ..bitcoin::TxIn::default()
+            };
+            let mut psbt = Psbt::from_unsigned_tx(bitcoin::Transaction {
+                version: bitcoin::transaction::Version::TWO,
+                lock_time: absolute::LockTime::ZERO,
+                input: vec![txin.clone(), txin],
+                output: vec![bitcoin::TxOut::NULL],
+            })
+            .unwrap();
+            let info = desc.partial_spend_info(&psbt).unwrap();
+            assert!(!info.primary_path().missing_preimage);
+            assert!(info.recovery_paths()[&52560].missing_preimage);
+            assert!(matches!(
+                desc.add_preimage(&mut psbt, &[43; 32]),
+                Err(LianaDescError::UnknownPreimage)
+            ));
+            assert_eq!(desc.add_preimage(&mut psbt, &preimage).unwrap(), sha_lock);
+            assert!(psbt
+                .inputs
+                .iter()
+                .all(|psbt_in| sha_lock.preimage_in(psbt_in)));
+            let info = desc.partial_spend_info(&psbt).unwrap();
+            assert!(!info.recovery_paths()[&52560].missing_preimage);
+        }
+    }
+
+    #[test]
+    fn musig_primary_path() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let (key_a, key_b, key_c) = (
+            random_desc_key(&secp),
+            random_desc_key(&secp),
+            random_desc_key(&secp),
+        );
+        let musig_path = PathInfo::Musig(vec![key_a.clone(), key_b.clone()]);
+        let recovery_path = PathInfo::Single(key_c.clone());
+        let recovery_paths: BTreeMap<_, _> = [(52560, recovery_path.clone())].into();
+
+        // The aggregate key is used as the internal key, and the descriptor is displayed using a
+        // BIP390 musig() key expression.
+        let policy = LianaPolicy::new(musig_path.clone(), recovery_paths.clone()).unwrap();
+        let desc = LianaDescriptor::new(policy);
+        let desc_str = desc.to_string();
+        assert!(desc_str.starts_with(&format!(
+            "tr(musig({},{})/<0;1>/*,",
+            key_a.to_string().trim_end_matches("/<0;1>/*"),
+            key_b.to_string().trim_end_matches("/<0;1>/*"),
+        )));
+        let agg_key = musig_path.musig_aggregate_key().unwrap();
+        if let descriptor::Descriptor::Tr(ref tr_desc) = desc.multi_desc {
+            assert_eq!(*tr_desc.internal_key(), agg_key);
+        } else {
+            panic!("Must be Taproot");
+        }
+        roundtrip(&desc_str);
+        assert_eq!(LianaDescriptor::from_str(&desc_str).unwrap(), desc);
+        assert_eq!(desc.policy().primary_path, musig_path);
+
+        // The checksum is checked.
+        let (desc_body, checksum) = desc_str.split_at(desc_str.len() - 1);
+        let bad_checksum = if checksum == "q" { "p" } else { "q" };
+        let bad_desc_str = format!("{}{}", desc_body, bad_checksum);
+        assert!(LianaDescriptor::from_str(&bad_desc_str)
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid checksum"));
+
+        // Spending through the primary path only needs a single signature.
+        let single_desc = LianaDescriptor::new(
+            LianaPolicy::new(PathInfo::Single(key_a.clone()), recovery_paths.clone()).unwrap(),
+        );
+        assert_eq!(desc.max_sat_weight(true), single_desc.max_sat_weight(true));
+
+        // A MuSig2 aggregate key may only be used for the primary path of a Taproot descriptor,
+        // without hash lock, and needs at least two participants.
+        assert!(matches!(
+            LianaPolicy::new_legacy(musig_path.clone(), recovery_paths.clone()),
+            Err(LianaPolicyError::InvalidMusig)
+        ));
+        assert!(matches!(
+            LianaPolicy::new(PathInfo::Musig(vec![key_a.clone()]), recovery_paths.clone()),
+            Err(LianaPolicyError::InvalidMusig)
+        ));
+        assert!(matches!(
+            LianaPolicy::new(
+                PathInfo::Single(key_c.clone()),
+                [(52560, musig_path.clone())].into()
+            ),
+            Err(LianaPolicyError::InvalidMusig)
+        ));
+        let sha_lock = HashLock::Sha256(bitcoin::hashes::sha256::Hash::hash(&[42; 32]));
+        assert!(matches!(
+            LianaPolicy::new(musig_path.clone().with_hash_lock(sha_lock), recovery_paths),
+            Err(LianaPolicyError::InvalidMusig)
+        ));
+    }
+
     #[test]
     fn partial_spend_info_p2wsh() {
         let secp = secp256k1::Secp256k1::signing_only();
@@ -0,0 +1,276 @@
+//! Wallet policy
+//!
+//! Conversion of Liana descriptors to and from BIP388 wallet policies: a descriptor template,
+//! in which keys are replaced by `@i` placeholders, along with the vector of the keys' information.
+
+use crate::descriptors::{LianaDescError, LianaDescriptor};
+
+use miniscript::{
+    bitcoin::bip32,
+    descriptor::{self, DescriptorPublicKey},
+    ForEachKey,
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{error, fmt, str::FromStr};
+
+/// The derivation steps of the key placeholders in a descriptor template, and the multipath
+/// derivation steps they stand for.
+const TEMPLATE_DERIVATION: &str = "/**";
+const MULTIPATH_DERIVATION: &str = "/<0;1>/*";
+
+#[derive(Debug)]
+pub enum WalletPolicyError {
+    /// A key whose derivation steps can't be expressed in a descriptor template.
+    UnsupportedKey(String),
+    /// A key placeholder in the template doesn't refer to any key information.
+    InvalidPlaceholder(String),
+    /// A key information which isn't referred to in the template.
+    UnusedKey(usize),
+    /// A key information which isn't an extended public key without derivation steps.
+    InvalidKeyInfo(String),
+    Descriptor(LianaDescError),
+}
+
+impl fmt::Display for WalletPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedKey(key) => write!(
+                f,
+                "Key '{}' can't be expressed in a wallet policy: its derivation path must be of \
+                 the form /<M;N>/*.",
+                key
+            ),
+            Self::InvalidPlaceholder(p) => write!(f, "Invalid key placeholder '{}'.", p),
+            Self::UnusedKey(i) => write!(f, "Key @{} isn't used in the descriptor template.", i),
+            Self::InvalidKeyInfo(key) => write!(f, "Invalid key information '{}'.", key),
+            Self::Descriptor(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for WalletPolicyError {}
+
+/// A BIP388 wallet policy. The field names follow those used by the signing devices which
+/// implement BIP388, so the JSON serialization may be shared as is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletPolicy {
+    pub descriptor_template: String,
+    pub keys_info: Vec<String>,
+}
+
+impl fmt::Display for WalletPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_json::to_string(self).expect("Serialization can't fail.")
+        )
+    }
+}
+
+impl FromStr for WalletPolicy {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+// The key information of a multipath xpub: its origin and the xpub itself, without derivation
+// steps.
+fn key_info(key: &DescriptorPublicKey) -> Result<String, WalletPolicyError> {
+    match key {
+        DescriptorPublicKey::MultiXPub(xpub)
+            if xpub.wildcard == descriptor::Wildcard::Unhardened
+                && xpub.derivation_paths.paths().iter().all(|p| p.len() == 1) =>
+        {
+            Ok(DescriptorPublicKey::XPub(descriptor::DescriptorXKey {
+                origin: xpub.origin.clone(),
+                xkey: xpub.xkey,
+                derivation_path: bip32::DerivationPath::master(),
+                wildcard: descriptor::Wildcard::None,
+            })
+            .to_string())
+        }
+        _ => Err(WalletPolicyError::UnsupportedKey(key.to_string())),
+    }
+}
+
+impl LianaDescriptor {
+    /// Get the BIP388 wallet policy for this descriptor. Keys are numbered in the order in which
+    /// they first appear in the descriptor, and `/<0;1>/*` derivation steps are abbreviated as
+    /// `/**`.
+    pub fn to_wallet_policy(&self) -> Result<WalletPolicy, WalletPolicyError> {
+        let desc_str = self.to_string();
+        let desc_str = desc_str
+            .split('#')
+            .next()
+            .expect("Always at least one element.");
+
+        let mut keys = Vec::new();
+        self.multi_desc.for_each_key(|key| {
+            keys.push(key.clone());
+            true
+        });
+        keys.extend(self.musig_participants.iter().flatten().cloned());
+        let mut keys_info = Vec::with_capacity(keys.len());
+        for key in &keys {
+            let info = key_info(key)?;
+            // The MuSig2 aggregate key doesn't appear in the descriptor string, it's replaced by
+            // the participants' keys.
+            if desc_str.contains(&info) && !keys_info.contains(&info) {
+                keys_info.push(info);
+            }
+        }
+        keys_info.sort_by_key(|info| desc_str.find(info.as_str()));
+
+        // Replace the longest key information first, in case one is contained in another.
+        let mut by_len: Vec<_> = keys_info.iter().enumerate().collect();
+        by_len.sort_by_key(|(_, info)| std::cmp::Reverse(info.len()));
+        let template = by_len
+            .into_iter()
+            .fold(desc_str.to_string(), |template, (i, info)| {
+                template.replace(info.as_str(), &format!("@{}", i))
+            })
+            .replace(MULTIPATH_DERIVATION, TEMPLATE_DERIVATION);
+
+        Ok(WalletPolicy {
+            descriptor_template: template,
+            keys_info,
+        })
+    }
+
+    /// Get the descriptor for a BIP388 wallet policy. It must correspond to a valid Liana
+    /// spending policy.
+    pub fn from_wallet_policy(policy: &WalletPolicy) -> Result<LianaDescriptor, WalletPolicyError> {
+        for info in &policy.keys_info {
+            match DescriptorPublicKey::from_str(info) {
+                Ok(DescriptorPublicKey::XPub(xpub))
+                    if xpub.derivation_path.is_master()
+                        && xpub.wildcard == descriptor::Wildcard::None => {}
+                _ => return Err(WalletPolicyError::InvalidKeyInfo(info.clone())),
+            }
+        }
+
+        let template = policy
+            .descriptor_template
+            .replace(TEMPLATE_DERIVATION, MULTIPATH_DERIVATION);
+        let mut desc_str = String::with_capacity(template.len());
+        let mut used = vec![false; policy.keys_info.len()];
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '@' {
+                desc_str.push(c);
+                continue;
+            }
+            let mut index = String::new();
+            while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                index.push(d);
+            }
+            let info = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| {
+                    let info = policy.keys_info.get(i)?;
+                    used[i] = true;
+                    Some(info)
+                })
+                .ok_or_else(|| WalletPolicyError::InvalidPlaceholder(format!("@{}", index)))?;
+            desc_str.push_str(info);
+        }
+        if let Some(i) = used.iter().position(|used| !used) {
+            return Err(WalletPolicyError::UnusedKey(i));
+        }
+
+        LianaDescriptor::from_str(&desc_str).map_err(WalletPolicyError::Descriptor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        descriptors::{LianaPolicy, PathInfo},
+        signer::HotSigner,
+    };
+    use miniscript::bitcoin::{secp256k1, Network};
+
+    const PRIM_KEY: &str = "[92162c45]tpubD6NzVbkrYhZ4WzTf9SsD6h7AH7oQEippXK2KP8qvhMMqFoNeN5YFVi7vRyeRSDGtgd2bPyMxUNmHui8t5yCgszxPPxMafu1VVzDpg9aruYW";
+    const RECO_KEY: &str = "[abcdef01]tpubD6NzVbkrYhZ4Wdgu2yfdmrce5g4fiH1ZLmKhewsnNKupbi4sxjH1ZVAorkBLWSkhsjhg8kiq8C4BrBjMy3SjAKDyDdbuvUa1ToAHbiR98js";
+
+    #[test]
+    fn wallet_policy_roundtrip() {
+        // The primary key is reused in the recovery path, with different derivation steps.
+        let desc = LianaDescriptor::from_str(&format!(
+            "wsh(or_d(pk({}/<0;1>/*),and_v(v:multi(1,{}/<2;3>/*,{}/<0;1>/*),older(2))))",
+            PRIM_KEY, PRIM_KEY, RECO_KEY
+        ))
+        .unwrap();
+        let policy = desc.to_wallet_policy().unwrap();
+        assert_eq!(
+            policy.descriptor_template,
+            "wsh(or_d(pk(@0/**),and_v(v:multi(1,@0/<2;3>/*,@1/**),older(2))))"
+        );
+        assert_eq!(policy.keys_info, vec![PRIM_KEY, RECO_KEY]);
+        assert_eq!(LianaDescriptor::from_wallet_policy(&policy).unwrap(), desc);
+
+        // Through its JSON serialization.
+        let policy_str = policy.to_string();
+        assert!(policy_str.contains("\"descriptor_template\""));
+        let policy = WalletPolicy::from_str(&policy_str).unwrap();
+        assert_eq!(LianaDescriptor::from_wallet_policy(&policy).unwrap(), desc);
+
+        // Invalid placeholders, unused or invalid keys are rejected.
+        let mut invalid = policy.clone();
+        invalid.descriptor_template = invalid.descriptor_template.replace("@1", "@2");
+        assert!(matches!(
+            LianaDescriptor::from_wallet_policy(&invalid),
+            Err(WalletPolicyError::InvalidPlaceholder(_))
+        ));
+        let mut invalid = policy.clone();
+        invalid.descriptor_template = invalid.descriptor_template.replace("@1", "@0");
+        assert!(matches!(
+            LianaDescriptor::from_wallet_policy(&invalid),
+            Err(WalletPolicyError::UnusedKey(1))
+        ));
+        let mut invalid = policy;
+        invalid.keys_info[1] = format!("{}/0", RECO_KEY);
+        assert!(matches!(
+            LianaDescriptor::from_wallet_policy(&invalid),
+            Err(WalletPolicyError::InvalidKeyInfo(_))
+        ));
+    }
+
+    #[test]
+    fn wallet_policy_musig() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let keys: Vec<_> = (0..3)
+            .map(|_| {
+                let signer = HotSigner::generate(Network::Bitcoin).unwrap();
+                DescriptorPublicKey::from_str(&format!(
+                    "[{}]{}/<0;1>/*",
+                    signer.fingerprint(&secp),
+                    signer.xpub_at(&bip32::DerivationPath::master(), &secp)
+                ))
+                .unwrap()
+            })
+            .collect();
+        let policy = LianaPolicy::new(
+            PathInfo::Musig(keys[..2].to_vec()),
+            [(52560, PathInfo::Single(keys[2].clone()))].into(),
+        )
+        .unwrap();
+        let desc = LianaDescriptor::new(policy);
+
+        // The participants get a placeholder, not the aggregate key.
+        let policy = desc.to_wallet_policy().unwrap();
+        assert!(policy
+            .descriptor_template
+            .starts_with("tr(musig(@0,@1)/**,"));
+        assert!(policy.descriptor_template.contains("@2/**"));
+        assert_eq!(policy.keys_info.len(), 3);
+        assert_eq!(LianaDescriptor::from_wallet_policy(&policy).unwrap(), desc);
+    }
+}
@@ -0,0 +1,359 @@
+//! External signer
+//!
+//! Communicate with a signer through an external executable, using the same protocol as
+//! bitcoind's `-signer` option (itself compatible with HWI). The executable is invoked with a
+//! command as argument and answers with a JSON object on its standard output. PSBTs are exchanged
+//! as base64 strings.
+
+use miniscript::bitcoin::{self, bip32, psbt::Psbt};
+
+use std::{
+    error, fmt,
+    io::{self, Read, Write},
+    path,
+    process::{Command, Stdio},
+    str::FromStr,
+    thread, time,
+};
+
+// How long to wait for the executable to answer. It's generous as the signer may be waiting for
+// the user to confirm on the device.
+const COMMAND_TIMEOUT: time::Duration = time::Duration::from_secs(300);
+
+#[derive(Debug)]
+pub enum ExternalSignerError {
+    /// Failed to run the executable.
+    Command(io::Error),
+    /// The executable didn't answer in time. It was killed.
+    Timeout,
+    /// The executable exited with a failure, or returned an error.
+    Signer(String),
+    /// The response of the executable isn't the one expected.
+    InvalidResponse(String),
+    /// The PSBT returned by the signer isn't for the same transaction as the one sent.
+    PsbtMismatch,
+}
+
+impl fmt::Display for ExternalSignerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Command(e) => write!(f, "Failed to run the external signer: {}", e),
+            Self::Timeout => write!(f, "The external signer didn't answer in time."),
+            Self::Signer(e) => write!(f, "External signer error: {}", e),
+            Self::InvalidResponse(e) => {
+                write!(f, "Invalid response from the external signer: {}", e)
+            }
+            Self::PsbtMismatch => write!(
+                f,
+                "The PSBT returned by the external signer is for a different transaction."
+            ),
+        }
+    }
+}
+
+impl error::Error for ExternalSignerError {}
+
+// Read this output of the executable in a separate thread, so it never blocks on a full pipe.
+fn read_output(mut output: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        // On error, what was read so far is returned. It would fail to parse anyways.
+        let _ = output.read_to_end(&mut buf);
+        buf
+    })
+}
+
+// Run the executable with the given arguments, optionally writing to its standard input, and
+// parse its JSON response. The executable is killed if it doesn't exit within the timeout.
+fn run(
+    command: &path::Path,
+    args: &[&str],
+    stdin: Option<&str>,
+    timeout: time::Duration,
+) -> Result<serde_json::Value, ExternalSignerError> {
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ExternalSignerError::Command)?;
+    let stdout = read_output(child.stdout.take().expect("Stdout is piped."));
+    let stderr = read_output(child.stderr.take().expect("Stderr is piped."));
+    let mut input = child.stdin.take().expect("Stdin is piped.");
+    if let Some(data) = stdin {
+        if let Err(e) = input.write_all(data.as_bytes()) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(ExternalSignerError::Command(e));
+        }
+    }
+    // Dropping the handle to the standard input closes it.
+    drop(input);
+
+    let start = time::Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(ExternalSignerError::Command)? {
+            break status;
+        }
+        if start.elapsed() > timeout {
+            // Make sure it doesn't linger, the error if it exited in the meantime is irrelevant.
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(ExternalSignerError::Timeout);
+        }
+        thread::sleep(time::Duration::from_millis(10));
+    };
+    let stdout = stdout.join().expect("Never panics.");
+    let stderr = stderr.join().expect("Never panics.");
+    if !status.success() {
+        return Err(ExternalSignerError::Signer(format!(
+            "'{}' exited with {}: {}",
+            args.join(" "),
+            status,
+            String::from_utf8_lossy(&stderr).trim()
+        )));
+    }
+
+    let res: serde_json::Value = serde_json::from_slice(&stdout)
+        .map_err(|e| ExternalSignerError::InvalidResponse(e.to_string()))?;
+    if let Some(error) = res.get("error") {
+        return Err(ExternalSignerError::Signer(match error.as_str() {
+            Some(e) => e.to_string(),
+            None => error.to_string(),
+        }));
+    }
+    Ok(res)
+}
+
+// Get a string field from the executable's response.
+fn response_field<'a>(
+    res: &'a serde_json::Value,
+    field: &str,
+) -> Result<&'a str, ExternalSignerError> {
+    res.get(field).and_then(|v| v.as_str()).ok_or_else(|| {
+        ExternalSignerError::InvalidResponse(format!("missing '{}' in '{}'.", field, res))
+    })
+}
+
+/// A signer available through an external executable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalSigner {
+    command: path::PathBuf,
+    network: bitcoin::Network,
+    /// The fingerprint of the master key of this signer.
+    pub fingerprint: bip32::Fingerprint,
+    /// The name of this signer, as reported by the executable.
+    pub name: String,
+}
+
+impl ExternalSigner {
+    /// List the signers available through this executable for the given network. Signers which
+    /// don't report a fingerprint, for instance because they are locked, are ignored.
+    pub fn enumerate(
+        command: &path::Path,
+        network: bitcoin::Network,
+    ) -> Result<Vec<Self>, ExternalSignerError> {
+        let res = run(
+            command,
+            &["--chain", network.to_core_arg(), "enumerate"],
+            None,
+            COMMAND_TIMEOUT,
+        )?;
+        let entries = res.as_array().ok_or_else(|| {
+            ExternalSignerError::InvalidResponse(format!("expected a list of signers: '{}'.", res))
+        })?;
+
+        let mut signers: Vec<Self> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let fingerprint = match entry.get("fingerprint").and_then(|fg| fg.as_str()) {
+                Some(fg) => bip32::Fingerprint::from_str(fg).map_err(|e| {
+                    ExternalSignerError::InvalidResponse(format!(
+                        "invalid fingerprint '{}': {}",
+                        fg, e
+                    ))
+                })?,
+                None => continue,
+            };
+            if signers.iter().any(|s| s.fingerprint == fingerprint) {
+                continue;
+            }
+            let name = entry
+                .get("name")
+                .or_else(|| entry.get("model"))
+                .and_then(|name| name.as_str())
+                .unwrap_or_default()
+                .to_string();
+            signers.push(Self {
+                command: command.to_path_buf(),
+                network,
+                fingerprint,
+                name,
+            });
+        }
+
+        Ok(signers)
+    }
+
+    // Run a command of the executable for this signer.
+    fn run(
+        &self,
+        args: &[&str],
+        stdin: Option<&str>,
+    ) -> Result<serde_json::Value, ExternalSignerError> {
+        let fingerprint = self.fingerprint.to_string();
+        let mut all_args = Vec::with_capacity(args.len() + 4);
+        // The standard input flag must come first, as with bitcoind.
+        if stdin.is_some() {
+            all_args.push("--stdin");
+        }
+        all_args.extend_from_slice(&[
+            "--fingerprint",
+            &fingerprint,
+            "--chain",
+            self.network.to_core_arg(),
+        ]);
+        all_args.extend_from_slice(args);
+        run(&self.command, &all_args, stdin, COMMAND_TIMEOUT)
+    }
+
+    /// Get the xpub at the given derivation path from the master key of this signer.
+    pub fn get_xpub(
+        &self,
+        path: &bip32::DerivationPath,
+    ) -> Result<bip32::Xpub, ExternalSignerError> {
+        let res = self.run(&["getxpub", &path.to_string()], None)?;
+        let xpub = response_field(&res, "xpub")?;
+        bip32::Xpub::from_str(xpub).map_err(|e| {
+            ExternalSignerError::InvalidResponse(format!("invalid xpub '{}': {}", xpub, e))
+        })
+    }
+
+    /// Display the address for this descriptor, without wildcard, on the signer. Returns the
+    /// address as reported by the signer, for the caller to check it's the expected one.
+    pub fn display_address(&self, descriptor: &str) -> Result<String, ExternalSignerError> {
+        let res = self.run(&["displayaddress", "--desc", descriptor], None)?;
+        response_field(&res, "address").map(|addr| addr.to_string())
+    }
+
+    /// Have the signer sign this PSBT. The signatures it provides are merged into the PSBT.
+    pub fn sign_psbt(&self, mut psbt: Psbt) -> Result<Psbt, ExternalSignerError> {
+        let res = self.run(&[], Some(&format!("signtx {}\n", psbt)))?;
+        let signed_psbt = response_field(&res, "psbt")?;
+        let signed_psbt = Psbt::from_str(signed_psbt).map_err(|e| {
+            ExternalSignerError::InvalidResponse(format!("invalid PSBT '{}': {}", signed_psbt, e))
+        })?;
+        if signed_psbt.unsigned_tx != psbt.unsigned_tx {
+            return Err(ExternalSignerError::PsbtMismatch);
+        }
+        psbt.combine(signed_psbt)
+            .map_err(|_| ExternalSignerError::PsbtMismatch)?;
+        Ok(psbt)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::{absolute, transaction, Transaction, TxIn};
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    const XPUB: &str = "tpubD6NzVbkrYhZ4Wdgu2yfdmrce5g4fiH1ZLmKhewsnNKupbi4sxjH1ZVAorkBLWSkhsjhg8kiq8C4BrBjMy3SjAKDyDdbuvUa1ToAHbiR98js";
+
+    // An executable which answers the commands it's called with as expected for a signer with
+    // fingerprint 'abcdef01' on regtest.
+    fn signer_executable() -> path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "lianad-external-signer-{}-{:?}",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("signer.sh");
+        let script = format!(
+            r#"#!/bin/sh
+case "$*" in
+    "--chain regtest enumerate")
+        echo '[{{"fingerprint": "abcdef01", "name": "HSM"}}, {{"error": "Locked"}}]';;
+    "--fingerprint abcdef01 --chain regtest getxpub m/48'/1'/0'/2'")
+        echo '{{"xpub": "{}"}}';;
+    "--fingerprint abcdef01 --chain regtest displayaddress --desc "*)
+        echo '{{"address": "bcrt1qaddress"}}';;
+    "--stdin --fingerprint abcdef01 --chain regtest")
+        read -r cmd psbt
+        echo "{{\"psbt\": \"$psbt\"}}";;
+    "--fingerprint abcdef01 --chain regtest getxpub m/0")
+        echo "Device disconnected" >&2
+        exit 1;;
+    "--chain regtest hang")
+        exec sleep 10;;
+    *)
+        echo '{{"error": "Unknown command"}}';;
+esac
+"#,
+            XPUB
+        );
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o700)).unwrap();
+        path
+    }
+
+    #[test]
+    fn external_signer() {
+        let command = signer_executable();
+        let signers = ExternalSigner::enumerate(&command, bitcoin::Network::Regtest).unwrap();
+        assert_eq!(signers.len(), 1);
+        let signer = &signers[0];
+        assert_eq!(signer.fingerprint.to_string(), "abcdef01");
+        assert_eq!(signer.name, "HSM");
+
+        let path = bip32::DerivationPath::from_str("m/48'/1'/0'/2'").unwrap();
+        assert_eq!(signer.get_xpub(&path).unwrap().to_string(), XPUB);
+        assert_eq!(
+            signer.display_address("wpkh(xpub/0/1)").unwrap(),
+            "bcrt1qaddress"
+        );
+
+        let psbt = Psbt::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: Vec::new(),
+        })
+        .unwrap();
+        assert_eq!(signer.sign_psbt(psbt.clone()).unwrap(), psbt);
+
+        // Errors reported by the executable, or through its exit status, are surfaced.
+        let path = bip32::DerivationPath::from_str("m/0").unwrap();
+        assert!(matches!(
+            signer.get_xpub(&path),
+            Err(ExternalSignerError::Signer(e)) if e.contains("Device disconnected")
+        ));
+        assert!(matches!(
+            ExternalSigner::enumerate(&command, bitcoin::Network::Testnet),
+            Err(ExternalSignerError::Signer(e)) if e == "Unknown command"
+        ));
+        assert!(matches!(
+            ExternalSigner::enumerate(
+                &command.with_file_name("none.sh"),
+                bitcoin::Network::Regtest
+            ),
+            Err(ExternalSignerError::Command(_))
+        ));
+
+        // The executable is killed if it doesn't answer in time.
+        let start = time::Instant::now();
+        assert!(matches!(
+            run(
+                &command,
+                &["--chain", "regtest", "hang"],
+                None,
+                time::Duration::from_millis(200)
+            ),
+            Err(ExternalSignerError::Timeout)
+        ));
+        assert!(start.elapsed() < time::Duration::from_secs(5));
+
+        fs::remove_dir_all(command.parent().unwrap()).unwrap();
+    }
+}
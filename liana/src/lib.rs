@@ -1,4 +1,9 @@
+pub mod bip21;
+pub mod bsms;
 pub mod descriptors;
+pub mod external_signer;
+pub mod musig;
+pub mod payjoin;
 pub mod random;
 pub mod signer;
 pub mod spend;
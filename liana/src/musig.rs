@@ -0,0 +1,1409 @@
+//! MuSig2
+//!
+//! An implementation of the MuSig2 multi-signature scheme (BIP327), to use an aggregate of the
+//! keys of several participants as the internal key of a Taproot descriptor. Spending through the
+//! key path then looks like (and costs as much as) a single-signature spend.
+//!
+//! Aggregate keys are used as extended public keys (BIP328) in descriptors, and the signing
+//! sessions are coordinated through PSBT fields (BIP373).
+
+use crate::random;
+
+use std::{collections::BTreeMap, convert::TryInto, error, fmt};
+
+use miniscript::bitcoin::{
+    bip32,
+    hashes::{sha256, Hash, HashEngine},
+    psbt::{raw, Input as PsbtIn},
+    secp256k1::{
+        self,
+        constants::{CURVE_ORDER, GENERATOR_X},
+        schnorr, Parity, PublicKey, Scalar, SecretKey, XOnlyPublicKey,
+    },
+    taproot::TapTweakHash,
+};
+
+// The chaincode of the BIP32 extended public key for an aggregate key, as defined in BIP328.
+const AGGREGATE_XPUB_CHAINCODE: [u8; 32] = [
+    0x86, 0x80, 0x87, 0xca, 0x02, 0xa6, 0xf9, 0x74, 0xc4, 0x59, 0x89, 0x24, 0xc3, 0x6b, 0x57, 0x76,
+    0x2d, 0x32, 0xcb, 0x45, 0x71, 0x71, 0x67, 0xe3, 0x00, 0x62, 0x2c, 0x71, 0x67, 0xe3, 0x89, 0x65,
+];
+
+// The BIP373 PSBT input fields.
+const PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x1a;
+const PSBT_IN_MUSIG2_PUB_NONCE: u8 = 0x1b;
+const PSBT_IN_MUSIG2_PARTIAL_SIG: u8 = 0x1c;
+
+#[derive(Debug)]
+pub enum MusigError {
+    Randomness(random::RandomnessError),
+    /// The participants' keys don't aggregate to a valid public key.
+    KeyAggregation,
+    /// Tweaking the aggregate key doesn't result in a valid public key.
+    InvalidTweak,
+    InvalidPubNonce,
+    InvalidAggNonce,
+    InvalidPartialSig,
+    /// The secret key used for signing isn't the one of a participant, or not the one the secret
+    /// nonce was generated for.
+    SecretKeyMismatch,
+    /// One of the MuSig2 fields of the PSBT input is malformed or inconsistent.
+    InvalidPsbtField,
+    /// An intermediate value in the signing session is zero. This happens with negligible
+    /// probability.
+    DegenerateSession,
+    /// The descriptor key expression for an aggregate key is malformed.
+    InvalidDescriptorKey(String),
+}
+
+impl fmt::Display for MusigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Randomness(e) => write!(f, "Error when generating a MuSig2 nonce: {}", e),
+            Self::KeyAggregation => write!(f, "Keys don't aggregate to a valid MuSig2 key."),
+            Self::InvalidTweak => write!(f, "Invalid tweak of the MuSig2 aggregate key."),
+            Self::InvalidPubNonce => write!(f, "Invalid MuSig2 public nonce."),
+            Self::InvalidAggNonce => write!(f, "Invalid MuSig2 aggregate nonce."),
+            Self::InvalidPartialSig => write!(f, "Invalid MuSig2 partial signature."),
+            Self::SecretKeyMismatch => write!(
+                f,
+                "The secret key doesn't correspond to the MuSig2 participant or nonce."
+            ),
+            Self::InvalidPsbtField => write!(f, "Malformed or inconsistent MuSig2 PSBT field."),
+            Self::DegenerateSession => write!(f, "Degenerate MuSig2 signing session."),
+            Self::InvalidDescriptorKey(s) => {
+                write!(f, "Invalid MuSig2 descriptor key expression '{}'.", s)
+            }
+        }
+    }
+}
+
+impl error::Error for MusigError {}
+
+// The BIP340 tagged hash of the concatenation of the given data.
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag_hash.as_ref());
+    engine.input(tag_hash.as_ref());
+    for d in data {
+        engine.input(d);
+    }
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+// Interpret these 32 bytes as a big endian integer, reduced modulo the curve order.
+fn scalar_mod_n(bytes: [u8; 32]) -> Scalar {
+    Scalar::from_be_bytes(bytes).unwrap_or_else(|_| {
+        // The value is between n and 2^256, a single subtraction of n is enough.
+        let mut reduced = [0; 32];
+        let mut borrow = 0;
+        for i in (0..32).rev() {
+            let diff = bytes[i] as i16 - CURVE_ORDER[i] as i16 - borrow;
+            reduced[i] = diff.rem_euclid(256) as u8;
+            borrow = (diff < 0) as i16;
+        }
+        Scalar::from_be_bytes(reduced).expect("Reduced value is below the curve order.")
+    })
+}
+
+// Add a scalar to another, modulo the curve order. Zero is represented as `None`.
+fn scalar_add(a: Option<SecretKey>, b: &Scalar) -> Option<SecretKey> {
+    match a {
+        // This only fails if the sum is zero.
+        Some(a) => a.add_tweak(b).ok(),
+        None => SecretKey::from_slice(&b.to_be_bytes()).ok(),
+    }
+}
+
+// The generator point of secp256k1.
+fn generator() -> PublicKey {
+    PublicKey::from_slice(&[&[0x02][..], &GENERATOR_X].concat())
+        .expect("The generator is a valid point.")
+}
+
+// Add two points. The point at infinity is represented as `None`.
+fn point_add(a: Option<PublicKey>, b: Option<PublicKey>) -> Option<PublicKey> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.combine(&b).ok(),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn is_odd(point: &PublicKey) -> bool {
+    point.x_only_public_key().1 == Parity::Odd
+}
+
+// The "KeyAgg coefficient" of a key within a list of keys.
+fn key_agg_coeff(keys: &[PublicKey], key: &PublicKey) -> Scalar {
+    // The second distinct key in the list always gets a coefficient of 1.
+    if keys.iter().find(|k| *k != &keys[0]) == Some(key) {
+        return Scalar::ONE;
+    }
+    let keys_ser: Vec<u8> = keys.iter().flat_map(|k| k.serialize()).collect();
+    let keys_hash = tagged_hash("KeyAgg list", &[&keys_ser]);
+    scalar_mod_n(tagged_hash(
+        "KeyAgg coefficient",
+        &[&keys_hash, &key.serialize()],
+    ))
+}
+
+/// Sort the participants' keys, as done before aggregating them in descriptors.
+pub fn key_sort(keys: &mut [PublicKey]) {
+    keys.sort_by_key(|k| k.serialize());
+}
+
+/// The aggregate of the participants' keys, along with the tweaks applied to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAggContext {
+    keys: Vec<PublicKey>,
+    q: PublicKey,
+    // Whether the accumulated sign (gacc) is negative.
+    gacc_neg: bool,
+    // The accumulated tweak (tacc). Zero is represented as `None`.
+    tacc: Option<SecretKey>,
+}
+
+impl KeyAggContext {
+    /// Aggregate these keys, in this order.
+    pub fn new(
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+        keys: Vec<PublicKey>,
+    ) -> Result<Self, MusigError> {
+        let terms = keys
+            .iter()
+            .map(|k| k.mul_tweak(secp, &key_agg_coeff(&keys, k)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| MusigError::KeyAggregation)?;
+        let q = PublicKey::combine_keys(&terms.iter().collect::<Vec<_>>())
+            .map_err(|_| MusigError::KeyAggregation)?;
+        Ok(Self {
+            keys,
+            q,
+            gacc_neg: false,
+            tacc: None,
+        })
+    }
+
+    /// Tweak the aggregate key. A plain tweak is used for BIP32 derivation, an x-only tweak for
+    /// the BIP341 Taproot tweak.
+    pub fn tweak(
+        self,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+        tweak: &Scalar,
+        is_xonly: bool,
+    ) -> Result<Self, MusigError> {
+        let negate = is_xonly && is_odd(&self.q);
+        let (q, tacc) = if negate {
+            (self.q.negate(secp), self.tacc.map(SecretKey::negate))
+        } else {
+            (self.q, self.tacc)
+        };
+        Ok(Self {
+            keys: self.keys,
+            q: q.add_exp_tweak(secp, tweak)
+                .map_err(|_| MusigError::InvalidTweak)?,
+            gacc_neg: self.gacc_neg ^ negate,
+            tacc: scalar_add(tacc, tweak),
+        })
+    }
+
+    /// The participants' keys.
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.keys
+    }
+
+    /// The (tweaked) aggregate key.
+    pub fn aggregate_key(&self) -> PublicKey {
+        self.q
+    }
+}
+
+/// Get the BIP328 extended public key for the aggregate of these participants' keys. The keys
+/// are sorted before aggregation.
+pub fn aggregate_xpub(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    mut keys: Vec<PublicKey>,
+    network: miniscript::bitcoin::Network,
+) -> Result<bip32::Xpub, MusigError> {
+    key_sort(&mut keys);
+    let ctx = KeyAggContext::new(secp, keys)?;
+    Ok(bip32::Xpub {
+        network,
+        depth: 0,
+        parent_fingerprint: [0; 4].into(),
+        child_number: 0.into(),
+        public_key: ctx.q,
+        chain_code: AGGREGATE_XPUB_CHAINCODE.into(),
+    })
+}
+
+/// The secret part of a nonce. It must never be reused across signing sessions, which is why it
+/// can't be copied nor serialized.
+#[derive(Debug)]
+pub struct SecNonce {
+    k1: SecretKey,
+    k2: SecretKey,
+    pk: PublicKey,
+}
+
+/// The public part of a nonce, to be shared with the other participants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PubNonce {
+    r1: PublicKey,
+    r2: PublicKey,
+}
+
+impl PubNonce {
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut ser = [0; 66];
+        ser[..33].copy_from_slice(&self.r1.serialize());
+        ser[33..].copy_from_slice(&self.r2.serialize());
+        ser
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Self, MusigError> {
+        if data.len() != 66 {
+            return Err(MusigError::InvalidPubNonce);
+        }
+        let parse = |data| PublicKey::from_slice(data).map_err(|_| MusigError::InvalidPubNonce);
+        Ok(Self {
+            r1: parse(&data[..33])?,
+            r2: parse(&data[33..])?,
+        })
+    }
+}
+
+/// The aggregate of all the participants' public nonces. Either of the points may be at
+/// infinity, represented as `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggNonce {
+    r1: Option<PublicKey>,
+    r2: Option<PublicKey>,
+}
+
+impl AggNonce {
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut ser = [0; 66];
+        if let Some(r1) = self.r1 {
+            ser[..33].copy_from_slice(&r1.serialize());
+        }
+        if let Some(r2) = self.r2 {
+            ser[33..].copy_from_slice(&r2.serialize());
+        }
+        ser
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Self, MusigError> {
+        if data.len() != 66 {
+            return Err(MusigError::InvalidAggNonce);
+        }
+        // The point at infinity is serialized as 33 zero bytes.
+        let parse = |data: &[u8]| {
+            if data.iter().all(|b| *b == 0) {
+                return Ok(None);
+            }
+            PublicKey::from_slice(data)
+                .map(Some)
+                .map_err(|_| MusigError::InvalidAggNonce)
+        };
+        Ok(Self {
+            r1: parse(&data[..33])?,
+            r2: parse(&data[33..])?,
+        })
+    }
+}
+
+/// A participant's partial signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialSig(Scalar);
+
+impl PartialSig {
+    pub fn serialize(&self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Self, MusigError> {
+        let data: [u8; 32] = data.try_into().map_err(|_| MusigError::InvalidPartialSig)?;
+        Scalar::from_be_bytes(data)
+            .map(Self)
+            .map_err(|_| MusigError::InvalidPartialSig)
+    }
+}
+
+/// Generate a nonce for a participant. The optional secret key, aggregate key, message and extra
+/// input are only used to strengthen the generation against a weak randomness source.
+pub fn nonce_gen(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    sk: Option<&SecretKey>,
+    pk: &PublicKey,
+    aggregate_key: Option<&XOnlyPublicKey>,
+    msg: Option<&[u8]>,
+    extra_in: Option<&[u8]>,
+) -> Result<(SecNonce, PubNonce), MusigError> {
+    let rand = random::random_bytes().map_err(MusigError::Randomness)?;
+    nonce_gen_internal(secp, rand, sk, pk, aggregate_key, msg, extra_in)
+}
+
+fn nonce_gen_internal(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    mut rand: [u8; 32],
+    sk: Option<&SecretKey>,
+    pk: &PublicKey,
+    aggregate_key: Option<&XOnlyPublicKey>,
+    msg: Option<&[u8]>,
+    extra_in: Option<&[u8]>,
+) -> Result<(SecNonce, PubNonce), MusigError> {
+    if let Some(sk) = sk {
+        let aux = tagged_hash("MuSig/aux", &[&rand]);
+        for (r, (s, a)) in rand
+            .iter_mut()
+            .zip(sk.secret_bytes().iter().zip(aux.iter()))
+        {
+            *r = s ^ a;
+        }
+    }
+    let aggregate_key = aggregate_key
+        .map(|k| k.serialize().to_vec())
+        .unwrap_or_default();
+    let msg_prefixed = match msg {
+        None => vec![0],
+        Some(msg) => [&[1][..], &(msg.len() as u64).to_be_bytes(), msg].concat(),
+    };
+    let extra_in = extra_in.unwrap_or_default();
+    let k = |i: u8| {
+        let hash = tagged_hash(
+            "MuSig/nonce",
+            &[
+                &rand,
+                &[33],
+                &pk.serialize(),
+                &[aggregate_key.len() as u8],
+                &aggregate_key,
+                &msg_prefixed,
+                &(extra_in.len() as u32).to_be_bytes(),
+                extra_in,
+                &[i],
+            ],
+        );
+        SecretKey::from_slice(&scalar_mod_n(hash).to_be_bytes())
+            .map_err(|_| MusigError::DegenerateSession)
+    };
+    let (k1, k2) = (k(0)?, k(1)?);
+    let pub_nonce = PubNonce {
+        r1: k1.public_key(secp),
+        r2: k2.public_key(secp),
+    };
+    Ok((SecNonce { k1, k2, pk: *pk }, pub_nonce))
+}
+
+/// Aggregate the public nonces of all participants.
+pub fn nonce_agg(pub_nonces: &[PubNonce]) -> AggNonce {
+    pub_nonces.iter().fold(
+        AggNonce { r1: None, r2: None },
+        |AggNonce { r1, r2 }, nonce| AggNonce {
+            r1: point_add(r1, Some(nonce.r1)),
+            r2: point_add(r2, Some(nonce.r2)),
+        },
+    )
+}
+
+// The values shared by all participants in a signing session.
+struct SessionValues {
+    b: Scalar,
+    r: PublicKey,
+    e: Scalar,
+}
+
+impl SessionValues {
+    fn new(
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+        ctx: &KeyAggContext,
+        agg_nonce: &AggNonce,
+        msg: &[u8],
+    ) -> Result<Self, MusigError> {
+        let q_x = ctx.q.x_only_public_key().0.serialize();
+        let b = scalar_mod_n(tagged_hash(
+            "MuSig/noncecoef",
+            &[&agg_nonce.serialize(), &q_x, msg],
+        ));
+        let r2 = agg_nonce
+            .r2
+            .map(|r2| r2.mul_tweak(secp, &b))
+            .transpose()
+            .map_err(|_| MusigError::DegenerateSession)?;
+        // If the final nonce is at infinity, use the generator instead.
+        let r = point_add(agg_nonce.r1, r2).unwrap_or_else(generator);
+        let r_x = r.x_only_public_key().0.serialize();
+        let e = scalar_mod_n(tagged_hash("BIP0340/challenge", &[&r_x, &q_x, msg]));
+        Ok(Self { b, r, e })
+    }
+}
+
+/// Create a partial signature for this message. Consumes the secret nonce so it can't be reused.
+pub fn sign(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Signing + secp256k1::Verification>,
+    sec_nonce: SecNonce,
+    sk: &SecretKey,
+    ctx: &KeyAggContext,
+    agg_nonce: &AggNonce,
+    msg: &[u8],
+) -> Result<PartialSig, MusigError> {
+    let SessionValues { b, r, e } = SessionValues::new(secp, ctx, agg_nonce, msg)?;
+    let pk = sk.public_key(secp);
+    if pk != sec_nonce.pk || !ctx.keys.contains(&pk) {
+        return Err(MusigError::SecretKeyMismatch);
+    }
+    let (k1, k2) = if is_odd(&r) {
+        (sec_nonce.k1.negate(), sec_nonce.k2.negate())
+    } else {
+        (sec_nonce.k1, sec_nonce.k2)
+    };
+    let d = if is_odd(&ctx.q) ^ ctx.gacc_neg {
+        sk.negate()
+    } else {
+        *sk
+    };
+
+    // s = k1 + b * k2 + e * a * d
+    let a = key_agg_coeff(&ctx.keys, &pk);
+    let degenerate = |_| MusigError::DegenerateSession;
+    let bk2 = k2.mul_tweak(&b).map_err(degenerate)?;
+    let ead = d
+        .mul_tweak(&e)
+        .and_then(|d| d.mul_tweak(&a))
+        .map_err(degenerate)?;
+    let s = scalar_add(Some(k1), &bk2.into())
+        .and_then(|s| scalar_add(Some(s), &ead.into()))
+        .ok_or(MusigError::DegenerateSession)?;
+    let sig = PartialSig(s.into());
+
+    // Make sure we never hand out an invalid partial signature.
+    let pub_nonce = PubNonce {
+        r1: sec_nonce.k1.public_key(secp),
+        r2: sec_nonce.k2.public_key(secp),
+    };
+    if !partial_sig_verify(secp, &sig, &pub_nonce, &pk, ctx, agg_nonce, msg) {
+        return Err(MusigError::InvalidPartialSig);
+    }
+    Ok(sig)
+}
+
+/// Verify the partial signature of a participant, given its public nonce.
+pub fn partial_sig_verify(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    sig: &PartialSig,
+    pub_nonce: &PubNonce,
+    pk: &PublicKey,
+    ctx: &KeyAggContext,
+    agg_nonce: &AggNonce,
+    msg: &[u8],
+) -> bool {
+    let SessionValues { b, r, e } = match SessionValues::new(secp, ctx, agg_nonce, msg) {
+        Ok(values) => values,
+        Err(_) => return false,
+    };
+    if !ctx.keys.contains(pk) {
+        return false;
+    }
+
+    // s * G == Re + e * a * g * P
+    let re = match pub_nonce
+        .r2
+        .mul_tweak(secp, &b)
+        .ok()
+        .and_then(|r2| point_add(Some(pub_nonce.r1), Some(r2)))
+    {
+        Some(re) if is_odd(&r) => re.negate(secp),
+        Some(re) => re,
+        None => return false,
+    };
+    let ea = SecretKey::from_slice(&e.to_be_bytes())
+        .and_then(|e| e.mul_tweak(&key_agg_coeff(&ctx.keys, pk)));
+    let eap = match ea.and_then(|ea| pk.mul_tweak(secp, &ea.into())) {
+        Ok(eap) if is_odd(&ctx.q) ^ ctx.gacc_neg => eap.negate(secp),
+        Ok(eap) => eap,
+        Err(_) => return false,
+    };
+    let sg = generator().mul_tweak(secp, &sig.0);
+    match (sg, point_add(Some(re), Some(eap))) {
+        (Ok(sg), Some(expected)) => sg == expected,
+        _ => false,
+    }
+}
+
+/// Aggregate the partial signatures of all participants into a BIP340 signature for the tweaked
+/// aggregate key.
+pub fn partial_sig_agg(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ctx: &KeyAggContext,
+    agg_nonce: &AggNonce,
+    msg: &[u8],
+    sigs: &[PartialSig],
+) -> Result<schnorr::Signature, MusigError> {
+    let SessionValues { r, e, .. } = SessionValues::new(secp, ctx, agg_nonce, msg)?;
+
+    // s = s1 + .. + su + e * g * tacc
+    let mut s = sigs.iter().fold(None, |s, sig| scalar_add(s, &sig.0));
+    if let Some(tacc) = ctx.tacc {
+        let etacc = tacc
+            .mul_tweak(&e)
+            .map_err(|_| MusigError::DegenerateSession)?;
+        let etacc = if is_odd(&ctx.q) {
+            etacc.negate()
+        } else {
+            etacc
+        };
+        s = scalar_add(s, &etacc.into());
+    }
+
+    let mut sig = [0; 64];
+    sig[..32].copy_from_slice(&r.x_only_public_key().0.serialize());
+    if let Some(s) = s {
+        sig[32..].copy_from_slice(&s.secret_bytes());
+    }
+    schnorr::Signature::from_slice(&sig).map_err(|_| MusigError::DegenerateSession)
+}
+
+// Parse a concatenation of compressed public keys.
+fn parse_pubkeys(data: &[u8]) -> Result<Vec<PublicKey>, MusigError> {
+    let chunks = data.chunks_exact(33);
+    if data.is_empty() || !chunks.remainder().is_empty() {
+        return Err(MusigError::InvalidPsbtField);
+    }
+    chunks
+        .map(|k| PublicKey::from_slice(k).map_err(|_| MusigError::InvalidPsbtField))
+        .collect()
+}
+
+/// Set the participants of an aggregate key in this PSBT input. The keys are sorted as they would
+/// be for aggregation.
+pub fn set_participants(psbt_in: &mut PsbtIn, aggregate_key: &PublicKey, mut keys: Vec<PublicKey>) {
+    key_sort(&mut keys);
+    let key = raw::Key {
+        type_value: PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS,
+        key: aggregate_key.serialize().to_vec(),
+    };
+    psbt_in
+        .unknown
+        .insert(key, keys.iter().flat_map(|k| k.serialize()).collect());
+}
+
+/// A MuSig2 signing session for spending a PSBT input through the Taproot key path, when the
+/// internal key is derived from an aggregate key.
+#[derive(Debug, Clone)]
+pub struct KeyPathSession {
+    ctx: KeyAggContext,
+    internal_key: PublicKey,
+}
+
+impl KeyPathSession {
+    /// Get the signing session for the key path of this PSBT input. Returns `None` if the internal
+    /// key isn't derived from an aggregate key whose participants are known.
+    pub fn from_psbt_in(
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+        psbt_in: &PsbtIn,
+    ) -> Result<Option<Self>, MusigError> {
+        let int_key = match psbt_in.tap_internal_key {
+            Some(int_key) => int_key,
+            None => return Ok(None),
+        };
+        let (fg, der_path) = match psbt_in.tap_key_origins.get(&int_key) {
+            Some((_, origin)) => origin,
+            None => return Ok(None),
+        };
+
+        for (key, value) in &psbt_in.unknown {
+            if key.type_value != PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS {
+                continue;
+            }
+            let aggregate_key =
+                PublicKey::from_slice(&key.key).map_err(|_| MusigError::InvalidPsbtField)?;
+            let mut keys = parse_pubkeys(value)?;
+            key_sort(&mut keys);
+            let mut ctx = KeyAggContext::new(secp, keys)?;
+            if ctx.q != aggregate_key {
+                return Err(MusigError::InvalidPsbtField);
+            }
+            let mut xpub = aggregate_xpub(secp, ctx.keys.clone(), bitcoin_network())?;
+            if xpub.fingerprint() != *fg {
+                continue;
+            }
+
+            // Apply the BIP32 derivation as plain tweaks, and then the Taproot tweak.
+            for child in der_path {
+                let (tweak, _) = xpub
+                    .ckd_pub_tweak(*child)
+                    .map_err(|_| MusigError::InvalidTweak)?;
+                ctx = ctx.tweak(secp, &tweak.into(), false)?;
+                xpub = xpub
+                    .ckd_pub(secp, *child)
+                    .map_err(|_| MusigError::InvalidTweak)?;
+            }
+            let internal_key = ctx.q;
+            if internal_key.x_only_public_key().0 != int_key {
+                return Err(MusigError::InvalidPsbtField);
+            }
+            let tweak = TapTweakHash::from_key_and_tweak(int_key, psbt_in.tap_merkle_root);
+            ctx = ctx.tweak(secp, &tweak.to_scalar(), true)?;
+            return Ok(Some(Self { ctx, internal_key }));
+        }
+
+        Ok(None)
+    }
+
+    /// The participants' keys.
+    pub fn participants(&self) -> &[PublicKey] {
+        self.ctx.keys()
+    }
+
+    /// The Taproot output key this session produces a signature for.
+    pub fn output_key(&self) -> XOnlyPublicKey {
+        self.ctx.q.x_only_public_key().0
+    }
+
+    // The key of a nonce or partial signature field for this participant.
+    fn field_key(&self, type_value: u8, participant: &PublicKey) -> raw::Key {
+        raw::Key {
+            type_value,
+            key: [participant.serialize(), self.internal_key.serialize()].concat(),
+        }
+    }
+
+    /// The public nonces provided by the participants so far.
+    pub fn pub_nonces(
+        &self,
+        psbt_in: &PsbtIn,
+    ) -> Result<BTreeMap<PublicKey, PubNonce>, MusigError> {
+        self.participants()
+            .iter()
+            .filter_map(|pk| {
+                let nonce = psbt_in
+                    .unknown
+                    .get(&self.field_key(PSBT_IN_MUSIG2_PUB_NONCE, pk))?;
+                Some(PubNonce::from_slice(nonce).map(|nonce| (*pk, nonce)))
+            })
+            .collect()
+    }
+
+    pub fn set_pub_nonce(&self, psbt_in: &mut PsbtIn, participant: &PublicKey, nonce: &PubNonce) {
+        psbt_in.unknown.insert(
+            self.field_key(PSBT_IN_MUSIG2_PUB_NONCE, participant),
+            nonce.serialize().to_vec(),
+        );
+    }
+
+    /// The partial signatures provided by the participants so far.
+    pub fn partial_sigs(
+        &self,
+        psbt_in: &PsbtIn,
+    ) -> Result<BTreeMap<PublicKey, PartialSig>, MusigError> {
+        self.participants()
+            .iter()
+            .filter_map(|pk| {
+                let sig = psbt_in
+                    .unknown
+                    .get(&self.field_key(PSBT_IN_MUSIG2_PARTIAL_SIG, pk))?;
+                Some(PartialSig::from_slice(sig).map(|sig| (*pk, sig)))
+            })
+            .collect()
+    }
+
+    pub fn set_partial_sig(&self, psbt_in: &mut PsbtIn, participant: &PublicKey, sig: &PartialSig) {
+        psbt_in.unknown.insert(
+            self.field_key(PSBT_IN_MUSIG2_PARTIAL_SIG, participant),
+            sig.serialize().to_vec(),
+        );
+    }
+
+    /// Remove the partial signatures of all the participants, for instance because they were
+    /// made for a nonce which was since replaced.
+    pub fn remove_partial_sigs(&self, psbt_in: &mut PsbtIn) {
+        for participant in self.participants() {
+            psbt_in
+                .unknown
+                .remove(&self.field_key(PSBT_IN_MUSIG2_PARTIAL_SIG, participant));
+        }
+    }
+
+    // The aggregate of all the participants' nonces, if they were all provided.
+    fn agg_nonce(&self, psbt_in: &PsbtIn) -> Result<Option<AggNonce>, MusigError> {
+        let pub_nonces = self.pub_nonces(psbt_in)?;
+        Ok((pub_nonces.len() == self.participants().len())
+            .then(|| nonce_agg(&pub_nonces.into_values().collect::<Vec<_>>())))
+    }
+
+    /// Create a partial signature for the given sighash, once all participants provided their
+    /// nonce. Returns `None` if some nonces are still missing.
+    pub fn sign(
+        &self,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing + secp256k1::Verification>,
+        psbt_in: &PsbtIn,
+        sec_nonce: SecNonce,
+        sk: &SecretKey,
+        sighash: &[u8; 32],
+    ) -> Result<Option<PartialSig>, MusigError> {
+        self.agg_nonce(psbt_in)?
+            .map(|agg_nonce| sign(secp, sec_nonce, sk, &self.ctx, &agg_nonce, sighash))
+            .transpose()
+    }
+
+    /// Aggregate the partial signatures of all participants for the given sighash, after checking
+    /// them. Returns `None` if some partial signatures are still missing.
+    pub fn aggregate(
+        &self,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+        psbt_in: &PsbtIn,
+        sighash: &[u8; 32],
+    ) -> Result<Option<schnorr::Signature>, MusigError> {
+        let (agg_nonce, pub_nonces) = match self.agg_nonce(psbt_in)? {
+            Some(agg_nonce) => (agg_nonce, self.pub_nonces(psbt_in)?),
+            None => return Ok(None),
+        };
+        let sigs = self.partial_sigs(psbt_in)?;
+        if sigs.len() != self.participants().len() {
+            return Ok(None);
+        }
+        for (pk, sig) in &sigs {
+            let pub_nonce = pub_nonces.get(pk).expect("All nonces are present.");
+            if !partial_sig_verify(secp, sig, pub_nonce, pk, &self.ctx, &agg_nonce, sighash) {
+                return Err(MusigError::InvalidPartialSig);
+            }
+        }
+        let sigs: Vec<_> = sigs.into_values().collect();
+        partial_sig_agg(secp, &self.ctx, &agg_nonce, sighash, &sigs).map(Some)
+    }
+}
+
+/// Merge the unknown fields, among which the MuSig2 nonces and partial signatures, of another
+/// PSBT input for the same transaction input into this one. The fields of this input take
+/// precedence. If a participant's nonce differs between the two, the partial signatures of the
+/// other input are for another signing session and aren't merged.
+pub fn merge_psbt_in(psbt_in: &mut PsbtIn, other: &PsbtIn) {
+    let other_session = other.unknown.iter().any(|(key, value)| {
+        key.type_value == PSBT_IN_MUSIG2_PUB_NONCE
+            && psbt_in
+                .unknown
+                .get(key)
+                .map_or(false, |nonce| nonce != value)
+    });
+    for (key, value) in &other.unknown {
+        if other_session && key.type_value == PSBT_IN_MUSIG2_PARTIAL_SIG {
+            continue;
+        }
+        psbt_in
+            .unknown
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+}
+
+// The network is irrelevant to the fingerprint and derivation of an extended key.
+fn bitcoin_network() -> miniscript::bitcoin::Network {
+    miniscript::bitcoin::Network::Bitcoin
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::hex::FromHex;
+    use std::str::FromStr;
+
+    fn pubkey(s: &str) -> PublicKey {
+        PublicKey::from_str(s).unwrap()
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        Vec::from_hex(s).unwrap()
+    }
+
+    fn scalar(s: &str) -> Option<Scalar> {
+        Scalar::from_be_bytes(hex(s).try_into().unwrap()).ok()
+    }
+
+    // Parse a secret nonce serialized as in BIP327, which isn't possible outside of tests.
+    fn sec_nonce(s: &str) -> Option<SecNonce> {
+        let data = hex(s);
+        Some(SecNonce {
+            k1: SecretKey::from_slice(&data[..32]).ok()?,
+            k2: SecretKey::from_slice(&data[32..64]).ok()?,
+            pk: PublicKey::from_slice(&data[64..]).ok()?,
+        })
+    }
+
+    fn assert_sec_nonce(nonce: &SecNonce, expected: &str) {
+        let expected = sec_nonce(expected).unwrap();
+        assert_eq!(nonce.k1, expected.k1);
+        assert_eq!(nonce.k2, expected.k2);
+        assert_eq!(nonce.pk, expected.pk);
+    }
+
+    // Aggregate these keys and apply these tweaks, along with whether they are x-only.
+    fn key_agg_ctx(keys: Vec<PublicKey>, tweaks: &[(&str, bool)]) -> KeyAggContext {
+        let secp = secp256k1::Secp256k1::verification_only();
+        tweaks.iter().fold(
+            KeyAggContext::new(&secp, keys).unwrap(),
+            |ctx, (t, xonly)| ctx.tweak(&secp, &scalar(t).unwrap(), *xonly).unwrap(),
+        )
+    }
+
+    // Test vectors from BIP327.
+    #[test]
+    fn key_agg_vectors() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let pks = [
+            pubkey("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            pubkey("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            pubkey("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66"),
+        ];
+        for (indices, expected) in [
+            (
+                &[0, 1, 2][..],
+                "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C",
+            ),
+            (
+                &[2, 1, 0][..],
+                "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B",
+            ),
+            (
+                &[0, 0, 0][..],
+                "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935",
+            ),
+            (
+                &[0, 0, 1, 1][..],
+                "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E",
+            ),
+        ] {
+            let keys = indices.iter().map(|i| pks[*i]).collect();
+            let ctx = KeyAggContext::new(&secp, keys).unwrap();
+            assert_eq!(
+                ctx.aggregate_key().x_only_public_key().0,
+                XOnlyPublicKey::from_str(expected).unwrap()
+            );
+        }
+
+        // Invalid public keys, and tweaks which are out of range or lead to an invalid key.
+        for invalid in [
+            "020000000000000000000000000000000000000000000000000000000000000005",
+            "02FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30",
+            "04F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        ] {
+            PublicKey::from_str(invalid).unwrap_err();
+        }
+        assert!(
+            scalar("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141").is_none()
+        );
+        let ctx = KeyAggContext::new(
+            &secp,
+            vec![pubkey(
+                "03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+            )],
+        )
+        .unwrap();
+        let tweak =
+            scalar("252E4BD67410A76CDF933D30EAA1608214037F1B105A013ECCD3C5C184A6110B").unwrap();
+        assert!(matches!(
+            ctx.tweak(&secp, &tweak, false),
+            Err(MusigError::InvalidTweak)
+        ));
+
+        // Sorting makes the aggregation independent of the order of the keys.
+        let (mut keys_a, mut keys_b) =
+            (pks.to_vec(), pks.iter().rev().copied().collect::<Vec<_>>());
+        key_sort(&mut keys_a);
+        key_sort(&mut keys_b);
+        assert_eq!(keys_a, keys_b);
+        assert_eq!(keys_a[0], pks[2]);
+    }
+
+    // Test vectors from BIP327.
+    #[test]
+    fn nonce_gen_vectors() {
+        let secp = secp256k1::Secp256k1::new();
+        let rand = [0x0f; 32];
+
+        let sk = SecretKey::from_slice(&[0x02; 32]).unwrap();
+        let pk = pubkey("024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766");
+        let aggregate_key = XOnlyPublicKey::from_slice(&[0x07; 32]).unwrap();
+        let (sec_nonce, pub_nonce) = nonce_gen_internal(
+            &secp,
+            rand,
+            Some(&sk),
+            &pk,
+            Some(&aggregate_key),
+            Some(&[0x01; 32]),
+            Some(&[0x08; 32]),
+        )
+        .unwrap();
+        assert_sec_nonce(&sec_nonce, "B114E502BEAA4E301DD08A50264172C84E41650E6CB726B410C0694D59EFFB6495B5CAF28D045B973D63E3C99A44B807BDE375FD6CB39E46DC4A511708D0E9D2024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766");
+        assert_eq!(
+            pub_nonce.serialize().to_vec(),
+            hex("02F7BE7089E8376EB355272368766B17E88E7DB72047D05E56AA881EA52B3B35DF02C29C8046FDD0DED4C7E55869137200FBDBFE2EB654267B6D7013602CAED3115A")
+        );
+
+        let pk = pubkey("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9");
+        let (sec_nonce, pub_nonce) =
+            nonce_gen_internal(&secp, rand, None, &pk, None, None, None).unwrap();
+        assert_sec_nonce(&sec_nonce, "89BDD787D0284E5E4D5FC572E49E316BAB7E21E3B1830DE37DFE80156FA41A6D0B17AE8D024C53679699A6FD7944D9C4A366B514BAF43088E0708B1023DD289702F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9");
+        assert_eq!(
+            pub_nonce.serialize().to_vec(),
+            hex("02C96E7CB1E8AA5DAC64D872947914198F607D90ECDE5200DE52978AD5DED63C000299EC5117C2D29EDEE8A2092587C3909BE694D5CFF0667D6C02EA4059F7CD9786")
+        );
+    }
+
+    #[test]
+    fn nonce_agg_vectors() {
+        let pub_nonces = [
+            "020151C80F435648DF67A22B749CD798CE54E0321D034B92B709B567D60A42E66603BA47FBC1834437B3212E89A84D8425E7BF12E0245D98262268EBDCB385D50641",
+            "03FF406FFD8ADB9CD29877E4985014F66A59F6CD01C0E88CAA8E5F3166B1F676A60248C264CDD57D3C24D79990B0F865674EB62A0F9018277A95011B41BFC193B833",
+            "020151C80F435648DF67A22B749CD798CE54E0321D034B92B709B567D60A42E6660279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+            "03FF406FFD8ADB9CD29877E4985014F66A59F6CD01C0E88CAA8E5F3166B1F676A60379BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+            "04FF406FFD8ADB9CD29877E4985014F66A59F6CD01C0E88CAA8E5F3166B1F676A60248C264CDD57D3C24D79990B0F865674EB62A0F9018277A95011B41BFC193B833",
+            "03FF406FFD8ADB9CD29877E4985014F66A59F6CD01C0E88CAA8E5F3166B1F676A60248C264CDD57D3C24D79990B0F865674EB62A0F9018277A95011B41BFC193B831",
+            "03FF406FFD8ADB9CD29877E4985014F66A59F6CD01C0E88CAA8E5F3166B1F676A602FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30",
+        ];
+        let pub_nonce = |i: usize| PubNonce::from_slice(&hex(pub_nonces[i]));
+
+        for (indices, expected) in [
+            ([0, 1], "035FE1873B4F2967F52FEA4A06AD5A8ECCBE9D0FD73068012C894E2E87CCB5804B024725377345BDE0E9C33AF3C43C0A29A9249F2F2956FA8CFEB55C8573D0262DC8"),
+            // The second point sums to infinity.
+            ([2, 3], "035FE1873B4F2967F52FEA4A06AD5A8ECCBE9D0FD73068012C894E2E87CCB5804B000000000000000000000000000000000000000000000000000000000000000000"),
+        ] {
+            let nonces: Vec<_> = indices.iter().map(|i| pub_nonce(*i).unwrap()).collect();
+            let agg_nonce = nonce_agg(&nonces);
+            assert_eq!(agg_nonce.serialize().to_vec(), hex(expected));
+            assert_eq!(AggNonce::from_slice(&hex(expected)).unwrap(), agg_nonce);
+        }
+
+        // Public nonces with an invalid first or second point.
+        for invalid in 4..=6 {
+            assert!(matches!(
+                pub_nonce(invalid),
+                Err(MusigError::InvalidPubNonce)
+            ));
+        }
+    }
+
+    #[test]
+    fn sign_verify_vectors() {
+        let secp = secp256k1::Secp256k1::new();
+        let sk =
+            SecretKey::from_str("7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671")
+                .unwrap();
+        let pks = [
+            "03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+            "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+            "02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA661",
+            "020000000000000000000000000000000000000000000000000000000000000007",
+        ];
+        let sec_nonces = [
+            "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F703935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+            "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000003935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+        ];
+        let pub_nonces = [
+            "0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+            "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F817980279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+            "032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE9303E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046",
+            "0237C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0387BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+            "0200000000000000000000000000000000000000000000000000000000000000090287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+        ];
+        let agg_nonces = [
+            "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9",
+            "000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            "048465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9",
+            "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61020000000000000000000000000000000000000000000000000000000000000009",
+            "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD6102FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30",
+        ];
+        let msg = hex("F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF");
+        let keys =
+            |indices: &[usize]| -> Vec<_> { indices.iter().map(|i| pubkey(pks[*i])).collect() };
+        let pub_nonce = |i: usize| PubNonce::from_slice(&hex(pub_nonces[i]));
+        let agg_nonce = |i: usize| AggNonce::from_slice(&hex(agg_nonces[i]));
+        assert_eq!(sk.public_key(&secp), pubkey(pks[0]));
+
+        // Our key at different positions, and an aggregate nonce at infinity.
+        for (key_indices, nonce_indices, agg_index, signer, expected) in [
+            (
+                &[0, 1, 2][..],
+                &[0, 1, 2][..],
+                0,
+                0,
+                "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB",
+            ),
+            (
+                &[1, 0, 2][..],
+                &[1, 0, 2][..],
+                0,
+                1,
+                "9FF2F7AAA856150CC8819254218D3ADEEB0535269051897724F9DB3789513A52",
+            ),
+            (
+                &[1, 2, 0][..],
+                &[1, 2, 0][..],
+                0,
+                2,
+                "FA23C359F6FAC4E7796BB93BC9F0532A95468C539BA20FF86D7C76ED92227900",
+            ),
+            (
+                &[0, 1][..],
+                &[0, 3][..],
+                1,
+                0,
+                "AE386064B26105404798F75DE2EB9AF5EDA5387B064B83D049CB7C5E08879531",
+            ),
+        ] {
+            let ctx = KeyAggContext::new(&secp, keys(key_indices)).unwrap();
+            let nonces: Vec<_> = nonce_indices
+                .iter()
+                .map(|i| pub_nonce(*i).unwrap())
+                .collect();
+            let agg_nonce = agg_nonce(agg_index).unwrap();
+            assert_eq!(nonce_agg(&nonces), agg_nonce);
+            let sig = sign(
+                &secp,
+                sec_nonce(sec_nonces[0]).unwrap(),
+                &sk,
+                &ctx,
+                &agg_nonce,
+                &msg,
+            )
+            .unwrap();
+            assert_eq!(sig.serialize().to_vec(), hex(expected));
+            assert!(partial_sig_verify(
+                &secp,
+                &sig,
+                &nonces[signer],
+                &ctx.keys()[signer],
+                &ctx,
+                &agg_nonce,
+                &msg
+            ));
+        }
+
+        // Our key isn't part of the aggregate key, or one of the other keys is invalid.
+        let ctx = KeyAggContext::new(&secp, keys(&[1, 2])).unwrap();
+        assert!(matches!(
+            sign(
+                &secp,
+                sec_nonce(sec_nonces[0]).unwrap(),
+                &sk,
+                &ctx,
+                &agg_nonce(0).unwrap(),
+                &msg
+            ),
+            Err(MusigError::SecretKeyMismatch)
+        ));
+        PublicKey::from_str(pks[3]).unwrap_err();
+        // The aggregate nonce is invalid.
+        for invalid in 2..=4 {
+            assert!(matches!(
+                agg_nonce(invalid),
+                Err(MusigError::InvalidAggNonce)
+            ));
+        }
+        // The secret nonce was zeroed after use, which can't be represented.
+        assert!(sec_nonce(sec_nonces[1]).is_none());
+
+        // Invalid partial signatures, or partial signatures for another participant.
+        let ctx = KeyAggContext::new(&secp, keys(&[0, 1, 2])).unwrap();
+        let nonces: Vec<_> = (0..3).map(|i| pub_nonce(i).unwrap()).collect();
+        let agg_nonce = agg_nonce(0).unwrap();
+        for (sig, signer) in [
+            (
+                "FED54434AD4CFE953FC527DC6A5E5BE8F6234907B7C187559557CE87A0541C46",
+                0,
+            ),
+            (
+                "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB",
+                1,
+            ),
+        ] {
+            let sig = PartialSig::from_slice(&hex(sig)).unwrap();
+            assert!(!partial_sig_verify(
+                &secp,
+                &sig,
+                &nonces[signer],
+                &ctx.keys()[signer],
+                &ctx,
+                &agg_nonce,
+                &msg
+            ));
+        }
+        assert!(matches!(
+            PartialSig::from_slice(&hex(
+                "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141"
+            )),
+            Err(MusigError::InvalidPartialSig)
+        ));
+        // The public nonce of the signer is invalid.
+        assert!(matches!(pub_nonce(4), Err(MusigError::InvalidPubNonce)));
+    }
+
+    #[test]
+    fn tweak_vectors() {
+        let secp = secp256k1::Secp256k1::new();
+        let sk =
+            SecretKey::from_str("7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671")
+                .unwrap();
+        let sec_nonce_hex = "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F703935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9";
+        let agg_nonce = AggNonce::from_slice(&hex("028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9")).unwrap();
+        let msg = hex("F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF");
+        // Our key and nonce come last.
+        let keys = vec![
+            pubkey("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            pubkey("02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            pubkey("03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"),
+        ];
+        let pub_nonce = PubNonce::from_slice(&hex("0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480")).unwrap();
+        let tweaks = [
+            "E8F791FF9225A2AF0102AFFF4A9A723D9612A682A25EBE79802B263CDFCD83BB",
+            "AE2EA797CC0FE72AC5B97B97F3C6957D7E4199A167A58EB08BCAFFDA70AC0455",
+            "F52ECBC565B3D8BEA2DFD5B75A4F457E54369809322E4120831626F290FA87E0",
+            "1969AD73CC177FA0B4FCED6DF1F7BF9907E665FDE9BA196A74FED0A3CF5AEF9D",
+        ];
+
+        for (tweaks, expected) in [
+            (
+                vec![(tweaks[0], true)],
+                "E28A5C66E61E178C2BA19DB77B6CF9F7E2F0F56C17918CD13135E60CC848FE91",
+            ),
+            (
+                vec![(tweaks[0], false)],
+                "38B0767798252F21BF5702C48028B095428320F73A4B14DB1E25DE58543D2D2D",
+            ),
+            (
+                vec![(tweaks[0], false), (tweaks[1], true)],
+                "408A0A21C4A0F5DACAF9646AD6EB6FECD7F7A11F03ED1F48DFFF2185BC2C2408",
+            ),
+            (
+                vec![
+                    (tweaks[0], false),
+                    (tweaks[1], false),
+                    (tweaks[2], true),
+                    (tweaks[3], true),
+                ],
+                "45ABD206E61E3DF2EC9E264A6FEC8292141A633C28586388235541F9ADE75435",
+            ),
+            (
+                vec![
+                    (tweaks[0], true),
+                    (tweaks[1], false),
+                    (tweaks[2], true),
+                    (tweaks[3], false),
+                ],
+                "B255FDCAC27B40C7CE7848E2D3B7BF5EA0ED756DA81565AC804CCCA3E1D5D239",
+            ),
+        ] {
+            let ctx = key_agg_ctx(keys.clone(), &tweaks);
+            let sig = sign(
+                &secp,
+                sec_nonce(sec_nonce_hex).unwrap(),
+                &sk,
+                &ctx,
+                &agg_nonce,
+                &msg,
+            )
+            .unwrap();
+            assert_eq!(sig.serialize().to_vec(), hex(expected));
+            assert!(partial_sig_verify(
+                &secp, &sig, &pub_nonce, &keys[2], &ctx, &agg_nonce, &msg
+            ));
+        }
+
+        // The tweak is out of range.
+        assert!(
+            scalar("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141").is_none()
+        );
+    }
+
+    #[test]
+    fn sig_agg_vectors() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let pks = [
+            "03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+            "02D2DC6F5DF7C56ACF38C7FA0AE7A759AE30E19B37359DFDE015872324C7EF6E05",
+            "03C7FB101D97FF930ACD0C6760852EF64E69083DE0B06AC6335724754BB4B0522C",
+            "02352433B21E7E05D3B452B81CAE566E06D2E003ECE16D1074AABA4289E0E3D581",
+        ];
+        let tweaks = [
+            "B511DA492182A91B0FFB9A98020D55F260AE86D7ECBD0399C7383D59A5F2AF7C",
+            "A815FE049EE3C5AAB66310477FBC8BCCCAC2F3395F59F921C364ACD78A2F48DC",
+            "75448A87274B056468B977BE06EB1E9F657577B7320B0A3376EA51FD420D18A8",
+        ];
+        let psigs = [
+            "B15D2CD3C3D22B04DAE438CE653F6B4ECF042F42CFDED7C41B64AAF9B4AF53FB",
+            "6193D6AC61B354E9105BBDC8937A3454A6D705B6D57322A5A472A02CE99FCB64",
+            "9A87D3B79EC67228CB97878B76049B15DBD05B8158D17B5B9114D3C226887505",
+            "66F82EA90923689B855D36C6B7E032FB9970301481B99E01CDB4D6AC7C347A15",
+            "4F5AEE41510848A6447DCD1BBC78457EF69024944C87F40250D3EF2C25D33EFE",
+            "DDEF427BBB847CC027BEFF4EDB01038148917832253EBC355FC33F4A8E2FCCE4",
+            "97B890A26C981DA8102D3BC294159D171D72810FDF7C6A691DEF02F0F7AF3FDC",
+            "53FA9E08BA5243CBCB0D797C5EE83BC6728E539EB76C2D0BF0F971EE4E909971",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
+        ];
+        let msg = hex("599C67EA410D005B9DA90817CF03ED3B1C868E4DA4EDF00A5880B0082C237869");
+
+        for (key_indices, tweaks, agg_nonce, psig_indices, expected) in [
+            (
+                [0, 1],
+                vec![],
+                "0341432722C5CD0268D829C702CF0D1CBCE57033EED201FD335191385227C3210C03D377F2D258B64AADC0E16F26462323D701D286046A2EA93365656AFD9875982B",
+                [0, 1],
+                "041DA22223CE65C92C9A0D6C2CAC828AAF1EEE56304FEC371DDF91EBB2B9EF0912F1038025857FEDEB3FF696F8B99FA4BB2C5812F6095A2E0004EC99CE18DE1E",
+            ),
+            (
+                [0, 2],
+                vec![],
+                "0224AFD36C902084058B51B5D36676BBA4DC97C775873768E58822F87FE437D792028CB15929099EEE2F5DAE404CD39357591BA32E9AF4E162B8D3E7CB5EFE31CB20",
+                [2, 3],
+                "1069B67EC3D2F3C7C08291ACCB17A9C9B8F2819A52EB5DF8726E17E7D6B52E9F01800260A7E9DAC450F4BE522DE4CE12BA91AEAF2B4279219EF74BE1D286ADD9",
+            ),
+            (
+                [0, 2],
+                vec![(tweaks[0], false)],
+                "0208C5C438C710F4F96A61E9FF3C37758814B8C3AE12BFEA0ED2C87FF6954FF186020B1816EA104B4FCA2D304D733E0E19CEAD51303FF6420BFD222335CAA402916D",
+                [4, 5],
+                "5C558E1DCADE86DA0B2F02626A512E30A22CF5255CAEA7EE32C38E9A71A0E9148BA6C0E6EC7683B64220F0298696F1B878CD47B107B81F7188812D593971E0CC",
+            ),
+            (
+                [0, 3],
+                vec![(tweaks[0], true), (tweaks[1], false), (tweaks[2], true)],
+                "02B5AD07AFCD99B6D92CB433FBD2A28FDEB98EAE2EB09B6014EF0F8197CD58403302E8616910F9293CF692C49F351DB86B25E352901F0E237BAFDA11F1C1CEF29FFD",
+                [6, 7],
+                "839B08820B681DBA8DAF4CC7B104E8F2638F9388F8D7A555DC17B6E6971D7426CE07BF6AB01F1DB50E4E33719295F4094572B79868E440FB3DEFD3FAC1DB589E",
+            ),
+        ] {
+            let ctx = key_agg_ctx(key_indices.iter().map(|i| pubkey(pks[*i])).collect(), &tweaks);
+            let agg_nonce = AggNonce::from_slice(&hex(agg_nonce)).unwrap();
+            let sigs: Vec<_> = psig_indices
+                .iter()
+                .map(|i| PartialSig::from_slice(&hex(psigs[*i])).unwrap())
+                .collect();
+            let sig = partial_sig_agg(&secp, &ctx, &agg_nonce, &msg, &sigs).unwrap();
+            assert_eq!(sig.as_ref().to_vec(), hex(expected));
+            secp.verify_schnorr(
+                &sig,
+                &secp256k1::Message::from_digest_slice(&msg).unwrap(),
+                &ctx.aggregate_key().x_only_public_key().0,
+            )
+            .unwrap();
+        }
+
+        // One of the partial signatures is out of range.
+        assert!(matches!(
+            PartialSig::from_slice(&hex(psigs[8])),
+            Err(MusigError::InvalidPartialSig)
+        ));
+    }
+
+    #[test]
+    fn signing_session() {
+        let secp = secp256k1::Secp256k1::new();
+        let sks: Vec<_> = (1..=3)
+            .map(|i| SecretKey::from_slice(&[i; 32]).unwrap())
+            .collect();
+        let mut pks: Vec<_> = sks.iter().map(|sk| sk.public_key(&secp)).collect();
+        key_sort(&mut pks);
+        let msg = [42; 32];
+
+        // Apply both a plain and an x-only tweak, as done when signing for a derived internal key.
+        let ctx = KeyAggContext::new(&secp, pks)
+            .unwrap()
+            .tweak(&secp, &Scalar::from_be_bytes([7; 32]).unwrap(), false)
+            .unwrap()
+            .tweak(&secp, &Scalar::from_be_bytes([8; 32]).unwrap(), true)
+            .unwrap();
+        let agg_pk = ctx.aggregate_key().x_only_public_key().0;
+
+        let nonces: Vec<_> = sks
+            .iter()
+            .map(|sk| {
+                nonce_gen(
+                    &secp,
+                    Some(sk),
+                    &sk.public_key(&secp),
+                    Some(&agg_pk),
+                    Some(&msg),
+                    None,
+                )
+                .unwrap()
+            })
+            .collect();
+        let pub_nonces: Vec<_> = nonces.iter().map(|(_, pub_nonce)| *pub_nonce).collect();
+        let agg_nonce = nonce_agg(&pub_nonces);
+
+        let mut sigs = Vec::new();
+        for (sk, (sec_nonce, pub_nonce)) in sks.iter().zip(nonces) {
+            let sig = sign(&secp, sec_nonce, sk, &ctx, &agg_nonce, &msg).unwrap();
+            assert!(partial_sig_verify(
+                &secp,
+                &sig,
+                &pub_nonce,
+                &sk.public_key(&secp),
+                &ctx,
+                &agg_nonce,
+                &msg
+            ));
+            // It's not valid for another participant, nor for another message.
+            assert!(!partial_sig_verify(
+                &secp,
+                &sig,
+                &pub_nonce,
+                &sks[(sigs.len() + 1) % 3].public_key(&secp),
+                &ctx,
+                &agg_nonce,
+                &msg
+            ));
+            assert!(!partial_sig_verify(
+                &secp,
+                &sig,
+                &pub_nonce,
+                &sk.public_key(&secp),
+                &ctx,
+                &agg_nonce,
+                &[43; 32]
+            ));
+            sigs.push(sig);
+        }
+
+        // The aggregate is a valid BIP340 signature for the tweaked aggregate key, but only once
+        // all participants signed.
+        let sig = partial_sig_agg(&secp, &ctx, &agg_nonce, &msg, &sigs).unwrap();
+        let msg = secp256k1::Message::from_digest(msg);
+        secp.verify_schnorr(&sig, &msg, &agg_pk).unwrap();
+        let sig = partial_sig_agg(&secp, &ctx, &agg_nonce, msg.as_ref(), &sigs[1..]).unwrap();
+        secp.verify_schnorr(&sig, &msg, &agg_pk).unwrap_err();
+
+        // A secret nonce is bound to the key it was generated for.
+        let (sec_nonce, _) =
+            nonce_gen(&secp, None, &sks[0].public_key(&secp), None, None, None).unwrap();
+        assert!(matches!(
+            sign(&secp, sec_nonce, &sks[1], &ctx, &agg_nonce, msg.as_ref()),
+            Err(MusigError::SecretKeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn aggregate_xpub() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let keys = vec![
+            pubkey("03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"),
+            pubkey("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+        ];
+        let xpub = super::aggregate_xpub(&secp, keys.clone(), bitcoin_network()).unwrap();
+        let mut sorted = keys.clone();
+        key_sort(&mut sorted);
+        assert_eq!(
+            xpub.public_key,
+            KeyAggContext::new(&secp, sorted).unwrap().aggregate_key()
+        );
+        assert_eq!(xpub.depth, 0);
+        assert_eq!(xpub.chain_code.to_bytes(), AGGREGATE_XPUB_CHAINCODE);
+        let reversed = keys.into_iter().rev().collect();
+        assert_eq!(
+            super::aggregate_xpub(&secp, reversed, bitcoin_network()).unwrap(),
+            xpub
+        );
+    }
+}
@@ -0,0 +1,655 @@
+//! Payjoin
+//!
+//! Helpers to build and check the transactions exchanged in a payjoin (BIP78), either as the
+//! sender or as the receiver. The communication between the two parties is left to the caller.
+
+use crate::random;
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error, fmt,
+};
+
+use miniscript::bitcoin::{
+    self,
+    psbt::{Input as PsbtIn, Output as PsbtOut, Psbt},
+};
+
+/// The version of the payjoin protocol we implement.
+pub const PAYJOIN_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayjoinError {
+    /// This input of the original transaction isn't finalized.
+    OriginalNotFinalized(bitcoin::OutPoint),
+    /// The coin spent by this input is unknown.
+    MissingUtxo(bitcoin::OutPoint),
+    /// The transaction spends more than it has.
+    NegativeFee,
+    /// The output to increase with the receiver's contribution doesn't exist.
+    InvalidReceiverOutput(usize),
+    Randomness(String),
+    /// The original transaction can't be used for a payjoin. Contains the reason.
+    InvalidOriginal(&'static str),
+    /// The proposal doesn't follow the protocol. Contains the rule it breaks.
+    InvalidProposal(&'static str),
+}
+
+impl fmt::Display for PayjoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OriginalNotFinalized(op) => write!(
+                f,
+                "Input spending '{}' of the original transaction isn't finalized.",
+                op
+            ),
+            Self::MissingUtxo(op) => write!(f, "Missing the spent output for input '{}'.", op),
+            Self::NegativeFee => write!(f, "The transaction would have a negative fee."),
+            Self::InvalidReceiverOutput(i) => {
+                write!(f, "The original transaction has no output at index {}.", i)
+            }
+            Self::Randomness(e) => write!(f, "Error when getting randomness: {}", e),
+            Self::InvalidOriginal(reason) => {
+                write!(f, "Invalid payjoin original transaction: {}.", reason)
+            }
+            Self::InvalidProposal(rule) => write!(f, "Invalid payjoin proposal: {}.", rule),
+        }
+    }
+}
+
+impl error::Error for PayjoinError {}
+
+fn is_finalized(psbt_in: &PsbtIn) -> bool {
+    psbt_in.final_script_sig.is_some() || psbt_in.final_script_witness.is_some()
+}
+
+fn is_signed(psbt_in: &PsbtIn) -> bool {
+    !psbt_in.partial_sigs.is_empty()
+        || psbt_in.tap_key_sig.is_some()
+        || !psbt_in.tap_script_sigs.is_empty()
+}
+
+// The output spent by this input, if it's present in the PSBT input.
+fn spent_txo(txin: &bitcoin::TxIn, psbt_in: &PsbtIn) -> Option<bitcoin::TxOut> {
+    psbt_in.witness_utxo.clone().or_else(|| {
+        psbt_in
+            .non_witness_utxo
+            .as_ref()
+            .and_then(|tx| tx.output.get(txin.previous_output.vout as usize).cloned())
+    })
+}
+
+// Whether these two scriptpubkeys are of the same type.
+fn same_script_type(a: &bitcoin::Script, b: &bitcoin::Script) -> bool {
+    a.is_p2tr() == b.is_p2tr()
+        && a.is_p2wsh() == b.is_p2wsh()
+        && a.is_p2wpkh() == b.is_p2wpkh()
+        && a.is_p2sh() == b.is_p2sh()
+        && a.is_p2pkh() == b.is_p2pkh()
+}
+
+// The fee paid by this transaction, given the outputs spent by its inputs.
+fn tx_fee(
+    tx: &bitcoin::Transaction,
+    spent_txos: &HashMap<bitcoin::OutPoint, bitcoin::TxOut>,
+) -> Result<bitcoin::Amount, PayjoinError> {
+    let mut in_value = bitcoin::Amount::ZERO;
+    for txin in &tx.input {
+        let txo = spent_txos
+            .get(&txin.previous_output)
+            .ok_or(PayjoinError::MissingUtxo(txin.previous_output))?;
+        in_value += txo.value;
+    }
+    let out_value = tx.output.iter().map(|txo| txo.value).sum();
+    in_value
+        .checked_sub(out_value)
+        .ok_or(PayjoinError::NegativeFee)
+}
+
+// The weight of this transaction once finalized, using the finalized inputs of the given PSBTs.
+fn finalized_weight(tx: &bitcoin::Transaction, psbts: &[&Psbt]) -> bitcoin::Weight {
+    let finalized: HashMap<_, _> = psbts
+        .iter()
+        .flat_map(|psbt| psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter()))
+        .filter(|(_, psbt_in)| is_finalized(psbt_in))
+        .map(|(txin, psbt_in)| (txin.previous_output, psbt_in))
+        .collect();
+    let mut tx = tx.clone();
+    for txin in tx.input.iter_mut() {
+        if let Some(psbt_in) = finalized.get(&txin.previous_output) {
+            txin.script_sig = psbt_in.final_script_sig.clone().unwrap_or_default();
+            txin.witness = psbt_in.final_script_witness.clone().unwrap_or_default();
+        }
+    }
+    tx.weight()
+}
+
+/// Make sure all the inputs of this original transaction are finalized, and that the outputs
+/// they spend are known.
+pub fn check_original(original: &Psbt) -> Result<(), PayjoinError> {
+    for (txin, psbt_in) in original
+        .unsigned_tx
+        .input
+        .iter()
+        .zip(original.inputs.iter())
+    {
+        if !is_finalized(psbt_in) {
+            return Err(PayjoinError::OriginalNotFinalized(txin.previous_output));
+        }
+        if spent_txo(txin, psbt_in).is_none() {
+            return Err(PayjoinError::MissingUtxo(txin.previous_output));
+        }
+    }
+    Ok(())
+}
+
+/// Parameters of a payjoin request, set by the sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SenderParams {
+    /// Forbid the receiver to substitute its output.
+    pub disable_output_substitution: bool,
+    /// The index of the output the receiver may decrease to have us pay for the inputs it adds,
+    /// and by how much at most.
+    pub additional_fee_contribution: Option<(usize, bitcoin::Amount)>,
+    /// The minimum feerate of the proposal, in sats/vb.
+    pub min_feerate_vb: Option<u64>,
+}
+
+impl SenderParams {
+    /// The query string to add to the receiver's endpoint when sending the original PSBT.
+    pub fn to_query(&self) -> String {
+        let mut query = format!("v={}", PAYJOIN_VERSION);
+        if let Some((index, max_contribution)) = self.additional_fee_contribution {
+            query.push_str(&format!(
+                "&additionalfeeoutputindex={}&maxadditionalfeecontribution={}",
+                index,
+                max_contribution.to_sat()
+            ));
+        }
+        if self.disable_output_substitution {
+            query.push_str("&disableoutputsubstitution=true");
+        }
+        if let Some(feerate) = self.min_feerate_vb {
+            query.push_str(&format!("&minfeerate={}", feerate));
+        }
+        query
+    }
+}
+
+/// Get the original PSBT to send to the receiver from our finalized PSBT. All the information
+/// which isn't necessary to the receiver is stripped from it.
+pub fn original_to_send(finalized: &Psbt) -> Result<Psbt, PayjoinError> {
+    check_original(finalized)?;
+    let mut original = finalized.clone();
+    original.xpub.clear();
+    for psbt_in in original.inputs.iter_mut() {
+        *psbt_in = PsbtIn {
+            witness_utxo: psbt_in.witness_utxo.take(),
+            non_witness_utxo: psbt_in.non_witness_utxo.take(),
+            final_script_sig: psbt_in.final_script_sig.take(),
+            final_script_witness: psbt_in.final_script_witness.take(),
+            ..PsbtIn::default()
+        };
+    }
+    for psbt_out in original.outputs.iter_mut() {
+        *psbt_out = PsbtOut::default();
+    }
+    Ok(original)
+}
+
+/// Check the payjoin proposal returned by the receiver against the original PSBT we sent it, as
+/// per the sender checklist of BIP78.
+///
+/// `receiver_spk` is the scriptpubkey of the output paying the receiver in the original
+/// transaction. All other outputs are considered ours.
+pub fn check_proposal(
+    original: &Psbt,
+    proposal: &Psbt,
+    receiver_spk: &bitcoin::Script,
+    params: &SenderParams,
+) -> Result<(), PayjoinError> {
+    let (original_tx, proposal_tx) = (&original.unsigned_tx, &proposal.unsigned_tx);
+    if proposal_tx.version != original_tx.version {
+        return Err(PayjoinError::InvalidProposal("the version changed"));
+    }
+    if proposal_tx.lock_time != original_tx.lock_time {
+        return Err(PayjoinError::InvalidProposal("the locktime changed"));
+    }
+    if proposal.inputs.len() != proposal_tx.input.len()
+        || proposal.outputs.len() != proposal_tx.output.len()
+    {
+        return Err(PayjoinError::InvalidProposal(
+            "the PSBT doesn't match its transaction",
+        ));
+    }
+
+    // The outputs spent by the inputs of both transactions, to compute the fees.
+    let mut spent_txos = HashMap::with_capacity(proposal_tx.input.len());
+    for (txin, psbt_in) in original_tx.input.iter().zip(original.inputs.iter()) {
+        let txo =
+            spent_txo(txin, psbt_in).ok_or(PayjoinError::MissingUtxo(txin.previous_output))?;
+        spent_txos.insert(txin.previous_output, txo);
+    }
+
+    // Check the inputs. Ours must be unchanged and not signed. The receiver's must be finalized
+    // and look like ours.
+    let sender_input = original_tx
+        .input
+        .first()
+        .ok_or(PayjoinError::InvalidProposal("we have no input"))?;
+    let sender_spk = spent_txos[&sender_input.previous_output]
+        .script_pubkey
+        .clone();
+    let original_inputs: HashMap<_, _> = original_tx
+        .input
+        .iter()
+        .map(|txin| (txin.previous_output, txin))
+        .collect();
+    let mut sender_inputs_count = 0;
+    for (txin, psbt_in) in proposal_tx.input.iter().zip(proposal.inputs.iter()) {
+        if let Some(original_txin) = original_inputs.get(&txin.previous_output) {
+            sender_inputs_count += 1;
+            if txin.sequence != original_txin.sequence {
+                return Err(PayjoinError::InvalidProposal(
+                    "the sequence of one of our inputs changed",
+                ));
+            }
+            if is_finalized(psbt_in) || is_signed(psbt_in) {
+                return Err(PayjoinError::InvalidProposal("one of our inputs is signed"));
+            }
+        } else {
+            if !is_finalized(psbt_in) {
+                return Err(PayjoinError::InvalidProposal(
+                    "one of the receiver's inputs isn't finalized",
+                ));
+            }
+            let txo =
+                spent_txo(txin, psbt_in).ok_or(PayjoinError::MissingUtxo(txin.previous_output))?;
+            if !same_script_type(&txo.script_pubkey, &sender_spk) {
+                return Err(PayjoinError::InvalidProposal(
+                    "one of the receiver's inputs is of a different type than ours",
+                ));
+            }
+            if txin.sequence != sender_input.sequence {
+                return Err(PayjoinError::InvalidProposal(
+                    "one of the receiver's inputs has a different sequence than ours",
+                ));
+            }
+            spent_txos.insert(txin.previous_output, txo);
+        }
+    }
+    if sender_inputs_count != original_tx.input.len() {
+        return Err(PayjoinError::InvalidProposal(
+            "some of our inputs are missing",
+        ));
+    }
+
+    // Check the outputs. Ours must be unchanged, except for the one we allowed the receiver to
+    // take fees from. The receiver's may only be substituted if we allowed it.
+    let mut matched_outputs = HashSet::with_capacity(original_tx.output.len());
+    let mut fee_contribution = bitcoin::Amount::ZERO;
+    for (i, original_txo) in original_tx.output.iter().enumerate() {
+        let txo = proposal_tx
+            .output
+            .iter()
+            .enumerate()
+            .find(|(j, txo)| {
+                !matched_outputs.contains(j) && txo.script_pubkey == original_txo.script_pubkey
+            })
+            .map(|(j, txo)| {
+                matched_outputs.insert(j);
+                txo
+            });
+        if original_txo.script_pubkey.as_script() == receiver_spk {
+            match txo {
+                Some(txo) if txo.value < original_txo.value => {
+                    return Err(PayjoinError::InvalidProposal(
+                        "the payment to the receiver decreased",
+                    ));
+                }
+                None if params.disable_output_substitution => {
+                    return Err(PayjoinError::InvalidProposal(
+                        "the receiver's output was substituted",
+                    ));
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let txo = txo.ok_or(PayjoinError::InvalidProposal(
+            "one of our outputs is missing",
+        ))?;
+        match params.additional_fee_contribution {
+            Some((index, max_contribution)) if index == i && txo.value <= original_txo.value => {
+                fee_contribution = original_txo.value - txo.value;
+                if fee_contribution > max_contribution {
+                    return Err(PayjoinError::InvalidProposal(
+                        "the receiver took too much fees from our output",
+                    ));
+                }
+            }
+            _ => {
+                if txo.value != original_txo.value {
+                    return Err(PayjoinError::InvalidProposal(
+                        "the value of one of our outputs changed",
+                    ));
+                }
+            }
+        }
+    }
+
+    // Finally, check the fees. We only agree to pay more for the additional inputs.
+    let original_fee = tx_fee(original_tx, &spent_txos)?;
+    let proposal_fee = tx_fee(proposal_tx, &spent_txos)?;
+    if proposal_fee < original_fee {
+        return Err(PayjoinError::InvalidProposal("the fee decreased"));
+    }
+    if fee_contribution > proposal_fee - original_fee {
+        return Err(PayjoinError::InvalidProposal(
+            "our fee contribution isn't used for fees",
+        ));
+    }
+    if let Some(min_feerate_vb) = params.min_feerate_vb {
+        let vsize = finalized_weight(proposal_tx, &[original, proposal]).to_vbytes_ceil();
+        if proposal_fee.to_sat() < min_feerate_vb.saturating_mul(vsize) {
+            return Err(PayjoinError::InvalidProposal(
+                "the feerate is lower than the minimum",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Fill the payjoin proposal with the information about our inputs and outputs from the PSBT of
+/// our original transaction, since it's stripped when sent to the receiver. The signatures are
+/// not restored, as the transaction has to be signed again.
+pub fn restore_sender_data(proposal: &mut Psbt, original: &Psbt) {
+    let original_inputs: HashMap<_, _> = original
+        .unsigned_tx
+        .input
+        .iter()
+        .zip(original.inputs.iter())
+        .map(|(txin, psbt_in)| (txin.previous_output, psbt_in))
+        .collect();
+    for (txin, psbt_in) in proposal
+        .unsigned_tx
+        .input
+        .iter()
+        .zip(proposal.inputs.iter_mut())
+    {
+        if let Some(original_in) = original_inputs.get(&txin.previous_output) {
+            *psbt_in = PsbtIn {
+                partial_sigs: BTreeMap::new(),
+                tap_key_sig: None,
+                tap_script_sigs: BTreeMap::new(),
+                final_script_sig: None,
+                final_script_witness: None,
+                ..(*original_in).clone()
+            };
+        }
+    }
+    for (txo, psbt_out) in proposal
+        .unsigned_tx
+        .output
+        .iter()
+        .zip(proposal.outputs.iter_mut())
+    {
+        if let Some(i) = original
+            .unsigned_tx
+            .output
+            .iter()
+            .position(|original_txo| original_txo.script_pubkey == txo.script_pubkey)
+        {
+            *psbt_out = original.outputs[i].clone();
+        }
+    }
+    proposal.xpub = original.xpub.clone();
+}
+
+/// Create a payjoin proposal from the original PSBT of the sender.
+///
+/// The coin spent by `psbt_in`, which must contain the spent output, is added as an input at a
+/// random position. Its value, minus the fee to spend it at the feerate of the original
+/// transaction, is added to our output at index `receiver_output`. `input_weight` is the weight
+/// of the added input once satisfied.
+///
+/// The sender's inputs are stripped of everything but the spent outputs, which are needed to sign
+/// our input. Once it is signed and finalized they should be stripped entirely.
+pub fn create_proposal(
+    original: &Psbt,
+    receiver_output: usize,
+    previous_output: bitcoin::OutPoint,
+    psbt_in: PsbtIn,
+    input_weight: bitcoin::Weight,
+) -> Result<Psbt, PayjoinError> {
+    check_original(original)?;
+    let original_tx = &original.unsigned_tx;
+    if receiver_output >= original_tx.output.len() {
+        return Err(PayjoinError::InvalidReceiverOutput(receiver_output));
+    }
+
+    // Our input must pay for itself at the same feerate as the original transaction.
+    let spent_txos: HashMap<_, _> = original_tx
+        .input
+        .iter()
+        .zip(original.inputs.iter())
+        .filter_map(|(txin, psbt_in)| Some((txin.previous_output, spent_txo(txin, psbt_in)?)))
+        .collect();
+    let original_fee = tx_fee(original_tx, &spent_txos)?;
+    let original_weight = finalized_weight(original_tx, &[original]);
+    let input_fee = bitcoin::Amount::from_sat(
+        (original_fee.to_sat() * input_weight.to_wu()).div_ceil(original_weight.to_wu()),
+    );
+    let txin = bitcoin::TxIn {
+        previous_output,
+        sequence: original_tx
+            .input
+            .first()
+            .map(|txin| txin.sequence)
+            .unwrap_or(bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME),
+        ..bitcoin::TxIn::default()
+    };
+    let value = spent_txo(&txin, &psbt_in)
+        .ok_or(PayjoinError::MissingUtxo(previous_output))?
+        .value;
+
+    let mut proposal = original.clone();
+    for psbt_in in proposal.inputs.iter_mut() {
+        *psbt_in = PsbtIn {
+            witness_utxo: psbt_in.witness_utxo.take(),
+            non_witness_utxo: psbt_in.non_witness_utxo.take(),
+            ..PsbtIn::default()
+        };
+    }
+    let receiver_txo = &mut proposal.unsigned_tx.output[receiver_output];
+    receiver_txo.value = (receiver_txo.value + value)
+        .checked_sub(input_fee)
+        .ok_or(PayjoinError::NegativeFee)?;
+    let position = random::random_bytes().map_err(|e| PayjoinError::Randomness(e.to_string()))?[0]
+        as usize
+        % (original_tx.input.len() + 1);
+    proposal.unsigned_tx.input.insert(position, txin);
+    proposal.inputs.insert(position, psbt_in);
+
+    Ok(proposal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn txo(value: u64, spk_byte: u8) -> bitcoin::TxOut {
+        bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(value),
+            script_pubkey: bitcoin::ScriptBuf::from_bytes(
+                [&[0x00, 0x20][..], &[spk_byte; 32][..]].concat(),
+            ),
+        }
+    }
+
+    fn outpoint(vout: u32) -> bitcoin::OutPoint {
+        bitcoin::OutPoint::from_str(&format!(
+            "4753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:{}",
+            vout
+        ))
+        .unwrap()
+    }
+
+    fn finalized_input(value: u64, spk_byte: u8) -> PsbtIn {
+        PsbtIn {
+            witness_utxo: Some(txo(value, spk_byte)),
+            final_script_witness: Some(bitcoin::Witness::from_slice(&[vec![1; 72], vec![2; 40]])),
+            ..PsbtIn::default()
+        }
+    }
+
+    #[test]
+    fn payjoin_round() {
+        // Sender spends two coins to pay the receiver 50k sats, with 20k sats of change.
+        let receiver_spk = txo(0, 0xaa).script_pubkey;
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: [outpoint(0), outpoint(1)]
+                .iter()
+                .map(|previous_output| bitcoin::TxIn {
+                    previous_output: *previous_output,
+                    sequence: bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    ..bitcoin::TxIn::default()
+                })
+                .collect(),
+            output: vec![txo(50_000, 0xaa), txo(20_000, 0xbb)],
+        };
+        let mut original = Psbt::from_unsigned_tx(tx).unwrap();
+        original.inputs = vec![finalized_input(40_000, 0xbb), finalized_input(31_000, 0xbb)];
+        let original = original_to_send(&original).unwrap();
+        let params = SenderParams {
+            disable_output_substitution: true,
+            additional_fee_contribution: Some((1, bitcoin::Amount::from_sat(200))),
+            min_feerate_vb: Some(1),
+        };
+        assert_eq!(
+            params.to_query(),
+            "v=1&additionalfeeoutputindex=1&maxadditionalfeecontribution=200&disableoutputsubstitution=true&minfeerate=1"
+        );
+
+        // The original must be finalized.
+        let mut unfinalized = original.clone();
+        unfinalized.inputs[1].final_script_witness = None;
+        assert_eq!(
+            check_original(&unfinalized),
+            Err(PayjoinError::OriginalNotFinalized(outpoint(1)))
+        );
+
+        // The receiver adds a 10k sats coin. It pays for its own input.
+        let receiver_in = PsbtIn {
+            witness_utxo: Some(txo(10_000, 0xcc)),
+            ..PsbtIn::default()
+        };
+        let mut proposal = create_proposal(
+            &original,
+            0,
+            outpoint(2),
+            receiver_in,
+            bitcoin::Weight::from_wu(400),
+        )
+        .unwrap();
+        assert_eq!(proposal.unsigned_tx.input.len(), 3);
+        let receiver_value = proposal.unsigned_tx.output[0].value.to_sat();
+        assert!(receiver_value < 60_000 && receiver_value > 59_000);
+        let position = proposal
+            .unsigned_tx
+            .input
+            .iter()
+            .position(|txin| txin.previous_output == outpoint(2))
+            .unwrap();
+        // The receiver must finalize its input and strip ours before sending the proposal.
+        assert_eq!(
+            check_proposal(&original, &proposal, &receiver_spk, &params),
+            Err(PayjoinError::InvalidProposal(
+                "one of the receiver's inputs isn't finalized"
+            ))
+        );
+        proposal.inputs[position] = finalized_input(10_000, 0xcc);
+        check_proposal(&original, &proposal, &receiver_spk, &params).unwrap();
+
+        // The receiver may take a bit of fees from our change, but not too much.
+        let mut with_contribution = proposal.clone();
+        with_contribution.unsigned_tx.output[1].value -= bitcoin::Amount::from_sat(150);
+        check_proposal(&original, &with_contribution, &receiver_spk, &params).unwrap();
+        with_contribution.unsigned_tx.output[1].value -= bitcoin::Amount::from_sat(100);
+        assert_eq!(
+            check_proposal(&original, &with_contribution, &receiver_spk, &params),
+            Err(PayjoinError::InvalidProposal(
+                "the receiver took too much fees from our output"
+            ))
+        );
+
+        // It may not decrease the payment, nor substitute its output if we don't allow it.
+        let mut decreased = proposal.clone();
+        decreased.unsigned_tx.output[0].value = bitcoin::Amount::from_sat(49_000);
+        assert_eq!(
+            check_proposal(&original, &decreased, &receiver_spk, &params),
+            Err(PayjoinError::InvalidProposal(
+                "the payment to the receiver decreased"
+            ))
+        );
+        let mut substituted = proposal.clone();
+        substituted.unsigned_tx.output[0] = txo(60_000, 0xdd);
+        assert_eq!(
+            check_proposal(&original, &substituted, &receiver_spk, &params),
+            Err(PayjoinError::InvalidProposal(
+                "the receiver's output was substituted"
+            ))
+        );
+        let allow_substitution = SenderParams {
+            disable_output_substitution: false,
+            ..params
+        };
+        check_proposal(&original, &substituted, &receiver_spk, &allow_substitution).unwrap();
+
+        // Our inputs must be kept, unsigned, and the receiver's must look like ours.
+        let mut removed = proposal.clone();
+        let sender_position = (position + 1) % 3;
+        removed.unsigned_tx.input.remove(sender_position);
+        removed.inputs.remove(sender_position);
+        assert_eq!(
+            check_proposal(&original, &removed, &receiver_spk, &params),
+            Err(PayjoinError::InvalidProposal(
+                "some of our inputs are missing"
+            ))
+        );
+        let mut signed = proposal.clone();
+        signed.inputs[sender_position] = finalized_input(40_000, 0xbb);
+        assert_eq!(
+            check_proposal(&original, &signed, &receiver_spk, &params),
+            Err(PayjoinError::InvalidProposal("one of our inputs is signed"))
+        );
+        let mut other_type = proposal.clone();
+        other_type.inputs[position].witness_utxo = Some(bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(10_000),
+            script_pubkey: bitcoin::ScriptBuf::from_bytes(vec![0x00, 0x14, 0xcc, 0xcc]),
+        });
+        assert_eq!(
+            check_proposal(&original, &other_type, &receiver_spk, &params),
+            Err(PayjoinError::InvalidProposal(
+                "one of the receiver's inputs is of a different type than ours"
+            ))
+        );
+
+        // The information stripped from our inputs can be restored for signing.
+        let mut unsigned_original = original.clone();
+        for psbt_in in unsigned_original.inputs.iter_mut() {
+            psbt_in.final_script_witness = None;
+            psbt_in.witness_script = Some(bitcoin::ScriptBuf::from_bytes(vec![0x51]));
+        }
+        restore_sender_data(&mut proposal, &unsigned_original);
+        assert_eq!(
+            proposal.inputs[sender_position].witness_script,
+            Some(bitcoin::ScriptBuf::from_bytes(vec![0x51]))
+        );
+        assert!(proposal.inputs[position].final_script_witness.is_some());
+    }
+}
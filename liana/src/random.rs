@@ -75,6 +75,13 @@ fn additional_data() -> Result<[u8; 32], RandomnessError> {
 /// Windows. In addition some randomness may be taken directly from the CPU if it is
 /// available, and some contextual information are added to the mix as well.
 pub fn random_bytes() -> Result<[u8; 32], RandomnessError> {
+    random_bytes_with_entropy(&[])
+}
+
+/// Get 32 random bytes as in [`random_bytes`], additionally mixing in some entropy provided by the
+/// user (for instance from dice rolls or coin flips) for those who don't trust their machine's
+/// randomness. It's only added to the mix, so a poor user entropy can't weaken the result.
+pub fn random_bytes_with_entropy(user_entropy: &[u8]) -> Result<[u8; 32], RandomnessError> {
     let mut engine = sha256::HashEngine::default();
 
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
@@ -84,6 +91,7 @@ pub fn random_bytes() -> Result<[u8; 32], RandomnessError> {
 
     engine.input(&system_randomness()?);
     engine.input(&additional_data()?);
+    engine.input(user_entropy);
     // TODO: add more sources of randomness
 
     Ok(sha256::Hash::from_engine(engine).to_byte_array())
@@ -105,6 +113,14 @@ mod tests {
             assert!(!set.contains(&rand));
             set.insert(rand);
         }
+
+        // The user entropy doesn't replace the other sources.
+        let user_entropy = b"3141592653";
+        for _ in 0..100 {
+            let rand = random_bytes_with_entropy(user_entropy).unwrap();
+            assert!(!set.contains(&rand));
+            set.insert(rand);
+        }
     }
 
     // I used this to perform statistical tests of the random generation function using ENT
@@ -3,21 +3,28 @@
 //! Some helpers to facilitate the usage of a signer in client of the Liana daemon. For now
 //! only contains a hot signer.
 
-use crate::random;
+use crate::{bsms, musig, random};
 
 use std::{
-    convert::TryInto,
+    collections::BTreeMap,
+    convert::{TryFrom, TryInto},
     error, fmt, fs,
     io::{self, Write},
     path,
     str::FromStr,
+    sync::Mutex,
 };
 
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305,
+};
 use miniscript::bitcoin::{
     self,
     bip32::{self, Error as Bip32Error},
     ecdsa,
     hashes::Hash,
+    hex::{DisplayHex, FromHex},
     key::TapTweak,
     psbt::{Input as PsbtIn, Psbt},
     secp256k1, sighash,
@@ -32,6 +39,14 @@ pub enum SignerError {
     MnemonicStorage(io::Error),
     InsanePsbt,
     IncompletePsbt,
+    Musig(musig::MusigError),
+    /// The master key derived using the given BIP39 passphrase doesn't match the expected
+    /// fingerprint.
+    PassphraseMismatch(bip32::Fingerprint),
+    MnemonicEncryption(String),
+    /// The password is wrong, or missing, for decrypting a mnemonic.
+    MnemonicDecryption,
+    Slip39(String),
 }
 
 impl fmt::Display for SignerError {
@@ -46,6 +61,17 @@ impl fmt::Display for SignerError {
                 f,
                 "The PSBT is missing some information necessary for signing."
             ),
+            Self::Musig(e) => write!(f, "MuSig2 error: {}", e),
+            Self::PassphraseMismatch(fg) => write!(
+                f,
+                "The passphrase doesn't match the key with fingerprint {}.",
+                fg
+            ),
+            Self::MnemonicEncryption(e) => write!(f, "Error when encrypting the mnemonic: {}", e),
+            Self::MnemonicDecryption => {
+                write!(f, "Could not decrypt the mnemonic: wrong password.")
+            }
+            Self::Slip39(e) => write!(f, "SLIP39 error: {}", e),
         }
     }
 }
@@ -54,14 +80,247 @@ impl error::Error for SignerError {}
 
 pub const MNEMONICS_FOLDER_NAME: &str = "mnemonics";
 
-// TODO: zeroize, mlock, etc.. For now we don't even encrypt the seed on disk so that'd be
+/// The maximum number of SLIP39 shares in a share set.
+pub const SLIP39_MAX_SHARES: u8 = 16;
+// The SLIP39 iteration exponent. The shares created by sssmc39 always encode an exponent of 0
+// whichever one was used to encrypt the secret, so using another one would create shares from
+// which the secret can't be recovered.
+const SLIP39_ITERATION_EXPONENT: u8 = 0;
+
+// Encrypted mnemonic files start with this, followed by the key derivation parameters as
+// `argon2id:v=<version>:m=<memory cost>,t=<time cost>,p=<parallelism>:` and by the hex encoding of
+// the salt, the nonce and the ciphertext.
+const ENCRYPTED_MNEMONIC_PREFIX: &str = "encrypted-mnemonic-v1:";
+const ARGON2_ALGORITHM: &str = "argon2id";
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+// The Argon2id parameters used to encrypt new mnemonics. Those of an encrypted mnemonic are read
+// from its file, so they may be changed without breaking the decryption of existing ones.
+const ARGON2_VERSION: argon2::Version = argon2::Version::V0x13;
+const ARGON2_M_COST: u32 = 19 * 1024;
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+
+/// A mnemonic encrypted using a password. The encryption key is derived from the password using
+/// Argon2id, and the mnemonic is encrypted using ChaCha20-Poly1305.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedMnemonic {
+    version: argon2::Version,
+    params: argon2::Params,
+    salt: [u8; SALT_SIZE],
+    nonce: [u8; NONCE_SIZE],
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedMnemonic {
+    fn cipher(&self, password: &str) -> Result<ChaCha20Poly1305, SignerError> {
+        let mut key = chacha20poly1305::Key::default();
+        argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            self.version,
+            self.params.clone(),
+        )
+        .hash_password_into(password.as_bytes(), &self.salt, &mut key)
+        .map_err(|e| SignerError::MnemonicEncryption(e.to_string()))?;
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    fn encrypt(mnemonic: &str, password: &str) -> Result<Self, SignerError> {
+        let random_32bytes = random::random_bytes().map_err(SignerError::Randomness)?;
+        let mut salt = [0; SALT_SIZE];
+        salt.copy_from_slice(&random_32bytes[..SALT_SIZE]);
+        let mut nonce = [0; NONCE_SIZE];
+        nonce.copy_from_slice(&random_32bytes[SALT_SIZE..SALT_SIZE + NONCE_SIZE]);
+        let params = argon2::Params::new(ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST, None)
+            .map_err(|e| SignerError::MnemonicEncryption(e.to_string()))?;
+        let mut encrypted = Self {
+            version: ARGON2_VERSION,
+            params,
+            salt,
+            nonce,
+            ciphertext: Vec::new(),
+        };
+        encrypted.ciphertext = encrypted
+            .cipher(password)?
+            .encrypt(&nonce.into(), mnemonic.as_bytes())
+            .map_err(|e| SignerError::MnemonicEncryption(e.to_string()))?;
+        Ok(encrypted)
+    }
+
+    fn decrypt(&self, password: &str) -> Result<String, SignerError> {
+        let plaintext = self
+            .cipher(password)?
+            .decrypt(&self.nonce.into(), self.ciphertext.as_slice())
+            .map_err(|_| SignerError::MnemonicDecryption)?;
+        String::from_utf8(plaintext).map_err(|_| SignerError::MnemonicDecryption)
+    }
+}
+
+impl fmt::Display for EncryptedMnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}:v={}:m={},t={},p={}:{}{}{}",
+            ENCRYPTED_MNEMONIC_PREFIX,
+            ARGON2_ALGORITHM,
+            u32::from(self.version),
+            self.params.m_cost(),
+            self.params.t_cost(),
+            self.params.p_cost(),
+            self.salt.to_lower_hex_string(),
+            self.nonce.to_lower_hex_string(),
+            self.ciphertext.to_lower_hex_string()
+        )
+    }
+}
+
+impl FromStr for EncryptedMnemonic {
+    type Err = SignerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s
+            .trim()
+            .strip_prefix(ENCRYPTED_MNEMONIC_PREFIX)
+            .ok_or(SignerError::MnemonicDecryption)?
+            .split(':');
+        if parts.next() != Some(ARGON2_ALGORITHM) {
+            return Err(SignerError::MnemonicDecryption);
+        }
+        let version = parts
+            .next()
+            .and_then(|v| v.strip_prefix("v=")?.parse::<u32>().ok())
+            .and_then(|v| argon2::Version::try_from(v).ok())
+            .ok_or(SignerError::MnemonicDecryption)?;
+        let params = parts
+            .next()
+            .and_then(
+                |costs| match costs.split(',').collect::<Vec<_>>().as_slice() {
+                    [m_cost, t_cost, p_cost] => argon2::Params::new(
+                        m_cost.strip_prefix("m=")?.parse().ok()?,
+                        t_cost.strip_prefix("t=")?.parse().ok()?,
+                        p_cost.strip_prefix("p=")?.parse().ok()?,
+                        None,
+                    )
+                    .ok(),
+                    _ => None,
+                },
+            )
+            .ok_or(SignerError::MnemonicDecryption)?;
+        let data = parts
+            .next()
+            .filter(|_| parts.next().is_none())
+            .and_then(|data| Vec::<u8>::from_hex(data).ok())
+            .filter(|data| data.len() > SALT_SIZE + NONCE_SIZE)
+            .ok_or(SignerError::MnemonicDecryption)?;
+        let (salt, data) = data.split_at(SALT_SIZE);
+        let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+        Ok(Self {
+            version,
+            params,
+            salt: salt.try_into().expect("Checked size."),
+            nonce: nonce.try_into().expect("Checked size."),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum StoredContent {
+    Plain(String),
+    Encrypted(EncryptedMnemonic),
+}
+
+/// A mnemonic stored in the datadir, possibly encrypted.
+#[derive(Debug, Clone)]
+pub struct StoredMnemonic {
+    /// The fingerprint of the master key the mnemonic was stored for. If the signer was created
+    /// with a BIP39 passphrase, it differs from the fingerprint of the signer until the passphrase
+    /// is set using [`HotSigner::with_passphrase`].
+    pub fingerprint: bip32::Fingerprint,
+    content: StoredContent,
+}
+
+impl StoredMnemonic {
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self.content, StoredContent::Encrypted(_))
+    }
+
+    /// Get the signer for this mnemonic. The password is only used if the mnemonic is encrypted,
+    /// in which case it is necessary.
+    pub fn signer(
+        &self,
+        network: bitcoin::Network,
+        password: Option<&str>,
+    ) -> Result<HotSigner, SignerError> {
+        match &self.content {
+            StoredContent::Plain(mnemonic) => HotSigner::from_str(network, mnemonic),
+            StoredContent::Encrypted(encrypted) => {
+                let password = password.ok_or(SignerError::MnemonicDecryption)?;
+                HotSigner::from_str(network, &encrypted.decrypt(password)?)
+            }
+        }
+    }
+}
+
+/// The number of words of a generated BIP39 mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MnemonicLength {
+    Words12,
+    Words18,
+    Words24,
+}
+
+impl MnemonicLength {
+    pub const ALL: [MnemonicLength; 3] = [Self::Words12, Self::Words18, Self::Words24];
+
+    pub fn from_word_count(count: usize) -> Option<Self> {
+        Self::ALL.iter().copied().find(|l| l.word_count() == count)
+    }
+
+    pub fn word_count(&self) -> usize {
+        match self {
+            Self::Words12 => 12,
+            Self::Words18 => 18,
+            Self::Words24 => 24,
+        }
+    }
+
+    // Each word encodes 11 bits, of which 1/33 is the checksum.
+    fn entropy_size(&self) -> usize {
+        self.word_count() * 4 / 3
+    }
+}
+
+impl Default for MnemonicLength {
+    fn default() -> Self {
+        Self::Words12
+    }
+}
+
+impl fmt::Display for MnemonicLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} words", self.word_count())
+    }
+}
+
+// TODO: zeroize, mlock, etc.. For now the seed is only optionally encrypted on disk so that'd be
 // overkill.
-/// A signer that keeps the key on the laptop. Based on BIP39.
+/// A signer that keeps the key on the laptop. Based on BIP39. The master key may be derived using a
+/// BIP39 passphrase, which is never stored.
+///
+/// It may take part in MuSig2 signing sessions. The secret nonces are only kept in memory, but
+/// they are shared by all the signers of the process. If the secret part of our nonce was lost,
+/// for instance after a restart, a new nonce is provided.
 pub struct HotSigner {
     mnemonic: bip39::Mnemonic,
     master_xpriv: bip32::Xpriv,
 }
 
+// The secret nonces of our ongoing MuSig2 signing sessions, by public nonce. They are shared by
+// all the signer instances, as a signer is typically instantiated anew for each signing request.
+// They are removed once used to make sure they are never reused.
+static MUSIG_NONCES: Mutex<BTreeMap<[u8; 66], musig::SecNonce>> = Mutex::new(BTreeMap::new());
+
 // TODO: instead of copying them here we could have a util module with those helpers.
 // Create a directory with no permission for group and other users.
 fn create_dir(path: &path::Path) -> io::Result<()> {
@@ -99,13 +358,23 @@ fn create_file(path: &path::Path) -> Result<fs::File, std::io::Error> {
     };
 }
 
+// Recover the secret split into these SLIP39 shares, encrypted with this passphrase.
+fn combine_slip39_shares(shares: &[String], passphrase: &str) -> Result<Vec<u8>, SignerError> {
+    let shares: Vec<Vec<String>> = shares
+        .iter()
+        .map(|share| share.split_whitespace().map(|w| w.to_lowercase()).collect())
+        .collect();
+    sssmc39::combine_mnemonics(&shares, passphrase).map_err(|e| SignerError::Slip39(e.to_string()))
+}
+
 impl HotSigner {
     fn from_mnemonic(
         network: bitcoin::Network,
         mnemonic: bip39::Mnemonic,
+        passphrase: &str,
     ) -> Result<Self, SignerError> {
-        let master_xpriv =
-            bip32::Xpriv::new_master(network, &mnemonic.to_seed("")).map_err(SignerError::Bip32)?;
+        let master_xpriv = bip32::Xpriv::new_master(network, &mnemonic.to_seed(passphrase))
+            .map_err(SignerError::Bip32)?;
         Ok(Self {
             mnemonic,
             master_xpriv,
@@ -114,16 +383,33 @@ impl HotSigner {
 
     /// Create a new hot signer from random bytes. Uses a 12-words mnemonics without a passphrase.
     pub fn generate(network: bitcoin::Network) -> Result<Self, SignerError> {
-        // We want a 12-words mnemonic so we only use 16 of the 32 bytes.
-        let random_32bytes = random::random_bytes().map_err(SignerError::Randomness)?;
-        let mnemonic =
-            bip39::Mnemonic::from_entropy(&random_32bytes[..16]).map_err(SignerError::Mnemonic)?;
-        Self::from_mnemonic(network, mnemonic)
+        Self::generate_with(network, MnemonicLength::Words12, &[])
+    }
+
+    /// Create a new hot signer from random bytes, using a mnemonic of the given length without a
+    /// passphrase. The entropy provided by the user, if any, is mixed in with the random bytes.
+    pub fn generate_with(
+        network: bitcoin::Network,
+        length: MnemonicLength,
+        user_entropy: &[u8],
+    ) -> Result<Self, SignerError> {
+        // A 24-words mnemonic uses all the 32 bytes, shorter ones only part of them.
+        let random_32bytes =
+            random::random_bytes_with_entropy(user_entropy).map_err(SignerError::Randomness)?;
+        let mnemonic = bip39::Mnemonic::from_entropy(&random_32bytes[..length.entropy_size()])
+            .map_err(SignerError::Mnemonic)?;
+        Self::from_mnemonic(network, mnemonic, "")
     }
 
     pub fn from_str(network: bitcoin::Network, s: &str) -> Result<Self, SignerError> {
         let mnemonic = bip39::Mnemonic::from_str(s).map_err(SignerError::Mnemonic)?;
-        Self::from_mnemonic(network, mnemonic)
+        Self::from_mnemonic(network, mnemonic, "")
+    }
+
+    /// Get a signer for the same mnemonic, whose master key is derived using this BIP39
+    /// passphrase instead.
+    pub fn with_passphrase(&self, passphrase: &str) -> Result<Self, SignerError> {
+        Self::from_mnemonic(self.master_xpriv.network, self.mnemonic.clone(), passphrase)
     }
 
     fn mnemonics_folder(datadir_root: &path::Path, network: bitcoin::Network) -> path::PathBuf {
@@ -136,33 +422,132 @@ impl HotSigner {
         .collect()
     }
 
-    /// Read all the mnemonics from the datadir for the given network.
+    /// Read all the mnemonics from the datadir for the given network. Encrypted mnemonics are
+    /// decrypted using the password. Those which can't be, because no password is given or
+    /// because they were encrypted with another one, are skipped. The signers don't use a
+    /// passphrase, see [`HotSigner::stored_mnemonics`].
     pub fn from_datadir(
         datadir_root: &path::Path,
         network: bitcoin::Network,
+        password: Option<&str>,
     ) -> Result<Vec<Self>, SignerError> {
         let mut signers = Vec::new();
+        for stored in Self::stored_mnemonics(datadir_root, network)? {
+            match stored.signer(network, password) {
+                Ok(signer) => signers.push(signer),
+                Err(SignerError::MnemonicDecryption) => {
+                    if password.is_some() {
+                        log::warn!(
+                            "Skipping mnemonic '{}' which can't be decrypted with this password.",
+                            stored.fingerprint
+                        );
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(signers)
+    }
+
+    /// Read all the mnemonics from the datadir for the given network, without decrypting them.
+    pub fn stored_mnemonics(
+        datadir_root: &path::Path,
+        network: bitcoin::Network,
+    ) -> Result<Vec<StoredMnemonic>, SignerError> {
+        let mut mnemonics = Vec::new();
 
         let mnemonic_paths = fs::read_dir(Self::mnemonics_folder(datadir_root, network))
             .map_err(SignerError::MnemonicStorage)?;
+        let secp = secp256k1::Secp256k1::signing_only();
         for entry in mnemonic_paths {
-            let mnemonic = fs::read_to_string(entry.map_err(SignerError::MnemonicStorage)?.path())
-                .map_err(SignerError::MnemonicStorage)?;
-            signers.push(Self::from_str(network, &mnemonic)?);
+            let path = entry.map_err(SignerError::MnemonicStorage)?.path();
+            let content = fs::read_to_string(&path).map_err(SignerError::MnemonicStorage)?;
+            let content = if content.starts_with(ENCRYPTED_MNEMONIC_PREFIX) {
+                StoredContent::Encrypted(EncryptedMnemonic::from_str(&content)?)
+            } else {
+                StoredContent::Plain(content)
+            };
+            let fingerprint = match path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("mnemonic-")?.strip_suffix(".txt"))
+                .and_then(|fg| bip32::Fingerprint::from_str(fg).ok())
+            {
+                Some(fingerprint) => fingerprint,
+                None => match content {
+                    StoredContent::Plain(ref mnemonic) => {
+                        Self::from_str(network, mnemonic)?.fingerprint(&secp)
+                    }
+                    StoredContent::Encrypted(_) => continue,
+                },
+            };
+            mnemonics.push(StoredMnemonic {
+                fingerprint,
+                content,
+            });
         }
 
-        Ok(signers)
+        Ok(mnemonics)
     }
 
-    /// The BIP39 mnemonics from which the master key of this signer is derived.
-    pub fn words(&self) -> [&'static str; 12] {
-        let words: Vec<&'static str> = self.mnemonic.words().collect();
-        words.try_into().expect("Always 12 words")
+    /// The BIP39 mnemonics from which the master key of this signer is derived. There are 12, 18
+    /// or 24 words.
+    pub fn words(&self) -> Vec<&'static str> {
+        self.mnemonic.words().collect()
+    }
+
+    /// Split this signer's mnemonic into a set of `share_count` SLIP39 shares, any `threshold` of
+    /// which are needed to recover it. The threshold must be at least 2, so that a single share
+    /// doesn't expose the key.
+    ///
+    /// The secret which is split is the entropy of the BIP39 mnemonic, so the same mnemonic is
+    /// recovered from the shares using [`HotSigner::from_slip39_shares`]. This isn't the master
+    /// secret of a standard SLIP39 wallet: other SLIP39 implementations would derive different
+    /// keys from these shares. The BIP39 passphrase, if any, isn't part of the shares.
+    pub fn slip39_shares(
+        &self,
+        threshold: u8,
+        share_count: u8,
+    ) -> Result<Vec<String>, SignerError> {
+        if threshold < 2 || threshold > share_count || share_count > SLIP39_MAX_SHARES {
+            return Err(SignerError::Slip39(format!(
+                "Invalid {}-of-{} share set: the threshold must be at least 2 and at most the \
+                 number of shares, which is at most {}.",
+                threshold, share_count, SLIP39_MAX_SHARES
+            )));
+        }
+        let groups = sssmc39::generate_mnemonics(
+            1,
+            &[(threshold, share_count)],
+            &self.mnemonic.to_entropy(),
+            "",
+            SLIP39_ITERATION_EXPONENT,
+        )
+        .map_err(|e| SignerError::Slip39(e.to_string()))?;
+        let mut shares = Vec::with_capacity(share_count as usize);
+        for group in groups {
+            let mnemonics = group
+                .mnemonic_list()
+                .map_err(|e| SignerError::Slip39(e.to_string()))?;
+            shares.extend(mnemonics.into_iter().map(|words| words.join(" ")));
+        }
+        Ok(shares)
+    }
+
+    /// Recover a signer from enough SLIP39 shares created by [`HotSigner::slip39_shares`]. The
+    /// signer doesn't use a passphrase.
+    pub fn from_slip39_shares(
+        network: bitcoin::Network,
+        shares: &[String],
+    ) -> Result<Self, SignerError> {
+        let entropy = combine_slip39_shares(shares, "")?;
+        let mnemonic = bip39::Mnemonic::from_entropy(&entropy).map_err(SignerError::Mnemonic)?;
+        Self::from_mnemonic(network, mnemonic, "")
     }
 
     /// The BIP39 mnemonic words as a string.
     pub fn mnemonic_str(&self) -> String {
-        let mut mnemonic_str = String::with_capacity(12 * 7);
+        let mut mnemonic_str = String::with_capacity(24 * 7);
         let words = self.words();
 
         for (i, word) in words.iter().enumerate() {
@@ -185,12 +570,36 @@ impl HotSigner {
 
     /// Store the mnemonic in a file within the given "data directory".
     /// The file is stored within a "mnemonics" folder, with the filename set to the fingerprint of
-    /// the master xpub corresponding to this mnemonic.
+    /// the master xpub corresponding to this mnemonic. The BIP39 passphrase, if any, is not stored
+    /// but the fingerprint is the one of the master xpub derived using it.
     pub fn store(
         &self,
         datadir_root: &path::Path,
         network: bitcoin::Network,
         secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    ) -> Result<(), SignerError> {
+        self.store_content(datadir_root, network, &self.mnemonic_str(), secp)
+    }
+
+    /// Store the mnemonic encrypted with this password, as in [`HotSigner::store`]. The password
+    /// will be needed to read it from the datadir.
+    pub fn store_encrypted(
+        &self,
+        datadir_root: &path::Path,
+        network: bitcoin::Network,
+        password: &str,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    ) -> Result<(), SignerError> {
+        let encrypted = EncryptedMnemonic::encrypt(&self.mnemonic_str(), password)?;
+        self.store_content(datadir_root, network, &encrypted.to_string(), secp)
+    }
+
+    fn store_content(
+        &self,
+        datadir_root: &path::Path,
+        network: bitcoin::Network,
+        content: &str,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
     ) -> Result<(), SignerError> {
         let mut mnemonics_folder = Self::mnemonics_folder(datadir_root, network);
         if !mnemonics_folder.exists() {
@@ -203,7 +612,7 @@ impl HotSigner {
         let mut mnemonic_file =
             create_file(&mnemonic_path).map_err(SignerError::MnemonicStorage)?;
         mnemonic_file
-            .write_all(self.mnemonic_str().as_bytes())
+            .write_all(content.as_bytes())
             .map_err(SignerError::MnemonicStorage)?;
 
         Ok(())
@@ -229,6 +638,26 @@ impl HotSigner {
         bip32::Xpub::from_priv(secp, &xpriv)
     }
 
+    /// Create a BSMS key record for the extended key at the given derivation path, signed with
+    /// this key.
+    pub fn bsms_key_record(
+        &self,
+        token: bsms::Token,
+        der_path: bip32::DerivationPath,
+        description: String,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    ) -> bsms::KeyRecord {
+        let xpriv = self.xpriv_at(&der_path, secp);
+        bsms::KeyRecord::sign(
+            token,
+            self.fingerprint(secp),
+            der_path,
+            &xpriv,
+            description,
+            secp,
+        )
+    }
+
     // Provide an ECDSA signature for this transaction input from the PSBT input information.
     fn sign_p2wsh(
         &self,
@@ -348,6 +777,105 @@ impl HotSigner {
             }
         }
 
+        // If the internal key is a MuSig2 aggregate key, take part in the signing session.
+        if let Some(session) =
+            musig::KeyPathSession::from_psbt_in(secp, psbt_in).map_err(SignerError::Musig)?
+        {
+            let sighash = sighash_cache
+                .taproot_key_spend_signature_hash(input_index, &prevouts, sig_type)
+                .map_err(|_| SignerError::InsanePsbt)?;
+            self.sign_musig(
+                secp,
+                &session,
+                master_fingerprint,
+                psbt_in,
+                sighash.as_byte_array(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Take part in the MuSig2 signing session for the key path of this input, if its internal key
+    // is an aggregate key we are a participant of. We first provide a nonce, then once all the
+    // participants provided theirs a partial signature.
+    fn sign_musig(
+        &self,
+        secp: &secp256k1::Secp256k1<secp256k1::All>,
+        session: &musig::KeyPathSession,
+        master_fingerprint: bip32::Fingerprint,
+        psbt_in: &mut PsbtIn,
+        sighash: &[u8; 32],
+    ) -> Result<(), SignerError> {
+        let pub_nonces = session.pub_nonces(psbt_in).map_err(SignerError::Musig)?;
+        let partial_sigs = session.partial_sigs(psbt_in).map_err(SignerError::Musig)?;
+        let mut sec_nonces = MUSIG_NONCES.lock().expect("Never poisoned.");
+
+        // The participants we are which have yet to sign, along with their private key.
+        let mut signing = Vec::new();
+        for participant in session.participants() {
+            let der_path = match psbt_in
+                .tap_key_origins
+                .get(&participant.x_only_public_key().0)
+            {
+                Some((_, (fg, der_path))) if *fg == master_fingerprint => der_path,
+                _ => continue,
+            };
+            let privkey = self.xpriv_at(der_path, secp).private_key;
+            if privkey.public_key(secp) != *participant {
+                return Err(SignerError::InsanePsbt);
+            }
+            if !partial_sigs.contains_key(participant) {
+                signing.push((participant, privkey));
+            }
+        }
+
+        // Provide a nonce if we didn't already, or if we lost its secret part. A new nonce makes
+        // the partial signatures already provided for the previous one invalid.
+        let mut sigs_invalidated = false;
+        for (participant, privkey) in &signing {
+            let prev_nonce = pub_nonces.get(participant);
+            if prev_nonce.map_or(false, |nonce| sec_nonces.contains_key(&nonce.serialize())) {
+                continue;
+            }
+            let (sec_nonce, pub_nonce) = musig::nonce_gen(
+                secp,
+                Some(privkey),
+                participant,
+                Some(&session.output_key()),
+                Some(sighash),
+                None,
+            )
+            .map_err(SignerError::Musig)?;
+            sec_nonces.insert(pub_nonce.serialize(), sec_nonce);
+            session.set_pub_nonce(psbt_in, participant, &pub_nonce);
+            if prev_nonce.is_some() && !partial_sigs.is_empty() {
+                session.remove_partial_sigs(psbt_in);
+                sigs_invalidated = true;
+            }
+        }
+
+        // Sign once all the participants provided their nonce. If we just invalidated the partial
+        // signatures of others, they need to provide a new nonce too: signing now could only
+        // result in our partial signature being invalidated in turn.
+        let pub_nonces = session.pub_nonces(psbt_in).map_err(SignerError::Musig)?;
+        if sigs_invalidated || pub_nonces.len() != session.participants().len() {
+            return Ok(());
+        }
+        for (participant, privkey) in signing {
+            // We can only sign with a nonce we generated, and only once.
+            let pub_nonce = pub_nonces
+                .get(participant)
+                .expect("All nonces are present.");
+            if let Some(sec_nonce) = sec_nonces.remove(&pub_nonce.serialize()) {
+                let sig = session
+                    .sign(secp, psbt_in, sec_nonce, &privkey, sighash)
+                    .map_err(SignerError::Musig)?
+                    .expect("All nonces are present.");
+                session.set_partial_sig(psbt_in, participant, &sig);
+            }
+        }
+
         Ok(())
     }
 
@@ -459,6 +987,123 @@ mod tests {
             &bip32::DerivationPath::from_str("m/42'/43/0987'/0/2").unwrap(),
             &secp,
         );
+
+        // Longer mnemonics, optionally with some entropy from the user.
+        for length in MnemonicLength::ALL {
+            let signer =
+                HotSigner::generate_with(bitcoin::Network::Bitcoin, length, b"HTTHHTHTTT").unwrap();
+            assert_eq!(signer.words().len(), length.word_count());
+            assert_eq!(
+                HotSigner::from_str(bitcoin::Network::Bitcoin, &signer.mnemonic_str())
+                    .unwrap()
+                    .words(),
+                signer.words()
+            );
+        }
+        assert_eq!(
+            HotSigner::generate(bitcoin::Network::Bitcoin)
+                .unwrap()
+                .words()
+                .len(),
+            12
+        );
+
+        // A 24-words mnemonic from the BIP39 test vectors.
+        let signer = HotSigner::from_str(
+            bitcoin::Network::Bitcoin,
+            "void come effort suffer camp survey warrior heavy shoot primary clutch crush open \
+             amazing screen patrol group space point ten exist slush involve unfold",
+        )
+        .unwrap()
+        .with_passphrase("TREZOR")
+        .unwrap();
+        assert_eq!(signer.words().len(), 24);
+        assert_eq!(
+            signer.master_xpriv.to_string(),
+            "xprv9s21ZrQH143K39rnQJknpH1WEPFJrzmAqqasiDcVrNuk926oizzJDDQkdiTvNPr2FYDYzWgiMiC63YmfPAa2oPyNB23r2g7d1yiK6WpqaQS"
+        );
+    }
+
+    #[test]
+    fn hot_signer_slip39() {
+        let network = bitcoin::Network::Bitcoin;
+        for length in MnemonicLength::ALL {
+            let signer = HotSigner::generate_with(network, length, &[]).unwrap();
+            let shares = signer.slip39_shares(2, 3).unwrap();
+            assert_eq!(shares.len(), 3);
+            // A share doesn't contain any of the BIP39 mnemonic.
+            assert!(shares.iter().all(|share| share != &signer.mnemonic_str()));
+
+            // Any 2 of the 3 shares recover the same mnemonic.
+            for (i, j) in [(0, 1), (0, 2), (2, 1)] {
+                let recovered =
+                    HotSigner::from_slip39_shares(network, &[shares[i].clone(), shares[j].clone()])
+                        .unwrap();
+                assert_eq!(recovered.words(), signer.words());
+            }
+
+            // A single share isn't enough.
+            for share in &shares {
+                assert!(HotSigner::from_slip39_shares(network, &[share.clone()]).is_err());
+            }
+        }
+
+        // Share sets which would let a single share expose the key, or which are too large, are
+        // refused.
+        let signer = HotSigner::generate(network).unwrap();
+        signer.slip39_shares(1, 3).unwrap_err();
+        signer.slip39_shares(4, 3).unwrap_err();
+        signer.slip39_shares(2, SLIP39_MAX_SHARES + 1).unwrap_err();
+        assert_eq!(
+            signer.slip39_shares(3, SLIP39_MAX_SHARES).unwrap().len(),
+            SLIP39_MAX_SHARES as usize
+        );
+    }
+
+    #[test]
+    fn slip39_vectors() {
+        // From the official SLIP39 test vectors, which all use the passphrase "TREZOR".
+        let valid_vectors: &[(&[&str], &str)] = &[
+            (
+                &["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"],
+                "bb54aac4b89dc868ba37d9cc21b2cece",
+            ),
+            (
+                &[
+                    "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+                    "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+                ],
+                "b43ceb7e57a0ea8766221624d01b0864",
+            ),
+            (
+                &[
+                    "humidity disease academic always aluminum jewelry energy woman receiver strategy amuse duckling lying evidence network walnut tactics forget hairy rebound impulse brother survive clothes stadium mailman rival ocean reward venture always armed unwrap",
+                    "humidity disease academic agency actress jacket gross physics cylinder solution fake mortgage benefit public busy prepare sharp friar change work slow purchase ruler again tricycle involve viral wireless mixture anatomy desert cargo upgrade",
+                ],
+                "c938b319067687e990e05e0da0ecce1278f75ff58d9853f19dcaeed5de104aae",
+            ),
+        ];
+        for (shares, secret) in valid_vectors {
+            let shares: Vec<String> = shares.iter().map(|s| s.to_string()).collect();
+            let recovered = combine_slip39_shares(&shares, "TREZOR").unwrap();
+            assert_eq!(recovered.to_lower_hex_string(), *secret);
+        }
+
+        let invalid_vectors: &[&[&str]] = &[
+            // Invalid checksum.
+            &["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney"],
+            // Not enough shares.
+            &["shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed"],
+            // Different iteration exponents.
+            &[
+                "peasant leaves academic acid desert exact olympic math alive axle trial tackle drug deny decent smear dominant desert bucket remind",
+                "peasant leader academic agency cultural blessing percent network envelope medal junk primary human pumps jacket fragment payroll ticket evoke voice",
+            ],
+        ];
+        for shares in invalid_vectors {
+            let shares: Vec<String> = shares.iter().map(|s| s.to_string()).collect();
+            assert!(combine_slip39_shares(&shares, "TREZOR").is_err());
+        }
     }
 
     #[test]
@@ -475,7 +1120,7 @@ mod tests {
                 signer.words()
             })
             .collect();
-        let words_read: HashSet<_> = HotSigner::from_datadir(&tmp_dir, network)
+        let words_read: HashSet<_> = HotSigner::from_datadir(&tmp_dir, network, None)
             .unwrap()
             .into_iter()
             .map(|signer| signer.words())
@@ -485,6 +1130,138 @@ mod tests {
         fs::remove_dir_all(tmp_dir).unwrap();
     }
 
+    #[test]
+    fn hot_signer_passphrase() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let network = bitcoin::Network::Bitcoin;
+
+        // BIP39 test vector.
+        let signer = HotSigner::from_str(network, "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about").unwrap();
+        let signer_pass = signer.with_passphrase("TREZOR").unwrap();
+        assert_eq!(signer_pass.master_xpriv.to_string(), "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF");
+        assert_ne!(signer.fingerprint(&secp), signer_pass.fingerprint(&secp));
+        assert_eq!(signer.words(), signer_pass.words());
+
+        // The passphrase isn't stored, but the mnemonic is stored for the fingerprint derived
+        // using it.
+        let tmp_dir = tmp_dir();
+        fs::create_dir_all(&tmp_dir).unwrap();
+        signer_pass.store(&tmp_dir, network, &secp).unwrap();
+        let mnemonic_path = HotSigner::mnemonics_folder(&tmp_dir, network)
+            .join(format!("mnemonic-{:x}.txt", signer_pass.fingerprint(&secp)));
+        assert_eq!(
+            fs::read_to_string(mnemonic_path).unwrap(),
+            signer.mnemonic_str()
+        );
+        let mut stored = HotSigner::stored_mnemonics(&tmp_dir, network).unwrap();
+        assert_eq!(stored.len(), 1);
+        let stored = stored.pop().unwrap();
+        let (fingerprint, stored_signer) =
+            (stored.fingerprint, stored.signer(network, None).unwrap());
+        assert_eq!(fingerprint, signer_pass.fingerprint(&secp));
+        assert_eq!(stored_signer.fingerprint(&secp), signer.fingerprint(&secp));
+        assert_eq!(
+            stored_signer
+                .with_passphrase("TREZOR")
+                .unwrap()
+                .fingerprint(&secp),
+            fingerprint
+        );
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn hot_signer_encrypted_storage() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let tmp_dir = tmp_dir();
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let network = bitcoin::Network::Bitcoin;
+
+        let plain_signer = HotSigner::generate(network).unwrap();
+        plain_signer.store(&tmp_dir, network, &secp).unwrap();
+        let signer = HotSigner::generate(network).unwrap();
+        signer
+            .store_encrypted(&tmp_dir, network, "correct horse", &secp)
+            .unwrap();
+        let other_signer = HotSigner::generate(network).unwrap();
+        other_signer
+            .store_encrypted(&tmp_dir, network, "battery staple", &secp)
+            .unwrap();
+
+        // The mnemonic isn't written in plaintext.
+        let mnemonic_path = HotSigner::mnemonics_folder(&tmp_dir, network)
+            .join(format!("mnemonic-{:x}.txt", signer.fingerprint(&secp)));
+        let content = fs::read_to_string(mnemonic_path).unwrap();
+        assert!(content.starts_with(ENCRYPTED_MNEMONIC_PREFIX));
+        assert!(!signer.words().iter().any(|w| content.contains(w)));
+
+        // The key derivation parameters are recorded in the file, and those read from it are used
+        // to decrypt the mnemonic.
+        assert!(content.starts_with("encrypted-mnemonic-v1:argon2id:v=19:m=19456,t=2,p=1:"));
+        let mut encrypted = EncryptedMnemonic::from_str(&content).unwrap();
+        encrypted.params = argon2::Params::new(64, 1, 1, None).unwrap();
+        encrypted.ciphertext = encrypted
+            .cipher("correct horse")
+            .unwrap()
+            .encrypt(&encrypted.nonce.into(), signer.mnemonic_str().as_bytes())
+            .unwrap();
+        let content = encrypted.to_string();
+        assert!(content.contains(":argon2id:v=19:m=64,t=1,p=1:"));
+        assert_eq!(
+            EncryptedMnemonic::from_str(&content)
+                .unwrap()
+                .decrypt("correct horse")
+                .unwrap(),
+            signer.mnemonic_str()
+        );
+        for content in [
+            content.replace("argon2id", "argon2d"),
+            content.replace("v=19", "v=17"),
+            content.replace("t=1,", ""),
+            content.replace(",p=1", ",p=1,p=1"),
+        ] {
+            EncryptedMnemonic::from_str(&content).unwrap_err();
+        }
+
+        // Encrypted mnemonics are skipped without the password, and those encrypted with another
+        // password than the one given are skipped too.
+        let words_read = |password| -> HashSet<_> {
+            HotSigner::from_datadir(&tmp_dir, network, password)
+                .unwrap()
+                .into_iter()
+                .map(|signer| signer.words())
+                .collect()
+        };
+        assert_eq!(words_read(None), HashSet::from([plain_signer.words()]));
+        assert_eq!(
+            words_read(Some("wrong horse")),
+            HashSet::from([plain_signer.words()])
+        );
+        assert_eq!(
+            words_read(Some("correct horse")),
+            HashSet::from([signer.words(), plain_signer.words()])
+        );
+        assert_eq!(
+            words_read(Some("battery staple")),
+            HashSet::from([other_signer.words(), plain_signer.words()])
+        );
+
+        // The fingerprint of the encrypted mnemonic is known without the password.
+        let stored = HotSigner::stored_mnemonics(&tmp_dir, network).unwrap();
+        let encrypted = stored
+            .iter()
+            .find(|s| s.fingerprint == signer.fingerprint(&secp))
+            .unwrap();
+        assert!(encrypted.is_encrypted());
+        assert!(matches!(
+            encrypted.signer(network, None),
+            Err(SignerError::MnemonicDecryption)
+        ));
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
     #[test]
     fn hot_signer_sign_p2wsh() {
         let secp = secp256k1::Secp256k1::new();
@@ -1062,6 +1839,183 @@ mod tests {
             .all(|psbt_in| psbt_in.partial_sigs.is_empty()));
     }
 
+    #[test]
+    fn hot_signer_sign_musig() {
+        use miniscript::psbt::PsbtExt;
+
+        let secp = secp256k1::Secp256k1::new();
+        let network = bitcoin::Network::Bitcoin;
+
+        // Create a Liana descriptor with as primary path a MuSig2 aggregate key between two hot
+        // signers, and a single hot signer as recovery path.
+        let (prim_signer_a, prim_signer_b, recov_signer) = (
+            HotSigner::generate(network).unwrap(),
+            HotSigner::generate(network).unwrap(),
+            HotSigner::generate(network).unwrap(),
+        );
+        let desc_key = |signer: &HotSigner, origin_der: &str, derivs: [&str; 2]| {
+            let origin_der = bip32::DerivationPath::from_str(origin_der).unwrap();
+            DescriptorPublicKey::MultiXPub(DescriptorMultiXKey {
+                origin: Some((signer.fingerprint(&secp), origin_der.clone())),
+                xkey: signer.xpub_at(&origin_der, &secp),
+                derivation_paths: DerivPaths::new(
+                    derivs
+                        .iter()
+                        .map(|d| bip32::DerivationPath::from_str(d).unwrap())
+                        .collect(),
+                )
+                .unwrap(),
+                wildcard: Wildcard::Unhardened,
+            })
+        };
+        let prim_keys = descriptors::PathInfo::Musig(vec![
+            desc_key(&prim_signer_a, "m/48'/0'/0'/2'", ["m/0", "m/1"]),
+            desc_key(&prim_signer_b, "m/0'/12'/42", ["m/0", "m/1"]),
+        ]);
+        let recov_keys = descriptors::PathInfo::Single(desc_key(
+            &recov_signer,
+            "m/1/2'/3/4'",
+            ["m/5/6/0", "m/5/6/1"],
+        ));
+        let policy =
+            descriptors::LianaPolicy::new(prim_keys, [(46, recov_keys)].iter().cloned().collect())
+                .unwrap();
+        let desc = descriptors::LianaDescriptor::new(policy);
+        assert!(desc.to_string().starts_with("tr(musig(["));
+        let desc = descriptors::LianaDescriptor::from_str(&desc.to_string()).unwrap();
+
+        // Create a dummy PSBT spending a coin from this descriptor.
+        let spent_coin_desc = desc.receive_descriptor().derive(42.into(), &secp);
+        let mut psbt_in = PsbtIn::default();
+        spent_coin_desc.update_psbt_in(&mut psbt_in);
+        desc.update_musig_psbt_in(&mut psbt_in);
+        psbt_in.witness_utxo = Some(bitcoin::TxOut {
+            value: Amount::from_sat(19_000),
+            script_pubkey: spent_coin_desc.script_pubkey(),
+        });
+        let psbt = Psbt {
+            unsigned_tx: bitcoin::Transaction {
+                version: bitcoin::transaction::Version::TWO,
+                lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+                input: vec![bitcoin::TxIn {
+                    sequence: bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    previous_output: bitcoin::OutPoint::from_str(
+                        "4613e078e4cdbb0fce1bc6e44b028f0e11621a134a1605efdc456c32d155c922:19",
+                    )
+                    .unwrap(),
+                    ..bitcoin::TxIn::default()
+                }],
+                output: vec![bitcoin::TxOut {
+                    value: Amount::from_sat(18_420),
+                    script_pubkey: bitcoin::Address::from_str(
+                        "bc1qvklensptw5lk7d470ds60pcpsr0psdpgyvwepv",
+                    )
+                    .unwrap()
+                    .payload()
+                    .script_pubkey(),
+                }],
+            },
+            version: 0,
+            xpub: BTreeMap::new(),
+            proprietary: BTreeMap::new(),
+            unknown: BTreeMap::new(),
+            inputs: vec![psbt_in],
+            outputs: vec![bitcoin::psbt::Output::default()],
+        };
+        let session = musig::KeyPathSession::from_psbt_in(&secp, &psbt.inputs[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.participants().len(), 2);
+
+        // In a first round, both signers provide their nonce. They can't sign before all the
+        // nonces are present: the second signer signs right after providing the last one.
+        let psbt = prim_signer_a.sign_psbt(psbt, &secp).unwrap();
+        assert_eq!(session.pub_nonces(&psbt.inputs[0]).unwrap().len(), 1);
+        let psbt = prim_signer_a.sign_psbt(psbt, &secp).unwrap();
+        assert_eq!(session.pub_nonces(&psbt.inputs[0]).unwrap().len(), 1);
+        assert!(session.partial_sigs(&psbt.inputs[0]).unwrap().is_empty());
+        let psbt = prim_signer_b.sign_psbt(psbt, &secp).unwrap();
+        assert_eq!(session.pub_nonces(&psbt.inputs[0]).unwrap().len(), 2);
+        assert_eq!(session.partial_sigs(&psbt.inputs[0]).unwrap().len(), 1);
+        assert_eq!(
+            desc.partial_spend_info(&psbt)
+                .unwrap()
+                .primary_path()
+                .sigs_count,
+            1
+        );
+
+        // The recovery signer isn't a participant, it only signs for its own key.
+        let psbt = recov_signer.sign_psbt(psbt, &secp).unwrap();
+        assert_eq!(psbt.inputs[0].tap_script_sigs.len(), 1);
+        assert_eq!(session.partial_sigs(&psbt.inputs[0]).unwrap().len(), 1);
+        let mut bogus_psbt = psbt.clone();
+        let lost_nonce_psbt = psbt.clone();
+
+        // In a second round, the first signer provides its partial signature. The secret nonces
+        // are shared by the signer instances: another instance with the same key can sign, but
+        // only once.
+        let other_signer_a = HotSigner::from_str(network, &prim_signer_a.mnemonic_str()).unwrap();
+        let psbt = other_signer_a.sign_psbt(psbt, &secp).unwrap();
+        assert_eq!(session.partial_sigs(&psbt.inputs[0]).unwrap().len(), 2);
+        let psbt_sigs = psbt.inputs[0].unknown.clone();
+        let mut psbt = prim_signer_a.sign_psbt(psbt, &secp).unwrap();
+        assert_eq!(psbt.inputs[0].unknown, psbt_sigs);
+        assert_eq!(
+            desc.partial_spend_info(&psbt)
+                .unwrap()
+                .primary_path()
+                .sigs_count,
+            2
+        );
+
+        // The partial signatures can be aggregated into a valid signature for the key path.
+        // The key path is used for finalization.
+        desc.aggregate_musig_sigs(&secp, &mut psbt).unwrap();
+        assert!(psbt.inputs[0].tap_key_sig.is_some());
+        psbt.finalize_mut(&secp).unwrap();
+        assert_eq!(
+            psbt.inputs[0].final_script_witness.as_ref().unwrap().len(),
+            1
+        );
+
+        // Invalid partial signatures are detected upon aggregation.
+        let sig = musig::PartialSig::from_slice(&[1; 32]).unwrap();
+        for participant in session.participants() {
+            session.set_partial_sig(&mut bogus_psbt.inputs[0], participant, &sig);
+        }
+        assert!(desc.aggregate_musig_sigs(&secp, &mut bogus_psbt).is_err());
+        assert!(bogus_psbt.inputs[0].tap_key_sig.is_none());
+
+        // The secret nonce of the first signer was used already, as if it had been lost. For
+        // instance if it had restarted after the first round. It provides a new nonce, which
+        // invalidates the partial signature of the second signer. It doesn't sign yet, as the
+        // second signer needs to provide a new nonce too.
+        let prev_nonces = session.pub_nonces(&lost_nonce_psbt.inputs[0]).unwrap();
+        let mut psbt = prim_signer_a
+            .sign_psbt(lost_nonce_psbt.clone(), &secp)
+            .unwrap();
+        let nonces = session.pub_nonces(&psbt.inputs[0]).unwrap();
+        assert_eq!(nonces.len(), 2);
+        assert_ne!(nonces, prev_nonces);
+        assert!(session.partial_sigs(&psbt.inputs[0]).unwrap().is_empty());
+        // The invalidated partial signature isn't merged back from the previous PSBT.
+        musig::merge_psbt_in(&mut psbt.inputs[0], &lost_nonce_psbt.inputs[0]);
+        assert!(session.partial_sigs(&psbt.inputs[0]).unwrap().is_empty());
+
+        // The second signer provides a new nonce and signs, then the first one signs.
+        let psbt = prim_signer_b.sign_psbt(psbt, &secp).unwrap();
+        let nonces = session.pub_nonces(&psbt.inputs[0]).unwrap();
+        assert!(nonces
+            .values()
+            .all(|n| !prev_nonces.values().any(|p| p == n)));
+        assert_eq!(session.partial_sigs(&psbt.inputs[0]).unwrap().len(), 1);
+        let mut psbt = prim_signer_a.sign_psbt(psbt, &secp).unwrap();
+        assert_eq!(session.partial_sigs(&psbt.inputs[0]).unwrap().len(), 2);
+        desc.aggregate_musig_sigs(&secp, &mut psbt).unwrap();
+        psbt.finalize_mut(&secp).unwrap();
+    }
+
     #[test]
     fn signer_set_net() {
         let secp = secp256k1::Secp256k1::signing_only();
@@ -184,10 +184,58 @@ pub struct CandidateCoin {
     pub is_change: bool,
     /// Whether or not this coin must be selected by the coin selection algorithm.
     pub must_select: bool,
-    /// The nSequence field to set for an input spending this coin.
+    /// The nSequence field to set for an input spending this coin. Only set when spending
+    /// through a recovery path, to enable either its relative or its absolute timelock.
     pub sequence: Option<bitcoin::Sequence>,
     /// Information about in-mempool ancestors of the coin.
     pub ancestor_info: Option<AncestorInfo>,
+    /// Height of the block this coin was confirmed in, if it is confirmed.
+    pub block_height: Option<i32>,
+}
+
+/// The strategy to follow when selecting coins automatically for a transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoinSelectionStrategy {
+    /// Minimize the fees paid by this transaction and, in the long term, by spending the change.
+    #[default]
+    LowestFee,
+    /// Select the coins confirmed the earliest first, unconfirmed coins last. This can be used to
+    /// roll coins before their recovery path becomes available.
+    OldestFirst,
+    /// Select the coins with the largest value first, to use as few inputs as possible.
+    LargestFirst,
+    /// Avoid spending together coins received on different addresses, as doing so would reveal
+    /// they belong to the same wallet.
+    Privacy,
+    /// Select all the coins worth more than the fee needed to spend them, to reduce the number of
+    /// coins in the wallet.
+    Consolidation,
+}
+
+impl CoinSelectionStrategy {
+    pub fn from_arg(s: &str) -> Option<CoinSelectionStrategy> {
+        match s {
+            "lowest_fee" => Some(CoinSelectionStrategy::LowestFee),
+            "oldest_first" => Some(CoinSelectionStrategy::OldestFirst),
+            "largest_first" => Some(CoinSelectionStrategy::LargestFirst),
+            "privacy" => Some(CoinSelectionStrategy::Privacy),
+            "consolidation" => Some(CoinSelectionStrategy::Consolidation),
+            _ => None,
+        }
+    }
+
+    /// Converts a `CoinSelectionStrategy` to its equivalent argument name
+    /// as used in the `createspend` RPC command.
+    pub fn to_arg(&self) -> &'static str {
+        match self {
+            CoinSelectionStrategy::LowestFee => "lowest_fee",
+            CoinSelectionStrategy::OldestFirst => "oldest_first",
+            CoinSelectionStrategy::LargestFirst => "largest_first",
+            CoinSelectionStrategy::Privacy => "privacy",
+            CoinSelectionStrategy::Consolidation => "consolidation",
+        }
+    }
 }
 
 /// A coin selection result.
@@ -238,6 +286,97 @@ impl bdk_coin_select::BnbMetric for LowestFeeChangeCondition {
     }
 }
 
+/// Whether the current selection meets the target, with a change output if it must have one.
+fn is_selection_complete(
+    selector: &CoinSelector,
+    target: Target,
+    change_policy: ChangePolicy,
+    must_have_change: bool,
+) -> bool {
+    let drain = selector.drain(target, change_policy);
+    selector.is_target_met_with_drain(target, drain) && (drain.is_some() || !must_have_change)
+}
+
+/// Select the unselected candidates at the given indices, in this order, until the selection is
+/// complete. Returns `false` if it still isn't once all of them are selected.
+fn select_in_order(
+    selector: &mut CoinSelector,
+    order: impl IntoIterator<Item = usize>,
+    target: Target,
+    change_policy: ChangePolicy,
+    must_have_change: bool,
+) -> bool {
+    for i in order {
+        if is_selection_complete(selector, target, change_policy, must_have_change) {
+            return true;
+        }
+        selector.select(i);
+    }
+    is_selection_complete(selector, target, change_policy, must_have_change)
+}
+
+/// Select coins without mixing those received on different addresses, unless there is no other
+/// way to meet the target. Returns `false` if the target can't be met.
+///
+/// The remaining coins of the address clusters of coins already selected are used first, as
+/// spending them doesn't link any new address. Then the cluster with the smallest value that is
+/// enough on its own is used. If there is none, whole clusters are added by descending value.
+fn select_by_address_cluster(
+    selector: &mut CoinSelector,
+    candidate_coins: &[CandidateCoin],
+    target: Target,
+    change_policy: ChangePolicy,
+    must_have_change: bool,
+) -> bool {
+    let mut clusters = BTreeMap::<(bool, bip32::ChildNumber), Vec<usize>>::new();
+    for (i, cand) in candidate_coins.iter().enumerate() {
+        clusters
+            .entry((cand.is_change, cand.deriv_index))
+            .or_default()
+            .push(i);
+    }
+    let (used, mut unused): (Vec<_>, Vec<_>) = clusters
+        .into_values()
+        .partition(|indices| indices.iter().any(|i| selector.is_selected(*i)));
+    if select_in_order(
+        selector,
+        used.into_iter().flatten(),
+        target,
+        change_policy,
+        must_have_change,
+    ) {
+        return true;
+    }
+
+    let cluster_value = |indices: &Vec<usize>| -> u64 {
+        indices
+            .iter()
+            .map(|i| candidate_coins[*i].amount.to_sat())
+            .sum()
+    };
+    unused.sort_by_key(cluster_value);
+    for indices in &unused {
+        let mut trial = selector.clone();
+        if select_in_order(
+            &mut trial,
+            indices.iter().copied(),
+            target,
+            change_policy,
+            must_have_change,
+        ) {
+            *selector = trial;
+            return true;
+        }
+    }
+    select_in_order(
+        selector,
+        unused.into_iter().rev().flatten(),
+        target,
+        change_policy,
+        must_have_change,
+    )
+}
+
 /// Select coins for spend.
 ///
 /// Returns the selected coins and the change amount, which could be zero.
@@ -263,6 +402,9 @@ impl bdk_coin_select::BnbMetric for LowestFeeChangeCondition {
 ///
 /// `must_have_change` indicates whether the transaction must have a change output.
 /// If `true`, the returned change amount will be positive.
+///
+/// `strategy` is how to select coins among the candidates which aren't mandatory.
+#[allow(clippy::too_many_arguments)]
 fn select_coins_for_spend(
     candidate_coins: &[CandidateCoin],
     base_tx: bitcoin::Transaction,
@@ -271,6 +413,7 @@ fn select_coins_for_spend(
     replaced_fee: Option<u64>,
     max_sat_weight: u32,
     must_have_change: bool,
+    strategy: CoinSelectionStrategy,
 ) -> Result<CoinSelectionRes, InsufficientFunds> {
     let out_value_nochange = base_tx.output.iter().map(|o| o.value.to_sat()).sum();
     let out_weight_nochange: u32 = {
@@ -367,8 +510,7 @@ fn select_coins_for_spend(
         long_term_feerate,
     );
 
-    // Finally, run the coin selection algorithm. We use an opportunistic BnB and if it couldn't
-    // find any solution we fall back to selecting coins by descending value.
+    // Finally, run the coin selection algorithm according to the requested strategy.
     let replace = replaced_fee.map(Replace::new);
     let target_fee = TargetFee {
         rate: feerate,
@@ -383,53 +525,108 @@ fn select_coins_for_spend(
         fee: target_fee,
         outputs: target_outputs,
     };
-    let lowest_fee = LowestFee {
-        target,
-        long_term_feerate,
-        change_policy,
-    };
-    let lowest_fee_change_cond = LowestFeeChangeCondition {
-        lowest_fee,
-        must_have_change,
-    };
-    // Scale down the number of rounds to perform if there is too many candidates. If the binary
-    // isn't optimized, scale it down further to avoid lags in hot loops.
-    let bnb_rounds = match candidate_coins.len() {
-        i if i >= 500 => 1_000,
-        i if i >= 100 => 10_000,
-        _ => 100_000,
-    };
-    #[cfg(debug_assertions)]
-    let bnb_rounds = bnb_rounds / 1_000;
-    if let Err(e) = selector.run_bnb(lowest_fee_change_cond, bnb_rounds) {
-        log::debug!(
-            "Coin selection error: '{}'. Selecting coins by descending value per weight unit...",
-            e.to_string()
-        );
-        selector.sort_candidates_by_descending_value_pwu();
-        // Select more coins until target is met and change condition satisfied.
-        loop {
-            let drain = selector.drain(target, change_policy);
-            if selector.is_target_met_with_drain(target, drain)
-                && (drain.is_some() || !must_have_change)
-            {
-                break;
-            }
-            if !selector.select_next() {
-                // If the solution must have change, we calculate how much is missing from the current
-                // selection in order for there to be a change output with the smallest possible value.
-                let drain = if must_have_change {
-                    bdk_coin_select::Drain {
-                        weights: drain_weights,
-                        value: DUST_OUTPUT_SATS,
+    let is_complete = match strategy {
+        // We use an opportunistic BnB and if it couldn't find any solution we fall back to
+        // selecting coins by descending value.
+        CoinSelectionStrategy::LowestFee => {
+            let lowest_fee = LowestFee {
+                target,
+                long_term_feerate,
+                change_policy,
+            };
+            let lowest_fee_change_cond = LowestFeeChangeCondition {
+                lowest_fee,
+                must_have_change,
+            };
+            // Scale down the number of rounds to perform if there is too many candidates. If the binary
+            // isn't optimized, scale it down further to avoid lags in hot loops.
+            let bnb_rounds = match candidate_coins.len() {
+                i if i >= 500 => 1_000,
+                i if i >= 100 => 10_000,
+                _ => 100_000,
+            };
+            #[cfg(debug_assertions)]
+            let bnb_rounds = bnb_rounds / 1_000;
+            if let Err(e) = selector.run_bnb(lowest_fee_change_cond, bnb_rounds) {
+                log::debug!(
+                    "Coin selection error: '{}'. Selecting coins by descending value per weight unit...",
+                    e.to_string()
+                );
+                selector.sort_candidates_by_descending_value_pwu();
+                // Select more coins until target is met and change condition satisfied.
+                loop {
+                    if is_selection_complete(&selector, target, change_policy, must_have_change) {
+                        break true;
                     }
-                } else {
-                    drain
-                };
-                let missing = selector.excess(target, drain).unsigned_abs();
-                return Err(InsufficientFunds { missing });
+                    if !selector.select_next() {
+                        break false;
+                    }
+                }
+            } else {
+                true
             }
         }
+        CoinSelectionStrategy::OldestFirst => {
+            let mut order: Vec<usize> = (0..candidate_coins.len()).collect();
+            order.sort_by_key(|i| candidate_coins[*i].block_height.unwrap_or(i32::MAX));
+            select_in_order(
+                &mut selector,
+                order,
+                target,
+                change_policy,
+                must_have_change,
+            )
+        }
+        CoinSelectionStrategy::LargestFirst => {
+            let mut order: Vec<usize> = (0..candidate_coins.len()).collect();
+            order.sort_by_key(|i| std::cmp::Reverse(candidate_coins[*i].amount));
+            select_in_order(
+                &mut selector,
+                order,
+                target,
+                change_policy,
+                must_have_change,
+            )
+        }
+        CoinSelectionStrategy::Privacy => select_by_address_cluster(
+            &mut selector,
+            candidate_coins,
+            target,
+            change_policy,
+            must_have_change,
+        ),
+        CoinSelectionStrategy::Consolidation => {
+            // First select all the coins which are worth spending at this feerate, then the
+            // others by descending value if this isn't enough.
+            for (i, cand) in candidates.iter().enumerate() {
+                if cand.value as f32 > cand.weight as f32 * feerate.spwu() {
+                    selector.select(i);
+                }
+            }
+            let mut order: Vec<usize> = (0..candidate_coins.len()).collect();
+            order.sort_by_key(|i| std::cmp::Reverse(candidate_coins[*i].amount));
+            select_in_order(
+                &mut selector,
+                order,
+                target,
+                change_policy,
+                must_have_change,
+            )
+        }
+    };
+    if !is_complete {
+        // If the solution must have change, we calculate how much is missing from the current
+        // selection in order for there to be a change output with the smallest possible value.
+        let drain = if must_have_change {
+            bdk_coin_select::Drain {
+                weights: drain_weights,
+                value: DUST_OUTPUT_SATS,
+            }
+        } else {
+            selector.drain(target, change_policy)
+        };
+        let missing = selector.excess(target, drain).unsigned_abs();
+        return Err(InsufficientFunds { missing });
     }
     // By now, selection is complete and we can check how much change to give according to our policy.
     let drain = selector.drain(target, change_policy);
@@ -608,6 +805,7 @@ pub struct CreateSpendRes {
 ///   `destinations` is empty, they will all be included as inputs of the transaction. Otherwise, a
 ///   coin selection algorithm will be run to spend the most efficient subset of them to meet the
 ///   `destinations` requirements.
+/// * `coin_selection`: the strategy followed by the coin selection algorithm, if it is run.
 /// * `fees`: the target feerate (in sats/vb) and, if necessary, minimum absolute fee for this tx.
 /// * `change_addr`: the address to use for a change output if we need to create one. Can be set to
 ///   an external address (if combined with an empty list of `destinations` it's useful to sweep some
@@ -620,6 +818,7 @@ pub fn create_spend(
     tx_getter: &mut impl TxGetter,
     destinations: &[(SpendOutputAddress, bitcoin::Amount)],
     candidate_coins: &[CandidateCoin],
+    coin_selection: CoinSelectionStrategy,
     fees: SpendTxFees,
     change_addr: SpendOutputAddress,
     locktime: LockTime,
@@ -689,13 +888,10 @@ pub fn create_spend(
         value: bitcoin::Amount::MAX,
         script_pubkey: change_addr.addr.script_pubkey(),
     };
-    // If no candidates have relative locktime, then we should use the primary spending path.
-    // Note we set this value before actually selecting the coins, but we expect either all
-    // candidates or none to have relative locktime sequence so this is fine.
-    let use_primary_path = !candidate_coins
-        .iter()
-        .filter_map(|cand| cand.sequence)
-        .any(|seq| seq.is_relative_lock_time());
+    // If no candidates have a sequence set to enable a timelock, then we should use the primary
+    // spending path. Note we set this value before actually selecting the coins, but we expect
+    // either all candidates or none to have a sequence set so this is fine.
+    let use_primary_path = candidate_coins.iter().all(|cand| cand.sequence.is_none());
     // Now select the coins necessary using the provided candidates and determine whether
     // there is any leftover to create a change output.
     let CoinSelectionRes {
@@ -728,6 +924,7 @@ pub fn create_spend(
             replaced_fee,
             max_sat_wu,
             is_self_send,
+            coin_selection,
         )
         .map_err(SpendCreationError::CoinSelection)?
     };
@@ -784,6 +981,7 @@ pub fn create_spend(
         let mut psbt_in = PsbtIn::default();
         let coin_desc = derived_desc(secp, main_descriptor, cand);
         coin_desc.update_psbt_in(&mut psbt_in);
+        main_descriptor.update_musig_psbt_in(&mut psbt_in);
         psbt_in.witness_utxo = Some(bitcoin::TxOut {
             value: cand.amount,
             script_pubkey: coin_desc.script_pubkey(),
@@ -818,7 +1016,7 @@ pub fn create_spend(
 mod tests {
     use super::*;
 
-    use std::time::Duration;
+    use std::{str::FromStr, time::Duration};
 
     use miniscript::bitcoin::absolute::{Height, LockTime};
 
@@ -931,4 +1129,103 @@ mod tests {
             LockTime::from_height(1).unwrap() // subtract 90
         );
     }
+
+    #[test]
+    fn coin_selection_strategies() {
+        let candidate =
+            |vout: u32, amount: u64, deriv_index: u32, block_height: Option<i32>| CandidateCoin {
+                outpoint: bitcoin::OutPoint::from_str(&format!(
+                    "4753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:{}",
+                    vout
+                ))
+                .unwrap(),
+                amount: bitcoin::Amount::from_sat(amount),
+                deriv_index: bip32::ChildNumber::from_normal_idx(deriv_index).unwrap(),
+                is_change: false,
+                must_select: false,
+                sequence: None,
+                ancestor_info: None,
+                block_height,
+            };
+        // The last two coins were received on the same address.
+        let candidates = [
+            candidate(0, 100_000, 0, Some(10)),
+            candidate(1, 200_000, 1, Some(5)),
+            candidate(2, 300_000, 2, None),
+            candidate(3, 150_000, 3, Some(20)),
+            candidate(4, 60_000, 3, Some(30)),
+        ];
+        let p2wsh_txo = |value: u64| bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(value),
+            script_pubkey: bitcoin::ScriptBuf::from_bytes(
+                [&[0x00, 0x20][..], &[0; 32][..]].concat(),
+            ),
+        };
+        let base_tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![p2wsh_txo(205_000)],
+        };
+        let select = |strategy| {
+            let mut selected: Vec<u32> = select_coins_for_spend(
+                &candidates,
+                base_tx.clone(),
+                p2wsh_txo(0),
+                1.0,
+                None,
+                300,
+                false,
+                strategy,
+            )
+            .unwrap()
+            .selected
+            .into_iter()
+            .map(|c| c.outpoint.vout)
+            .collect();
+            selected.sort();
+            selected
+        };
+
+        // The coins confirmed the earliest are selected first.
+        assert_eq!(select(CoinSelectionStrategy::OldestFirst), vec![0, 1]);
+        // The largest coin is enough on its own.
+        assert_eq!(select(CoinSelectionStrategy::LargestFirst), vec![2]);
+        // The smallest address cluster which is enough on its own is selected.
+        assert_eq!(select(CoinSelectionStrategy::Privacy), vec![3, 4]);
+        // All coins are worth spending at this feerate.
+        assert_eq!(
+            select(CoinSelectionStrategy::Consolidation),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert!(!select(CoinSelectionStrategy::LowestFee).is_empty());
+
+        // If the target can't be met, we are told how much is missing whatever the strategy.
+        let mut base_tx = base_tx;
+        base_tx.output[0].value = bitcoin::Amount::from_sat(1_000_000);
+        for strategy in [
+            CoinSelectionStrategy::LowestFee,
+            CoinSelectionStrategy::OldestFirst,
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::Privacy,
+            CoinSelectionStrategy::Consolidation,
+        ] {
+            let InsufficientFunds { missing } = select_coins_for_spend(
+                &candidates,
+                base_tx.clone(),
+                p2wsh_txo(0),
+                1.0,
+                None,
+                300,
+                false,
+                strategy,
+            )
+            .unwrap_err();
+            assert!(missing > 190_000, "{}", missing);
+            assert_eq!(
+                CoinSelectionStrategy::from_arg(strategy.to_arg()),
+                Some(strategy)
+            );
+        }
+    }
 }
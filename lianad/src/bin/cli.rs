@@ -16,12 +16,17 @@ use std::os::unix::net::UnixStream;
 // Exits with error
 fn show_usage() {
     eprintln!("Usage:");
-    eprintln!(" liana-cli [--conf conf_path] [--raw] <command> [<param 1> <param 2> ...]");
+    eprintln!(
+        " liana-cli [--conf conf_path] [--wallet name] [--raw] <command> [<param 1> <param 2> ...]"
+    );
     process::exit(1);
 }
 
-// Returns (Maybe(special conf file), Raw, Method name, Maybe(List of parameters))
-fn parse_args(mut args: Vec<String>) -> (Option<PathBuf>, bool, String, Vec<String>) {
+// Returns (Maybe(special conf file), Maybe(wallet name), Raw, Method name, Maybe(List of parameters))
+#[allow(clippy::type_complexity)]
+fn parse_args(
+    mut args: Vec<String>,
+) -> (Option<PathBuf>, Option<String>, bool, String, Vec<String>) {
     if args.len() < 2 {
         eprintln!("Not enough arguments.");
         show_usage();
@@ -32,6 +37,7 @@ fn parse_args(mut args: Vec<String>) -> (Option<PathBuf>, bool, String, Vec<Stri
     let mut args = args.into_iter();
     let mut raw = false;
     let mut conf_file = None;
+    let mut wallet = None;
 
     loop {
         match args.next().as_deref() {
@@ -43,6 +49,14 @@ fn parse_args(mut args: Vec<String>) -> (Option<PathBuf>, bool, String, Vec<Stri
 
                 conf_file = Some(PathBuf::from(args.next().expect("Just checked")));
             }
+            Some("--wallet") => {
+                if args.len() < 2 {
+                    eprintln!("Not enough arguments.");
+                    show_usage();
+                }
+
+                wallet = Some(args.next().expect("Just checked"));
+            }
             Some("--raw") => {
                 if args.len() < 1 {
                     eprintln!("Not enough arguments.");
@@ -50,7 +64,7 @@ fn parse_args(mut args: Vec<String>) -> (Option<PathBuf>, bool, String, Vec<Stri
                 }
                 raw = true;
             }
-            Some(method) => return (conf_file, raw, method.to_owned(), args.collect()),
+            Some(method) => return (conf_file, wallet, raw, method.to_owned(), args.collect()),
             None => {
                 // Should never happen...
                 eprintln!("Not enough arguments.");
@@ -68,7 +82,7 @@ fn from_str_hack(token: String) -> Json {
     }
 }
 
-fn rpc_request(method: String, params: Vec<String>) -> Json {
+fn rpc_request(method: String, params: Vec<String>, wallet: Option<String>) -> Json {
     let method = Json::String(method);
     let params = Json::Array(params.into_iter().map(from_str_hack).collect::<Vec<Json>>());
     let mut object = serde_json::Map::<String, Json>::new();
@@ -79,6 +93,9 @@ fn rpc_request(method: String, params: Vec<String>) -> Json {
     );
     object.insert("method".to_string(), method);
     object.insert("params".to_string(), params);
+    if let Some(wallet) = wallet {
+        object.insert("wallet".to_string(), Json::String(wallet));
+    }
 
     Json::Object(object)
 }
@@ -119,8 +136,8 @@ fn trimmed(mut vec: Vec<u8>, bytes_read: usize) -> Vec<u8> {
 
 fn main() {
     let args = env::args().collect();
-    let (conf_file, raw, method, params) = parse_args(args);
-    let request = rpc_request(method, params);
+    let (conf_file, wallet, raw, method, params) = parse_args(args);
+    let request = rpc_request(method, params, wallet);
     let socket_file = socket_file(conf_file);
     let mut raw_response = vec![0; 256];
 
@@ -668,6 +668,57 @@ impl BitcoinD {
         Ok(())
     }
 
+    /// Import a descriptor in our watchonly wallet, if it isn't already. This is used for the
+    /// additional wallets, whose descriptors are imported in the same watchonly wallet as the
+    /// main descriptor.
+    pub fn maybe_import_descriptor(
+        &self,
+        descriptor: &LianaDescriptor,
+    ) -> Result<(), BitcoindError> {
+        if self.is_descriptor_imported(descriptor) {
+            return Ok(());
+        }
+        log::info!(
+            "Importing descriptor '{}' in our watchonly wallet.",
+            descriptor
+        );
+        if let Some(err) = self.import_descriptor(descriptor) {
+            return Err(BitcoindError::Wallet(
+                self.watchonly_wallet_path.clone(),
+                WalletError::ImportingDescriptor(err),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Whether both the receive and change descriptors are imported in our watchonly wallet.
+    fn is_descriptor_imported(&self, descriptor: &LianaDescriptor) -> bool {
+        let receive_desc = descriptor.receive_descriptor();
+        let change_desc = descriptor.change_descriptor();
+        let desc_list: Vec<_> = self
+            .list_descriptors()
+            .into_iter()
+            .filter_map(|entry| {
+                match descriptor::Descriptor::<descriptor::DescriptorPublicKey>::from_str(
+                    &entry.desc,
+                ) {
+                    Ok(desc) => Some(desc),
+                    Err(e) => {
+                        log::error!(
+                            "Error deserializing descriptor: {}. Descriptor: {}.",
+                            e,
+                            entry.desc
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+        desc_list.iter().any(|desc| *receive_desc == *desc)
+            && desc_list.iter().any(|desc| *change_desc == *desc)
+    }
+
     /// Load the watchonly wallet on bitcoind, if it isn't already.
     pub fn maybe_load_watchonly_wallet(&self) -> Result<(), BitcoindError> {
         if self.list_wallets().contains(&self.watchonly_wallet_path) {
@@ -752,30 +803,7 @@ impl BitcoinD {
         }
 
         // Check our main descriptor is imported in this wallet.
-        let receive_desc = main_descriptor.receive_descriptor();
-        let change_desc = main_descriptor.change_descriptor();
-        let desc_list: Vec<_> = self
-            .list_descriptors()
-            .into_iter()
-            .filter_map(|entry| {
-                match descriptor::Descriptor::<descriptor::DescriptorPublicKey>::from_str(
-                    &entry.desc,
-                ) {
-                    Ok(desc) => Some(desc),
-                    Err(e) => {
-                        log::error!(
-                            "Error deserializing descriptor: {}. Descriptor: {}.",
-                            e,
-                            entry.desc
-                        );
-                        None
-                    }
-                }
-            })
-            .collect();
-        if !desc_list.iter().any(|desc| *receive_desc == *desc)
-            || !desc_list.iter().any(|desc| *change_desc == *desc)
-        {
+        if !self.is_descriptor_imported(main_descriptor) {
             return Err(BitcoindError::Wallet(
                 self.watchonly_wallet_path.clone(),
                 WalletError::MissingDescriptor,
@@ -1054,6 +1082,38 @@ impl BitcoinD {
         Ok(())
     }
 
+    /// Check whether this transaction would be accepted in the mempool, without broadcasting it.
+    /// Returns the reason for its rejection, if it would be rejected.
+    pub fn test_mempool_accept(
+        &self,
+        tx: &bitcoin::Transaction,
+    ) -> Result<Option<String>, BitcoindError> {
+        let res = self.make_fallible_node_request(
+            "testmempoolaccept",
+            params!(Json::Array(vec![
+                bitcoin::consensus::encode::serialize_hex(tx).into()
+            ])),
+        )?;
+        let entry = res
+            .get(0)
+            .expect("There is one result per transaction in 'testmempoolaccept' response.");
+        let allowed = entry
+            .get("allowed")
+            .and_then(Json::as_bool)
+            .expect("No valid 'allowed' in 'testmempoolaccept' response?");
+        Ok(if allowed {
+            None
+        } else {
+            Some(
+                entry
+                    .get("reject-reason")
+                    .and_then(Json::as_str)
+                    .unwrap_or("unknown")
+                    .to_string(),
+            )
+        })
+    }
+
     // For the given descriptor strings check if they are imported at this timestamp in the
     // watchonly wallet.
     fn check_descs_timestamp(&self, descs: &[String], timestamp: u32) -> bool {
@@ -1185,6 +1245,23 @@ impl BitcoinD {
         )
     }
 
+    /// Get the feerate estimate, in BTC/kvB, for a confirmation within `conf_target` blocks.
+    /// Returns `None` if bitcoind doesn't have enough data for an estimate.
+    pub fn estimate_smart_fee(&self, conf_target: u16) -> Option<f64> {
+        let res = match self.make_fallible_node_request(
+            "estimatesmartfee",
+            params!(Json::Number(conf_target.into())),
+        ) {
+            Ok(res) => res,
+            Err(e) => {
+                log::warn!("Error when estimating the feerate: {}", e);
+                return None;
+            }
+        };
+        // The feerate is absent from the result if there isn't enough data.
+        res.get("feerate").and_then(Json::as_f64)
+    }
+
     /// Whether this transaction is in the mempool.
     pub fn is_in_mempool(&self, txid: &bitcoin::Txid) -> bool {
         self.mempool_entry(txid).is_some()
@@ -102,6 +102,14 @@ impl Client {
         self.0.transaction_broadcast(tx).map_err(Error::Server)
     }
 
+    /// Get the feerate estimate, in BTC/kvB, for a confirmation within `conf_target` blocks.
+    /// The server returns a negative value if it doesn't have enough data for an estimate.
+    pub fn estimate_fee(&self, conf_target: u16) -> Result<f64, Error> {
+        self.0
+            .estimate_fee(conf_target.into())
+            .map_err(Error::Server)
+    }
+
     pub fn tip_time(&self) -> Result<u32, Error> {
         let tip_height = self.chain_tip()?.height;
         self.0
@@ -110,6 +118,25 @@ impl Client {
             .map(|bh| bh.time)
     }
 
+    pub fn tip_median_time_past(&self) -> Result<u32, Error> {
+        let tip_height = self.chain_tip()?.height;
+        self.median_time_past(tip_height)
+    }
+
+    /// Get the median time of the 11 blocks up to and including the block at this height.
+    pub fn median_time_past(&self, height: i32) -> Result<u32, Error> {
+        let mut times = ((height - 10).max(0)..=height)
+            .map(|height| {
+                self.0
+                    .block_header(height_usize_from_i32(height))
+                    .map_err(Error::Server)
+                    .map(|bh| bh.time)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        times.sort_unstable();
+        Ok(times[times.len() / 2])
+    }
+
     fn sync_with_confirmation_height_anchor(
         &self,
         request: SyncRequest,
@@ -19,6 +19,16 @@ type SpentCoin = (bitcoin::OutPoint, bitcoin::Txid, i32, u32);
 
 const COINBASE_MATURITY: i32 = 100;
 
+/// Convert a feerate in BTC/kvB, as returned by fee estimators, to a feerate in sats/vb rounded
+/// up. Returns `None` if the feerate isn't a valid estimate.
+fn feerate_from_btc_kvb(btc_kvb: f64) -> Option<u64> {
+    if !btc_kvb.is_finite() || btc_kvb <= 0.0 {
+        return None;
+    }
+    // 1 BTC/kvB is 100_000_000 sats per 1_000 vbytes.
+    Some(((btc_kvb * 100_000.0).ceil() as u64).max(1))
+}
+
 /// Information about a block
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
 pub struct Block {
@@ -57,6 +67,14 @@ pub trait BitcoinInterface: Send {
     /// Get the timestamp set in the best block's header.
     fn tip_time(&self) -> Option<u32>;
 
+    /// Get the median time of the 11 blocks up to and including the best block, against which
+    /// time-based absolute locktimes are checked (BIP113).
+    fn tip_median_time_past(&self) -> Option<u32>;
+
+    /// Get the median time of the 11 blocks up to and including the block at this height in the
+    /// best chain.
+    fn median_time_past(&self, height: i32) -> Option<u32>;
+
     /// Check whether this former tip is part of the current best chain.
     fn is_in_chain(&self, tip: &BlockChainTip) -> bool;
 
@@ -105,6 +123,10 @@ pub trait BitcoinInterface: Send {
     /// Broadcast this transaction to the Bitcoin P2P network
     fn broadcast_tx(&self, tx: &bitcoin::Transaction) -> Result<(), String>;
 
+    /// Check whether this transaction could be broadcast, without broadcasting it. Returns the
+    /// reason why it couldn't.
+    fn test_mempool_accept(&self, tx: &bitcoin::Transaction) -> Result<(), String>;
+
     /// Trigger a rescan of the block chain for transactions related to this descriptor since
     /// the given date.
     fn start_rescan(
@@ -133,6 +155,12 @@ pub trait BitcoinInterface: Send {
     ///
     /// Returns `None` if the transaction is not in the mempool.
     fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry>;
+
+    /// Estimate the feerate, in sats/vb, for a transaction to be confirmed within this number of
+    /// blocks.
+    ///
+    /// Returns `None` if the backend doesn't have enough data to provide an estimate.
+    fn estimate_fee(&self, conf_target: u16) -> Option<u64>;
 }
 
 impl BitcoinInterface for d::BitcoinD {
@@ -362,6 +390,19 @@ impl BitcoinInterface for d::BitcoinD {
         }
     }
 
+    fn test_mempool_accept(&self, tx: &bitcoin::Transaction) -> Result<(), String> {
+        match self.test_mempool_accept(tx) {
+            Ok(None) => Ok(()),
+            Ok(Some(reason)) => Err(reason),
+            Err(BitcoindError::Server(e)) => Err(e.to_string()),
+            // We assume the Bitcoin backend doesn't fail, so it must be a JSONRPC error.
+            Err(e) => panic!(
+                "Unexpected Bitcoin error when testing mempool acceptance: '{}'.",
+                e
+            ),
+        }
+    }
+
     fn start_rescan(
         &mut self,
         desc: &descriptors::LianaDescriptor,
@@ -385,6 +426,16 @@ impl BitcoinInterface for d::BitcoinD {
         Some(self.get_block_stats(tip.hash)?.time)
     }
 
+    fn tip_median_time_past(&self) -> Option<u32> {
+        let tip = self.chain_tip();
+        Some(self.get_block_stats(tip.hash)?.median_time_past)
+    }
+
+    fn median_time_past(&self, height: i32) -> Option<u32> {
+        let hash = self.get_block_hash(height)?;
+        Some(self.get_block_stats(hash)?.median_time_past)
+    }
+
     fn wallet_transaction(
         &self,
         txid: &bitcoin::Txid,
@@ -402,6 +453,11 @@ impl BitcoinInterface for d::BitcoinD {
     fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
         self.mempool_entry(txid)
     }
+
+    fn estimate_fee(&self, conf_target: u16) -> Option<u64> {
+        self.estimate_smart_fee(conf_target)
+            .and_then(feerate_from_btc_kvb)
+    }
 }
 
 impl BitcoinInterface for electrum::Electrum {
@@ -543,6 +599,11 @@ impl BitcoinInterface for electrum::Electrum {
         }
     }
 
+    fn test_mempool_accept(&self, _tx: &bitcoin::Transaction) -> Result<(), String> {
+        // The Electrum protocol has no way to check a transaction without broadcasting it.
+        Err("not supported by the Electrum backend".to_string())
+    }
+
     fn wallet_transaction(
         &self,
         txid: &bitcoin::Txid,
@@ -589,6 +650,24 @@ impl BitcoinInterface for electrum::Electrum {
     fn tip_time(&self) -> Option<u32> {
         self.client().tip_time().ok()
     }
+
+    fn tip_median_time_past(&self) -> Option<u32> {
+        self.client().tip_median_time_past().ok()
+    }
+
+    fn median_time_past(&self, height: i32) -> Option<u32> {
+        self.client().median_time_past(height).ok()
+    }
+
+    fn estimate_fee(&self, conf_target: u16) -> Option<u64> {
+        match self.client().estimate_fee(conf_target) {
+            Ok(btc_kvb) => feerate_from_btc_kvb(btc_kvb),
+            Err(e) => {
+                log::error!("Error when estimating the feerate: {}", e);
+                None
+            }
+        }
+    }
 }
 
 // FIXME: do we need to repeat the entire trait implemenation? Isn't there a nicer way?
@@ -660,6 +739,10 @@ impl BitcoinInterface for sync::Arc<sync::Mutex<dyn BitcoinInterface + 'static>>
         self.lock().unwrap().broadcast_tx(tx)
     }
 
+    fn test_mempool_accept(&self, tx: &bitcoin::Transaction) -> Result<(), String> {
+        self.lock().unwrap().test_mempool_accept(tx)
+    }
+
     fn start_rescan(
         &mut self,
         desc: &descriptors::LianaDescriptor,
@@ -680,6 +763,14 @@ impl BitcoinInterface for sync::Arc<sync::Mutex<dyn BitcoinInterface + 'static>>
         self.lock().unwrap().tip_time()
     }
 
+    fn tip_median_time_past(&self) -> Option<u32> {
+        self.lock().unwrap().tip_median_time_past()
+    }
+
+    fn median_time_past(&self, height: i32) -> Option<u32> {
+        self.lock().unwrap().median_time_past(height)
+    }
+
     fn wallet_transaction(
         &self,
         txid: &bitcoin::Txid,
@@ -694,6 +785,10 @@ impl BitcoinInterface for sync::Arc<sync::Mutex<dyn BitcoinInterface + 'static>>
     fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
         self.lock().unwrap().mempool_entry(txid)
     }
+
+    fn estimate_fee(&self, conf_target: u16) -> Option<u64> {
+        self.lock().unwrap().estimate_fee(conf_target)
+    }
 }
 
 // FIXME: We could avoid this type (and all the conversions entailing allocations) if bitcoind
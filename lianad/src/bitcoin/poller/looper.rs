@@ -3,7 +3,11 @@ use crate::{
     database::{Coin, DatabaseConnection, DatabaseInterface},
 };
 
-use std::{collections::HashSet, convert::TryInto, sync, thread, time};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryInto,
+    sync, thread, time,
+};
 
 use liana::descriptors;
 use miniscript::bitcoin::{self, secp256k1};
@@ -12,6 +16,7 @@ use miniscript::bitcoin::{self, secp256k1};
 struct UpdatedCoins {
     pub received: Vec<Coin>,
     pub confirmed: Vec<(bitcoin::OutPoint, i32, u32)>,
+    pub prev_mtps: Vec<(bitcoin::OutPoint, u32)>,
     pub expired: Vec<bitcoin::OutPoint>,
     pub spending: Vec<(bitcoin::OutPoint, bitcoin::Txid)>,
     pub expired_spending: Vec<bitcoin::OutPoint>,
@@ -116,6 +121,34 @@ fn update_coins(
     log::debug!("Newly confirmed coins: {:?}", confirmed);
     log::debug!("Expired coins: {:?}", expired);
 
+    // Time-based relative timelocks count from the median time past of the block preceding the
+    // one which confirmed the coin. Query it for the newly confirmed coins, as well as for the
+    // confirmed coins for which it wasn't recorded yet (for instance if they were confirmed before
+    // we started recording it).
+    let mut mtps_by_height = HashMap::new();
+    let prev_mtps: Vec<(bitcoin::OutPoint, u32)> = confirmed
+        .iter()
+        .map(|(op, height, _)| (*op, *height))
+        .chain(curr_coins.values().filter_map(|coin| {
+            let block_info = coin.block_info?;
+            if block_info.prev_mtp.is_none() {
+                Some((coin.outpoint, block_info.height))
+            } else {
+                None
+            }
+        }))
+        .filter_map(|(op, height)| {
+            let prev_mtp = *mtps_by_height
+                .entry(height)
+                .or_insert_with(|| bit.median_time_past(height.checked_sub(1)?));
+            prev_mtp.map(|mtp| (op, mtp))
+        })
+        .collect();
+    log::debug!(
+        "Newly recorded previous block median time past: {:?}",
+        prev_mtps
+    );
+
     // We need to take the newly received ones into account as well, as they may have been
     // spent within the previous tip and the current one, and we may not poll this chunk of the
     // chain anymore.
@@ -155,6 +188,7 @@ fn update_coins(
     UpdatedCoins {
         received,
         confirmed,
+        prev_mtps,
         expired,
         spending,
         expired_spending,
@@ -313,6 +347,7 @@ fn updates(
     db_conn.new_unspent_coins(&updated_coins.received);
     db_conn.remove_coins(&updated_coins.expired);
     db_conn.confirm_coins(&updated_coins.confirmed);
+    db_conn.set_coins_prev_mtp(&updated_coins.prev_mtps);
     db_conn.unspend_coins(&updated_coins.expired_spending);
     db_conn.spend_coins(&updated_coins.spending);
     db_conn.confirm_spend(&updated_coins.spent);
@@ -1,14 +1,22 @@
 mod looper;
 
-use crate::{bitcoin::BitcoinInterface, database::DatabaseInterface};
+use crate::{
+    bitcoin::{BitcoinInterface, SyncProgress},
+    commands::{expiring_coins, ExpiringCoin},
+    config::ExpiryWatchConfig,
+    database::{CoinStatus, DatabaseInterface},
+};
 use liana::descriptors;
 
 use std::{
+    collections::HashSet,
+    io::Write,
+    process,
     sync::{self, mpsc},
-    time,
+    thread, time,
 };
 
-use miniscript::bitcoin::secp256k1;
+use miniscript::bitcoin::{self, relative, secp256k1};
 
 #[derive(Debug, Clone)]
 pub enum PollerMessage {
@@ -18,35 +26,258 @@ pub enum PollerMessage {
     PollNow(mpsc::SyncSender<()>),
 }
 
-/// The Bitcoin poller handler.
-pub struct Poller {
+// A wallet whose state is updated by the poller.
+struct PolledWallet {
+    name: String,
     bit: sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
     db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
-    secp: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
     // The receive and change descriptors (in this order).
     descs: [descriptors::SinglePathLianaDesc; 2],
+    // The timelock of the first recovery path. None if all are behind an absolute timelock, in
+    // which case no coin ever expires.
+    timelock: Option<relative::LockTime>,
+    // The coins for which the expiry hook is currently being executed. They are recorded in
+    // database once it exits successfully.
+    notifying: sync::Arc<sync::Mutex<HashSet<bitcoin::OutPoint>>>,
 }
 
-impl Poller {
-    pub fn new(
+impl PolledWallet {
+    fn new(
+        name: String,
         bit: sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
         db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
         desc: descriptors::LianaDescriptor,
-    ) -> Poller {
-        let secp = secp256k1::Secp256k1::verification_only();
+    ) -> PolledWallet {
         let descs = [
             desc.receive_descriptor().clone(),
             desc.change_descriptor().clone(),
         ];
+        let timelock = desc.first_relative_timelock();
 
         // On first startup the tip may be NULL. Make sure it's set as the poller relies on it.
         looper::maybe_initialize_tip(&bit, &db);
 
-        Poller {
+        PolledWallet {
+            name,
             bit,
             db,
-            secp,
             descs,
+            timelock,
+            notifying: sync::Arc::new(sync::Mutex::new(HashSet::new())),
+        }
+    }
+
+    // Get the expiring coins we haven't notified about yet, nor are currently notifying about.
+    fn newly_expiring_coins(&self, threshold: u32) -> Vec<ExpiringCoin> {
+        let mut db_conn = self.db.connection();
+        let tip_height = match db_conn.chain_tip() {
+            Some(tip) => tip.height,
+            None => return Vec::new(),
+        };
+        let timelock = match self.timelock {
+            Some(timelock) => timelock,
+            None => return Vec::new(),
+        };
+        let tip_mtp = match timelock {
+            relative::LockTime::Blocks(_) => None,
+            relative::LockTime::Time(_) => self.bit.tip_median_time_past(),
+        };
+        let expiring = expiring_coins(
+            db_conn.coins(&[CoinStatus::Confirmed], &[]).into_values(),
+            tip_height,
+            tip_mtp,
+            timelock,
+            threshold,
+        );
+        let notified = db_conn.expiry_notified_coins();
+        let notifying = self.notifying.lock().unwrap();
+        expiring
+            .into_iter()
+            .filter(|coin| {
+                !notified.contains(&coin.outpoint) && !notifying.contains(&coin.outpoint)
+            })
+            .collect()
+    }
+}
+
+// Execute the hook command, passing it the newly expiring coins of this wallet on its standard
+// input. Kill it if it doesn't exit before the timeout. Returns whether it exited successfully.
+fn execute_expiry_hook(
+    command: &std::path::Path,
+    timeout: time::Duration,
+    payload: String,
+) -> bool {
+    let mut child = match process::Command::new(command)
+        .stdin(process::Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            log::error!("Error executing expiry hook '{}': {}", command.display(), e);
+            return false;
+        }
+    };
+
+    // Write to its standard input from another thread, as it may block if the hook doesn't read
+    // it. The write fails once the hook is killed.
+    if let Some(mut stdin) = child.stdin.take() {
+        let res = thread::Builder::new()
+            .name("Expiry hook input".to_string())
+            .spawn(move || {
+                if let Err(e) = stdin.write_all(payload.as_bytes()) {
+                    log::error!("Error writing to expiry hook's standard input: {}", e);
+                }
+            });
+        if let Err(e) = res {
+            log::error!("Error spawning the expiry hook input thread: {}", e);
+        }
+    }
+
+    let deadline = time::Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                log::debug!("Expiry hook executed.");
+                return true;
+            }
+            Ok(Some(status)) => {
+                log::error!("Expiry hook exited with {}.", status);
+                return false;
+            }
+            Ok(None) if time::Instant::now() >= deadline => {
+                log::error!(
+                    "Expiry hook didn't exit after {} seconds. Killing it.",
+                    timeout.as_secs()
+                );
+                if let Err(e) = child.kill() {
+                    log::error!("Error killing the expiry hook: {}", e);
+                }
+                // Reap it.
+                let _ = child.wait();
+                return false;
+            }
+            Ok(None) => thread::sleep(time::Duration::from_millis(100)),
+            Err(e) => {
+                log::error!("Error waiting for the expiry hook: {}", e);
+                return false;
+            }
+        }
+    }
+}
+
+// Execute the hook command in the background for these newly expiring coins of this wallet. They
+// are only recorded as notified once it exits successfully, otherwise it's executed again for them
+// at the next poll.
+fn run_expiry_hook(
+    command: &std::path::Path,
+    timeout: time::Duration,
+    wallet: &PolledWallet,
+    coins: &[ExpiringCoin],
+) {
+    let outpoints: Vec<_> = coins.iter().map(|coin| coin.outpoint).collect();
+    let payload = serde_json::json!({
+        "wallet": wallet.name,
+        "coins": coins,
+    })
+    .to_string();
+    let command = command.to_path_buf();
+    let db = wallet.db.clone();
+    let notifying = wallet.notifying.clone();
+    notifying.lock().unwrap().extend(outpoints.iter().cloned());
+
+    let res = thread::Builder::new()
+        .name("Expiry hook".to_string())
+        .spawn({
+            let outpoints = outpoints.clone();
+            let notifying = notifying.clone();
+            move || {
+                if execute_expiry_hook(&command, timeout, payload) {
+                    db.connection().mark_expiry_notified(&outpoints);
+                }
+                let mut notifying = notifying.lock().unwrap();
+                for outpoint in &outpoints {
+                    notifying.remove(outpoint);
+                }
+            }
+        });
+    if let Err(e) = res {
+        log::error!("Error spawning the expiry hook thread: {}", e);
+        let mut notifying = notifying.lock().unwrap();
+        for outpoint in &outpoints {
+            notifying.remove(outpoint);
+        }
+    }
+}
+
+/// The Bitcoin poller handler.
+pub struct Poller {
+    secp: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    // The main wallet first, then the additional ones.
+    wallets: Vec<PolledWallet>,
+    expiry_watch: ExpiryWatchConfig,
+}
+
+impl Poller {
+    pub fn new(
+        bit: sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
+        db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
+        desc: descriptors::LianaDescriptor,
+        expiry_watch: ExpiryWatchConfig,
+    ) -> Poller {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let wallets = vec![PolledWallet::new(
+            crate::config::MAIN_WALLET_NAME.to_string(),
+            bit,
+            db,
+            desc,
+        )];
+
+        Poller {
+            secp,
+            wallets,
+            expiry_watch,
+        }
+    }
+
+    /// Also update the state of this additional wallet at each poll.
+    pub fn add_wallet(
+        &mut self,
+        name: String,
+        bit: sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
+        db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
+        desc: descriptors::LianaDescriptor,
+    ) {
+        self.wallets.push(PolledWallet::new(name, bit, db, desc));
+    }
+
+    // The sync progress of the Bitcoin backend of the main wallet.
+    fn sync_progress(&self) -> SyncProgress {
+        self.wallets[0].bit.sync_progress()
+    }
+
+    // Update the state of all our wallets, and notify about the coins which started expiring.
+    fn poll(&mut self) {
+        for wallet in self.wallets.iter_mut() {
+            looper::poll(&mut wallet.bit, &wallet.db, &self.secp, &wallet.descs);
+
+            let expiring = wallet.newly_expiring_coins(self.expiry_watch.threshold_blocks);
+            if expiring.is_empty() {
+                continue;
+            }
+            log::warn!(
+                "{} coin(s) of wallet '{}' will be spendable through a recovery path within {} blocks: {:?}",
+                expiring.len(),
+                wallet.name,
+                self.expiry_watch.threshold_blocks,
+                expiring.iter().map(|coin| coin.outpoint).collect::<Vec<_>>()
+            );
+            if let Some(command) = &self.expiry_watch.hook_command {
+                let timeout = time::Duration::from_secs(self.expiry_watch.hook_timeout_secs);
+                run_expiry_hook(command, timeout, wallet, &expiring);
+            } else {
+                let outpoints: Vec<_> = expiring.iter().map(|coin| coin.outpoint).collect();
+                wallet.db.connection().mark_expiry_notified(&outpoints);
+            }
         }
     }
 
@@ -95,7 +326,7 @@ impl Poller {
                     // if the height increases before completion, and in any case this is consistent
                     // with regular poller behaviour.
                     if !synced {
-                        let progress = self.bit.sync_progress();
+                        let progress = self.sync_progress();
                         log::info!(
                             "Block chain synchronization progress: {:.2}% ({} blocks / {} headers)",
                             progress.rounded_up_progress() * 100.0,
@@ -108,7 +339,7 @@ impl Poller {
                     // poll too soon.
                     last_poll = Some(time::Instant::now());
                     if synced {
-                        looper::poll(&mut self.bit, &self.db, &self.secp, &self.descs);
+                        self.poll();
                     } else {
                         log::warn!("Skipped poll as block chain is still synchronizing.");
                     }
@@ -129,7 +360,7 @@ impl Poller {
 
             // Don't poll until the Bitcoin backend is fully synced.
             if !synced {
-                let progress = self.bit.sync_progress();
+                let progress = self.sync_progress();
                 log::info!(
                     "Block chain synchronization progress: {:.2}% ({} blocks / {} headers)",
                     progress.rounded_up_progress() * 100.0,
@@ -142,7 +373,7 @@ impl Poller {
                 }
             }
 
-            looper::poll(&mut self.bit, &self.db, &self.secp, &self.descs);
+            self.poll();
         }
     }
 }
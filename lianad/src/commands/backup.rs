@@ -0,0 +1,67 @@
+//! Wallet backup.
+//!
+//! A versioned JSON bundle containing everything needed to rebuild a wallet on a new machine
+//! along with its metadata.
+
+use super::{
+    bip329::Bip329Record,
+    utils::{deser_fromstr, ser_to_string},
+};
+
+use liana::descriptors::LianaDescriptor;
+use miniscript::bitcoin::{self, bip32, psbt::Psbt};
+use serde::{Deserialize, Serialize};
+
+/// The version of the backup format we produce. We can restore backups with a lower or equal
+/// version.
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletBackup {
+    /// Version of the backup format.
+    pub version: u32,
+    pub network: bitcoin::Network,
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub descriptor: LianaDescriptor,
+    /// Timestamp at wallet creation time.
+    pub timestamp: u32,
+    /// Derivation index for the next receiving address.
+    pub receive_index: bip32::ChildNumber,
+    /// Derivation index for the next change address.
+    pub change_index: bip32::ChildNumber,
+    /// The wallet labels, as BIP329 records.
+    #[serde(default)]
+    pub labels: Vec<Bip329Record>,
+    /// The Spend transactions which were not broadcast yet.
+    #[serde(default)]
+    pub spend_txs: Vec<BackupSpendTx>,
+    /// Aliases of the keys of the descriptor. They aren't known to the daemon and are left
+    /// for the wallet frontend to fill in and restore.
+    #[serde(default)]
+    pub key_aliases: Vec<BackupKeyAlias>,
+    /// Registration of the descriptor on signing devices. They aren't known to the daemon and
+    /// are left for the wallet frontend to fill in and restore.
+    #[serde(default)]
+    pub hardware_wallets: Vec<BackupHardwareWallet>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSpendTx {
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub psbt: Psbt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupKeyAlias {
+    pub fingerprint: bip32::Fingerprint,
+    pub alias: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupHardwareWallet {
+    /// The kind of signing device (for instance "ledger").
+    pub kind: String,
+    pub fingerprint: bip32::Fingerprint,
+    /// The registration token returned by the device, such as the Ledger HMAC, in hex.
+    pub token: String,
+}
@@ -0,0 +1,165 @@
+//! BIP329 wallet labels.
+//!
+//! Records are exported and imported as JSON Lines, one record per line. See
+//! https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki.
+
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bip329Type {
+    Tx,
+    Addr,
+    Pubkey,
+    Input,
+    Output,
+    Xpub,
+}
+
+/// A single BIP329 label record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bip329Record {
+    #[serde(rename = "type")]
+    pub record_type: Bip329Type,
+    /// Reference to the labelled item: a txid, an address, a public key, an outpoint or an
+    /// extended public key depending on the type of the record.
+    #[serde(rename = "ref")]
+    pub reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Key origin information of the descriptor this item relates to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Only meaningful for `output` records.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deser_spendable"
+    )]
+    pub spendable: Option<bool>,
+}
+
+// The examples in BIP329 itself use a string for the `spendable` field, so accept both a boolean
+// and its string representation.
+fn deser_spendable<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    match Option::<BoolOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(BoolOrString::Bool(b)) => Ok(Some(b)),
+        Some(BoolOrString::String(s)) => s.parse().map(Some).map_err(de::Error::custom),
+    }
+}
+
+#[derive(Debug)]
+pub struct Bip329ParseError {
+    /// The line number (starting at 1) of the invalid record.
+    pub line: usize,
+    pub error: serde_json::Error,
+}
+
+impl fmt::Display for Bip329ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid BIP329 record at line {}: {}",
+            self.line, self.error
+        )
+    }
+}
+
+impl std::error::Error for Bip329ParseError {}
+
+/// Serialize records as JSON Lines.
+pub fn to_jsonl(records: &[Bip329Record]) -> String {
+    records
+        .iter()
+        .map(|rec| serde_json::to_string(rec).expect("Serialization must not fail"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Parse records from JSON Lines, along with the number (starting at 1) of the line of each.
+/// Blank lines are ignored.
+pub fn from_jsonl(jsonl: &str) -> Result<Vec<(usize, Bip329Record)>, Bip329ParseError> {
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map(|record| (i + 1, record))
+                .map_err(|error| Bip329ParseError { line: i + 1, error })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bip329_jsonl_roundtrip() {
+        // Records from the BIP329 test vectors.
+        let jsonl = r#"{ "type": "tx", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd", "label": "Transaction", "origin": "wpkh([d34db33f/84'/0'/0'])" }
+{ "type": "addr", "ref": "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c", "label": "Address" }
+
+{ "type": "pubkey", "ref": "0283409659355b6d1cc3c32decd5d561abaac86c37a353b52895a5e6c196d6f448", "label": "Public Key" }
+{ "type": "input", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0", "label": "Input" }
+{ "type": "output", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1", "label": "Output" , "spendable" : "false" }
+{ "type": "xpub", "ref": "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8", "label": "Extended Public Key" }
+{ "type": "output", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:2", "spendable": true }"#;
+        let (lines, records): (Vec<_>, Vec<_>) = from_jsonl(jsonl).unwrap().into_iter().unzip();
+        assert_eq!(lines, vec![1, 2, 4, 5, 6, 7, 8]);
+        assert_eq!(records.len(), 7);
+        assert_eq!(records[0].record_type, Bip329Type::Tx);
+        assert_eq!(
+            records[0].origin.as_deref(),
+            Some("wpkh([d34db33f/84'/0'/0'])")
+        );
+        assert_eq!(records[1].record_type, Bip329Type::Addr);
+        assert_eq!(records[1].label.as_deref(), Some("Address"));
+        assert_eq!(records[2].record_type, Bip329Type::Pubkey);
+        assert_eq!(records[3].record_type, Bip329Type::Input);
+        assert_eq!(records[4].record_type, Bip329Type::Output);
+        assert_eq!(records[4].spendable, Some(false));
+        assert_eq!(records[5].record_type, Bip329Type::Xpub);
+        assert_eq!(records[6].label, None);
+        assert_eq!(records[6].spendable, Some(true));
+
+        // Absent optional fields are not serialized.
+        let exported = to_jsonl(&records);
+        assert_eq!(exported.lines().count(), 7);
+        assert_eq!(
+            exported.lines().nth(1).unwrap(),
+            r#"{"type":"addr","ref":"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c","label":"Address"}"#
+        );
+        assert_eq!(
+            from_jsonl(&exported)
+                .unwrap()
+                .into_iter()
+                .map(|(_, record)| record)
+                .collect::<Vec<_>>(),
+            records
+        );
+
+        // Unknown record types are rejected, as well as invalid "spendable" values.
+        let err = from_jsonl(&format!(
+            "{}\n{{\"type\":\"block\",\"ref\":\"00\"}}",
+            exported
+        ))
+        .unwrap_err();
+        assert_eq!(err.line, 8);
+        assert!(from_jsonl(r#"{"type":"output","ref":"00:0","spendable":"yes"}"#).is_err());
+    }
+}
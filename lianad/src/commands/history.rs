@@ -0,0 +1,173 @@
+//! Transaction history export.
+//!
+//! The confirmed history of the wallet is exported as CSV, one transaction per line, for use in
+//! accounting software.
+
+use std::fmt;
+
+use miniscript::bitcoin;
+
+/// The first line of the exported CSV.
+pub const CSV_HEADER: &str = "date,txid,direction,amount,fee,balance,block_height,labels";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxDirection {
+    /// Only received coins, none of our coins were spent.
+    Incoming,
+    /// Some of our coins were spent to an external address.
+    Outgoing,
+    /// All the outputs of the transaction are ours.
+    SelfTransfer,
+}
+
+impl fmt::Display for TxDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Incoming => write!(f, "incoming"),
+            Self::Outgoing => write!(f, "outgoing"),
+            Self::SelfTransfer => write!(f, "self"),
+        }
+    }
+}
+
+/// A confirmed transaction of the wallet history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Block time of the transaction.
+    pub time: u32,
+    pub txid: bitcoin::Txid,
+    pub direction: TxDirection,
+    /// Change in the wallet balance caused by this transaction.
+    pub amount: bitcoin::SignedAmount,
+    /// Only known if all the inputs of the transaction are ours.
+    pub fee: Option<bitcoin::Amount>,
+    /// Wallet balance once this transaction is confirmed.
+    pub balance: bitcoin::SignedAmount,
+    pub height: i32,
+    /// Labels of the transaction and of its outputs.
+    pub labels: Vec<String>,
+}
+
+/// Serialize history entries as CSV, including the header line.
+pub fn to_csv(entries: &[HistoryEntry]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    for entry in entries {
+        csv.push('\n');
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}",
+            utc_datetime(entry.time),
+            entry.txid,
+            entry.direction,
+            btc_string(entry.amount.to_sat()),
+            entry
+                .fee
+                .map(|fee| btc_string(fee.to_sat() as i64))
+                .unwrap_or_default(),
+            btc_string(entry.balance.to_sat()),
+            entry.height,
+            csv_field(&entry.labels.join("; ")),
+        ));
+    }
+    csv
+}
+
+// Format a number of satoshis as a decimal amount of bitcoins, without loss of precision.
+fn btc_string(sats: i64) -> String {
+    let sign = if sats < 0 { "-" } else { "" };
+    let sats = sats.unsigned_abs();
+    format!("{}{}.{:08}", sign, sats / 100_000_000, sats % 100_000_000)
+}
+
+// Quote a field if it contains a character with a special meaning in CSV.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Format a unix timestamp as an ISO 8601 UTC date and time.
+fn utc_datetime(timestamp: u32) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+
+    // Convert the number of days since the epoch to a civil date. See
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn history_csv() {
+        assert_eq!(utc_datetime(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_datetime(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(utc_datetime(1_231_006_505), "2009-01-03T18:15:05Z");
+        assert_eq!(utc_datetime(u32::MAX), "2106-02-07T06:28:15Z");
+
+        assert_eq!(btc_string(0), "0.00000000");
+        assert_eq!(btc_string(1), "0.00000001");
+        assert_eq!(btc_string(-150_000_000), "-1.50000000");
+        assert_eq!(btc_string(2_100_000_000_000_000), "21000000.00000000");
+
+        let txid = bitcoin::Txid::from_str(
+            "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd",
+        )
+        .unwrap();
+        let entries = vec![
+            HistoryEntry {
+                time: 1_700_000_000,
+                txid,
+                direction: TxDirection::Incoming,
+                amount: bitcoin::SignedAmount::from_sat(100_000),
+                fee: None,
+                balance: bitcoin::SignedAmount::from_sat(100_000),
+                height: 800_000,
+                labels: vec!["salary".to_string()],
+            },
+            HistoryEntry {
+                time: 1_700_000_600,
+                txid,
+                direction: TxDirection::Outgoing,
+                amount: bitcoin::SignedAmount::from_sat(-60_000),
+                fee: Some(bitcoin::Amount::from_sat(1_000)),
+                balance: bitcoin::SignedAmount::from_sat(40_000),
+                height: 800_001,
+                labels: vec!["rent, \"march\"".to_string(), "change".to_string()],
+            },
+        ];
+        assert_eq!(
+            to_csv(&entries),
+            format!(
+                "{}\n\
+                 2023-11-14T22:13:20Z,{txid},incoming,0.00100000,,0.00100000,800000,salary\n\
+                 2023-11-14T22:23:20Z,{txid},outgoing,-0.00060000,0.00001000,0.00040000,800001,\"rent, \"\"march\"\"; change\"",
+                CSV_HEADER,
+                txid = txid
+            )
+        );
+        assert_eq!(to_csv(&[]), CSV_HEADER);
+    }
+}
@@ -2,10 +2,15 @@
 //!
 //! External interface to the Liana daemon.
 
+pub mod backup;
+pub mod bip329;
+pub mod history;
+mod payjoin;
 mod utils;
 
 use crate::{
     bitcoin::BitcoinInterface,
+    config::ExpiryWatchConfig,
     database::{Coin, DatabaseConnection, DatabaseInterface},
     miniscript::bitcoin::absolute::LockTime,
     poller::PollerMessage,
@@ -15,13 +20,21 @@ use crate::{
 pub use crate::database::{CoinStatus, LabelItem};
 
 use liana::{
+    bip21::PaymentUri,
     descriptors,
+    external_signer::ExternalSigner,
+    musig,
+    payjoin::{PayjoinError, SenderParams},
+    signer::{HotSigner, SignerError},
     spend::{
-        self, create_spend, AddrInfo, AncestorInfo, CandidateCoin, CreateSpendRes,
-        SpendCreationError, SpendOutputAddress, SpendTxFees, TxGetter,
+        self, create_spend, AddrInfo, AncestorInfo, CandidateCoin, CoinSelectionStrategy,
+        CreateSpendRes, SpendCreationError, SpendOutputAddress, SpendTxFees, TxGetter,
     },
 };
 
+use backup::{BackupSpendTx, WalletBackup, BACKUP_VERSION};
+use bip329::{Bip329Record, Bip329Type};
+use history::{HistoryEntry, TxDirection};
 use utils::{
     deser_addr_assume_checked, deser_amount_from_sats, deser_fromstr, deser_hex, ser_amount,
     ser_hex, ser_to_string,
@@ -30,17 +43,26 @@ use utils::{
 use std::{
     collections::{hash_map, HashMap, HashSet},
     convert::TryInto,
-    fmt,
+    fmt, io,
+    str::FromStr,
     sync::{self, mpsc},
     time::SystemTime,
 };
 
 use miniscript::{
-    bitcoin::{self, address, bip32, psbt::Psbt},
+    bitcoin::{
+        self, address, bip32,
+        psbt::{Input as PsbtIn, Output as PsbtOut, Psbt},
+        relative, secp256k1,
+    },
     psbt::PsbtExt,
 };
 use serde::{Deserialize, Serialize};
 
+/// The maximum confirmation target for a fee estimation, in blocks. This is the maximum
+/// supported by bitcoind.
+pub const MAX_CONF_TARGET: u16 = 1_008;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CommandError {
     NoOutpointForSelfSend,
@@ -68,6 +90,50 @@ pub enum CommandError {
     InvalidDerivationIndex,
     RbfError(RbfErrorInfo),
     EmptyFilterList,
+    /// A label record, at this line, references an item which is invalid for its type or our
+    /// network.
+    InvalidLabelReference(usize, String),
+    /// The label of the record at this line is longer than 100 characters.
+    LabelTooLong(usize, String),
+    UnsupportedBackupVersion(u32),
+    /// The backup is for another wallet. Contains what differs.
+    BackupMismatch(&'static str),
+    InvalidConfTarget(u16),
+    /// The Bitcoin backend could not estimate the feerate for this confirmation target.
+    FeeEstimationUnavailable(u16),
+    /// No coin becomes spendable through a recovery path within this number of blocks.
+    NoCoinToRefresh(u32),
+    /// The payment URI doesn't support payjoin.
+    NoPayjoinEndpoint,
+    /// We have no confirmed coin to contribute to a payjoin as the receiver.
+    NoPayjoinContribution,
+    Payjoin(PayjoinError),
+    /// An error when communicating with the receiver of a payjoin.
+    PayjoinRequest(String),
+    /// The original transaction of a payjoin sender couldn't be broadcast. Contains the reason.
+    OriginalNotBroadcastable(String),
+    /// A payment URI doesn't specify the amount to pay to this address.
+    NoPaymentAmount(String),
+    /// This address is paid to more than once.
+    DuplicateDestination(String),
+    /// There is no recovery path behind this absolute timelock.
+    UnknownAbsoluteTimelock(u32),
+    /// The preimage doesn't unlock any of the hash locks of our descriptor.
+    UnknownPreimage,
+    /// Signing with the stored hot keys isn't enabled in the configuration, and no external signer
+    /// is set.
+    SigningDisabled,
+    /// None of the hot keys stored in the data directory, nor of the external signers, is used in
+    /// this Spend transaction.
+    NoSigner,
+    HotSigner(String),
+    /// No external signer executable is set in the configuration.
+    NoExternalSigner,
+    /// The external signer executable doesn't enumerate a signer with this fingerprint.
+    UnknownExternalSigner(bip32::Fingerprint),
+    /// This address wasn't derived from our descriptor.
+    UnknownAddress(String),
+    ExternalSigner(String),
 }
 
 impl fmt::Display for CommandError {
@@ -121,6 +187,80 @@ impl fmt::Display for CommandError {
             }
             Self::RbfError(e) => write!(f, "RBF error: '{}'.", e),
             Self::EmptyFilterList => write!(f, "Filter list is empty, should supply None instead."),
+            Self::InvalidLabelReference(line, r) => write!(
+                f,
+                "Invalid label reference '{}' at line {}: not a valid item for this record type and network.",
+                r, line
+            ),
+            Self::LabelTooLong(line, r) => write!(
+                f,
+                "Label for '{}' at line {} is too long: must be less or equal than 100 characters.",
+                r, line
+            ),
+            Self::UnsupportedBackupVersion(v) => write!(
+                f,
+                "Unsupported backup version {}. Maximum supported version is {}.",
+                v, BACKUP_VERSION
+            ),
+            Self::BackupMismatch(what) => {
+                write!(f, "Backup is for a wallet with a different {}.", what)
+            }
+            Self::InvalidConfTarget(target) => write!(
+                f,
+                "Invalid confirmation target: {}. Must be between 1 and {} blocks.",
+                target, MAX_CONF_TARGET
+            ),
+            Self::FeeEstimationUnavailable(target) => write!(
+                f,
+                "Fee estimation unavailable for a confirmation within {} blocks.",
+                target
+            ),
+            Self::NoCoinToRefresh(threshold) => write!(
+                f,
+                "No coin becomes spendable through a recovery path within {} blocks.",
+                threshold
+            ),
+            Self::NoPayjoinEndpoint => write!(f, "The payment URI has no payjoin endpoint."),
+            Self::NoPayjoinContribution => {
+                write!(f, "No confirmed coin to contribute to the payjoin.")
+            }
+            Self::Payjoin(e) => write!(f, "Payjoin error: {}", e),
+            Self::PayjoinRequest(e) => write!(f, "Payjoin request failed: {}.", e),
+            Self::OriginalNotBroadcastable(reason) => write!(
+                f,
+                "The payjoin original transaction can't be broadcast: {}.",
+                reason
+            ),
+            Self::NoPaymentAmount(addr) => write!(f, "No amount to pay to '{}'.", addr),
+            Self::DuplicateDestination(addr) => {
+                write!(f, "Address '{}' is a destination more than once.", addr)
+            }
+            Self::UnknownAbsoluteTimelock(tl) => {
+                write!(f, "No recovery path behind absolute timelock '{}'.", tl)
+            }
+            Self::UnknownPreimage => write!(
+                f,
+                "Preimage doesn't unlock any of the hash locks of the descriptor."
+            ),
+            Self::SigningDisabled => write!(
+                f,
+                "Signing is disabled. Set 'enable_signspend' in the configuration to enable it."
+            ),
+            Self::NoSigner => write!(
+                f,
+                "No hot key stored in the data directory, nor any external signer, is used in \
+                 this spend transaction."
+            ),
+            Self::HotSigner(e) => write!(f, "Error when signing with a hot key: {}", e),
+            Self::NoExternalSigner => write!(
+                f,
+                "No external signer executable is set in the configuration."
+            ),
+            Self::UnknownExternalSigner(fg) => {
+                write!(f, "No external signer with fingerprint '{}'.", fg)
+            }
+            Self::UnknownAddress(addr) => write!(f, "Unknown address '{}'.", addr),
+            Self::ExternalSigner(e) => write!(f, "External signer error: {}", e),
         }
     }
 }
@@ -133,6 +273,12 @@ impl From<SpendCreationError> for CommandError {
     }
 }
 
+impl From<PayjoinError> for CommandError {
+    fn from(e: PayjoinError) -> Self {
+        CommandError::Payjoin(e)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RbfErrorInfo {
     MissingFeerate,
@@ -190,6 +336,53 @@ impl<'a> TxGetter for DbTxGetter<'a> {
     }
 }
 
+// Order these transactions so that each comes after its parents among them, otherwise keeping
+// their order. Transactions confirmed in the same block may spend one another.
+fn sort_parents_first(txs: Vec<bitcoin::Transaction>) -> Vec<bitcoin::Transaction> {
+    let positions: HashMap<bitcoin::Txid, usize> = txs
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| (tx.txid(), i))
+        .collect();
+    let mut visited = vec![false; txs.len()];
+    let mut order = Vec::with_capacity(txs.len());
+    for root in 0..txs.len() {
+        // Depth-first, a transaction being pushed to the order once its parents were.
+        let mut stack = vec![(root, false)];
+        while let Some((i, parents_done)) = stack.pop() {
+            if parents_done {
+                order.push(i);
+                continue;
+            }
+            if visited[i] {
+                continue;
+            }
+            visited[i] = true;
+            stack.push((i, true));
+            for txin in txs[i].input.iter().rev() {
+                if let Some(&parent) = positions.get(&txin.previous_output.txid) {
+                    if !visited[parent] {
+                        stack.push((parent, false));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut txs: Vec<Option<bitcoin::Transaction>> = txs.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .map(|i| txs[i].take().expect("Each transaction is visited once"))
+        .collect()
+}
+
+// A checked Spend transaction, or payjoin proposal along with the txid of its original Spend
+// transaction, ready to be stored.
+enum SpendUpdate {
+    Spend(Psbt),
+    PayjoinProposal(bitcoin::Txid, Psbt),
+}
+
 fn coin_to_candidate(
     coin: &Coin,
     must_select: bool,
@@ -204,9 +397,29 @@ fn coin_to_candidate(
         must_select,
         sequence,
         ancestor_info,
+        block_height: coin.block_info.map(|info| info.height),
     }
 }
 
+// Merge the signatures, the hash preimages and the MuSig2 nonces and partial signatures of
+// another PSBT input for the same transaction input into this one, on a best effort basis.
+fn merge_signatures(psbt_in: &mut PsbtIn, other: &PsbtIn) {
+    psbt_in.partial_sigs.extend(other.partial_sigs.clone());
+    psbt_in
+        .tap_script_sigs
+        .extend(other.tap_script_sigs.clone());
+    if psbt_in.tap_key_sig.is_none() {
+        psbt_in.tap_key_sig = other.tap_key_sig;
+    }
+    psbt_in
+        .sha256_preimages
+        .extend(other.sha256_preimages.clone());
+    psbt_in
+        .hash160_preimages
+        .extend(other.hash160_preimages.clone());
+    musig::merge_psbt_in(psbt_in, other);
+}
+
 impl DaemonControl {
     // Get the derived descriptor for this coin
     fn derived_desc(&self, coin: &Coin) -> descriptors::DerivedSinglePathLianaDesc {
@@ -252,6 +465,25 @@ impl DaemonControl {
         }
     }
 
+    // Finalize the inputs of this Spend transaction PSBT. The inputs contributed by the receiver
+    // of a payjoin are already finalized. The MuSig2 partial signatures, if any, are aggregated
+    // first.
+    fn finalize_spend(&self, psbt: &mut Psbt) -> Result<(), CommandError> {
+        self.config
+            .main_descriptor
+            .aggregate_musig_sigs(&self.secp, psbt)
+            .map_err(|e| CommandError::SpendFinalization(e.to_string()))?;
+        for i in 0..psbt.inputs.len() {
+            let psbt_in = &psbt.inputs[i];
+            if psbt_in.final_script_witness.is_some() || psbt_in.final_script_sig.is_some() {
+                continue;
+            }
+            psbt.finalize_inp_mut(&self.secp, i)
+                .map_err(|e| CommandError::SpendFinalization(e.to_string()))?;
+        }
+        Ok(())
+    }
+
     // Get the change address for the next derivation index.
     fn next_change_addr(&self, db_conn: &mut Box<dyn DatabaseConnection>) -> SpendOutputAddress {
         let index = db_conn.change_index();
@@ -292,6 +524,214 @@ impl DaemonControl {
         }
     }
 
+    // Sanity check the inputs of a Spend transaction which isn't stored yet: they must all spend
+    // coins of ours.
+    // FIXME: should we allow for external inputs?
+    fn check_spend_inputs(
+        &self,
+        db_conn: &mut Box<dyn DatabaseConnection>,
+        psbt: &Psbt,
+    ) -> Result<(), CommandError> {
+        let outpoints: Vec<bitcoin::OutPoint> = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect();
+        let coins = db_conn.coins_by_outpoints(&outpoints);
+        if coins.len() != outpoints.len() {
+            for op in outpoints {
+                if !coins.contains_key(&op) {
+                    return Err(CommandError::UnknownOutpoint(op));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Get the stored Spend transaction, or payjoin proposal, with this txid.
+    // Check a Spend transaction to be stored and merge into it the content of the version which
+    // is already stored, if any. Nothing is written to the database.
+    fn prepare_spend_update(
+        &self,
+        db_conn: &mut Box<dyn DatabaseConnection>,
+        mut psbt: Psbt,
+    ) -> Result<SpendUpdate, CommandError> {
+        let tx = &psbt.unsigned_tx;
+        let txid = tx.txid();
+
+        // A payjoin proposal is stored apart from the Spend transactions, as it spends inputs
+        // which aren't ours. It was checked when it was received: only our own inputs may be
+        // updated, the receiver's inputs are kept as it finalized them.
+        if let Some((original_txid, db_psbt)) = db_conn.payjoin_proposal(&txid) {
+            let original_outpoints: HashSet<_> = db_conn
+                .spend_tx(&original_txid)
+                .ok_or(CommandError::UnknownSpend(original_txid))?
+                .unsigned_tx
+                .input
+                .into_iter()
+                .map(|txin| txin.previous_output)
+                .collect();
+            for (i, txin) in tx.input.iter().enumerate() {
+                let (psbtin, db_psbtin) = match (psbt.inputs.get_mut(i), db_psbt.inputs.get(i)) {
+                    (Some(psbtin), Some(db_psbtin)) => (psbtin, db_psbtin),
+                    _ => continue,
+                };
+                if original_outpoints.contains(&txin.previous_output) {
+                    merge_signatures(psbtin, db_psbtin);
+                } else {
+                    *psbtin = db_psbtin.clone();
+                }
+            }
+            return Ok(SpendUpdate::PayjoinProposal(original_txid, psbt));
+        }
+
+        // If the transaction already exists in DB, merge the signatures (and the hash preimages,
+        // and the MuSig2 nonces and partial signatures) for each input on a best effort basis.
+        // We work on the newly provided PSBT, in case its content was updated.
+        if let Some(db_psbt) = db_conn.spend_tx(&txid) {
+            let db_tx = db_psbt.unsigned_tx;
+            for i in 0..db_tx.input.len() {
+                if tx
+                    .input
+                    .get(i)
+                    .map(|tx_in| tx_in.previous_output == db_tx.input[i].previous_output)
+                    != Some(true)
+                {
+                    continue;
+                }
+                let psbtin = match psbt.inputs.get_mut(i) {
+                    Some(psbtin) => psbtin,
+                    None => continue,
+                };
+                let db_psbtin = match db_psbt.inputs.get(i) {
+                    Some(db_psbtin) => db_psbtin,
+                    None => continue,
+                };
+                merge_signatures(psbtin, db_psbtin);
+            }
+        } else {
+            // If the transaction doesn't exist in DB already, sanity check its inputs.
+            self.check_spend_inputs(db_conn, &psbt)?;
+        }
+
+        Ok(SpendUpdate::Spend(psbt))
+    }
+
+    // Insert (or update) a checked Spend transaction, or payjoin proposal, in database.
+    fn apply_spend_update(&self, db_conn: &mut Box<dyn DatabaseConnection>, update: SpendUpdate) {
+        match update {
+            SpendUpdate::PayjoinProposal(original_txid, psbt) => {
+                db_conn.store_payjoin_proposal(&original_txid, &psbt);
+            }
+            SpendUpdate::Spend(psbt) => {
+                db_conn.store_spend(&psbt);
+
+                // If we created this Spend to pay to payment URIs, label the outputs paying to
+                // them.
+                let labels = self
+                    .pending_labels
+                    .lock()
+                    .expect("Must not be poisoned")
+                    .remove(&psbt.unsigned_tx.txid());
+                if let Some(labels) = labels {
+                    db_conn.update_labels(&labels);
+                }
+            }
+        }
+    }
+
+    fn stored_spend_or_proposal(&self, txid: &bitcoin::Txid) -> Option<Psbt> {
+        let mut db_conn = self.db.connection();
+        db_conn
+            .spend_tx(txid)
+            .or_else(|| db_conn.payjoin_proposal(txid).map(|(_, psbt)| psbt))
+    }
+
+    // Get all the labels of the wallet as BIP329 records.
+    fn bip329_records(&self, db_conn: &mut Box<dyn DatabaseConnection>) -> Vec<Bip329Record> {
+        db_conn
+            .list_labels()
+            .into_iter()
+            .map(|(item, label)| {
+                let record_type = match item {
+                    LabelItem::Address(_) => Bip329Type::Addr,
+                    LabelItem::Txid(_) => Bip329Type::Tx,
+                    LabelItem::OutPoint(_) => Bip329Type::Output,
+                };
+                // In BIP329 an unspendable output is a frozen one. We don't support freezing
+                // coins, so they are all spendable.
+                let spendable = if record_type == Bip329Type::Output {
+                    Some(true)
+                } else {
+                    None
+                };
+                Bip329Record {
+                    record_type,
+                    reference: item.to_string(),
+                    label: Some(label),
+                    origin: None,
+                    spendable,
+                }
+            })
+            .collect()
+    }
+
+    // Get the labels to be stored from BIP329 records, given along with their line number, and
+    // the number of records skipped.
+    fn labels_from_bip329<'a>(
+        &self,
+        records: impl IntoIterator<Item = (usize, &'a Bip329Record)>,
+    ) -> Result<(HashMap<LabelItem, Option<String>>, usize), CommandError> {
+        let network = self.config.bitcoin_config.network;
+        let mut items = HashMap::new();
+        let mut skipped = 0;
+
+        for (line, record) in records {
+            let label = match record.label {
+                Some(ref label) if !label.is_empty() => label,
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let item = match record.record_type {
+                Bip329Type::Addr => bitcoin::Address::from_str(&record.reference)
+                    .ok()
+                    .filter(|addr| addr.is_valid_for_network(network))
+                    .map(|addr| LabelItem::Address(addr.assume_checked())),
+                Bip329Type::Tx => bitcoin::Txid::from_str(&record.reference)
+                    .ok()
+                    .map(LabelItem::Txid),
+                Bip329Type::Output => bitcoin::OutPoint::from_str(&record.reference)
+                    .ok()
+                    .map(LabelItem::OutPoint),
+                Bip329Type::Input | Bip329Type::Pubkey | Bip329Type::Xpub => {
+                    skipped += 1;
+                    continue;
+                }
+            }
+            .ok_or_else(|| CommandError::InvalidLabelReference(line, record.reference.clone()))?;
+            if label.len() > 100 {
+                return Err(CommandError::LabelTooLong(line, record.reference.clone()));
+            }
+            items.insert(item, Some(label.clone()));
+        }
+
+        Ok((items, skipped))
+    }
+
+    // The given number of blocks under which a coin is considered expiring, or the configured one.
+    fn expiry_threshold(&self, threshold: Option<u32>) -> u32 {
+        threshold.unwrap_or_else(|| {
+            self.config
+                .expiry_watch
+                .as_ref()
+                .map(|watch| watch.threshold_blocks)
+                .unwrap_or_else(|| ExpiryWatchConfig::default().threshold_blocks)
+        })
+    }
+
     // Pass relevant values to the spend module function of same name.
     fn anti_fee_sniping_locktime(&self) -> LockTime {
         let now = SystemTime::now()
@@ -331,6 +771,39 @@ impl DaemonControl {
         }
     }
 
+    /// Get a human-readable explanation of the spending paths of the main descriptor, along with
+    /// the cost of spending a coin through each of them at the given feerate (1 sat/vb by default).
+    pub fn get_policy(&self, feerate_vb: Option<u64>) -> Result<GetPolicyResult, CommandError> {
+        let feerate_vb = feerate_vb.unwrap_or(1);
+        if feerate_vb < 1 {
+            return Err(CommandError::InvalidFeerate(feerate_vb));
+        }
+        let desc = &self.config.main_descriptor;
+        Ok(GetPolicyResult {
+            descriptor: desc.clone(),
+            explanation: desc.explain(feerate_vb),
+        })
+    }
+
+    /// Get advisory warnings about risky, but valid, settings in the given descriptor, or in the
+    /// main descriptor if none is given.
+    pub fn lint_descriptor(
+        &self,
+        descriptor: Option<descriptors::LianaDescriptor>,
+    ) -> LintDescriptorResult {
+        let warnings = descriptor
+            .as_ref()
+            .unwrap_or(&self.config.main_descriptor)
+            .lint()
+            .into_iter()
+            .map(|warning| LintWarning {
+                message: warning.to_string(),
+                warning,
+            })
+            .collect();
+        LintDescriptorResult { warnings }
+    }
+
     /// Get a new deposit address. This will always generate a new deposit address, regardless of
     /// whether it was actually used.
     pub fn get_new_address(&self) -> GetAddressResult {
@@ -349,6 +822,25 @@ impl DaemonControl {
         GetAddressResult::new(address, index)
     }
 
+    /// Same as [`DaemonControl::get_new_address`], but also get a BIP21 payment URI for the new
+    /// address with the given amount, label and message.
+    pub fn get_new_address_uri(
+        &self,
+        amount: Option<bitcoin::Amount>,
+        label: Option<String>,
+        message: Option<String>,
+    ) -> GetAddressResult {
+        let mut res = self.get_new_address();
+        let uri = PaymentUri {
+            amount,
+            label,
+            message,
+            ..PaymentUri::new(res.address.as_unchecked().clone())
+        };
+        res.uri = Some(uri.to_string());
+        res
+    }
+
     /// list addresses
     pub fn list_addresses(
         &self,
@@ -453,12 +945,153 @@ impl DaemonControl {
         ListCoinsResult { coins }
     }
 
+    /// List the unspent coins whose first recovery path becomes available within `threshold`
+    /// blocks, or already is. The threshold defaults to the one from the configuration. Coins never
+    /// expire if all the recovery paths are behind an absolute timelock.
+    pub fn list_expiring_coins(&self, threshold: Option<u32>) -> ListExpiringCoinsResult {
+        let threshold = self.expiry_threshold(threshold);
+        let timelock = match self.config.main_descriptor.first_relative_timelock() {
+            Some(timelock) => timelock,
+            None => return ListExpiringCoinsResult { coins: Vec::new() },
+        };
+        let mut db_conn = self.db.connection();
+        let tip_height = db_conn.chain_tip().map(|tip| tip.height).unwrap_or(0);
+        // The median time past is only necessary for a time-based timelock.
+        let tip_mtp = match timelock {
+            relative::LockTime::Blocks(_) => None,
+            relative::LockTime::Time(_) => self.bitcoin.tip_median_time_past(),
+        };
+        let coins = expiring_coins(
+            db_conn.coins(&[CoinStatus::Confirmed], &[]).into_values(),
+            tip_height,
+            tip_mtp,
+            timelock,
+            threshold,
+        );
+        ListExpiringCoinsResult { coins }
+    }
+
+    /// Estimate the feerate for a transaction to be confirmed within `conf_target` blocks,
+    /// using the Bitcoin backend.
+    pub fn estimate_fee(&self, conf_target: u16) -> Result<EstimateFeeResult, CommandError> {
+        if !(1..=MAX_CONF_TARGET).contains(&conf_target) {
+            return Err(CommandError::InvalidConfTarget(conf_target));
+        }
+        let feerate = self
+            .bitcoin
+            .estimate_fee(conf_target)
+            .ok_or(CommandError::FeeEstimationUnavailable(conf_target))?;
+        Ok(EstimateFeeResult { feerate })
+    }
+
     pub fn create_spend(
         &self,
         destinations: &HashMap<bitcoin::Address<bitcoin::address::NetworkUnchecked>, u64>,
         coins_outpoints: &[bitcoin::OutPoint],
         feerate_vb: u64,
         change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
+    ) -> Result<CreateSpendResult, CommandError> {
+        self.create_spend_with_coin_selection(
+            destinations,
+            coins_outpoints,
+            feerate_vb,
+            change_address,
+            CoinSelectionStrategy::default(),
+        )
+    }
+
+    /// Same as [`DaemonControl::create_spend_with_coin_selection`], but with the destinations given
+    /// as BIP21 payment URIs. They must all specify an amount. The label of a payment URI, if any,
+    /// is set on the output paying to it.
+    ///
+    /// If a payment URI has a payjoin endpoint, it is returned along with the Spend transaction.
+    /// Once the Spend transaction is stored and signed, the payjoin may be requested using
+    /// [`DaemonControl::request_payjoin`].
+    pub fn create_spend_to_uris(
+        &self,
+        uris: &[PaymentUri],
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+        change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
+        coin_selection: CoinSelectionStrategy,
+    ) -> Result<CreateSpendResult, CommandError> {
+        let mut destinations = HashMap::with_capacity(uris.len());
+        for uri in uris {
+            let addr = uri.address.clone().assume_checked().to_string();
+            let amount = uri
+                .amount
+                .ok_or_else(|| CommandError::NoPaymentAmount(addr.clone()))?;
+            if destinations
+                .insert(uri.address.clone(), amount.to_sat())
+                .is_some()
+            {
+                return Err(CommandError::DuplicateDestination(addr));
+            }
+        }
+
+        let mut res = self.create_spend_with_coin_selection(
+            &destinations,
+            coins_outpoints,
+            feerate_vb,
+            change_address,
+            coin_selection,
+        )?;
+        // The outputs are only labelled once the Spend is stored. Until then the labels are kept
+        // in memory, so they are only set for the Spends we created.
+        if let CreateSpendResult::Success {
+            ref psbt,
+            ref mut warnings,
+            ref mut payjoin_endpoint,
+        } = res
+        {
+            let tx = &psbt.unsigned_tx;
+            let txid = tx.txid();
+            let mut labels = HashMap::new();
+            for uri in uris {
+                let label = match uri.label {
+                    Some(ref label) => label,
+                    None => continue,
+                };
+                let addr = uri.address.clone().assume_checked();
+                if label.len() > 100 {
+                    warnings.push(format!(
+                        "The label of the payment URI for '{}' is longer than 100 characters. It \
+                         won't be set.",
+                        addr
+                    ));
+                    continue;
+                }
+                let spk = addr.script_pubkey();
+                if let Some(vout) = tx.output.iter().position(|txo| txo.script_pubkey == spk) {
+                    let outpoint =
+                        bitcoin::OutPoint::new(txid, vout.try_into().expect("Must fit in a u32"));
+                    labels.insert(LabelItem::OutPoint(outpoint), Some(label.clone()));
+                }
+            }
+            if !labels.is_empty() {
+                self.pending_labels
+                    .lock()
+                    .expect("Must not be poisoned")
+                    .insert(txid, labels);
+            }
+
+            // The payjoin (BIP78) can only be requested once the Spend is stored and signed, the
+            // caller is given the endpoint to do so.
+            *payjoin_endpoint = uris.iter().find_map(|uri| uri.payjoin_endpoint.clone());
+        }
+
+        Ok(res)
+    }
+
+    /// Same as [`DaemonControl::create_spend`], but with the strategy to follow when coins are
+    /// selected automatically, that is if no coin is specified.
+    pub fn create_spend_with_coin_selection(
+        &self,
+        destinations: &HashMap<bitcoin::Address<bitcoin::address::NetworkUnchecked>, u64>,
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+        change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
+        coin_selection: CoinSelectionStrategy,
     ) -> Result<CreateSpendResult, CommandError> {
         let is_self_send = destinations.is_empty();
         // For self-send, the coins must be specified.
@@ -598,6 +1231,7 @@ impl DaemonControl {
             &mut tx_getter,
             &destinations_checked,
             &candidate_coins,
+            coin_selection,
             SpendTxFees::Regular(feerate_vb),
             change_address,
             locktime,
@@ -620,64 +1254,224 @@ impl DaemonControl {
         Ok(CreateSpendResult::Success {
             psbt,
             warnings: warnings.iter().map(|w| w.to_string()).collect(),
+            payjoin_endpoint: None,
         })
     }
 
-    pub fn update_spend(&self, mut psbt: Psbt) -> Result<(), CommandError> {
+    /// Create a transaction spending all the coins whose first recovery path becomes available
+    /// within `threshold` blocks, or already is, to a single change output. Once confirmed, the
+    /// recovery path of the resulting coin is available only after the full timelock again.
+    /// The threshold defaults to the one from the configuration.
+    ///
+    /// As for [`DaemonControl::create_spend`], the returned PSBT isn't stored.
+    pub fn refresh_coins(
+        &self,
+        threshold: Option<u32>,
+        feerate_vb: u64,
+    ) -> Result<CreateSpendResult, CommandError> {
+        let threshold = self.expiry_threshold(threshold);
+        let expiring = self.list_expiring_coins(Some(threshold)).coins;
+        if expiring.is_empty() {
+            return Err(CommandError::NoCoinToRefresh(threshold));
+        }
+        let outpoints: Vec<_> = expiring.into_iter().map(|coin| coin.outpoint).collect();
+        self.create_spend(&HashMap::new(), &outpoints, feerate_vb, None)
+    }
+
+    pub fn update_spend(&self, psbt: Psbt) -> Result<(), CommandError> {
         let mut db_conn = self.db.connection();
-        let tx = &psbt.unsigned_tx;
+        let update = self.prepare_spend_update(&mut db_conn, psbt)?;
+        self.apply_spend_update(&mut db_conn, update);
+        Ok(())
+    }
 
-        // If the transaction already exists in DB, merge the signatures for each input on a best
-        // effort basis.
-        // We work on the newly provided PSBT, in case its content was updated.
-        let txid = tx.txid();
-        if let Some(db_psbt) = db_conn.spend_tx(&txid) {
-            let db_tx = db_psbt.unsigned_tx;
-            for i in 0..db_tx.input.len() {
-                if tx
-                    .input
-                    .get(i)
-                    .map(|tx_in| tx_in.previous_output == db_tx.input[i].previous_output)
-                    != Some(true)
-                {
-                    continue;
-                }
-                let psbtin = match psbt.inputs.get_mut(i) {
-                    Some(psbtin) => psbtin,
-                    None => continue,
-                };
-                let db_psbtin = match db_psbt.inputs.get(i) {
-                    Some(db_psbtin) => db_psbtin,
-                    None => continue,
-                };
-                psbtin
-                    .partial_sigs
-                    .extend(db_psbtin.partial_sigs.clone().into_iter());
-                psbtin
-                    .tap_script_sigs
-                    .extend(db_psbtin.tap_script_sigs.clone().into_iter());
-                if psbtin.tap_key_sig.is_none() {
-                    psbtin.tap_key_sig = db_psbtin.tap_key_sig;
-                }
+    /// Set the preimage of one of the hash locks of our descriptor in all the inputs of this
+    /// stored Spend transaction, for it to be spendable through the hash-locked path.
+    pub fn add_spend_preimage(
+        &self,
+        txid: &bitcoin::Txid,
+        preimage: &[u8],
+    ) -> Result<(), CommandError> {
+        let mut db_conn = self.db.connection();
+        let mut spend_psbt = db_conn
+            .spend_tx(txid)
+            .ok_or(CommandError::UnknownSpend(*txid))?;
+        self.config
+            .main_descriptor
+            .add_preimage(&mut spend_psbt, preimage)
+            .map_err(|_| CommandError::UnknownPreimage)?;
+        db_conn.store_spend(&spend_psbt);
+        Ok(())
+    }
+
+    /// Sign this stored Spend transaction with the hot keys whose mnemonics are stored in the data
+    /// directory for our network, if enabled, and store the signed transaction. Encrypted
+    /// mnemonics can't be used, nor those whose master key is derived using a BIP39 passphrase. If
+    /// an external signer executable is configured, the signers it enumerates sign the transaction
+    /// too. The hot keys which are participants of a MuSig2 aggregate key sign in two rounds if
+    /// necessary, so the transaction may be fully signed if they are all the participants.
+    pub fn sign_spend(&self, txid: &bitcoin::Txid) -> Result<SignSpendResult, CommandError> {
+        if !self.config.enable_signspend {
+            return Err(CommandError::SigningDisabled);
+        }
+        let mut spend_psbt = self
+            .stored_spend_or_proposal(txid)
+            .ok_or(CommandError::UnknownSpend(*txid))?;
+
+        let data_dir = self.config.data_dir().expect("Checked at startup.");
+        let network = self.config.bitcoin_config.network;
+        let hot_signers = match HotSigner::from_datadir(&data_dir, network, None) {
+            Ok(signers) => signers,
+            Err(SignerError::MnemonicStorage(e)) if e.kind() == io::ErrorKind::NotFound => {
+                Vec::new()
             }
-        } else {
-            // If the transaction doesn't exist in DB already, sanity check its inputs.
-            // FIXME: should we allow for external inputs?
-            let outpoints: Vec<bitcoin::OutPoint> =
-                tx.input.iter().map(|txin| txin.previous_output).collect();
-            let coins = db_conn.coins_by_outpoints(&outpoints);
-            if coins.len() != outpoints.len() {
-                for op in outpoints {
-                    if !coins.contains_key(&op) {
-                        return Err(CommandError::UnknownOutpoint(op));
-                    }
+            Err(e) => return Err(CommandError::HotSigner(e.to_string())),
+        };
+
+        // Only sign with the keys which are part of the derivation paths of the inputs.
+        let psbt_fingerprints: HashSet<bip32::Fingerprint> = spend_psbt
+            .inputs
+            .iter()
+            .flat_map(|psbt_in| {
+                psbt_in
+                    .bip32_derivation
+                    .values()
+                    .map(|(fg, _)| *fg)
+                    .chain(psbt_in.tap_key_origins.values().map(|(_, (fg, _))| *fg))
+            })
+            .collect();
+        let secp = secp256k1::Secp256k1::new();
+        let mut signers = Vec::new();
+        let mut psbt_signers = Vec::new();
+        for signer in hot_signers {
+            let fingerprint = signer.fingerprint(&secp);
+            if psbt_fingerprints.contains(&fingerprint) && !signers.contains(&fingerprint) {
+                signers.push(fingerprint);
+                psbt_signers.push(signer);
+            }
+        }
+        // Signing for a MuSig2 aggregate key takes two rounds: all the participants provide a
+        // nonce, then a partial signature. If some of our hot signers provided a nonce, have them
+        // sign again so they can provide their partial signature.
+        let unknown_fields = |psbt: &Psbt| -> Vec<_> {
+            psbt.inputs
+                .iter()
+                .map(|psbt_in| psbt_in.unknown.clone())
+                .collect()
+        };
+        for _ in 0..2 {
+            let prev_fields = unknown_fields(&spend_psbt);
+            for signer in &psbt_signers {
+                spend_psbt = signer
+                    .sign_psbt(spend_psbt, &secp)
+                    .map_err(|e| CommandError::HotSigner(e.to_string()))?;
+            }
+            if unknown_fields(&spend_psbt) == prev_fields {
+                break;
+            }
+        }
+        if self.config.external_signer.is_some() {
+            for signer in self.external_signers()? {
+                if psbt_fingerprints.contains(&signer.fingerprint)
+                    && !signers.contains(&signer.fingerprint)
+                {
+                    spend_psbt = signer
+                        .sign_psbt(spend_psbt)
+                        .map_err(|e| CommandError::ExternalSigner(e.to_string()))?;
+                    signers.push(signer.fingerprint);
                 }
             }
         }
+        if signers.is_empty() {
+            return Err(CommandError::NoSigner);
+        }
+
+        self.update_spend(spend_psbt.clone())?;
+        Ok(SignSpendResult {
+            psbt: spend_psbt,
+            signers,
+        })
+    }
+
+    // The signers enumerated by the external signer executable set in the configuration.
+    fn external_signers(&self) -> Result<Vec<ExternalSigner>, CommandError> {
+        let command = self
+            .config
+            .external_signer
+            .as_ref()
+            .ok_or(CommandError::NoExternalSigner)?;
+        ExternalSigner::enumerate(command, self.config.bitcoin_config.network)
+            .map_err(|e| CommandError::ExternalSigner(e.to_string()))
+    }
+
+    // The external signer with this fingerprint.
+    fn external_signer(
+        &self,
+        fingerprint: bip32::Fingerprint,
+    ) -> Result<ExternalSigner, CommandError> {
+        self.external_signers()?
+            .into_iter()
+            .find(|signer| signer.fingerprint == fingerprint)
+            .ok_or(CommandError::UnknownExternalSigner(fingerprint))
+    }
+
+    /// List the signers enumerated by the external signer executable.
+    pub fn list_external_signers(&self) -> Result<ListExternalSignersResult, CommandError> {
+        let signers = self
+            .external_signers()?
+            .into_iter()
+            .map(|signer| ListExternalSignersEntry {
+                fingerprint: signer.fingerprint,
+                name: signer.name,
+            })
+            .collect();
+        Ok(ListExternalSignersResult { signers })
+    }
 
-        // Finally, insert (or update) the PSBT in database.
-        db_conn.store_spend(&psbt);
+    /// Get the xpub at this derivation path from the master key of an external signer.
+    pub fn get_external_xpub(
+        &self,
+        fingerprint: bip32::Fingerprint,
+        derivation_path: &bip32::DerivationPath,
+    ) -> Result<GetExternalXpubResult, CommandError> {
+        let xpub = self
+            .external_signer(fingerprint)?
+            .get_xpub(derivation_path)
+            .map_err(|e| CommandError::ExternalSigner(e.to_string()))?;
+        Ok(GetExternalXpubResult { xpub })
+    }
 
+    /// Display one of our addresses on an external signer, for the user to verify it. Fails if
+    /// the signer reports another address.
+    pub fn display_address(
+        &self,
+        fingerprint: bip32::Fingerprint,
+        address: bitcoin::Address<address::NetworkUnchecked>,
+    ) -> Result<(), CommandError> {
+        let address = self.validate_address(address)?;
+        let mut db_conn = self.db.connection();
+        let info = self
+            .addr_info(&mut db_conn, &address)
+            .ok_or_else(|| CommandError::UnknownAddress(address.to_string()))?;
+        let desc = if info.is_change {
+            self.config.main_descriptor.change_descriptor()
+        } else {
+            self.config.main_descriptor.receive_descriptor()
+        };
+        let desc = desc
+            .as_descriptor_public_key()
+            .at_derivation_index(info.index.into())
+            .expect("Derivation indexes of addresses are never hardened.");
+        let displayed = self
+            .external_signer(fingerprint)?
+            .display_address(&desc.to_string())
+            .map_err(|e| CommandError::ExternalSigner(e.to_string()))?;
+        if displayed != address.to_string() {
+            return Err(CommandError::ExternalSigner(format!(
+                "the signer displayed address '{}' instead of '{}'.",
+                displayed, address
+            )));
+        }
         Ok(())
     }
 
@@ -693,6 +1487,110 @@ impl DaemonControl {
         }
     }
 
+    /// Export all the labels of the wallet as BIP329 records.
+    pub fn export_labels(&self) -> ExportLabelsResult {
+        let mut db_conn = self.db.connection();
+        ExportLabelsResult {
+            bip329: bip329::to_jsonl(&self.bip329_records(&mut db_conn)),
+        }
+    }
+
+    /// Import labels from BIP329 records. Records for items we don't label (inputs, public keys
+    /// and extended public keys) as well as records without a label are skipped. A record for
+    /// an already labelled item overrides the existing label. Records are given along with their
+    /// line number, to report which one is invalid if any.
+    pub fn import_labels(
+        &self,
+        records: &[(usize, Bip329Record)],
+    ) -> Result<ImportLabelsResult, CommandError> {
+        let (items, skipped) =
+            self.labels_from_bip329(records.iter().map(|(line, record)| (*line, record)))?;
+        let mut db_conn = self.db.connection();
+        db_conn.update_labels(&items);
+        Ok(ImportLabelsResult {
+            imported: items.len(),
+            skipped,
+        })
+    }
+
+    /// Create a backup of the wallet, which can be used to restore it along with its metadata.
+    pub fn create_backup(&self) -> WalletBackup {
+        let mut db_conn = self.db.connection();
+        let wallet = db_conn.wallet();
+        let spend_txs = db_conn
+            .list_spend()
+            .into_iter()
+            .map(|(psbt, _)| BackupSpendTx { psbt })
+            .collect();
+
+        WalletBackup {
+            version: BACKUP_VERSION,
+            network: self.config.bitcoin_config.network,
+            descriptor: self.config.main_descriptor.clone(),
+            timestamp: wallet.timestamp,
+            receive_index: wallet.receive_index,
+            change_index: wallet.change_index,
+            labels: self.bip329_records(&mut db_conn),
+            spend_txs,
+            key_aliases: Vec::new(),
+            hardware_wallets: Vec::new(),
+        }
+    }
+
+    /// Restore the metadata of a wallet from a backup. The backup must be for the wallet this
+    /// daemon is running. Derivation indexes are only ever increased, labels and Spend
+    /// transactions present in the backup override existing ones. If the wallet was created
+    /// after the backed up one, a rescan is started from the backed up wallet creation time.
+    /// Nothing is restored if any part of the backup is invalid.
+    pub fn restore_backup(&mut self, backup: &WalletBackup) -> Result<(), CommandError> {
+        if backup.version > BACKUP_VERSION {
+            return Err(CommandError::UnsupportedBackupVersion(backup.version));
+        }
+        if backup.network != self.config.bitcoin_config.network {
+            return Err(CommandError::BackupMismatch("network"));
+        }
+        if backup.descriptor != self.config.main_descriptor {
+            return Err(CommandError::BackupMismatch("descriptor"));
+        }
+        if !backup.receive_index.is_normal() || !backup.change_index.is_normal() {
+            return Err(CommandError::InvalidDerivationIndex);
+        }
+        // The records of a backup are numbered as if they were exported as JSON Lines.
+        let (labels, _) = self.labels_from_bip329(
+            backup
+                .labels
+                .iter()
+                .enumerate()
+                .map(|(i, record)| (i + 1, record)),
+        )?;
+
+        // Check all the Spend transactions before writing anything, merging the signatures of
+        // those which are already stored.
+        let mut db_conn = self.db.connection();
+        let spend_updates = backup
+            .spend_txs
+            .iter()
+            .map(|spend| self.prepare_spend_update(&mut db_conn, spend.psbt.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Starting the rescan is the only write which may fail, so do it first.
+        if backup.timestamp < db_conn.timestamp() {
+            self.start_rescan(backup.timestamp)?;
+        }
+        if db_conn.receive_index() < backup.receive_index {
+            db_conn.set_receive_index(backup.receive_index, &self.secp);
+        }
+        if db_conn.change_index() < backup.change_index {
+            db_conn.set_change_index(backup.change_index, &self.secp);
+        }
+        db_conn.update_labels(&labels);
+        for update in spend_updates {
+            self.apply_spend_update(&mut db_conn, update);
+        }
+
+        Ok(())
+    }
+
     pub fn list_spend(
         &self,
         txids: Option<Vec<bitcoin::Txid>>,
@@ -728,21 +1626,12 @@ impl DaemonControl {
 
     /// Finalize and broadcast this stored Spend transaction.
     pub fn broadcast_spend(&self, txid: &bitcoin::Txid) -> Result<(), CommandError> {
-        let mut db_conn = self.db.connection();
-
         // First, try to finalize the spending transaction with the elements contained
         // in the PSBT.
-        let mut spend_psbt = db_conn
-            .spend_tx(txid)
+        let mut spend_psbt = self
+            .stored_spend_or_proposal(txid)
             .ok_or(CommandError::UnknownSpend(*txid))?;
-        spend_psbt.finalize_mut(&self.secp).map_err(|e| {
-            CommandError::SpendFinalization(
-                e.into_iter()
-                    .next()
-                    .map(|e| e.to_string())
-                    .unwrap_or_default(),
-            )
-        })?;
+        self.finalize_spend(&mut spend_psbt)?;
 
         // Then, broadcast it (or try to, we never know if we are not going to hit an
         // error at broadcast time).
@@ -764,6 +1653,211 @@ impl DaemonControl {
         Ok(())
     }
 
+    /// Perform the sender side of a payjoin (BIP78) for this stored Spend transaction, which pays
+    /// to the address of this payment URI.
+    ///
+    /// The Spend transaction must be signed, as the receiver may broadcast it if it doesn't want
+    /// to go through with the payjoin. The receiver may add inputs and increase its output. To
+    /// pay for its inputs, it may take from our change output up to the fee for one of our inputs
+    /// at the same feerate. It may not substitute its output.
+    ///
+    /// The payjoin proposal is stored apart from the Spend transactions, as it spends inputs
+    /// which aren't ours, and is returned to be signed again. It may be signed, updated and
+    /// broadcast as a Spend transaction, but only our inputs may be updated. Either the original
+    /// Spend transaction or the payjoin proposal may then be broadcast. The payjoin proposal is
+    /// deleted along with the original Spend transaction.
+    pub fn request_payjoin(
+        &self,
+        txid: &bitcoin::Txid,
+        uri: &PaymentUri,
+    ) -> Result<RequestPayjoinResult, CommandError> {
+        let endpoint = uri
+            .payjoin_endpoint
+            .as_ref()
+            .ok_or(CommandError::NoPayjoinEndpoint)?;
+        let receiver_spk = self.validate_address(uri.address.clone())?.script_pubkey();
+        let mut db_conn = self.db.connection();
+        let spend_psbt = db_conn
+            .spend_tx(txid)
+            .ok_or(CommandError::UnknownSpend(*txid))?;
+        let spend_tx = &spend_psbt.unsigned_tx;
+        if !spend_tx
+            .output
+            .iter()
+            .any(|txo| txo.script_pubkey == receiver_spk)
+        {
+            return Err(
+                PayjoinError::InvalidOriginal("it doesn't pay to the payment URI address").into(),
+            );
+        }
+
+        let mut finalized_psbt = spend_psbt.clone();
+        self.finalize_spend(&mut finalized_psbt)?;
+        let original = liana::payjoin::original_to_send(&finalized_psbt)?;
+        let in_value: bitcoin::Amount = spend_psbt
+            .inputs
+            .iter()
+            .filter_map(|psbt_in| psbt_in.witness_utxo.as_ref().map(|txo| txo.value))
+            .sum();
+        let out_value = spend_tx.output.iter().map(|txo| txo.value).sum();
+        let fee = in_value
+            .checked_sub(out_value)
+            .ok_or(PayjoinError::NegativeFee)?;
+        let vsize: u64 = finalized_psbt
+            .extract_tx_unchecked_fee_rate()
+            .vsize()
+            .try_into()
+            .expect("vsize must fit in a u64");
+        // Our change output, if any, is the last one paying to one of our addresses.
+        let change_index = spend_tx
+            .output
+            .iter()
+            .zip(spend_psbt.outputs.iter())
+            .rposition(|(txo, psbt_out)| {
+                txo.script_pubkey != receiver_spk
+                    && (!psbt_out.bip32_derivation.is_empty()
+                        || !psbt_out.tap_key_origins.is_empty())
+            });
+        let input_size: u64 = self
+            .config
+            .main_descriptor
+            .spender_input_size(true)
+            .try_into()
+            .expect("input size must fit in a u64");
+        let params = SenderParams {
+            disable_output_substitution: true,
+            additional_fee_contribution: change_index.map(|index| {
+                let max_contribution = fee.to_sat().div_ceil(vsize) * input_size;
+                (index, bitcoin::Amount::from_sat(max_contribution))
+            }),
+            min_feerate_vb: Some(fee.to_sat() / vsize),
+        };
+
+        let mut proposal = payjoin::send_original(endpoint, &params, &original)
+            .map_err(CommandError::PayjoinRequest)?;
+        liana::payjoin::check_proposal(&original, &proposal, &receiver_spk, &params)?;
+        liana::payjoin::restore_sender_data(&mut proposal, &spend_psbt);
+        db_conn.store_payjoin_proposal(txid, &proposal);
+
+        Ok(RequestPayjoinResult { psbt: proposal })
+    }
+
+    /// Perform the receiver side of a payjoin (BIP78) for this original transaction from the
+    /// sender, by contributing our oldest confirmed coin to it.
+    ///
+    /// The returned proposal must be signed by us, then passed to
+    /// [`DaemonControl::finalize_payjoin_proposal`] before being returned to the sender.
+    pub fn create_payjoin_proposal(
+        &self,
+        original: &Psbt,
+    ) -> Result<CreatePayjoinProposalResult, CommandError> {
+        liana::payjoin::check_original(original)?;
+        // As per BIP78, make sure we could broadcast the original transaction should the sender
+        // not go through with the payjoin. This prevents probing our coins at no cost.
+        let original_tx = original.clone().extract_tx_unchecked_fee_rate();
+        self.bitcoin
+            .test_mempool_accept(&original_tx)
+            .map_err(CommandError::OriginalNotBroadcastable)?;
+        let mut db_conn = self.db.connection();
+        let original_outpoints: Vec<_> = original
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect();
+        if !db_conn.coins_by_outpoints(&original_outpoints).is_empty() {
+            return Err(PayjoinError::InvalidOriginal("it spends some of our coins").into());
+        }
+        let network = self.config.bitcoin_config.network;
+        let receiver_output = original
+            .unsigned_tx
+            .output
+            .iter()
+            .position(|txo| {
+                bitcoin::Address::from_script(&txo.script_pubkey, network)
+                    .ok()
+                    .and_then(|addr| self.addr_info(&mut db_conn, &addr))
+                    .map(|info| !info.is_change)
+                    == Some(true)
+            })
+            .ok_or(PayjoinError::InvalidOriginal(
+                "it doesn't pay to any of our receive addresses",
+            ))?;
+
+        let coin = db_conn
+            .coins(&[CoinStatus::Confirmed], &[])
+            .into_values()
+            .filter(|coin| !coin.is_immature)
+            .min_by_key(|coin| (coin.block_info.map(|info| info.height), coin.outpoint))
+            .ok_or(CommandError::NoPayjoinContribution)?;
+        let coin_desc = self.derived_desc(&coin);
+        let mut psbt_in = PsbtIn {
+            witness_utxo: Some(bitcoin::TxOut {
+                value: coin.amount,
+                script_pubkey: coin_desc.script_pubkey(),
+            }),
+            ..PsbtIn::default()
+        };
+        coin_desc.update_psbt_in(&mut psbt_in);
+        self.config
+            .main_descriptor
+            .update_musig_psbt_in(&mut psbt_in);
+        if !self.config.main_descriptor.is_taproot() {
+            psbt_in.non_witness_utxo = DbTxGetter::new(&self.db).get_tx(&coin.outpoint.txid);
+        }
+        let input_weight = bitcoin::Weight::from_vb(
+            self.config
+                .main_descriptor
+                .spender_input_size(true)
+                .try_into()
+                .expect("input size must fit in a u64"),
+        )
+        .expect("input weight must not overflow");
+        let psbt = liana::payjoin::create_proposal(
+            original,
+            receiver_output,
+            coin.outpoint,
+            psbt_in,
+            input_weight,
+        )?;
+
+        Ok(CreatePayjoinProposalResult { psbt })
+    }
+
+    /// Finalize our inputs in this signed payjoin proposal created with
+    /// [`DaemonControl::create_payjoin_proposal`], and strip all information which isn't necessary
+    /// to the sender.
+    pub fn finalize_payjoin_proposal(
+        &self,
+        mut psbt: Psbt,
+    ) -> Result<CreatePayjoinProposalResult, CommandError> {
+        let mut db_conn = self.db.connection();
+        let outpoints: Vec<_> = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect();
+        let coins = db_conn.coins_by_outpoints(&outpoints);
+        if coins.is_empty() {
+            return Err(CommandError::NoPayjoinContribution);
+        }
+        for (i, outpoint) in outpoints.iter().enumerate() {
+            if coins.contains_key(outpoint) {
+                psbt.finalize_inp_mut(&self.secp, i)
+                    .map_err(|e| CommandError::SpendFinalization(e.to_string()))?;
+            } else {
+                psbt.inputs[i] = PsbtIn::default();
+            }
+        }
+        for psbt_out in psbt.outputs.iter_mut() {
+            *psbt_out = PsbtOut::default();
+        }
+        psbt.xpub.clear();
+
+        Ok(CreatePayjoinProposalResult { psbt })
+    }
+
     /// Create PSBT to replace the given transaction using RBF.
     ///
     /// `txid` must point to a PSBT in our database.
@@ -971,6 +2065,7 @@ impl DaemonControl {
                 &mut tx_getter,
                 &destinations,
                 &candidate_coins,
+                CoinSelectionStrategy::default(),
                 SpendTxFees::Rbf(feerate_vb, replaced_fee),
                 change_address.clone(),
                 locktime,
@@ -992,6 +2087,7 @@ impl DaemonControl {
                     return Ok(CreateSpendResult::Success {
                         psbt,
                         warnings: warnings.iter().map(|w| w.to_string()).collect(),
+                        payjoin_endpoint: None,
                     });
                 }
                 Err(SpendCreationError::CoinSelection(e)) => {
@@ -1045,43 +2141,270 @@ impl DaemonControl {
         Ok(())
     }
 
-    /// list_confirmed_transactions retrieves a limited list of transactions which occured between two given dates.
-    pub fn list_confirmed_transactions(
-        &self,
-        start: u32,
-        end: u32,
-        limit: u64,
-    ) -> ListTransactionsResult {
-        let mut db_conn = self.db.connection();
-        // Note the result could in principle be retrieved in a single database query.
-        let txids = db_conn.list_txids(start, end, limit);
-        self.list_transactions(&txids)
-    }
+    /// list_confirmed_transactions retrieves a limited list of transactions which occured between two given dates.
+    pub fn list_confirmed_transactions(
+        &self,
+        start: u32,
+        end: u32,
+        limit: u64,
+    ) -> ListTransactionsResult {
+        let mut db_conn = self.db.connection();
+        // Note the result could in principle be retrieved in a single database query.
+        let txids = db_conn.list_txids(start, end, limit);
+        self.list_transactions(&txids)
+    }
+
+    /// list_transactions retrieves the transactions with the given txids.
+    pub fn list_transactions(&self, txids: &[bitcoin::Txid]) -> ListTransactionsResult {
+        let transactions = self
+            .db
+            .connection()
+            .list_wallet_transactions(txids)
+            .into_iter()
+            .map(|(tx, height, time)| TransactionInfo { tx, height, time })
+            .collect();
+        ListTransactionsResult { transactions }
+    }
+
+    /// Export as CSV the confirmed transactions of the wallet whose block time is between the
+    /// two given dates (inclusive bounds).
+    ///
+    /// The running balance is computed over the whole history of the wallet, regardless of the
+    /// requested date range.
+    pub fn export_transactions(&self, start: u32, end: u32) -> ExportTransactionsResult {
+        let mut db_conn = self.db.connection();
+
+        // For each confirmed transaction, the block it was confirmed in, the amounts it paid to
+        // and spent from the wallet, and the number of wallet coins it spent.
+        let mut txs: HashMap<bitcoin::Txid, (i32, u32, bitcoin::Amount, bitcoin::Amount, usize)> =
+            HashMap::new();
+        for coin in db_conn.coins(&[], &[]).into_values() {
+            if let Some(block) = coin.block_info {
+                let entry = txs.entry(coin.outpoint.txid).or_insert((
+                    block.height,
+                    block.time,
+                    bitcoin::Amount::ZERO,
+                    bitcoin::Amount::ZERO,
+                    0,
+                ));
+                entry.2 += coin.amount;
+            }
+            if let (Some(txid), Some(block)) = (coin.spend_txid, coin.spend_block) {
+                let entry = txs.entry(txid).or_insert((
+                    block.height,
+                    block.time,
+                    bitcoin::Amount::ZERO,
+                    bitcoin::Amount::ZERO,
+                    0,
+                ));
+                entry.3 += coin.amount;
+                entry.4 += 1;
+            }
+        }
+
+        let txids: Vec<bitcoin::Txid> = txs.keys().cloned().collect();
+        let mut transactions: Vec<bitcoin::Transaction> = db_conn
+            .list_wallet_transactions(&txids)
+            .into_iter()
+            .map(|(tx, _, _)| tx)
+            .collect();
+        transactions.sort_by_key(|tx| {
+            let (height, time, ..) = txs[&tx.txid()];
+            (height, time, tx.txid())
+        });
+        // A transaction may spend a coin received in the same block. Make sure the coin is
+        // accounted for in the running balance before it's spent.
+        let transactions = sort_parents_first(transactions);
+
+        let mut balance = bitcoin::SignedAmount::ZERO;
+        let mut entries = Vec::new();
+        for tx in transactions {
+            let txid = tx.txid();
+            let (height, time, received, spent, spent_count) = txs[&txid];
+            let amount =
+                received.to_signed().expect("Must fit") - spent.to_signed().expect("Must fit");
+            balance += amount;
+            if time < start || time > end {
+                continue;
+            }
+
+            let outputs_value: bitcoin::Amount = tx.output.iter().map(|txo| txo.value).sum();
+            let direction = if spent_count == 0 {
+                TxDirection::Incoming
+            } else if received == outputs_value {
+                TxDirection::SelfTransfer
+            } else {
+                TxDirection::Outgoing
+            };
+            let fee = if spent_count == tx.input.len() {
+                spent.checked_sub(outputs_value)
+            } else {
+                None
+            };
+
+            let items: Vec<LabelItem> = std::iter::once(LabelItem::Txid(txid))
+                .chain((0..tx.output.len()).map(|vout| {
+                    LabelItem::OutPoint(bitcoin::OutPoint::new(
+                        txid,
+                        vout.try_into().expect("Must fit in a u32"),
+                    ))
+                }))
+                .collect();
+            let labels = db_conn.labels(&items.iter().cloned().collect());
+            let labels = items
+                .iter()
+                .filter_map(|item| labels.get(&item.to_string()).cloned())
+                .collect();
+
+            entries.push(HistoryEntry {
+                time,
+                txid,
+                direction,
+                amount,
+                fee,
+                balance,
+                height,
+                labels,
+            });
+        }
+
+        ExportTransactionsResult {
+            csv: history::to_csv(&entries),
+        }
+    }
+
+    /// Create a transaction that sweeps all coins for which a timelocked recovery path is
+    /// currently available to a provided address with the provided feerate.
+    ///
+    /// The `timelock` parameter can be used to specify which recovery path to use, by its number
+    /// of blocks. By default, we'll use the first recovery path available, which may be behind a
+    /// time-based relative timelock. There is no default if all the recovery paths are behind an
+    /// absolute timelock: use [`DaemonControl::create_absolute_recovery`] instead.
+    ///
+    /// Note that not all coins may be spendable through a single recovery path at the same time.
+    pub fn create_recovery(
+        &self,
+        address: bitcoin::Address<address::NetworkUnchecked>,
+        feerate_vb: u64,
+        timelock: Option<u16>,
+    ) -> Result<CreateRecoveryResult, CommandError> {
+        let timelock = match timelock {
+            Some(timelock) => relative::Height::from(timelock).into(),
+            None => self
+                .config
+                .main_descriptor
+                .first_relative_timelock()
+                .ok_or(CommandError::RecoveryNotAvailable)?,
+        };
+        self.create_relative_recovery(address, feerate_vb, timelock)
+    }
+
+    /// Same as [`DaemonControl::create_recovery`], for a recovery path behind a time-based
+    /// relative timelock. The `timelock` is given in number of 512-second intervals.
+    pub fn create_time_recovery(
+        &self,
+        address: bitcoin::Address<address::NetworkUnchecked>,
+        feerate_vb: u64,
+        timelock: u16,
+    ) -> Result<CreateRecoveryResult, CommandError> {
+        if !self
+            .config
+            .main_descriptor
+            .policy()
+            .time_recovery_paths()
+            .contains_key(&timelock)
+        {
+            return Err(CommandError::RecoveryNotAvailable);
+        }
+        let timelock = relative::Time::from_512_second_intervals(timelock).into();
+        self.create_relative_recovery(address, feerate_vb, timelock)
+    }
+
+    fn create_relative_recovery(
+        &self,
+        address: bitcoin::Address<address::NetworkUnchecked>,
+        feerate_vb: u64,
+        timelock: relative::LockTime,
+    ) -> Result<CreateRecoveryResult, CommandError> {
+        if feerate_vb < 1 {
+            return Err(CommandError::InvalidFeerate(feerate_vb));
+        }
+        let mut tx_getter = DbTxGetter::new(&self.db);
+        let mut db_conn = self.db.connection();
+        let sweep_addr = self.spend_addr(&mut db_conn, self.validate_address(address)?);
+
+        // Query the coins that we can spend through the specified recovery path from the
+        // database. We are interested in coins available at the *next* block. For a time-based
+        // timelock, it is checked against the median time past of the current tip.
+        let current_height = self.bitcoin.chain_tip().height;
+        let (sequence, tip_mtp) = match timelock {
+            relative::LockTime::Blocks(height) => {
+                (bitcoin::Sequence::from_height(height.value()), None)
+            }
+            relative::LockTime::Time(time) => (
+                bitcoin::Sequence::from_512_second_intervals(time.value()),
+                self.bitcoin.tip_median_time_past(),
+            ),
+        };
+        let is_available = |c: &Coin| match timelock {
+            relative::LockTime::Blocks(height) => c
+                .recovery_height(height.value())
+                .map(|h| current_height + 1 >= h)
+                .unwrap_or(false),
+            relative::LockTime::Time(time) => match (c.recovery_time(time.value()), tip_mtp) {
+                (Some(t), Some(mtp)) => mtp >= t,
+                _ => false,
+            },
+        };
+        let sweepable_coins: Vec<_> = db_conn
+            .coins(&[CoinStatus::Confirmed], &[])
+            .into_values()
+            .filter(is_available)
+            .map(|c| {
+                coin_to_candidate(
+                    &c,
+                    /*must_select=*/ true,
+                    /*sequence=*/ Some(sequence),
+                    /*ancestor_info=*/ None,
+                )
+            })
+            .collect();
+        if sweepable_coins.is_empty() {
+            return Err(CommandError::RecoveryNotAvailable);
+        }
+
+        let sweep_addr_info = sweep_addr.info;
+        let locktime = self.anti_fee_sniping_locktime();
+        let CreateSpendRes {
+            psbt, has_change, ..
+        } = create_spend(
+            &self.config.main_descriptor,
+            &self.secp,
+            &mut tx_getter,
+            &[], // No destination, only the change address.
+            &sweepable_coins,
+            CoinSelectionStrategy::default(),
+            SpendTxFees::Regular(feerate_vb),
+            sweep_addr,
+            locktime,
+        )?;
+        if has_change {
+            self.maybe_increase_next_deriv_index(&mut db_conn, &sweep_addr_info);
+        }
 
-    /// list_transactions retrieves the transactions with the given txids.
-    pub fn list_transactions(&self, txids: &[bitcoin::Txid]) -> ListTransactionsResult {
-        let transactions = self
-            .db
-            .connection()
-            .list_wallet_transactions(txids)
-            .into_iter()
-            .map(|(tx, height, time)| TransactionInfo { tx, height, time })
-            .collect();
-        ListTransactionsResult { transactions }
+        Ok(CreateRecoveryResult { psbt })
     }
 
-    /// Create a transaction that sweeps all coins for which a timelocked recovery path is
-    /// currently available to a provided address with the provided feerate.
+    /// Create a transaction that sweeps all our confirmed coins through a recovery path behind an
+    /// absolute timelock, to a provided address with the provided feerate.
     ///
-    /// The `timelock` parameter can be used to specify which recovery path to use. By default,
-    /// we'll use the first recovery path available.
-    ///
-    /// Note that not all coins may be spendable through a single recovery path at the same time.
-    pub fn create_recovery(
+    /// The `timelock` parameter can be used to specify which of these recovery paths to use. By
+    /// default, we'll use the first one which is available.
+    pub fn create_absolute_recovery(
         &self,
         address: bitcoin::Address<address::NetworkUnchecked>,
         feerate_vb: u64,
-        timelock: Option<u16>,
+        timelock: Option<u32>,
     ) -> Result<CreateRecoveryResult, CommandError> {
         if feerate_vb < 1 {
             return Err(CommandError::InvalidFeerate(feerate_vb));
@@ -1090,30 +2413,47 @@ impl DaemonControl {
         let mut db_conn = self.db.connection();
         let sweep_addr = self.spend_addr(&mut db_conn, self.validate_address(address)?);
 
-        // Query the coins that we can spend through the specified recovery path (if no recovery
-        // path specified, use the first available one) from the database.
+        // A height-based timelock is available if the transaction can be included in the *next*
+        // block, and a time-based one if it is below the median time past of the current tip.
         let current_height = self.bitcoin.chain_tip().height;
-        let timelock =
-            timelock.unwrap_or_else(|| self.config.main_descriptor.first_timelock_value());
-        let height_delta: i32 = timelock.into();
+        let is_available = |timelock: &LockTime| match timelock {
+            LockTime::Blocks(height) => {
+                i64::from(height.to_consensus_u32()) <= i64::from(current_height)
+            }
+            LockTime::Seconds(time) => self
+                .bitcoin
+                .tip_median_time_past()
+                .map(|mtp| time.to_consensus_u32() < mtp)
+                .unwrap_or(false),
+        };
+        let policy = self.config.main_descriptor.policy();
+        let timelock = match timelock {
+            Some(tl) => {
+                if !policy.absolute_recovery_paths().contains_key(&tl) {
+                    return Err(CommandError::UnknownAbsoluteTimelock(tl));
+                }
+                Some(LockTime::from_consensus(tl)).filter(is_available)
+            }
+            None => policy
+                .absolute_recovery_paths()
+                .keys()
+                .map(|tl| LockTime::from_consensus(*tl))
+                .find(is_available),
+        }
+        .ok_or(CommandError::RecoveryNotAvailable)?;
+
+        // All our confirmed coins can be swept. The nSequence must enable the nLockTime.
         let sweepable_coins: Vec<_> = db_conn
             .coins(&[CoinStatus::Confirmed], &[])
             .into_values()
-            .filter_map(|c| {
-                // We are interested in coins available at the *next* block
-                if c.block_info
-                    .map(|b| current_height + 1 >= b.height + height_delta)
-                    .unwrap_or(false)
-                {
-                    Some(coin_to_candidate(
-                        &c,
-                        /*must_select=*/ true,
-                        /*sequence=*/ Some(bitcoin::Sequence::from_height(timelock)),
-                        /*ancestor_info=*/ None,
-                    ))
-                } else {
-                    None
-                }
+            .filter(|c| !c.is_immature)
+            .map(|c| {
+                coin_to_candidate(
+                    &c,
+                    /*must_select=*/ true,
+                    /*sequence=*/ Some(bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME),
+                    /*ancestor_info=*/ None,
+                )
             })
             .collect();
         if sweepable_coins.is_empty() {
@@ -1121,7 +2461,6 @@ impl DaemonControl {
         }
 
         let sweep_addr_info = sweep_addr.info;
-        let locktime = self.anti_fee_sniping_locktime();
         let CreateSpendRes {
             psbt, has_change, ..
         } = create_spend(
@@ -1130,9 +2469,10 @@ impl DaemonControl {
             &mut tx_getter,
             &[], // No destination, only the change address.
             &sweepable_coins,
+            CoinSelectionStrategy::default(),
             SpendTxFees::Regular(feerate_vb),
             sweep_addr,
-            locktime,
+            timelock,
         )?;
         if has_change {
             self.maybe_increase_next_deriv_index(&mut db_conn, &sweep_addr_info);
@@ -1163,11 +2503,41 @@ pub struct GetInfoResult {
     pub last_poll_timestamp: Option<u32>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct GetPolicyResult {
+    #[serde(serialize_with = "ser_to_string")]
+    pub descriptor: descriptors::LianaDescriptor,
+    #[serde(flatten)]
+    pub explanation: descriptors::PolicyExplanation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintWarning {
+    #[serde(flatten)]
+    pub warning: descriptors::PolicyWarning,
+    /// A human-readable description of the warning.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintDescriptorResult {
+    pub warnings: Vec<LintWarning>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EstimateFeeResult {
+    /// The estimated feerate in sats/vb.
+    pub feerate: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetAddressResult {
     #[serde(deserialize_with = "deser_addr_assume_checked")]
     pub address: bitcoin::Address,
     pub derivation_index: bip32::ChildNumber,
+    /// A BIP21 payment URI for this address, if one was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
 }
 
 impl GetAddressResult {
@@ -1175,6 +2545,7 @@ impl GetAddressResult {
         Self {
             address,
             derivation_index,
+            uri: None,
         }
     }
 }
@@ -1184,6 +2555,20 @@ pub struct GetLabelsResult {
     pub labels: HashMap<String, String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportLabelsResult {
+    /// The labels as BIP329 JSON Lines.
+    pub bip329: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportLabelsResult {
+    /// Number of labels stored.
+    pub imported: usize,
+    /// Number of records ignored.
+    pub skipped: usize,
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 pub struct AddressInfo {
     index: u32,
@@ -1242,6 +2627,80 @@ pub struct ListCoinsResult {
     pub coins: Vec<ListCoinsEntry>,
 }
 
+/// A coin whose recovery path is about to become available.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpiringCoin {
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    pub outpoint: bitcoin::OutPoint,
+    pub block_height: i32,
+    /// Height from which the coin is spendable through the first recovery path. This is an
+    /// estimate, assuming 10-minute blocks, if the recovery path is behind a time-based timelock.
+    pub expiry_height: i32,
+    /// Number of blocks until the expiry height, 0 if it is already reached. This is an estimate,
+    /// assuming 10-minute blocks, if the recovery path is behind a time-based timelock.
+    pub remaining_blocks: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListExpiringCoinsResult {
+    pub coins: Vec<ExpiringCoin>,
+}
+
+/// Get the coins, among those given, whose recovery path with this relative timelock becomes
+/// available within `threshold` blocks of the given tip. Ordered by expiry height.
+///
+/// For a time-based timelock, the remaining time until expiry is computed from the median time
+/// past of the tip, and converted to a number of blocks assuming 10-minute blocks. No coin is
+/// considered expiring if the median time past isn't known.
+pub fn expiring_coins(
+    coins: impl IntoIterator<Item = Coin>,
+    tip_height: i32,
+    tip_mtp: Option<u32>,
+    timelock: relative::LockTime,
+    threshold: u32,
+) -> Vec<ExpiringCoin> {
+    let mut expiring: Vec<_> = coins
+        .into_iter()
+        .filter(|coin| !coin.is_spent())
+        .filter_map(|coin| {
+            let block_height = coin.block_info?.height;
+            let (expiry_height, remaining_blocks) = match timelock {
+                relative::LockTime::Blocks(height) => {
+                    let expiry_height = coin.recovery_height(height.value())?;
+                    let remaining_blocks = expiry_height.saturating_sub(tip_height).max(0) as u32;
+                    (expiry_height, remaining_blocks)
+                }
+                relative::LockTime::Time(time) => {
+                    let remaining_secs = coin.recovery_time(time.value())?.saturating_sub(tip_mtp?);
+                    let remaining_blocks = (remaining_secs + 599) / 600;
+                    let timelock_blocks = (u32::from(time.value()) * 512 + 599) / 600;
+                    let expiry_height = if remaining_blocks > 0 {
+                        tip_height.saturating_add(remaining_blocks as i32)
+                    } else {
+                        block_height
+                            .saturating_add(timelock_blocks as i32)
+                            .min(tip_height)
+                    };
+                    (expiry_height, remaining_blocks)
+                }
+            };
+            (remaining_blocks <= threshold).then_some(ExpiringCoin {
+                amount: coin.amount,
+                outpoint: coin.outpoint,
+                block_height,
+                expiry_height,
+                remaining_blocks,
+            })
+        })
+        .collect();
+    expiring.sort_by_key(|coin| (coin.expiry_height, coin.outpoint));
+    expiring
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum CreateSpendResult {
@@ -1249,12 +2708,41 @@ pub enum CreateSpendResult {
         #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
         psbt: Psbt,
         warnings: Vec<String>,
+        /// The payjoin (BIP78) endpoint of the receiver, if paying to a payment URI which has one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payjoin_endpoint: Option<String>,
     },
     InsufficientFunds {
         missing: u64,
     },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignSpendResult {
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub psbt: Psbt,
+    /// The fingerprints of the hot keys and external signers which signed the Spend transaction.
+    pub signers: Vec<bip32::Fingerprint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListExternalSignersEntry {
+    pub fingerprint: bip32::Fingerprint,
+    /// The name of the signer, as reported by the executable.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListExternalSignersResult {
+    pub signers: Vec<ListExternalSignersEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetExternalXpubResult {
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub xpub: bip32::Xpub,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListSpendEntry {
     #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
@@ -1272,6 +2760,12 @@ pub struct ListTransactionsResult {
     pub transactions: Vec<TransactionInfo>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportTransactionsResult {
+    /// The transactions as CSV, including a header line.
+    pub csv: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionInfo {
     #[serde(serialize_with = "ser_hex", deserialize_with = "deser_hex")]
@@ -1286,6 +2780,31 @@ pub struct CreateRecoveryResult {
     pub psbt: Psbt,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RequestPayjoinResult {
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub psbt: Psbt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreatePayjoinProposalResult {
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub psbt: Psbt,
+}
+
+/// A wallet managed by the daemon.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletEntry {
+    pub name: String,
+    pub descriptor: descriptors::LianaDescriptor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListWalletsResult {
+    /// The main wallet first, then the additional wallets by name.
+    pub wallets: Vec<WalletEntry>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1308,6 +2827,164 @@ mod tests {
         ms.shutdown();
     }
 
+    #[test]
+    fn parents_first() {
+        let tx = |prevout: OutPoint, lock_time: u32| Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::from_consensus(lock_time),
+            input: vec![TxIn {
+                previous_output: prevout,
+                ..TxIn::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        // A chain a -> b -> c, and an unrelated transaction d.
+        let a = tx(OutPoint::null(), 0);
+        let b = tx(OutPoint::new(a.txid(), 0), 0);
+        let c = tx(OutPoint::new(b.txid(), 0), 0);
+        let d = tx(OutPoint::null(), 1);
+        let txids = |txs: Vec<Transaction>| txs.iter().map(|tx| tx.txid()).collect::<Vec<_>>();
+
+        // Parents are moved before their children, the order is otherwise kept.
+        assert_eq!(
+            txids(sort_parents_first(vec![c.clone(), b.clone(), a.clone()])),
+            txids(vec![a.clone(), b.clone(), c.clone()])
+        );
+        assert_eq!(
+            txids(sort_parents_first(vec![
+                d.clone(),
+                b.clone(),
+                c.clone(),
+                a.clone()
+            ])),
+            txids(vec![d.clone(), a.clone(), b.clone(), c.clone()])
+        );
+        assert_eq!(
+            txids(sort_parents_first(vec![a.clone(), d.clone(), c.clone()])),
+            txids(vec![a, d, c])
+        );
+        assert!(sort_parents_first(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn expiringcoins() {
+        let txid =
+            Txid::from_str("f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd")
+                .unwrap();
+        let coin = |vout: u32, height: Option<i32>, spend_txid: Option<Txid>| Coin {
+            outpoint: OutPoint { txid, vout },
+            is_immature: false,
+            block_info: height.map(|height| BlockInfo {
+                height,
+                time: 0,
+                prev_mtp: None,
+            }),
+            amount: Amount::from_sat(100_000),
+            derivation_index: ChildNumber::from(0),
+            is_change: false,
+            spend_txid,
+            spend_block: None,
+            is_from_self: false,
+        };
+        let coins = vec![
+            coin(0, Some(100), None),
+            coin(1, Some(90), None),
+            coin(2, Some(110), None),
+            coin(3, None, None),
+            coin(4, Some(90), Some(txid)),
+        ];
+
+        // With a 10 blocks timelock at height 105, the first coin expires in 5 blocks, the second
+        // already expired and the third expires in 15 blocks. The unconfirmed and spent coins are
+        // never expiring.
+        let timelock = relative::Height::from(10).into();
+        let expiring = expiring_coins(coins.clone(), 105, None, timelock, 5);
+        assert_eq!(
+            expiring,
+            vec![
+                ExpiringCoin {
+                    amount: Amount::from_sat(100_000),
+                    outpoint: OutPoint { txid, vout: 1 },
+                    block_height: 90,
+                    expiry_height: 100,
+                    remaining_blocks: 0,
+                },
+                ExpiringCoin {
+                    amount: Amount::from_sat(100_000),
+                    outpoint: OutPoint { txid, vout: 0 },
+                    block_height: 100,
+                    expiry_height: 110,
+                    remaining_blocks: 5,
+                }
+            ]
+        );
+        assert_eq!(
+            expiring_coins(coins.clone(), 105, None, timelock, 4).len(),
+            1
+        );
+        assert_eq!(
+            expiring_coins(coins.clone(), 105, None, timelock, 15).len(),
+            3
+        );
+        assert!(expiring_coins(coins.clone(), 50, None, timelock, 30).is_empty());
+
+        // With a time-based timelock of 75 intervals (38400 seconds), the coins confirmed at a
+        // time of 0 become available once the median time past reaches 38400. The remaining time
+        // is converted to a number of blocks, rounded up.
+        let timelock = relative::Time::from_512_second_intervals(75).into();
+        assert!(expiring_coins(coins.clone(), 105, Some(35_000), timelock, 5).is_empty());
+        let expiring = expiring_coins(coins.clone(), 105, Some(35_000), timelock, 6);
+        assert_eq!(expiring.len(), 3);
+        assert!(expiring.iter().all(|c| c.remaining_blocks == 6));
+        assert!(expiring_coins(coins.clone(), 105, Some(34_000), timelock, 6).is_empty());
+        let expiring = expiring_coins(coins.clone(), 105, Some(40_000), timelock, 0);
+        assert_eq!(expiring.len(), 3);
+        assert!(expiring.iter().all(|c| c.remaining_blocks == 0));
+        // The timelock counts from the median time past of the block preceding the coin's one
+        // once it's known, and from the coin's block timestamp until then.
+        let mut late_coin = coin(5, Some(100), None);
+        late_coin.block_info = Some(BlockInfo {
+            height: 100,
+            time: 10_000,
+            prev_mtp: None,
+        });
+        assert!(expiring_coins(vec![late_coin], 105, Some(40_000), timelock, 0).is_empty());
+        late_coin.block_info = Some(BlockInfo {
+            height: 100,
+            time: 10_000,
+            prev_mtp: Some(0),
+        });
+        assert_eq!(
+            expiring_coins(vec![late_coin], 105, Some(40_000), timelock, 0).len(),
+            1
+        );
+        // Without the median time past, we can't tell.
+        assert!(expiring_coins(coins, 105, None, timelock, 1_000).is_empty());
+    }
+
+    #[test]
+    fn estimatefee() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+
+        assert_eq!(control.estimate_fee(1).unwrap().feerate, 101);
+        assert_eq!(control.estimate_fee(6).unwrap().feerate, 17);
+        assert_eq!(control.estimate_fee(MAX_CONF_TARGET).unwrap().feerate, 1);
+        assert_eq!(
+            control.estimate_fee(0),
+            Err(CommandError::InvalidConfTarget(0))
+        );
+        assert_eq!(
+            control.estimate_fee(MAX_CONF_TARGET + 1),
+            Err(CommandError::InvalidConfTarget(MAX_CONF_TARGET + 1))
+        );
+
+        ms.shutdown();
+    }
+
     #[test]
     fn getnewaddress() {
         let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
@@ -1326,6 +3003,19 @@ mod tests {
         // We won't get the same twice.
         let addr2 = control.get_new_address().address;
         assert_ne!(addr, addr2);
+        assert!(control.get_new_address().uri.is_none());
+
+        // We can get a payment URI along with it.
+        let res = control.get_new_address_uri(
+            Some(bitcoin::Amount::from_sat(100_000)),
+            Some("Alice".to_string()),
+            None,
+        );
+        assert_ne!(res.address, addr2);
+        assert_eq!(
+            res.uri,
+            Some(format!("bitcoin:{}?amount=0.001&label=Alice", res.address))
+        );
 
         ms.shutdown();
     }
@@ -1445,6 +3135,82 @@ mod tests {
         ms.shutdown();
     }
 
+    #[test]
+    fn refresh_coins() {
+        let dummy_tx = |locktime: u32| bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::from_consensus(locktime),
+            input: vec![],
+            output: vec![],
+        };
+        let (tx_a, tx_b) = (dummy_tx(0), dummy_tx(1));
+        let (op_a, op_b) = (
+            bitcoin::OutPoint::new(tx_a.txid(), 0),
+            bitcoin::OutPoint::new(tx_b.txid(), 0),
+        );
+        // Start from the tip of the dummy bitcoind, so the poller doesn't change it.
+        let bitcoind = DummyBitcoind::new();
+        let db = DummyDatabase::new();
+        db.connection().update_tip(&bitcoind.chain_tip());
+        let ms = DummyLiana::new(bitcoind, db);
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_txs(&[tx_a, tx_b]);
+        let coin = |outpoint, height| Coin {
+            outpoint,
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height,
+                time: 0,
+                prev_mtp: None,
+            }),
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        };
+        // The recovery path is available after 10_000 blocks and the tip is at height 100. The
+        // first coin is expiring in 9_901 blocks and the second one in 14_900 blocks.
+        db_conn.new_unspent_coins(&[coin(op_a, 1), coin(op_b, 5_000)]);
+
+        assert_eq!(
+            control.refresh_coins(Some(9_900), 2),
+            Err(CommandError::NoCoinToRefresh(9_900))
+        );
+        assert_eq!(
+            control.refresh_coins(Some(9_901), 0),
+            Err(CommandError::InvalidFeerate(0))
+        );
+        let psbt = if let CreateSpendResult::Success { psbt, .. } =
+            control.refresh_coins(Some(9_901), 2).unwrap()
+        {
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        let tx = psbt.unsigned_tx;
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output, op_a);
+        // A single output, to a change address.
+        assert_eq!(tx.output.len(), 1);
+        assert!(!psbt.outputs[0].bip32_derivation.is_empty());
+
+        // With a large enough threshold, all the coins are refreshed at once.
+        let psbt = if let CreateSpendResult::Success { psbt, .. } =
+            control.refresh_coins(Some(14_900), 2).unwrap()
+        {
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        assert_eq!(psbt.unsigned_tx.input.len(), 2);
+        assert_eq!(psbt.unsigned_tx.output.len(), 1);
+
+        ms.shutdown();
+    }
+
     #[test]
     fn create_spend() {
         let dummy_tx = bitcoin::Transaction {
@@ -1505,7 +3271,7 @@ mod tests {
             control.create_spend(&destinations, &[], 1, None),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
-        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
+        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings, .. } = control
             .create_spend(&destinations, &[dummy_op], 1, None)
             .unwrap()
         {
@@ -1585,7 +3351,7 @@ mod tests {
         // If we ask for a large, but valid, output we won't get a change output. 95_000 because we
         // won't create an output lower than 5k sats.
         *destinations.get_mut(&dummy_addr).unwrap() = 95_000;
-        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
+        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings, .. } = control
             .create_spend(&destinations, &[dummy_op], 1, None)
             .unwrap()
         {
@@ -1614,7 +3380,7 @@ mod tests {
 
         // Increase the target value by the change amount and the warning will disappear.
         *destinations.get_mut(&dummy_addr).unwrap() = 95_000 + 4_839;
-        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
+        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings, .. } = control
             .create_spend(&destinations, &[dummy_op], 1, None)
             .unwrap()
         {
@@ -1629,7 +3395,7 @@ mod tests {
         // Now increase target also by the extra fee that was paying for change and we can still create the spend.
         *destinations.get_mut(&dummy_addr).unwrap() =
             95_000 + 4_830 + /* fee for change output */ 43;
-        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
+        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings, .. } = control
             .create_spend(&destinations, &[dummy_op], 1, None)
             .unwrap()
         {
@@ -1673,7 +3439,7 @@ mod tests {
         // Now decrease the target value so that we have enough for a change output.
         *destinations.get_mut(&dummy_addr).unwrap() =
             95_000 - /* fee without change */ 118 - /* extra fee for change output */ 43;
-        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
+        let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings, .. } = control
             .create_spend(&destinations, &[dummy_op], 1, None)
             .unwrap()
         {
@@ -1789,6 +3555,7 @@ mod tests {
             block_info: Some(BlockInfo {
                 height: 174500,
                 time: 174500,
+                prev_mtp: None,
             }),
             amount: bitcoin::Amount::from_sat(20_000),
             derivation_index: bip32::ChildNumber::from(43),
@@ -1916,6 +3683,7 @@ mod tests {
             block_info: Some(BlockInfo {
                 height: 174500,
                 time: 174500,
+                prev_mtp: None,
             }),
             amount: bitcoin::Amount::from_sat(5_250),
             derivation_index: bip32::ChildNumber::from(56),
@@ -2097,6 +3865,32 @@ mod tests {
         control.update_spend(psbt_c.clone()).unwrap();
         assert_eq!(db_conn.spend_tx(&txid_c).unwrap(), psbt_c);
 
+        // A payjoin proposal for the first transaction is stored apart. Only the inputs of the
+        // original transaction are updated, the others are the receiver's.
+        db_conn.delete_spend(&txid_c);
+        db_conn.store_payjoin_proposal(&txid_a, &psbt_c);
+        let (ours, theirs) = if psbt_c.unsigned_tx.input[0].previous_output == dummy_op_a {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        let mut proposal = psbt_c.clone();
+        for i in [ours, theirs] {
+            proposal.inputs[i]
+                .partial_sigs
+                .extend(psbt_a.inputs[0].partial_sigs.clone());
+        }
+        control.update_spend(proposal.clone()).unwrap();
+        assert!(db_conn.spend_tx(&txid_c).is_none());
+        let (original_txid, db_proposal) = db_conn.payjoin_proposal(&txid_c).unwrap();
+        assert_eq!(original_txid, txid_a);
+        assert_eq!(db_proposal.inputs[ours], proposal.inputs[ours]);
+        assert_eq!(db_proposal.inputs[theirs], psbt_c.inputs[theirs]);
+        // It's deleted along with the original transaction.
+        db_conn.delete_spend(&txid_a);
+        assert!(db_conn.payjoin_proposal(&txid_c).is_none());
+        control.update_spend(psbt_a.clone()).unwrap();
+
         // We can't store a PSBT spending an external coin
         let external_op = bitcoin::OutPoint::from_str(
             "8753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:2",
@@ -2158,6 +3952,7 @@ mod tests {
             block_info: Some(BlockInfo {
                 height: 174500,
                 time: 174500,
+                prev_mtp: None,
             }),
             amount: bitcoin::Amount::from_sat(300_000),
             derivation_index: bip32::ChildNumber::from(11),
@@ -2166,6 +3961,7 @@ mod tests {
             spend_block: Some(BlockInfo {
                 height: 184500,
                 time: 184500,
+                prev_mtp: None,
             }),
             is_from_self: false,
         }]);
@@ -2274,8 +4070,16 @@ mod tests {
                     txid: deposit1.txid(),
                     vout: 0,
                 },
-                block_info: Some(BlockInfo { height: 1, time: 1 }),
-                spend_block: Some(BlockInfo { height: 3, time: 3 }),
+                block_info: Some(BlockInfo {
+                    height: 1,
+                    time: 1,
+                    prev_mtp: None,
+                }),
+                spend_block: Some(BlockInfo {
+                    height: 3,
+                    time: 3,
+                    prev_mtp: None,
+                }),
                 derivation_index: ChildNumber::from(0),
                 amount: bitcoin::Amount::from_sat(100_000_000),
                 spend_txid: Some(spend_tx.txid()),
@@ -2289,7 +4093,11 @@ mod tests {
                     txid: deposit2.txid(),
                     vout: 0,
                 },
-                block_info: Some(BlockInfo { height: 2, time: 2 }),
+                block_info: Some(BlockInfo {
+                    height: 2,
+                    time: 2,
+                    prev_mtp: None,
+                }),
                 spend_block: None,
                 derivation_index: ChildNumber::from(1),
                 amount: bitcoin::Amount::from_sat(2000),
@@ -2301,7 +4109,11 @@ mod tests {
                 is_change: true,
                 is_immature: false,
                 outpoint: OutPoint::new(spend_tx.txid(), 1),
-                block_info: Some(BlockInfo { height: 3, time: 3 }),
+                block_info: Some(BlockInfo {
+                    height: 3,
+                    time: 3,
+                    prev_mtp: None,
+                }),
                 spend_block: None,
                 derivation_index: ChildNumber::from(2),
                 amount: bitcoin::Amount::from_sat(100_000_000 - 4000 - 1000),
@@ -2316,7 +4128,11 @@ mod tests {
                     txid: deposit3.txid(),
                     vout: 0,
                 },
-                block_info: Some(BlockInfo { height: 4, time: 4 }),
+                block_info: Some(BlockInfo {
+                    height: 4,
+                    time: 4,
+                    prev_mtp: None,
+                }),
                 spend_block: None,
                 derivation_index: ChildNumber::from(3),
                 amount: bitcoin::Amount::from_sat(3000),
@@ -2536,6 +4352,7 @@ mod tests {
                     block_info: block.map(|b| BlockInfo {
                         height: b.height,
                         time: b.time,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(100_000),
                     derivation_index: bip32::ChildNumber::from(13),
@@ -0,0 +1,82 @@
+//! Communication with the receiver of a payjoin (BIP78), as the sender.
+
+use liana::payjoin::SenderParams;
+
+use std::str::FromStr;
+
+use miniscript::bitcoin::psbt::Psbt;
+use url::{Host, Url};
+
+/// How long to wait for the receiver to answer, in seconds. BIP78 recommends receivers to
+/// answer within 30 seconds.
+const REQUEST_TIMEOUT_SECS: u64 = 60;
+
+// Whether we may communicate with this endpoint. BIP78 requires the endpoint to be authenticated
+// and encrypted. We only allow plaintext HTTP to a local endpoint, for testing purposes.
+fn is_allowed_endpoint(endpoint: &Url) -> bool {
+    match endpoint.scheme() {
+        "https" => endpoint.host().is_some(),
+        "http" => match endpoint.host() {
+            Some(Host::Domain(domain)) => domain == "localhost",
+            Some(Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(Host::Ipv6(ip)) => ip.is_loopback(),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Send our original PSBT to the receiver's endpoint and get its payjoin proposal in return.
+pub fn send_original(
+    endpoint: &str,
+    params: &SenderParams,
+    original: &Psbt,
+) -> Result<Psbt, String> {
+    let mut url =
+        Url::parse(endpoint).map_err(|e| format!("invalid endpoint '{}': {}", endpoint, e))?;
+    if !is_allowed_endpoint(&url) {
+        return Err(format!("endpoint '{}' must use HTTPS", endpoint));
+    }
+    let query = match url.query() {
+        Some(query) if !query.is_empty() => format!("{}&{}", query, params.to_query()),
+        _ => params.to_query(),
+    };
+    url.set_query(Some(&query));
+    let response = minreq::post(url.as_str())
+        .with_header("Content-Type", "text/plain")
+        .with_body(original.to_string())
+        .with_timeout(REQUEST_TIMEOUT_SECS)
+        .send()
+        .map_err(|e| e.to_string())?;
+    let body = response.as_str().map_err(|e| e.to_string())?;
+    if response.status_code != 200 {
+        return Err(format!(
+            "receiver answered with status {}: '{}'",
+            response.status_code, body
+        ));
+    }
+    Psbt::from_str(body.trim()).map_err(|e| format!("invalid proposal PSBT: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payjoin_endpoint() {
+        let allowed = |endpoint| is_allowed_endpoint(&Url::parse(endpoint).unwrap());
+        assert!(allowed("https://example.com/pj"));
+        assert!(allowed("HTTPS://example.com/pj"));
+        assert!(allowed("http://127.0.0.1:8080/pj"));
+        assert!(allowed("http://127.0.0.2/pj"));
+        assert!(allowed("http://[::1]:8080/pj"));
+        assert!(allowed("http://localhost/pj"));
+        assert!(allowed("http://LocalHost:80/pj"));
+        assert!(!allowed("http://example.com/pj"));
+        assert!(!allowed("http://localhost.example.com/pj"));
+        assert!(!allowed("http://127.0.0.1.example.com/pj"));
+        assert!(!allowed("http://localhost@example.com/pj"));
+        assert!(!allowed("http://192.168.1.1/pj"));
+        assert!(!allowed("ftp://localhost/pj"));
+    }
+}
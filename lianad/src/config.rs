@@ -80,6 +80,19 @@ fn default_poll_interval() -> Duration {
     Duration::from_secs(30)
 }
 
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+fn default_expiry_threshold() -> u32 {
+    // About a month.
+    4_320
+}
+
+fn default_expiry_hook_timeout() -> u64 {
+    60
+}
+
 /// Bitcoin backend config.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum BitcoinBackend {
@@ -143,6 +156,48 @@ pub struct BitcoinConfig {
     pub poll_interval_secs: Duration,
 }
 
+/// Settings for watching coins whose recovery path is about to become available.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExpiryWatchConfig {
+    /// A coin is expiring if its first recovery path becomes available within this number of
+    /// blocks.
+    #[serde(default = "default_expiry_threshold")]
+    pub threshold_blocks: u32,
+    /// A command to execute when coins start expiring. It is passed the details of the newly
+    /// expiring coins as JSON on its standard input.
+    pub hook_command: Option<PathBuf>,
+    /// The number of seconds after which the hook command is killed if it didn't exit.
+    #[serde(default = "default_expiry_hook_timeout")]
+    pub hook_timeout_secs: u64,
+}
+
+impl Default for ExpiryWatchConfig {
+    fn default() -> Self {
+        Self {
+            threshold_blocks: default_expiry_threshold(),
+            hook_command: None,
+            hook_timeout_secs: default_expiry_hook_timeout(),
+        }
+    }
+}
+
+/// The name used to refer to the wallet of the main descriptor.
+pub const MAIN_WALLET_NAME: &str = "main";
+
+/// An additional wallet to be managed by the daemon, besides the main one.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WalletConfig {
+    /// Identifier of the wallet, used to select it in RPC calls. It is also the name of the
+    /// directory holding its data.
+    pub name: String,
+    /// The descriptor to use for sending/receiving coins in this wallet
+    #[serde(
+        deserialize_with = "deserialize_fromstr",
+        serialize_with = "serialize_to_string"
+    )]
+    pub descriptor: LianaDescriptor,
+}
+
 /// Static informations we require to operate
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
@@ -161,17 +216,41 @@ pub struct Config {
         serialize_with = "serialize_to_string"
     )]
     pub main_descriptor: LianaDescriptor,
+    /// Whether to sign Spend transactions upon request with the hot keys whose mnemonics are
+    /// stored in the data directory, and with the external signers if any.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub enable_signspend: bool,
+    /// An executable through which to use external signers, following the protocol of bitcoind's
+    /// `-signer` option. Signing Spend transactions through it needs `enable_signspend`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_signer: Option<PathBuf>,
     /// Settings for the Bitcoin interface
     pub bitcoin_config: BitcoinConfig,
     /// Settings specific to the Bitcoin backend.
     #[serde(flatten)]
     pub bitcoin_backend: Option<BitcoinBackend>,
+    /// Additional wallets to manage, each with its own descriptor.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wallets: Vec<WalletConfig>,
+    /// Settings for watching coins about to become spendable through a recovery path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_watch: Option<ExpiryWatchConfig>,
 }
 
 impl Config {
     pub fn data_dir(&self) -> Option<PathBuf> {
         self.data_dir.clone().or_else(config_folder_path)
     }
+
+    /// The configuration for operating only the given additional wallet, as if its descriptor
+    /// was the main one.
+    pub fn wallet_config(&self, wallet: &WalletConfig) -> Config {
+        Config {
+            main_descriptor: wallet.descriptor.clone(),
+            wallets: Vec::new(),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -269,13 +348,52 @@ impl Config {
             Network::Bitcoin => Network::Bitcoin,
             _ => Network::Testnet,
         };
-        if !self.main_descriptor.all_xpubs_net_is(expected_network) {
+        if !self.main_descriptor.all_xpubs_net_is(expected_network)
+            || self
+                .wallets
+                .iter()
+                .any(|wallet| !wallet.descriptor.all_xpubs_net_is(expected_network))
+        {
             return Err(ConfigError::Unexpected(format!(
                 "Our bitcoin network is {} but one xpub is not for network {}",
                 self.bitcoin_config.network, expected_network
             )));
         }
 
+        // Check the additional wallets can be told apart, and their name can be used as a
+        // directory name.
+        for (i, wallet) in self.wallets.iter().enumerate() {
+            if wallet.name.is_empty()
+                || wallet.name == MAIN_WALLET_NAME
+                || !wallet
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(ConfigError::Unexpected(format!(
+                    "Invalid wallet name '{}'. It must only contain alphanumeric characters, '-' \
+                     and '_', and must not be '{}'.",
+                    wallet.name, MAIN_WALLET_NAME
+                )));
+            }
+            if self.wallets[..i].iter().any(|w| w.name == wallet.name) {
+                return Err(ConfigError::Unexpected(format!(
+                    "Duplicate wallet name '{}'.",
+                    wallet.name
+                )));
+            }
+            if wallet.descriptor == self.main_descriptor
+                || self.wallets[..i]
+                    .iter()
+                    .any(|w| w.descriptor == wallet.descriptor)
+            {
+                return Err(ConfigError::Unexpected(format!(
+                    "The descriptor of wallet '{}' is used by another wallet.",
+                    wallet.name
+                )));
+            }
+        }
+
         // TODO: check the semantics of the main descriptor
 
         Ok(())
@@ -404,6 +522,49 @@ mod tests {
         config_res.expect_err("Deserializing an invalid toml_str");
     }
 
+    // Test the configuration of additional wallets
+    #[test]
+    fn toml_wallets_config() {
+        let toml_str = r#"
+            main_descriptor = 'wsh(andor(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(10000),pk([aabbccdd]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))#dw4ulnrs'
+
+            [bitcoin_config]
+            network = 'testnet'
+
+            [bitcoind_config]
+            cookie_path = '/home/user/.bitcoin/.cookie'
+            addr = '127.0.0.1:8332'
+
+            [[wallets]]
+            name = 'treasury'
+            descriptor = 'wsh(andor(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(20000),pk([aabbccdd]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))'
+
+            [[wallets]]
+            name = 'pay-roll_2'
+            descriptor = 'wsh(andor(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(30000),pk([aabbccdd]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))'
+            "#;
+        let config = toml::from_str::<Config>(toml_str).expect("Deserializing toml_str");
+        config.check().expect("Valid wallets");
+        assert_eq!(config.wallets.len(), 2);
+        assert_eq!(config.wallets[0].name, "treasury");
+        let wallet_config = config.wallet_config(&config.wallets[1]);
+        assert_eq!(wallet_config.main_descriptor, config.wallets[1].descriptor);
+        assert!(wallet_config.wallets.is_empty());
+
+        // The wallets must have distinct valid names and distinct descriptors.
+        for name in ["treasury", "main", "", "../treasury"] {
+            let mut invalid_config = config.clone();
+            invalid_config.wallets[1].name = name.to_string();
+            invalid_config.check().expect_err("Invalid name");
+        }
+        let mut invalid_config = config.clone();
+        invalid_config.wallets[1].descriptor = config.wallets[0].descriptor.clone();
+        invalid_config.check().expect_err("Duplicate descriptor");
+        let mut invalid_config = config;
+        invalid_config.wallets[0].descriptor = invalid_config.main_descriptor.clone();
+        invalid_config.check().expect_err("Duplicate descriptor");
+    }
+
     // Test the format of the bitcoind_config section
     #[test]
     fn toml_bitcoind_config() {
@@ -7,7 +7,7 @@ pub mod sqlite;
 use crate::{
     bitcoin::BlockChainTip,
     database::sqlite::{
-        schema::{DbBlockInfo, DbCoin, DbTip},
+        schema::{DbBlockInfo, DbCoin, DbLabelledKind, DbTip},
         SqliteConn, SqliteDb,
     },
 };
@@ -137,6 +137,17 @@ pub trait DatabaseConnection {
     /// chain.
     fn confirm_coins(&mut self, outpoints: &[(bitcoin::OutPoint, i32, u32)]);
 
+    /// Set the median time past of the block preceding the one which confirmed each of these
+    /// coins. It is reset when a coin is confirmed again.
+    fn set_coins_prev_mtp(&mut self, outpoints: &[(bitcoin::OutPoint, u32)]);
+
+    /// Get the coins for which the expiry hook was successfully executed.
+    fn expiry_notified_coins(&mut self) -> HashSet<bitcoin::OutPoint>;
+
+    /// Record that the expiry hook was successfully executed for these coins. This is forgotten if
+    /// they get unconfirmed.
+    fn mark_expiry_notified(&mut self, outpoints: &[bitcoin::OutPoint]);
+
     /// Mark a set of coins as being spent by a specified txid of a pending transaction.
     fn spend_coins(&mut self, outpoints: &[(bitcoin::OutPoint, bitcoin::Txid)]);
 
@@ -160,15 +171,25 @@ pub trait DatabaseConnection {
     /// List all existing Spend transactions, along with an optional last update timestamp.
     fn list_spend(&mut self) -> Vec<(Psbt, Option<u32>)>;
 
-    /// Delete a Spend transaction from database.
+    /// Delete a Spend transaction from database, along with its payjoin proposals. A payjoin
+    /// proposal may be deleted by its txid too.
     fn delete_spend(&mut self, txid: &bitcoin::Txid);
 
+    /// Get a payjoin proposal, along with the txid of the Spend transaction it was made for.
+    fn payjoin_proposal(&mut self, txid: &bitcoin::Txid) -> Option<(bitcoin::Txid, Psbt)>;
+
+    /// Insert a new payjoin proposal for this Spend transaction or replace an existing one.
+    fn store_payjoin_proposal(&mut self, original_txid: &bitcoin::Txid, psbt: &Psbt);
+
     /// Update, for a set of items (as key), their label (as value). A `None` value deletes the
     /// label.
     fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>);
 
     fn labels(&mut self, labels: &HashSet<LabelItem>) -> HashMap<String, String>;
 
+    /// Retrieve all the labels stored for this wallet, along with the item they label.
+    fn list_labels(&mut self) -> Vec<(LabelItem, String)>;
+
     /// Mark the given tip as the new best seen block. Update stored data accordingly.
     fn rollback_tip(&mut self, new_tip: &BlockChainTip);
 
@@ -302,6 +323,18 @@ impl DatabaseConnection for SqliteConn {
         self.confirm_coins(outpoints)
     }
 
+    fn set_coins_prev_mtp(&mut self, outpoints: &[(bitcoin::OutPoint, u32)]) {
+        self.set_coins_prev_mtp(outpoints)
+    }
+
+    fn expiry_notified_coins(&mut self) -> HashSet<bitcoin::OutPoint> {
+        self.expiry_notified_coins()
+    }
+
+    fn mark_expiry_notified(&mut self, outpoints: &[bitcoin::OutPoint]) {
+        self.mark_expiry_notified(outpoints)
+    }
+
     fn spend_coins<'a>(&mut self, outpoints: &[(bitcoin::OutPoint, bitcoin::Txid)]) {
         self.spend_coins(outpoints)
     }
@@ -358,6 +391,15 @@ impl DatabaseConnection for SqliteConn {
         self.delete_spend(txid)
     }
 
+    fn payjoin_proposal(&mut self, txid: &bitcoin::Txid) -> Option<(bitcoin::Txid, Psbt)> {
+        self.db_payjoin_proposal(txid)
+            .map(|db_proposal| (db_proposal.original_txid, db_proposal.psbt))
+    }
+
+    fn store_payjoin_proposal(&mut self, original_txid: &bitcoin::Txid, psbt: &Psbt) {
+        self.store_payjoin_proposal(original_txid, psbt)
+    }
+
     fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>) {
         self.update_labels(items)
     }
@@ -367,6 +409,29 @@ impl DatabaseConnection for SqliteConn {
         HashMap::from_iter(labels.into_iter().map(|label| (label.item, label.value)))
     }
 
+    fn list_labels(&mut self) -> Vec<(LabelItem, String)> {
+        self.db_list_labels()
+            .into_iter()
+            .map(|label| {
+                let item = match label.item_kind {
+                    DbLabelledKind::Address => LabelItem::Address(
+                        bitcoin::Address::from_str(&label.item)
+                            .expect("We only store valid addresses")
+                            .assume_checked(),
+                    ),
+                    DbLabelledKind::OutPoint => LabelItem::OutPoint(
+                        bitcoin::OutPoint::from_str(&label.item)
+                            .expect("We only store valid outpoints"),
+                    ),
+                    DbLabelledKind::Txid => LabelItem::Txid(
+                        bitcoin::Txid::from_str(&label.item).expect("We only store valid txids"),
+                    ),
+                };
+                (item, label.value)
+            })
+            .collect()
+    }
+
     fn rollback_tip(&mut self, new_tip: &BlockChainTip) {
         self.rollback_tip(new_tip)
     }
@@ -409,6 +474,9 @@ impl DatabaseConnection for SqliteConn {
 pub struct BlockInfo {
     pub height: i32,
     pub time: u32,
+    /// The median time past of the previous block. Only known for the block confirming a coin,
+    /// once it was queried from the Bitcoin backend.
+    pub prev_mtp: Option<u32>,
 }
 
 impl From<DbBlockInfo> for BlockInfo {
@@ -416,6 +484,7 @@ impl From<DbBlockInfo> for BlockInfo {
         BlockInfo {
             height: b.height,
             time: b.time,
+            prev_mtp: b.prev_mtp,
         }
     }
 }
@@ -469,6 +538,28 @@ impl Coin {
     pub fn is_spent(&self) -> bool {
         self.spend_txid.is_some()
     }
+
+    /// The height from which this coin is spendable through a recovery path with this relative
+    /// timelock. Only known once the coin is confirmed.
+    pub fn recovery_height(&self, timelock: u16) -> Option<i32> {
+        self.block_info
+            .map(|info| info.height.saturating_add(timelock.into()))
+    }
+
+    /// The time from which this coin is spendable through a recovery path with this time-based
+    /// relative timelock, in number of 512-second intervals. It is to be compared to the median
+    /// time past of the chain tip. Only known once the coin is confirmed.
+    ///
+    /// BIP68 counts from the median time past of the block preceding the one which confirmed the
+    /// coin. Until it is known, we count from the timestamp of the confirming block instead, which
+    /// is always later.
+    pub fn recovery_time(&self, timelock: u16) -> Option<u32> {
+        self.block_info.map(|info| {
+            info.prev_mtp
+                .unwrap_or(info.time)
+                .saturating_add(u32::from(timelock) * 512)
+        })
+    }
 }
 
 /// Possible (mutually exclusive) status of a coin.
@@ -15,8 +15,8 @@ use crate::{
     database::{
         sqlite::{
             schema::{
-                DbAddress, DbCoin, DbLabel, DbLabelledKind, DbSpendTransaction, DbTip, DbWallet,
-                DbWalletTransaction, SCHEMA,
+                DbAddress, DbCoin, DbLabel, DbLabelledKind, DbPayjoinProposal, DbSpendTransaction,
+                DbTip, DbWallet, DbWalletTransaction, SCHEMA,
             },
             utils::{
                 create_fresh_db, curr_timestamp, db_exec, db_query, db_tx_query, db_version,
@@ -43,7 +43,7 @@ use miniscript::bitcoin::{
     secp256k1,
 };
 
-const DB_VERSION: i64 = 8;
+const DB_VERSION: i64 = 11;
 
 /// Last database version for which Bitcoin transactions were not stored in database. In practice
 /// this meant we relied on the bitcoind watchonly wallet to store them for us.
@@ -487,6 +487,10 @@ impl SqliteConn {
                     "DELETE FROM coins WHERE txid = ?1 AND vout = ?2",
                     rusqlite::params![outpoint.txid[..].to_vec(), outpoint.vout,],
                 )?;
+                db_tx.execute(
+                    "DELETE FROM expiry_notified_coins WHERE txid = ?1 AND vout = ?2",
+                    rusqlite::params![outpoint.txid[..].to_vec(), outpoint.vout,],
+                )?;
             }
 
             Ok(())
@@ -505,7 +509,7 @@ impl SqliteConn {
         db_exec(&mut self.conn, |db_tx| {
             for (outpoint, height, time) in outpoints {
                 db_tx.execute(
-                    "UPDATE coins SET blockheight = ?1, blocktime = ?2, is_immature = 0 WHERE txid = ?3 AND vout = ?4",
+                    "UPDATE coins SET blockheight = ?1, blocktime = ?2, block_prev_mtp = NULL, is_immature = 0 WHERE txid = ?3 AND vout = ?4",
                     rusqlite::params![height, time, outpoint.txid[..].to_vec(), outpoint.vout,],
                 )?;
             }
@@ -515,6 +519,25 @@ impl SqliteConn {
         .expect("Database must be available")
     }
 
+    /// Set the median time past of the block preceding the one which confirmed each of these
+    /// coins.
+    pub fn set_coins_prev_mtp<'a>(
+        &mut self,
+        outpoints: impl IntoIterator<Item = &'a (bitcoin::OutPoint, u32)>,
+    ) {
+        db_exec(&mut self.conn, |db_tx| {
+            for (outpoint, prev_mtp) in outpoints {
+                db_tx.execute(
+                    "UPDATE coins SET block_prev_mtp = ?1 WHERE txid = ?2 AND vout = ?3",
+                    rusqlite::params![prev_mtp, outpoint.txid[..].to_vec(), outpoint.vout,],
+                )?;
+            }
+
+            Ok(())
+        })
+        .expect("Database must be available")
+    }
+
     /// Mark a set of coins as spending.
     pub fn spend_coins<'a>(
         &mut self,
@@ -624,6 +647,66 @@ impl SqliteConn {
         .expect("Db must not fail");
     }
 
+    /// Get the coins for which the expiry hook was successfully executed.
+    pub fn expiry_notified_coins(&mut self) -> HashSet<bitcoin::OutPoint> {
+        db_query(
+            &mut self.conn,
+            "SELECT txid, vout FROM expiry_notified_coins",
+            rusqlite::params![],
+            |row| {
+                let txid: Vec<u8> = row.get(0)?;
+                let txid: bitcoin::Txid =
+                    encode::deserialize(&txid).expect("We only store valid txids");
+                let vout = row.get(1)?;
+                Ok(bitcoin::OutPoint { txid, vout })
+            },
+        )
+        .expect("Db must not fail")
+        .into_iter()
+        .collect()
+    }
+
+    /// Record that the expiry hook was successfully executed for these coins.
+    pub fn mark_expiry_notified(&mut self, outpoints: &[bitcoin::OutPoint]) {
+        db_exec(&mut self.conn, |db_tx| {
+            for outpoint in outpoints {
+                db_tx.execute(
+                    "INSERT OR IGNORE INTO expiry_notified_coins (txid, vout) VALUES (?1, ?2)",
+                    rusqlite::params![outpoint.txid[..].to_vec(), outpoint.vout],
+                )?;
+            }
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    pub fn db_payjoin_proposal(&mut self, txid: &bitcoin::Txid) -> Option<DbPayjoinProposal> {
+        db_query(
+            &mut self.conn,
+            "SELECT * FROM payjoin_proposals WHERE txid = ?1",
+            rusqlite::params![txid[..].to_vec()],
+            |row| row.try_into(),
+        )
+        .expect("Db must not fail")
+        .pop()
+    }
+
+    /// Insert a new payjoin proposal for the Spend transaction with this txid, or replace an
+    /// existing one.
+    pub fn store_payjoin_proposal(&mut self, original_txid: &bitcoin::Txid, psbt: &Psbt) {
+        let txid = &psbt.unsigned_tx.txid()[..].to_vec();
+
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT into payjoin_proposals (original_txid, psbt, txid) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT DO UPDATE SET psbt=excluded.psbt",
+                rusqlite::params![original_txid[..].to_vec(), psbt.serialize(), txid],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
     pub fn list_spend(&mut self) -> Vec<DbSpendTransaction> {
         db_query(
             &mut self.conn,
@@ -678,6 +761,17 @@ impl SqliteConn {
         .expect("Db must not fail")
     }
 
+    /// Retrieve all the labels stored for this wallet, in insertion order.
+    pub fn db_list_labels(&mut self) -> Vec<DbLabel> {
+        db_query(
+            &mut self.conn,
+            "SELECT * FROM labels WHERE wallet_id = ?1 ORDER BY id",
+            rusqlite::params![WALLET_ID],
+            |row| row.try_into(),
+        )
+        .expect("Db must not fail")
+    }
+
     /// Retrieves a limited and ordered list of transactions ids that happened during the given
     /// range.
     pub fn db_list_txids(&mut self, start: u32, end: u32, limit: u64) -> Vec<bitcoin::Txid> {
@@ -879,12 +973,18 @@ impl SqliteConn {
         w_txs
     }
 
+    /// Delete a Spend transaction, or a payjoin proposal, with this txid. The payjoin proposals
+    /// for a deleted Spend transaction are deleted too.
     pub fn delete_spend(&mut self, txid: &bitcoin::Txid) {
         db_exec(&mut self.conn, |db_tx| {
             db_tx.execute(
                 "DELETE FROM spend_transactions WHERE txid = ?1",
                 rusqlite::params![txid[..].to_vec()],
             )?;
+            db_tx.execute(
+                "DELETE FROM payjoin_proposals WHERE txid = ?1 OR original_txid = ?1",
+                rusqlite::params![txid[..].to_vec()],
+            )?;
             Ok(())
         })
         .expect("Db must not fail");
@@ -897,6 +997,7 @@ impl SqliteConn {
     /// This includes:
     /// - Coins (coinbase deposits that became immature isn't currently implemented)
     /// - Spending transactions confirmation
+    /// - Expiry notifications of the unconfirmed coins
     /// - Tip
     ///
     /// The `is_from_self` value for all unconfirmed coins following the rollback is
@@ -907,8 +1008,14 @@ impl SqliteConn {
     /// in the database eventually.
     pub fn rollback_tip(&mut self, new_tip: &BlockChainTip) {
         db_exec(&mut self.conn, |db_tx| {
+            // This must be run before unconfirming the coins below.
             db_tx.execute(
-                "UPDATE coins SET blockheight = NULL, blocktime = NULL, spend_block_height = NULL, spend_block_time = NULL WHERE blockheight > ?1",
+                "DELETE FROM expiry_notified_coins WHERE (txid, vout) IN \
+                 (SELECT txid, vout FROM coins WHERE blockheight > ?1)",
+                rusqlite::params![new_tip.height],
+            )?;
+            db_tx.execute(
+                "UPDATE coins SET blockheight = NULL, blocktime = NULL, block_prev_mtp = NULL, spend_block_height = NULL, spend_block_time = NULL WHERE blockheight > ?1",
                 rusqlite::params![new_tip.height],
             )?;
             db_tx.execute(
@@ -1377,11 +1484,29 @@ CREATE TABLE labels (
             let db_labels = conn.db_labels(&items);
             assert_eq!(db_labels[0].value, "hello again");
 
+            // All the labels of the wallet can be listed, whatever the kind of item.
+            let outpoint_str = "0c62a990d20d54429e70859292e82374ba6b1b951a3ab60f26bb65fee5724ff7:1";
+            let outpoint = LabelItem::from_str(outpoint_str, bitcoin::Network::Bitcoin).unwrap();
+            let mut outpoint_labels = HashMap::new();
+            outpoint_labels.insert(outpoint.clone(), Some("a coin".to_string()));
+            conn.update_labels(&outpoint_labels);
+            let db_labels = conn.db_list_labels();
+            assert_eq!(db_labels.len(), 2);
+            assert_eq!(db_labels[0].item, txid_str);
+            assert_eq!(db_labels[0].item_kind, DbLabelledKind::Txid);
+            assert_eq!(db_labels[0].value, "hello again");
+            assert_eq!(db_labels[1].item, outpoint_str);
+            assert_eq!(db_labels[1].item_kind, DbLabelledKind::OutPoint);
+            assert_eq!(db_labels[1].value, "a coin");
+            *outpoint_labels.get_mut(&outpoint).unwrap() = None;
+            conn.update_labels(&outpoint_labels);
+
             // Now delete the label by passing a None value.
             *txids_labels.get_mut(&txid).unwrap() = None;
             conn.update_labels(&txids_labels);
             let db_labels = conn.db_labels(&items);
             assert!(db_labels.is_empty());
+            assert!(conn.db_list_labels().is_empty());
         }
 
         fs::remove_dir_all(tmp_dir).unwrap();
@@ -1811,9 +1936,26 @@ CREATE TABLE labels (
             let time = 174500;
             conn.confirm_coins(&[(coin_a.outpoint, height, time)]);
             let coins = conn.coins(&[], &[]);
-            assert_eq!(coins[0].block_info, Some(DbBlockInfo { height, time }));
+            assert_eq!(
+                coins[0].block_info,
+                Some(DbBlockInfo {
+                    height,
+                    time,
+                    prev_mtp: None
+                })
+            );
             assert!(coins[1].block_info.is_none());
 
+            // We can then record the median time past of the block preceding the one which
+            // confirmed it. It is reset if the coin gets confirmed again.
+            conn.set_coins_prev_mtp(&[(coin_a.outpoint, time - 600)]);
+            let coins = conn.coins(&[], &[coin_a.outpoint]);
+            assert_eq!(coins[0].block_info.unwrap().prev_mtp, Some(time - 600));
+            conn.confirm_coins(&[(coin_a.outpoint, height, time)]);
+            let coins = conn.coins(&[], &[coin_a.outpoint]);
+            assert!(coins[0].block_info.unwrap().prev_mtp.is_none());
+            conn.set_coins_prev_mtp(&[(coin_a.outpoint, time - 600)]);
+
             // Now if we spend one, it'll be marked as such.
             conn.spend_coins(&[(coin_a.outpoint, txs.get(2).unwrap().txid())]);
             let coin = conn
@@ -2074,6 +2216,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_095,
                         time: 1_111_899,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(100).unwrap(),
@@ -2088,6 +2231,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_099,
                         time: 1_121_899,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(1000).unwrap(),
@@ -2096,6 +2240,7 @@ CREATE TABLE labels (
                     spend_block: Some(BlockInfo {
                         height: 101_199,
                         time: 1_231_678,
+                        prev_mtp: None,
                     }),
                     is_from_self: false,
                 },
@@ -2105,6 +2250,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_100,
                         time: 1_131_899,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(10000).unwrap(),
@@ -2119,6 +2265,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_102,
                         time: 1_134_899,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(100000).unwrap(),
@@ -2127,6 +2274,7 @@ CREATE TABLE labels (
                     spend_block: Some(BlockInfo {
                         height: 101_105,
                         time: 1_201_678,
+                        prev_mtp: None,
                     }),
                     is_from_self: false,
                 },
@@ -2161,6 +2309,9 @@ CREATE TABLE labels (
             db_coins.sort_by(|c1, c2| c1.outpoint.vout.cmp(&c2.outpoint.vout));
             assert_eq!(&db_coins[..], &coins[..]);
 
+            // The expiry hook was executed for the first and fourth coins.
+            conn.mark_expiry_notified(&[coins[0].outpoint, coins[3].outpoint]);
+
             // Now that everything is settled, reorg to a previous height.
             let new_tip = BlockChainTip {
                 hash: bitcoin::BlockHash::from_str(
@@ -2204,6 +2355,11 @@ CREATE TABLE labels (
             coin.block_info = None;
             coin.spend_block = None;
             assert_eq!(db_coins[&coins[4].outpoint], coin);
+            // The fourth one may expire again at a different height, the first one can't.
+            assert_eq!(
+                conn.expiry_notified_coins().into_iter().collect::<Vec<_>>(),
+                vec![coins[0].outpoint]
+            );
         }
 
         fs::remove_dir_all(tmp_dir).unwrap();
@@ -2282,6 +2438,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_095,
                         time: 1_121_000,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(100).unwrap(),
@@ -2296,6 +2453,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_099,
                         time: 1_122_000,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(1000).unwrap(),
@@ -2304,6 +2462,7 @@ CREATE TABLE labels (
                     spend_block: Some(BlockInfo {
                         height: 101_199,
                         time: 1_123_000,
+                        prev_mtp: None,
                     }),
                     is_from_self: false,
                 },
@@ -2313,6 +2472,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_100,
                         time: 1_124_000,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(10000).unwrap(),
@@ -2327,6 +2487,7 @@ CREATE TABLE labels (
                     block_info: Some(BlockInfo {
                         height: 101_102,
                         time: 1_125_000,
+                        prev_mtp: None,
                     }),
                     amount: bitcoin::Amount::from_sat(98765),
                     derivation_index: bip32::ChildNumber::from_normal_idx(100000).unwrap(),
@@ -2335,6 +2496,7 @@ CREATE TABLE labels (
                     spend_block: Some(BlockInfo {
                         height: 101_105,
                         time: 1_126_000,
+                        prev_mtp: None,
                     }),
                     is_from_self: false,
                 },
@@ -2427,6 +2589,7 @@ CREATE TABLE labels (
                             Some(BlockInfo {
                                 height: (i % 5) as i32 * 2_000,
                                 time: 1722488619 + (i % 5) * 84_999,
+                                prev_mtp: None,
                             })
                         } else {
                             None
@@ -2451,6 +2614,7 @@ CREATE TABLE labels (
                         Some(BlockInfo {
                             height: (i % 100) as i32 * 1_000,
                             time: 1722408619 + (i % 100) as u32 * 42_000,
+                            prev_mtp: None,
                         })
                     } else {
                         None
@@ -2538,6 +2702,7 @@ CREATE TABLE labels (
                             block_info: block_info.map(|info| DbBlockInfo {
                                 height: info.height,
                                 time: info.time,
+                                prev_mtp: None,
                             }),
                         },
                     )
@@ -2913,7 +3078,7 @@ CREATE TABLE labels (
     }
 
     #[test]
-    fn v0_to_v8_migration() {
+    fn v0_to_v11_migration() {
         let secp = secp256k1::Secp256k1::verification_only();
 
         // Create a database with version 0, using the old schema.
@@ -3019,7 +3184,7 @@ CREATE TABLE labels (
         {
             let mut conn = db.connection().unwrap();
             let version = conn.db_version();
-            assert_eq!(version, 8);
+            assert_eq!(version, 11);
         }
         // We should now be able to insert another PSBT, to query both, and the first PSBT must
         // have no associated timestamp.
@@ -3090,11 +3255,40 @@ CREATE TABLE labels (
             assert_eq!(conn.db_wallet().last_poll_timestamp, Some(1234567));
         }
 
+        // In v9, we can store payjoin proposals apart from the Spend transactions. They are
+        // deleted along with the Spend transaction they were made for.
+        {
+            let mut conn = db.connection().unwrap();
+            let original_txid = first_psbt.unsigned_tx.txid();
+            let proposal_txid = second_psbt.unsigned_tx.txid();
+            conn.store_payjoin_proposal(&original_txid, &second_psbt);
+            let db_proposal = conn.db_payjoin_proposal(&proposal_txid).unwrap();
+            assert_eq!(db_proposal.original_txid, original_txid);
+            assert_eq!(db_proposal.psbt, second_psbt);
+            assert!(conn.db_payjoin_proposal(&original_txid).is_none());
+            conn.delete_spend(&original_txid);
+            assert!(conn.db_spend(&original_txid).is_none());
+            assert!(conn.db_payjoin_proposal(&proposal_txid).is_none());
+        }
+
+        // In v11, we can record the coins for which the expiry hook was executed.
+        {
+            let mut conn = db.connection().unwrap();
+            let outpoint = bitcoin::OutPoint::new(first_psbt.unsigned_tx.txid(), 0);
+            assert!(conn.expiry_notified_coins().is_empty());
+            conn.mark_expiry_notified(&[outpoint]);
+            conn.mark_expiry_notified(&[outpoint]);
+            assert_eq!(
+                conn.expiry_notified_coins().into_iter().collect::<Vec<_>>(),
+                vec![outpoint]
+            );
+        }
+
         fs::remove_dir_all(tmp_dir).unwrap();
     }
 
     #[test]
-    fn v3_to_v8_migration() {
+    fn v3_to_v11_migration() {
         let secp = secp256k1::Secp256k1::verification_only();
 
         // Create a database with version 3, using the old schema.
@@ -3143,11 +3337,13 @@ CREATE TABLE labels (
                 block_info: Some(DbBlockInfo {
                     height: 175500,
                     time: 1755001001,
+                    prev_mtp: None,
                 }),
                 spend_txid: Some(bitcoin_txs.get(7).unwrap().txid()),
                 spend_block: Some(DbBlockInfo {
                     height: 245500,
                     time: 1755003000,
+                    prev_mtp: None,
                 }),
             };
             let coin_b = DbCoinV3 {
@@ -3161,6 +3357,7 @@ CREATE TABLE labels (
                 block_info: Some(DbBlockInfo {
                     height: 175502,
                     time: 1755001032,
+                    prev_mtp: None,
                 }),
                 spend_txid: Some(coin_d_outpoint.txid),
                 spend_block: None,
@@ -3176,6 +3373,7 @@ CREATE TABLE labels (
                 block_info: Some(DbBlockInfo {
                     height: 175504,
                     time: 1755005032,
+                    prev_mtp: None,
                 }),
                 spend_txid: None,
                 spend_block: None,
@@ -3215,6 +3413,7 @@ CREATE TABLE labels (
                 block_info: Some(DbBlockInfo {
                     height: 176001,
                     time: 1755001004,
+                    prev_mtp: None,
                 }),
                 spend_txid: None,
                 spend_block: None,
@@ -3244,10 +3443,10 @@ CREATE TABLE labels (
 
             // Migrate the DB.
             maybe_apply_migration(&db_path, &bitcoin_txs).unwrap();
-            assert_eq!(conn.db_version(), 8);
+            assert_eq!(conn.db_version(), 11);
             // Migrating twice will be a no-op. No need to pass `bitcoin_txs` second time.
             maybe_apply_migration(&db_path, &[]).unwrap();
-            assert!(conn.db_version() == 8);
+            assert!(conn.db_version() == 11);
 
             // Compare the `DbCoin`s with the expected values.
             let coins_post = conn.coins(&[], &[]);
@@ -3314,6 +3513,7 @@ CREATE TABLE labels (
                         Some(DbBlockInfo {
                             height: 1 + (i % 5) as i32 * 2_000,
                             time: 1722488619 + (i % 5) * 84_999,
+                            prev_mtp: None,
                         })
                     } else {
                         None
@@ -3342,6 +3542,7 @@ CREATE TABLE labels (
                     Some(DbBlockInfo {
                         height: 1 + (i % 100) as i32 * 1_000,
                         time: 1722408619 + (i % 100) as u32 * 42_000,
+                        prev_mtp: None,
                     })
                 } else {
                     None
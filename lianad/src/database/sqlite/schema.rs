@@ -51,6 +51,10 @@ CREATE TABLE wallets (
  * The `is_from_self` field indicates if the coin is the output of a transaction whose
  * inputs are all from the same wallet as the coin. For an unconfirmed coin, this also
  * means that all unconfirmed ancestors, if any, are from self.
+ *
+ * The 'block_prev_mtp' is the median time past of the block preceding the one which
+ * confirmed the coin, from which its time-based relative timelocks are counted (BIP68).
+ * It may be missing for a confirmed coin until it is queried from the Bitcoin backend.
  */
 CREATE TABLE coins (
     id INTEGER PRIMARY KEY NOT NULL,
@@ -67,6 +71,7 @@ CREATE TABLE coins (
     spend_block_time INTEGER,
     is_immature BOOLEAN NOT NULL CHECK (is_immature IN (0,1)),
     is_from_self BOOLEAN NOT NULL DEFAULT 0 CHECK (is_from_self IN (0,1)),
+    block_prev_mtp INTEGER,
     UNIQUE (txid, vout),
     FOREIGN KEY (wallet_id) REFERENCES wallets (id)
         ON UPDATE RESTRICT
@@ -106,6 +111,27 @@ CREATE TABLE spend_transactions (
     updated_at INTEGER
 );
 
+/* The payjoin (BIP78) proposals from the receivers of our Spend transactions. They spend inputs
+ * which aren't ours so they are kept apart from the Spend transactions. The 'original_txid' is the
+ * txid of the Spend transaction the proposal was made for.
+ */
+CREATE TABLE payjoin_proposals (
+    id INTEGER PRIMARY KEY NOT NULL,
+    original_txid BLOB NOT NULL,
+    psbt BLOB UNIQUE NOT NULL,
+    txid BLOB UNIQUE NOT NULL
+);
+
+/* The coins for which the expiry hook was successfully executed, so it isn't executed again for
+ * them. An entry is removed if the coin gets unconfirmed, as its expiry changes.
+ */
+CREATE TABLE expiry_notified_coins (
+    id INTEGER PRIMARY KEY NOT NULL,
+    txid BLOB NOT NULL,
+    vout INTEGER NOT NULL,
+    UNIQUE (txid, vout)
+);
+
 /* Labels applied on addresses (0), outpoints (1), txids (2) */
 CREATE TABLE labels (
     id INTEGER PRIMARY KEY NOT NULL,
@@ -193,6 +219,9 @@ impl TryFrom<&rusqlite::Row<'_>> for DbWallet {
 pub struct DbBlockInfo {
     pub height: i32,
     pub time: u32,
+    /// The median time past of the previous block. Only recorded for the block confirming a coin,
+    /// once it was queried from the Bitcoin backend.
+    pub prev_mtp: Option<u32>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -226,9 +255,11 @@ impl TryFrom<&rusqlite::Row<'_>> for DbCoin {
         let block_height: Option<i32> = row.get(2)?;
         let block_time: Option<u32> = row.get(3)?;
         assert_eq!(block_height.is_none(), block_time.is_none());
+        let block_prev_mtp: Option<u32> = row.get(14)?;
         let block_info = block_height.map(|height| DbBlockInfo {
             height,
             time: block_time.expect("Must be there if height is"),
+            prev_mtp: block_prev_mtp,
         });
         let txid: Vec<u8> = row.get(4)?;
         let txid: bitcoin::Txid = encode::deserialize(&txid).expect("We only store valid txids");
@@ -250,6 +281,7 @@ impl TryFrom<&rusqlite::Row<'_>> for DbCoin {
         let spend_block = spend_height.map(|height| DbBlockInfo {
             height,
             time: spend_time.expect("Must be there if height is"),
+            prev_mtp: None,
         });
 
         let is_immature: bool = row.get(12)?;
@@ -302,6 +334,41 @@ impl TryFrom<&rusqlite::Row<'_>> for DbAddress {
     }
 }
 
+/// A row in the "payjoin_proposals" table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbPayjoinProposal {
+    pub id: i64,
+    pub original_txid: bitcoin::Txid,
+    pub psbt: Psbt,
+    pub txid: bitcoin::Txid,
+}
+
+impl TryFrom<&rusqlite::Row<'_>> for DbPayjoinProposal {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row) -> Result<Self, Self::Error> {
+        let id: i64 = row.get(0)?;
+
+        let original_txid: Vec<u8> = row.get(1)?;
+        let original_txid: bitcoin::Txid =
+            encode::deserialize(&original_txid).expect("We only store valid txids");
+
+        let psbt: Vec<u8> = row.get(2)?;
+        let psbt = Psbt::deserialize(&psbt).expect("We only store valid PSBTs");
+
+        let txid: Vec<u8> = row.get(3)?;
+        let txid: bitcoin::Txid = encode::deserialize(&txid).expect("We only store valid txids");
+        assert_eq!(txid, psbt.unsigned_tx.txid());
+
+        Ok(DbPayjoinProposal {
+            id,
+            original_txid,
+            psbt,
+            txid,
+        })
+    }
+}
+
 /// A row in the "spend_transactions" table
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DbSpendTransaction {
@@ -406,6 +473,7 @@ impl TryFrom<&rusqlite::Row<'_>> for DbWalletTransaction {
         let block_info = block_height.map(|height| DbBlockInfo {
             height,
             time: block_time.expect("Must be there if height is"),
+            prev_mtp: None,
         });
 
         Ok(DbWalletTransaction {
@@ -487,6 +487,58 @@ fn migrate_v7_to_v8(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError
 /// did not store the Bitcoin transactions in database, to versions 5 and later, which do. For a
 /// migration from v4 or earlier to v5 or later it is assumed the caller passes *all* necessary
 /// transactions, otherwise the migration will fail.
+fn migrate_v8_to_v9(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE payjoin_proposals (
+                id INTEGER PRIMARY KEY NOT NULL,
+                original_txid BLOB NOT NULL,
+                psbt BLOB UNIQUE NOT NULL,
+                txid BLOB UNIQUE NOT NULL
+            );
+
+            UPDATE version SET version = 9;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v9_to_v10(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            ALTER TABLE coins ADD COLUMN block_prev_mtp INTEGER;
+
+            UPDATE version SET version = 10;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v10_to_v11(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE expiry_notified_coins (
+                id INTEGER PRIMARY KEY NOT NULL,
+                txid BLOB NOT NULL,
+                vout INTEGER NOT NULL,
+                UNIQUE (txid, vout)
+            );
+
+            UPDATE version SET version = 11;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
 pub fn maybe_apply_migration(
     db_path: &path::Path,
     bitcoin_txs: &[bitcoin::Transaction],
@@ -545,6 +597,21 @@ pub fn maybe_apply_migration(
                 migrate_v7_to_v8(&mut conn)?;
                 log::warn!("Migration from database version 7 to version 8 successful.");
             }
+            8 => {
+                log::warn!("Upgrading database from version 8 to version 9.");
+                migrate_v8_to_v9(&mut conn)?;
+                log::warn!("Migration from database version 8 to version 9 successful.");
+            }
+            9 => {
+                log::warn!("Upgrading database from version 9 to version 10.");
+                migrate_v9_to_v10(&mut conn)?;
+                log::warn!("Migration from database version 9 to version 10 successful.");
+            }
+            10 => {
+                log::warn!("Upgrading database from version 10 to version 11.");
+                migrate_v10_to_v11(&mut conn)?;
+                log::warn!("Migration from database version 10 to version 11 successful.");
+            }
             _ => return Err(SqliteDbError::UnsupportedVersion(version)),
         }
     }
@@ -1,8 +1,9 @@
 use crate::{
-    commands::{CoinStatus, LabelItem},
+    commands::{backup::WalletBackup, bip329, CoinStatus, LabelItem},
     jsonrpc::rpc::{Error, Params, Request, Response},
-    DaemonControl,
+    DaemonControl, WalletControls,
 };
+use liana::{bip21::PaymentUri, descriptors::LianaDescriptor, spend::CoinSelectionStrategy};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -10,23 +11,44 @@ use std::{
     str::FromStr,
 };
 
-use miniscript::bitcoin::{self, psbt::Psbt, Txid};
+use miniscript::bitcoin::{self, bip32, hashes::hex::FromHex, psbt::Psbt, Txid};
+
+// A destination of a Spend, given either as an address or as a payment URI. The value to pay may
+// be left null if the payment URI specifies an amount.
+fn destination_uri(dest: &str, value: &serde_json::Value) -> Result<PaymentUri, Error> {
+    let invalid = || Error::invalid_params(format!("Invalid 'destinations.{}' parameter.", dest));
+    let mut uri = if PaymentUri::is_uri(dest) {
+        PaymentUri::from_str(dest).map_err(|e| {
+            Error::invalid_params(format!("Invalid 'destinations.{}' parameter: {}", dest, e))
+        })?
+    } else {
+        PaymentUri::new(bitcoin::Address::from_str(dest).map_err(|_| invalid())?)
+    };
+    if !value.is_null() {
+        let amount = value
+            .as_u64()
+            .map(bitcoin::Amount::from_sat)
+            .ok_or_else(invalid)?;
+        if uri.amount.map_or(false, |uri_amount| uri_amount != amount) {
+            return Err(Error::invalid_params(format!(
+                "Invalid 'destinations.{}' parameter: value doesn't match the payment URI amount.",
+                dest
+            )));
+        }
+        uri.amount = Some(amount);
+    }
+    Ok(uri)
+}
 
 fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let destinations = params
         .get(0, "destinations")
         .ok_or_else(|| Error::invalid_params("Missing 'destinations' parameter."))?
         .as_object()
-        .and_then(|obj| {
-            obj.into_iter()
-                .map(|(k, v)| {
-                    let addr = bitcoin::Address::from_str(k).ok()?;
-                    let amount: u64 = v.as_i64()?.try_into().ok()?;
-                    Some((addr, amount))
-                })
-                .collect::<Option<HashMap<bitcoin::Address<bitcoin::address::NetworkUnchecked>, u64>>>()
-        })
-        .ok_or_else(|| Error::invalid_params("Invalid 'destinations' parameter."))?;
+        .ok_or_else(|| Error::invalid_params("Invalid 'destinations' parameter."))?
+        .into_iter()
+        .map(|(k, v)| destination_uri(k, v))
+        .collect::<Result<Vec<PaymentUri>, Error>>()?;
     let outpoints = params
         .get(1, "outpoints")
         .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?
@@ -41,11 +63,39 @@ fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
                 .collect::<Option<Vec<bitcoin::OutPoint>>>()
         })
         .ok_or_else(|| Error::invalid_params("Invalid 'outpoints' parameter."))?;
-    let feerate: u64 = params
+    // The feerate may be replaced by a confirmation target, in which case it is estimated by the
+    // Bitcoin backend. A null value is treated as absent for callers using positional parameters.
+    let feerate = params
         .get(2, "feerate")
-        .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?
-        .as_u64()
-        .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?;
+        .filter(|f| !f.is_null())
+        .map(|f| {
+            f.as_u64()
+                .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))
+        })
+        .transpose()?;
+    let conf_target = params
+        .get(4, "conf_target")
+        .filter(|t| !t.is_null())
+        .map(|t| {
+            t.as_u64()
+                .and_then(|t| t.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'conf_target' parameter."))
+        })
+        .transpose()?;
+    let feerate: u64 = match (feerate, conf_target) {
+        (Some(feerate), None) => feerate,
+        (None, Some(conf_target)) => control.estimate_fee(conf_target)?.feerate,
+        (None, None) => {
+            return Err(Error::invalid_params(
+                "Missing 'feerate' or 'conf_target' parameter.",
+            ))
+        }
+        (Some(_), Some(_)) => {
+            return Err(Error::invalid_params(
+                "Only one of 'feerate' and 'conf_target' may be given.",
+            ))
+        }
+    };
     let change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>> = params
         .get(3, "change_address")
         .map(|addr| {
@@ -57,8 +107,116 @@ fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
             })
         })
         .transpose()?;
+    let coin_selection = params
+        .get(5, "coin_selection")
+        .filter(|s| !s.is_null())
+        .map(|s| {
+            s.as_str()
+                .and_then(CoinSelectionStrategy::from_arg)
+                .ok_or_else(|| Error::invalid_params("Invalid 'coin_selection' parameter."))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let res = control.create_spend_to_uris(
+        &destinations,
+        &outpoints,
+        feerate,
+        change_address,
+        coin_selection,
+    )?;
+    Ok(serde_json::json!(&res))
+}
+
+fn get_new_address(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let amount = params
+        .get(0, "amount")
+        .filter(|a| !a.is_null())
+        .map(|a| {
+            a.as_u64()
+                .map(bitcoin::Amount::from_sat)
+                .ok_or_else(|| Error::invalid_params("Invalid 'amount' parameter."))
+        })
+        .transpose()?;
+    let label = params
+        .get(1, "label")
+        .filter(|l| !l.is_null())
+        .map(|l| {
+            l.as_str()
+                .map(|l| l.to_string())
+                .ok_or_else(|| Error::invalid_params("Invalid 'label' parameter."))
+        })
+        .transpose()?;
+    let message = params
+        .get(2, "message")
+        .filter(|m| !m.is_null())
+        .map(|m| {
+            m.as_str()
+                .map(|m| m.to_string())
+                .ok_or_else(|| Error::invalid_params("Invalid 'message' parameter."))
+        })
+        .transpose()?;
+
+    // Only return a payment URI if any of its parameters was given.
+    let res = if amount.is_none() && label.is_none() && message.is_none() {
+        control.get_new_address()
+    } else {
+        control.get_new_address_uri(amount, label, message)
+    };
+    Ok(serde_json::json!(&res))
+}
+
+fn estimate_fee(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let conf_target: u16 = params
+        .get(0, "conf_target")
+        .ok_or_else(|| Error::invalid_params("Missing 'conf_target' parameter."))?
+        .as_u64()
+        .and_then(|t| t.try_into().ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'conf_target' parameter."))?;
+
+    let res = control.estimate_fee(conf_target)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn get_policy(control: &DaemonControl, params: Option<Params>) -> Result<serde_json::Value, Error> {
+    let feerate = params
+        .as_ref()
+        .and_then(|p| p.get(0, "feerate"))
+        .filter(|f| !f.is_null())
+        .map(|f| {
+            f.as_u64()
+                .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))
+        })
+        .transpose()?;
+    Ok(serde_json::json!(&control.get_policy(feerate)?))
+}
+
+fn lint_descriptor(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let descriptor = params
+        .as_ref()
+        .and_then(|p| p.get(0, "descriptor"))
+        .filter(|d| !d.is_null())
+        .map(|d| {
+            d.as_str()
+                .and_then(|d| LianaDescriptor::from_str(d).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'descriptor' parameter."))
+        })
+        .transpose()?;
+    Ok(serde_json::json!(&control.lint_descriptor(descriptor)))
+}
 
-    let res = control.create_spend(&destinations, &outpoints, feerate, change_address)?;
+fn refresh_coins(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let feerate: u64 = params
+        .get(0, "feerate")
+        .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?
+        .as_u64()
+        .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?;
+    let threshold = get_opt_u32(&Some(params), 1, "threshold")?;
+
+    let res = control.refresh_coins(threshold, feerate)?;
     Ok(serde_json::json!(&res))
 }
 
@@ -74,6 +232,71 @@ fn update_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
     Ok(serde_json::json!({}))
 }
 
+fn add_spend_preimage(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    let preimage = params
+        .get(1, "preimage")
+        .ok_or_else(|| Error::invalid_params("Missing 'preimage' parameter."))?
+        .as_str()
+        .and_then(|s| Vec::<u8>::from_hex(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'preimage' parameter."))?;
+    control.add_spend_preimage(&txid, &preimage)?;
+
+    Ok(serde_json::json!({}))
+}
+
+fn sign_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    let res = control.sign_spend(&txid)?;
+
+    Ok(serde_json::json!(&res))
+}
+
+fn external_signer_fingerprint(params: &Params) -> Result<bip32::Fingerprint, Error> {
+    params
+        .get(0, "fingerprint")
+        .ok_or_else(|| Error::invalid_params("Missing 'fingerprint' parameter."))?
+        .as_str()
+        .and_then(|s| bip32::Fingerprint::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'fingerprint' parameter."))
+}
+
+fn get_external_xpub(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let fingerprint = external_signer_fingerprint(&params)?;
+    let derivation_path = params
+        .get(1, "derivation_path")
+        .ok_or_else(|| Error::invalid_params("Missing 'derivation_path' parameter."))?
+        .as_str()
+        .and_then(|s| bip32::DerivationPath::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'derivation_path' parameter."))?;
+    let res = control.get_external_xpub(fingerprint, &derivation_path)?;
+
+    Ok(serde_json::json!(&res))
+}
+
+fn display_address(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let fingerprint = external_signer_fingerprint(&params)?;
+    let address = params
+        .get(1, "address")
+        .ok_or_else(|| Error::invalid_params("Missing 'address' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Address::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'address' parameter."))?;
+    control.display_address(fingerprint, address)?;
+
+    Ok(serde_json::json!({}))
+}
+
 fn delete_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let txid = params
         .get(0, "txid")
@@ -98,6 +321,55 @@ fn broadcast_spend(control: &DaemonControl, params: Params) -> Result<serde_json
     Ok(serde_json::json!({}))
 }
 
+fn request_payjoin(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    let uri = params
+        .get(1, "uri")
+        .ok_or_else(|| Error::invalid_params("Missing 'uri' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'uri' parameter."))?;
+    let uri = PaymentUri::from_str(uri)
+        .map_err(|e| Error::invalid_params(format!("Invalid 'uri' parameter: {}", e)))?;
+    let res = control.request_payjoin(&txid, &uri)?;
+
+    Ok(serde_json::json!(&res))
+}
+
+fn create_payjoin_proposal(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let original: Psbt = params
+        .get(0, "original")
+        .ok_or_else(|| Error::invalid_params("Missing 'original' parameter."))?
+        .as_str()
+        .and_then(|s| Psbt::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'original' parameter."))?;
+    let res = control.create_payjoin_proposal(&original)?;
+
+    Ok(serde_json::json!(&res))
+}
+
+fn finalize_payjoin_proposal(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let psbt: Psbt = params
+        .get(0, "psbt")
+        .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?
+        .as_str()
+        .and_then(|s| Psbt::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'psbt' parameter."))?;
+    let res = control.finalize_payjoin_proposal(psbt)?;
+
+    Ok(serde_json::json!(&res))
+}
+
 fn rbf_psbt(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let txid = params
         .get(0, "txid")
@@ -188,6 +460,14 @@ where
     )
 }
 
+fn list_expiring_coins(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let threshold = get_opt_u32(&params, 0, "threshold")?;
+    Ok(serde_json::json!(&control.list_expiring_coins(threshold)))
+}
+
 fn list_addresses(
     control: &DaemonControl,
     params: Option<Params>,
@@ -292,14 +572,48 @@ fn create_recovery(control: &DaemonControl, params: Params) -> Result<serde_json
         .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?;
     let timelock: Option<u16> = params
         .get(2, "timelock")
+        .filter(|tl| !tl.is_null())
         .map(|tl| {
             tl.as_u64()
                 .and_then(|tl| tl.try_into().ok())
                 .ok_or_else(|| Error::invalid_params("Invalid 'timelock' parameter."))
         })
         .transpose()?;
+    // The recovery path may instead be one behind an absolute timelock.
+    let absolute_timelock: Option<u32> = params
+        .get(3, "absolute_timelock")
+        .filter(|tl| !tl.is_null())
+        .map(|tl| {
+            tl.as_u64()
+                .and_then(|tl| tl.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'absolute_timelock' parameter."))
+        })
+        .transpose()?;
+    // Or one behind a time-based relative timelock, in 512-second intervals.
+    let time_timelock: Option<u16> = params
+        .get(4, "time_timelock")
+        .filter(|tl| !tl.is_null())
+        .map(|tl| {
+            tl.as_u64()
+                .and_then(|tl| tl.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'time_timelock' parameter."))
+        })
+        .transpose()?;
 
-    let res = control.create_recovery(address, feerate, timelock)?;
+    let res = match (timelock, absolute_timelock, time_timelock) {
+        (timelock, None, None) => control.create_recovery(address, feerate, timelock)?,
+        (None, Some(_), None) => {
+            control.create_absolute_recovery(address, feerate, absolute_timelock)?
+        }
+        (None, None, Some(time_timelock)) => {
+            control.create_time_recovery(address, feerate, time_timelock)?
+        }
+        _ => {
+            return Err(Error::invalid_params(
+                "Only one of 'timelock', 'absolute_timelock' and 'time_timelock' may be given.",
+            ))
+        }
+    };
     Ok(serde_json::json!(&res))
 }
 
@@ -364,8 +678,55 @@ fn get_labels(control: &DaemonControl, params: Params) -> Result<serde_json::Val
     Ok(serde_json::json!(control.get_labels(&items)))
 }
 
+fn import_labels(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let jsonl = params
+        .get(0, "bip329")
+        .ok_or_else(|| Error::invalid_params("Missing 'bip329' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'bip329' parameter."))?;
+    let records = bip329::from_jsonl(jsonl).map_err(|e| Error::invalid_params(e.to_string()))?;
+    let res = control.import_labels(&records)?;
+
+    Ok(serde_json::json!(res))
+}
+
+fn restore_backup(control: &mut DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let backup = params
+        .get(0, "backup")
+        .ok_or_else(|| Error::invalid_params("Missing 'backup' parameter."))?;
+    let backup: WalletBackup = serde_json::from_value(backup.clone())
+        .map_err(|e| Error::invalid_params(format!("Invalid 'backup' parameter: {}", e)))?;
+    control.restore_backup(&backup)?;
+
+    Ok(serde_json::json!({}))
+}
+
+fn export_transactions(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let start = get_opt_u32(&params, 0, "start")?.unwrap_or(0);
+    let end = get_opt_u32(&params, 1, "end")?.unwrap_or(u32::MAX);
+
+    Ok(serde_json::json!(&control.export_transactions(start, end)))
+}
+
 /// Handle an incoming JSONRPC2 request.
-pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Response, Error> {
+pub fn handle_request(controls: &mut WalletControls, req: Request) -> Result<Response, Error> {
+    // Listing the wallets is the only command which isn't specific to a wallet.
+    if req.method == "listwallets" {
+        return Ok(Response::success(
+            req.id,
+            serde_json::json!(&controls.list_wallets()),
+        ));
+    }
+    let control = match req.wallet.as_deref() {
+        Some(name) => controls
+            .get_mut(Some(name))
+            .ok_or_else(|| Error::invalid_params(format!("Unknown wallet '{}'.", name)))?,
+        None => controls.get_mut(None).expect("Main wallet always exists"),
+    };
+
     let result = match req.method.as_str() {
         "broadcastspend" => {
             let params = req
@@ -382,7 +743,7 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
         "createspend" => {
             let params = req.params.ok_or_else(|| {
                 Error::invalid_params(
-                    "Missing 'outpoints', 'destinations' and 'feerate' parameters.",
+                    "Missing 'outpoints', 'destinations' and 'feerate' or 'conf_target' parameters.",
                 )
             })?;
             create_spend(control, params)?
@@ -393,18 +754,57 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
                 .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
             delete_spend(control, params)?
         }
+        "requestpayjoin" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'txid' and 'uri' parameters."))?;
+            request_payjoin(control, params)?
+        }
+        "createpayjoinproposal" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'original' parameter."))?;
+            create_payjoin_proposal(control, params)?
+        }
+        "finalizepayjoinproposal" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?;
+            finalize_payjoin_proposal(control, params)?
+        }
         "rbfpsbt" => {
             let params = req.params.ok_or_else(|| {
                 Error::invalid_params("Missing 'txid', 'feerate' and 'is_cancel' parameters.")
             })?;
             rbf_psbt(control, params)?
         }
+        "estimatefee" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'conf_target' parameter."))?;
+            estimate_fee(control, params)?
+        }
+        "refreshcoins" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?;
+            refresh_coins(control, params)?
+        }
         "getinfo" => serde_json::json!(&control.get_info()),
-        "getnewaddress" => serde_json::json!(&control.get_new_address()),
+        "getpolicy" => get_policy(control, req.params)?,
+        "lintdescriptor" => lint_descriptor(control, req.params)?,
+        "getnewaddress" => match req.params {
+            Some(params) => get_new_address(control, params)?,
+            None => serde_json::json!(&control.get_new_address()),
+        },
         "listcoins" => {
             let params = req.params;
             list_coins(control, params)?
         }
+        "listexpiringcoins" => {
+            let params = req.params;
+            list_expiring_coins(control, params)?
+        }
         "listaddresses" => {
             let params = req.params;
             list_addresses(control, params)?
@@ -439,6 +839,31 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
                 .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?;
             update_spend(control, params)?
         }
+        "addspendpreimage" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'txid' and 'preimage' parameters.")
+            })?;
+            add_spend_preimage(control, params)?
+        }
+        "signspend" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
+            sign_spend(control, params)?
+        }
+        "listexternalsigners" => serde_json::json!(&control.list_external_signers()?),
+        "getexternalxpub" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'fingerprint' and 'derivation_path' parameters.")
+            })?;
+            get_external_xpub(control, params)?
+        }
+        "displayaddress" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'fingerprint' and 'address' parameters.")
+            })?;
+            display_address(control, params)?
+        }
         "updatelabels" => {
             let params = req
                 .params
@@ -451,6 +876,24 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
                 .ok_or_else(|| Error::invalid_params("Missing 'items' parameter."))?;
             get_labels(control, params)?
         }
+        "exportlabels" => serde_json::json!(&control.export_labels()),
+        "importlabels" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'bip329' parameter."))?;
+            import_labels(control, params)?
+        }
+        "exporttransactions" => {
+            let params = req.params;
+            export_transactions(control, params)?
+        }
+        "createbackup" => serde_json::json!(&control.create_backup()),
+        "restorebackup" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'backup' parameter."))?;
+            restore_backup(control, params)?
+        }
         _ => {
             return Err(Error::method_not_found());
         }
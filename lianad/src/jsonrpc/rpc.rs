@@ -46,11 +46,17 @@ pub struct Request {
     pub params: Option<Params>,
     /// Request identifier.
     pub id: ReqId,
+    /// Name of the wallet this request is for. The main wallet if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<String>,
 }
 
 /// A failure to broadcast a transaction to the P2P network.
 const BROADCAST_ERROR: i64 = 1_000;
 
+/// A failure to perform a payjoin with another party.
+const PAYJOIN_ERROR: i64 = 1_001;
+
 /// JSONRPC2 error codes. See https://www.jsonrpc.org/specification#error_object.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ErrorCode {
@@ -163,15 +169,40 @@ impl From<commands::CommandError> for Error {
             | commands::CommandError::InvalidDerivationIndex
             | commands::CommandError::RbfError(..)
             | commands::CommandError::EmptyFilterList
+            | commands::CommandError::InvalidLabelReference(..)
+            | commands::CommandError::LabelTooLong(..)
+            | commands::CommandError::UnsupportedBackupVersion(..)
+            | commands::CommandError::BackupMismatch(..)
+            | commands::CommandError::InvalidConfTarget(..)
+            | commands::CommandError::FeeEstimationUnavailable(..)
+            | commands::CommandError::NoCoinToRefresh(..)
+            | commands::CommandError::NoPayjoinEndpoint
+            | commands::CommandError::NoPayjoinContribution
+            | commands::CommandError::NoPaymentAmount(..)
+            | commands::CommandError::DuplicateDestination(..)
+            | commands::CommandError::UnknownAbsoluteTimelock(..)
+            | commands::CommandError::UnknownPreimage
+            | commands::CommandError::SigningDisabled
+            | commands::CommandError::NoSigner
+            | commands::CommandError::NoExternalSigner
+            | commands::CommandError::UnknownExternalSigner(..)
+            | commands::CommandError::UnknownAddress(..)
             | commands::CommandError::RecoveryNotAvailable => {
                 Error::new(ErrorCode::InvalidParams, e.to_string())
             }
-            commands::CommandError::RescanTrigger(..) => {
+            commands::CommandError::RescanTrigger(..)
+            | commands::CommandError::HotSigner(..)
+            | commands::CommandError::ExternalSigner(..) => {
                 Error::new(ErrorCode::InternalError, e.to_string())
             }
             commands::CommandError::TxBroadcast(_) => {
                 Error::new(ErrorCode::ServerError(BROADCAST_ERROR), e.to_string())
             }
+            commands::CommandError::Payjoin(_)
+            | commands::CommandError::PayjoinRequest(_)
+            | commands::CommandError::OriginalNotBroadcastable(_) => {
+                Error::new(ErrorCode::ServerError(PAYJOIN_ERROR), e.to_string())
+            }
         }
     }
 }
@@ -6,17 +6,17 @@ use std::{
     sync::{atomic::AtomicBool, Arc},
 };
 
-use crate::DaemonControl;
+use crate::WalletControls;
 
 #[cfg(unix)]
 pub fn run(
     socket_path: &path::Path,
-    daemon_control: DaemonControl,
+    wallet_controls: WalletControls,
     shutdown: Arc<AtomicBool>,
 ) -> Result<(), io::Error> {
     let listener = unix::rpcserver_setup(socket_path)?;
     log::info!("JSONRPC server started.");
-    let res = unix::rpcserver_loop(listener, daemon_control, shutdown);
+    let res = unix::rpcserver_loop(listener, wallet_controls, shutdown);
     log::info!("JSONRPC server stopped.");
     res
 }
@@ -24,7 +24,7 @@ pub fn run(
 #[cfg(windows)]
 pub fn run(
     _socket_path: &path::Path,
-    _daemon_control: DaemonControl,
+    _wallet_controls: WalletControls,
     _shutdown: Arc<AtomicBool>,
 ) -> Result<(), io::Error> {
     todo!("Implement a json rpc server over Named pipe");
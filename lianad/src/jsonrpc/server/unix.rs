@@ -8,7 +8,7 @@ use crate::{
         api,
         rpc::{Request, Response},
     },
-    DaemonControl,
+    WalletControls,
 };
 
 use std::{
@@ -84,7 +84,7 @@ fn read_command(
 
 // Handle all messages from this connection.
 fn connection_handler(
-    mut control: DaemonControl,
+    mut controls: WalletControls,
     mut stream: net::UnixStream,
     shutdown: sync::Arc<atomic::AtomicBool>,
 ) -> Result<(), io::Error> {
@@ -109,7 +109,7 @@ fn connection_handler(
 
         log::trace!("JSONRPC request: {:?}", serde_json::to_string(&req));
         let response =
-            api::handle_request(&mut control, req).unwrap_or_else(|e| Response::error(req_id, e));
+            api::handle_request(&mut controls, req).unwrap_or_else(|e| Response::error(req_id, e));
         log::trace!("JSONRPC response: {:?}", serde_json::to_string(&response));
         if let Err(e) = serde_json::to_writer(&stream, &response) {
             log::error!("Error writing response: '{}'", e);
@@ -120,11 +120,10 @@ fn connection_handler(
     Ok(())
 }
 
-// FIXME: have a decent way to share the DaemonControl between connections. Maybe make it Clone?
 /// The main event loop. Wait for connections, and treat requests sent through them.
 pub fn rpcserver_loop(
     listener: net::UnixListener,
-    daemon_control: DaemonControl,
+    wallet_controls: WalletControls,
     shutdown: sync::Arc<atomic::AtomicBool>,
 ) -> Result<(), io::Error> {
     // Keep it simple. We don't need great performances so just treat each connection in
@@ -151,12 +150,12 @@ pub fn rpcserver_loop(
         thread::Builder::new()
             .name(format!("liana-jsonrpc-{}", handler_id))
             .spawn({
-                let control = daemon_control.clone();
+                let controls = wallet_controls.clone();
                 let counter = connections_counter.clone();
                 let shutdown = shutdown.clone();
 
                 move || {
-                    if let Err(e) = connection_handler(control, connection, shutdown) {
+                    if let Err(e) = connection_handler(controls, connection, shutdown) {
                         log::error!("Error while handling connection {}: '{}'", handler_id, e);
                     } else {
                         log::trace!("Connection {} terminated without error.", handler_id);
@@ -390,6 +389,7 @@ mod tests {
             method: "dummy".to_string(),
             params: Some(Params::Map(params)),
             id: ReqId::Num(0),
+            wallet: None,
         };
         write_messages(&socket_path, &[&serde_json::to_vec(&req).unwrap(), b"\n"]);
         let read_req = t.join().unwrap().unwrap();
@@ -421,6 +421,7 @@ mod tests {
             method: "stop".to_string(),
             params: None,
             id: ReqId::Num(0),
+            wallet: None,
         };
         write_messages(
             &socket_path,
@@ -21,7 +21,7 @@ use crate::{
     config::Config,
     database::{
         sqlite::{FreshDbOptions, SqliteDb, SqliteDbError, MAX_DB_VERSION_NO_TX_DB},
-        DatabaseInterface,
+        DatabaseInterface, LabelItem,
     },
 };
 
@@ -31,7 +31,7 @@ use std::{
     thread,
 };
 
-use miniscript::bitcoin::{constants::ChainHash, hashes::Hash, secp256k1, BlockHash};
+use miniscript::bitcoin::{constants::ChainHash, hashes::Hash, secp256k1, BlockHash, Txid};
 
 #[cfg(not(test))]
 use std::panic;
@@ -187,6 +187,17 @@ fn create_datadir(datadir_path: &path::Path) -> Result<(), StartupError> {
     };
 }
 
+// The data directory of an additional wallet, under the data directory of the main one.
+fn wallet_data_dir(data_dir: &path::Path, wallet_name: &str) -> path::PathBuf {
+    [
+        data_dir,
+        path::Path::new("wallets"),
+        path::Path::new(wallet_name),
+    ]
+    .iter()
+    .collect()
+}
+
 // Connect to the SQLite database. Create it if starting fresh, and do some sanity checks.
 // If all went well, returns the interface to the SQLite database.
 fn setup_sqlite(
@@ -272,7 +283,11 @@ fn setup_bitcoind(
     let bitcoind = BitcoinD::new(bitcoind_config, wo_path_str)?;
     bitcoind.node_sanity_checks(
         config.bitcoin_config.network,
-        config.main_descriptor.is_taproot(),
+        config.main_descriptor.is_taproot()
+            || config
+                .wallets
+                .iter()
+                .any(|wallet| wallet.descriptor.is_taproot()),
     )?;
     if fresh_data_dir {
         log::info!("Creating a new watchonly wallet on bitcoind.");
@@ -288,6 +303,10 @@ fn setup_bitcoind(
     bitcoind.maybe_load_watchonly_wallet()?;
     bitcoind.wallet_sanity_checks(&config.main_descriptor)?;
     log::info!("Watchonly wallet loaded on bitcoind and sanity checked.");
+    // The descriptors of the additional wallets are tracked by the same watchonly wallet.
+    for wallet in &config.wallets {
+        bitcoind.maybe_import_descriptor(&wallet.descriptor)?;
+    }
 
     Ok(bitcoind)
 }
@@ -357,6 +376,9 @@ fn setup_electrum(
     Ok(electrum)
 }
 
+// The labels of the outputs of a Spend transaction we created, to be set once it's stored.
+type PendingLabels = collections::HashMap<Txid, collections::HashMap<LabelItem, Option<String>>>;
+
 #[derive(Clone)]
 pub struct DaemonControl {
     config: Config,
@@ -365,6 +387,7 @@ pub struct DaemonControl {
     // FIXME: Should we require Sync on DatabaseInterface rather than using a Mutex?
     db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
     secp: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    pending_labels: sync::Arc<sync::Mutex<PendingLabels>>,
 }
 
 impl DaemonControl {
@@ -381,6 +404,7 @@ impl DaemonControl {
             poller_sender,
             db,
             secp,
+            pending_labels: sync::Arc::new(sync::Mutex::new(collections::HashMap::new())),
         }
     }
 
@@ -391,6 +415,39 @@ impl DaemonControl {
     }
 }
 
+/// The `DaemonControl` of each of the wallets managed by the daemon.
+#[derive(Clone)]
+pub(crate) struct WalletControls {
+    main: DaemonControl,
+    others: collections::BTreeMap<String, DaemonControl>,
+}
+
+impl WalletControls {
+    /// Get the control of the wallet with this name. The main wallet is selected by default.
+    pub fn get_mut(&mut self, name: Option<&str>) -> Option<&mut DaemonControl> {
+        match name {
+            None | Some(config::MAIN_WALLET_NAME) => Some(&mut self.main),
+            Some(name) => self.others.get_mut(name),
+        }
+    }
+
+    /// List the wallets managed by the daemon, the main wallet first.
+    pub fn list_wallets(&self) -> commands::ListWalletsResult {
+        let wallets = std::iter::once((config::MAIN_WALLET_NAME, &self.main))
+            .chain(
+                self.others
+                    .iter()
+                    .map(|(name, control)| (name.as_str(), control)),
+            )
+            .map(|(name, control)| commands::WalletEntry {
+                name: name.to_string(),
+                descriptor: control.config.main_descriptor.clone(),
+            })
+            .collect();
+        commands::ListWalletsResult { wallets }
+    }
+}
+
 /// The handle to a Liana daemon. It might either be the handle for a daemon which exposes a
 /// JSONRPC server or one which exposes its API through a `DaemonControl`.
 pub enum DaemonHandle {
@@ -398,6 +455,8 @@ pub enum DaemonHandle {
         poller_sender: mpsc::SyncSender<poller::PollerMessage>,
         poller_handle: thread::JoinHandle<()>,
         control: DaemonControl,
+        /// The controls of the additional wallets, by name.
+        wallets: collections::BTreeMap<String, DaemonControl>,
     },
     Server {
         poller_sender: mpsc::SyncSender<poller::PollerMessage>,
@@ -418,6 +477,10 @@ impl DaemonHandle {
     /// default Bitcoin interface (`bitcoind` JSONRPC) will be used.
     /// You may specify a custom Database interface through the `db` parameter. If `None`, the
     /// default Database interface (SQLite) will be used.
+    ///
+    /// The custom interfaces only apply to the main wallet. The additional wallets always use an
+    /// SQLite database under the data directory and share the Bitcoin interface of the main
+    /// wallet, except for Electrum which requires one interface per wallet.
     pub fn start(
         config: Config,
         bitcoin: Option<impl BitcoinInterface + 'static>,
@@ -464,7 +527,36 @@ impl DaemonHandle {
             )?)) as sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
         };
 
+        // As well as the database of each of the additional wallets, in their own directory.
+        let mut wallets = Vec::with_capacity(config.wallets.len());
+        for wallet in &config.wallets {
+            let wallet_config = config.wallet_config(wallet);
+            let wallet_data_dir = wallet_data_dir(&data_dir, &wallet.name);
+            let fresh_wallet_data_dir = !wallet_data_dir.as_path().exists();
+            if fresh_wallet_data_dir {
+                create_datadir(&wallet_data_dir)?;
+                log::info!(
+                    "Created a new data directory for wallet '{}' at '{}'",
+                    wallet.name,
+                    wallet_data_dir.display()
+                );
+            }
+            let wallet_db = sync::Arc::from(sync::Mutex::from(setup_sqlite(
+                &wallet_config,
+                &wallet_data_dir,
+                fresh_wallet_data_dir,
+                &secp,
+                &bitcoind,
+            )?)) as sync::Arc<sync::Mutex<dyn DatabaseInterface>>;
+            wallets.push((wallet.name.clone(), wallet_config, wallet_db));
+        }
+
         // Finally set up the Bitcoin backend.
+        let is_electrum = bitcoin.is_none()
+            && matches!(
+                config.bitcoin_backend,
+                Some(config::BitcoinBackend::Electrum(..))
+            );
         let bit = match (bitcoin, &config.bitcoin_backend) {
             (Some(bit), _) => sync::Arc::from(sync::Mutex::from(bit)),
             (None, Some(config::BitcoinBackend::Bitcoind(..))) => sync::Arc::from(
@@ -476,11 +568,37 @@ impl DaemonHandle {
             }
             (None, None) => Err(StartupError::MissingBitcoinBackendConfig)?,
         };
+        let wallets = wallets
+            .into_iter()
+            .map(|(name, wallet_config, wallet_db)| {
+                let wallet_bit = if is_electrum {
+                    sync::Arc::from(sync::Mutex::from(setup_electrum(
+                        &wallet_config,
+                        wallet_db.clone(),
+                    )?)) as sync::Arc<sync::Mutex<dyn BitcoinInterface>>
+                } else {
+                    bit.clone()
+                };
+                Ok((name, wallet_config, wallet_bit, wallet_db))
+            })
+            .collect::<Result<Vec<_>, StartupError>>()?;
 
         // Start the poller thread. Keep the thread handle to be able to check if it crashed. Store
         // an atomic to be able to stop it.
-        let mut bitcoin_poller =
-            poller::Poller::new(bit.clone(), db.clone(), config.main_descriptor.clone());
+        let mut bitcoin_poller = poller::Poller::new(
+            bit.clone(),
+            db.clone(),
+            config.main_descriptor.clone(),
+            config.expiry_watch.clone().unwrap_or_default(),
+        );
+        for (name, wallet_config, wallet_bit, wallet_db) in &wallets {
+            bitcoin_poller.add_wallet(
+                name.clone(),
+                wallet_bit.clone(),
+                wallet_db.clone(),
+                wallet_config.main_descriptor.clone(),
+            );
+        }
         let (poller_sender, poller_receiver) = mpsc::sync_channel(0);
         let poller_handle = thread::Builder::new()
             .name("Bitcoin Network poller".to_string())
@@ -496,7 +614,20 @@ impl DaemonHandle {
 
         // Create the API the external world will use to talk to us, either directly through the Rust
         // structure or through the JSONRPC server we may setup below.
-        let control = DaemonControl::new(config, bit, poller_sender.clone(), db, secp);
+        let control = DaemonControl::new(config, bit, poller_sender.clone(), db, secp.clone());
+        let wallets: collections::BTreeMap<_, _> = wallets
+            .into_iter()
+            .map(|(name, wallet_config, wallet_bit, wallet_db)| {
+                let wallet_control = DaemonControl::new(
+                    wallet_config,
+                    wallet_bit,
+                    poller_sender.clone(),
+                    wallet_db,
+                    secp.clone(),
+                );
+                (name, wallet_control)
+            })
+            .collect();
 
         if with_rpc_server {
             let rpcserver_shutdown = sync::Arc::from(sync::atomic::AtomicBool::from(false));
@@ -507,7 +638,11 @@ impl DaemonHandle {
                     move || {
                         let mut rpc_socket = data_dir;
                         rpc_socket.push("lianad_rpc");
-                        server::run(&rpc_socket, control, shutdown)?;
+                        let controls = WalletControls {
+                            main: control,
+                            others: wallets,
+                        };
+                        server::run(&rpc_socket, controls, shutdown)?;
                         Ok(())
                     }
                 })
@@ -525,6 +660,7 @@ impl DaemonHandle {
             poller_sender,
             poller_handle,
             control,
+            wallets,
         })
     }
 
@@ -825,6 +961,10 @@ mod tests {
             data_dir: Some(data_dir),
             log_level: log::LevelFilter::Debug,
             main_descriptor: desc,
+            enable_signspend: false,
+            external_signer: None,
+            wallets: Vec::new(),
+            expiry_watch: None,
         };
 
         // Start the daemon in a new thread so the current one acts as the bitcoind server.
@@ -112,6 +112,10 @@ impl BitcoinInterface for DummyBitcoind {
         todo!()
     }
 
+    fn test_mempool_accept(&self, _: &bitcoin::Transaction) -> Result<(), String> {
+        Ok(())
+    }
+
     fn start_rescan(&mut self, _: &descriptors::LianaDescriptor, _: u32) -> Result<(), String> {
         todo!()
     }
@@ -128,6 +132,14 @@ impl BitcoinInterface for DummyBitcoind {
         None
     }
 
+    fn tip_median_time_past(&self) -> Option<u32> {
+        None
+    }
+
+    fn median_time_past(&self, _: i32) -> Option<u32> {
+        None
+    }
+
     fn wallet_transaction(
         &self,
         txid: &bitcoin::Txid,
@@ -142,6 +154,13 @@ impl BitcoinInterface for DummyBitcoind {
     fn mempool_entry(&self, _: &bitcoin::Txid) -> Option<MempoolEntry> {
         None
     }
+
+    fn estimate_fee(&self, conf_target: u16) -> Option<u64> {
+        // Lower feerates for higher targets, no estimate past two weeks.
+        (1..=1_008)
+            .contains(&conf_target)
+            .then(|| 1 + 100 / u64::from(conf_target))
+    }
 }
 
 struct DummyDbState {
@@ -151,6 +170,8 @@ struct DummyDbState {
     coins: HashMap<bitcoin::OutPoint, Coin>,
     txs: HashMap<bitcoin::Txid, bitcoin::Transaction>,
     spend_txs: HashMap<bitcoin::Txid, (Psbt, Option<u32>)>,
+    payjoin_proposals: HashMap<bitcoin::Txid, (bitcoin::Txid, Psbt)>,
+    expiry_notified: HashSet<bitcoin::OutPoint>,
     timestamp: u32,
     rescan_timestamp: Option<u32>,
     last_poll_timestamp: Option<u32>,
@@ -185,6 +206,8 @@ impl DummyDatabase {
                 coins: HashMap::new(),
                 txs: HashMap::new(),
                 spend_txs: HashMap::new(),
+                payjoin_proposals: HashMap::new(),
+                expiry_notified: HashSet::new(),
                 timestamp: now,
                 rescan_timestamp: None,
                 last_poll_timestamp: None,
@@ -320,10 +343,33 @@ impl DatabaseConnection for DummyDatabase {
             coin.block_info = Some(BlockInfo {
                 height: *height,
                 time: *time,
+                prev_mtp: None,
             });
         }
     }
 
+    fn set_coins_prev_mtp(&mut self, outpoints: &[(bitcoin::OutPoint, u32)]) {
+        for (op, prev_mtp) in outpoints {
+            let mut db = self.db.write().unwrap();
+            let coin = &mut db.coins.get_mut(op).unwrap();
+            if let Some(block_info) = coin.block_info.as_mut() {
+                block_info.prev_mtp = Some(*prev_mtp);
+            }
+        }
+    }
+
+    fn expiry_notified_coins(&mut self) -> HashSet<bitcoin::OutPoint> {
+        self.db.read().unwrap().expiry_notified.clone()
+    }
+
+    fn mark_expiry_notified(&mut self, outpoints: &[bitcoin::OutPoint]) {
+        self.db
+            .write()
+            .unwrap()
+            .expiry_notified
+            .extend(outpoints.iter().cloned());
+    }
+
     fn spend_coins<'a>(&mut self, outpoints: &[(bitcoin::OutPoint, bitcoin::Txid)]) {
         for (op, spend_txid) in outpoints {
             let mut db = self.db.write().unwrap();
@@ -354,6 +400,7 @@ impl DatabaseConnection for DummyDatabase {
             spent.spend_block = Some(BlockInfo {
                 height: *height,
                 time: *time,
+                prev_mtp: None,
             });
         }
     }
@@ -410,7 +457,25 @@ impl DatabaseConnection for DummyDatabase {
     }
 
     fn delete_spend(&mut self, txid: &bitcoin::Txid) {
-        self.db.write().unwrap().spend_txs.remove(txid);
+        let mut db = self.db.write().unwrap();
+        db.spend_txs.remove(txid);
+        db.payjoin_proposals
+            .retain(|proposal_txid, (original_txid, _)| {
+                proposal_txid != txid && original_txid != txid
+            });
+    }
+
+    fn payjoin_proposal(&mut self, txid: &bitcoin::Txid) -> Option<(bitcoin::Txid, Psbt)> {
+        self.db.read().unwrap().payjoin_proposals.get(txid).cloned()
+    }
+
+    fn store_payjoin_proposal(&mut self, original_txid: &bitcoin::Txid, psbt: &Psbt) {
+        let txid = psbt.unsigned_tx.txid();
+        self.db
+            .write()
+            .unwrap()
+            .payjoin_proposals
+            .insert(txid, (*original_txid, psbt.clone()));
     }
 
     fn rollback_tip(&mut self, _: &BlockChainTip) {
@@ -445,6 +510,10 @@ impl DatabaseConnection for DummyDatabase {
         todo!()
     }
 
+    fn list_labels(&mut self) -> Vec<(LabelItem, String)> {
+        todo!()
+    }
+
     fn list_txids(&mut self, start: u32, end: u32, limit: u64) -> Vec<bitcoin::Txid> {
         let mut txids_and_time = Vec::new();
         let coins = &self.db.read().unwrap().coins;
@@ -576,6 +645,10 @@ impl DummyLiana {
             data_dir: Some(data_dir),
             log_level: log::LevelFilter::Debug,
             main_descriptor: desc,
+            enable_signspend: false,
+            external_signer: None,
+            wallets: Vec::new(),
+            expiry_watch: None,
         };
 
         let handle =
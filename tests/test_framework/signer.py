@@ -32,6 +32,10 @@ def sign_psbt_wsh(psbt, hds):
 
     # Sign each input.
     for i, psbt_in in enumerate(psbt.i):
+        # Inputs which aren't ours (for instance a payjoin counterparty's) have no derivation.
+        if PSBT_IN_BIP32_DERIVATION not in psbt_in.map:
+            continue
+
         # First, gather the needed information from the PSBT input.
         # 'hd_keypaths' is of the form {pubkey: (fingerprint (4 bytes), derivation path (n * 4 bytes))}
         fing_der = next(iter(psbt_in.map[PSBT_IN_BIP32_DERIVATION].values()))
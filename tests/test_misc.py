@@ -1,4 +1,6 @@
+import json
 import logging
+import os
 import pytest
 import shutil
 import time
@@ -387,3 +389,163 @@ def test_retry_on_workqueue_exceeded(lianad, bitcoind, executor):
     # We should have retried the request to bitcoind, which should now succeed along with the call.
     # This just checks the response we get is sane, nothing particular with this field.
     assert "block_height" in f_liana.result(TIMEOUT)
+
+
+def test_multiple_wallets(lianad, bitcoind):
+    """Test a single daemon managing an additional wallet alongside the main one."""
+    # Configure a second wallet with its own keys.
+    signer = SingleSigner(is_taproot=USE_TAPROOT)
+    second_desc = single_key_desc(
+        xpub_fingerprint(signer.primary_hd),
+        signer.primary_hd.get_xpub(),
+        xpub_fingerprint(signer.recovery_hd),
+        signer.recovery_hd.get_xpub(),
+        12,
+        is_taproot=USE_TAPROOT,
+    )
+    lianad.stop()
+    with open(lianad.conf_file, "a") as f:
+        f.write("\n[[wallets]]\n")
+        f.write('name = "second"\n')
+        f.write(f'descriptor = "{second_desc}"\n')
+    lianad.start()
+    second_rpc = lianad.rpc.for_wallet("second")
+
+    # Both wallets are listed, the main one first.
+    wallets = lianad.rpc.listwallets()["wallets"]
+    assert [w["name"] for w in wallets] == ["main", "second"]
+    assert wallets[0]["descriptor"] == lianad.rpc.getinfo()["descriptors"]["main"]
+    assert wallets[1]["descriptor"] == second_rpc.getinfo()["descriptors"]["main"]
+    assert wallets[0]["descriptor"] != wallets[1]["descriptor"]
+
+    # The main wallet can also be selected explicitly, but not an unknown one.
+    main_rpc = lianad.rpc.for_wallet("main")
+    assert main_rpc.getinfo()["descriptors"] == lianad.rpc.getinfo()["descriptors"]
+    with pytest.raises(RpcError, match="Unknown wallet 'third'."):
+        lianad.rpc.for_wallet("third").getinfo()
+
+    # Deposits to each of the wallets are only seen by this wallet.
+    main_coins = len(lianad.rpc.listcoins()["coins"])
+    addr = second_rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.02)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(second_rpc.listcoins()["coins"]) == 1)
+    assert second_rpc.listcoins()["coins"][0]["amount"] == 2_000_000
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.03)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == main_coins + 1)
+    wait_for(
+        lambda: second_rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+    assert len(second_rpc.listcoins()["coins"]) == 1
+
+    # The second wallet can spend its own coins.
+    outpoint = second_rpc.listcoins()["coins"][0]["outpoint"]
+    destinations = {bitcoind.rpc.getnewaddress(): 1_000_000}
+    res = second_rpc.createspend(destinations, [outpoint], 2)
+    psbt = PSBT.from_base64(res["psbt"])
+    txid = psbt.tx.txid().hex()
+    signed_psbt = signer.sign_psbt(psbt)
+    second_rpc.updatespend(signed_psbt.to_base64())
+    second_rpc.broadcastspend(txid)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(
+        lambda: all(
+            c["spend_info"] is not None and c["spend_info"]["height"] is not None
+            for c in second_rpc.listcoins([], [outpoint])["coins"]
+        )
+    )
+    assert len(lianad.rpc.listspendtxs()["spend_txs"]) == 0
+
+
+def test_expiring_coins(lianad, bitcoind):
+    """Test the listing of coins about to be spendable through the recovery path, and the
+    notification of the newly expiring coins through the hook command."""
+    # Set a hook which records the notifications, and a threshold of 5 blocks. The recovery
+    # path of the fixture's descriptor is available after 10 blocks.
+    # The hook fails as long as the failure flag file exists.
+    notif_path = os.path.join(lianad.datadir, "expiring_coins.json")
+    attempts_path = os.path.join(lianad.datadir, "expiry_hook_attempts")
+    fail_path = os.path.join(lianad.datadir, "expiry_hook_fail")
+    hook_path = os.path.join(lianad.datadir, "expiry_hook.sh")
+    with open(hook_path, "w") as f:
+        f.write(
+            f"#!/bin/sh\necho >> {attempts_path}\n"
+            f"if [ -f {fail_path} ]; then exit 1; fi\n"
+            f"cat >> {notif_path}\necho >> {notif_path}\n"
+        )
+    os.chmod(hook_path, 0o755)
+    lianad.stop()
+    with open(lianad.conf_file, "a") as f:
+        f.write("\n[expiry_watch]\n")
+        f.write("threshold_blocks = 5\n")
+        f.write(f'hook_command = "{hook_path}"\n')
+    lianad.start()
+
+    # Receive a coin. It's expiring with the default threshold, but not with the configured one.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+    coin = lianad.rpc.listcoins()["coins"][0]
+    assert lianad.rpc.listexpiringcoins()["coins"] == []
+    expiring = lianad.rpc.listexpiringcoins(100)["coins"]
+    assert len(expiring) == 1
+    assert expiring[0]["outpoint"] == coin["outpoint"]
+    assert expiring[0]["amount"] == coin["amount"]
+    assert expiring[0]["block_height"] == coin["block_height"]
+    assert expiring[0]["expiry_height"] == coin["block_height"] + 10
+    assert expiring[0]["remaining_blocks"] == 10
+
+    # Once it gets within 5 blocks of its expiry, it's listed and the hook is called. As long as
+    # it fails, it's called again.
+    open(fail_path, "w").close()
+    bitcoind.generate_block(5)
+    wait_for(lambda: len(lianad.rpc.listexpiringcoins()["coins"]) == 1)
+    assert lianad.rpc.listexpiringcoins()["coins"][0]["remaining_blocks"] == 5
+    wait_for(
+        lambda: os.path.exists(attempts_path)
+        and len(open(attempts_path).read().splitlines()) >= 2
+    )
+    assert not os.path.exists(notif_path)
+    os.remove(fail_path)
+    wait_for(lambda: os.path.exists(notif_path))
+    wait_for(lambda: open(notif_path).read().endswith("\n"))
+    notifs = [json.loads(l) for l in open(notif_path).read().splitlines()]
+    assert len(notifs) == 1
+    assert notifs[0]["wallet"] == "main"
+    assert [c["outpoint"] for c in notifs[0]["coins"]] == [coin["outpoint"]]
+
+    # The hook is only called for newly expiring coins.
+    bitcoind.generate_block(6)
+    wait_for(
+        lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+    assert lianad.rpc.listexpiringcoins()["coins"][0]["remaining_blocks"] == 0
+    assert len(open(notif_path).read().splitlines()) == 1
+
+    # Nor is it called again after a restart. Wait for two polls to make sure the hook would
+    # have been called by the first one.
+    attempts = len(open(attempts_path).read().splitlines())
+    lianad.stop()
+    lianad.start()
+    for _ in range(2):
+        bitcoind.generate_block(1)
+        wait_for(
+            lambda: lianad.rpc.getinfo()["block_height"]
+            == bitcoind.rpc.getblockcount()
+        )
+    assert len(open(attempts_path).read().splitlines()) == attempts
+    assert len(open(notif_path).read().splitlines()) == 1
+
+    # Once refreshed, the coin isn't expiring anymore.
+    res = lianad.rpc.createspend({}, [coin["outpoint"]], 2)
+    psbt = PSBT.from_base64(res["psbt"])
+    txid = psbt.tx.txid().hex()
+    signed_psbt = lianad.signer.sign_psbt(psbt)
+    lianad.rpc.updatespend(signed_psbt.to_base64())
+    lianad.rpc.broadcastspend(txid)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+    assert lianad.rpc.listexpiringcoins()["coins"] == []
@@ -1,14 +1,21 @@
+import hashlib
+import json
+import os
 import pytest
 import random
 import re
+import sys
 import time
 
+from bip32 import BIP32
 from fixtures import *
 from test_framework.serializations import (
     PSBT,
     PSBT_IN_PARTIAL_SIG,
     PSBT_IN_NON_WITNESS_UTXO,
+    PSBT_IN_SHA256,
 )
+from test_framework.signer import sign_psbt_taproot, sign_psbt_wsh
 from test_framework.utils import (
     wait_for,
     COIN,
@@ -38,6 +45,54 @@ def test_getinfo(lianad):
     assert res["last_poll_timestamp"] > last_poll_timestamp
 
 
+def test_getpolicy(lianad):
+    res = lianad.rpc.getpolicy()
+    info = lianad.rpc.getinfo()
+    assert res["descriptor"] == info["descriptors"]["main"]["multi_desc"]
+    assert res["is_taproot"] == USE_TAPROOT
+    primary, recovery = res["paths"]
+    assert primary["is_primary"] and primary["timelock"] is None
+    assert primary["description"].endswith("can spend at any time.")
+    assert recovery["timelock"] == {"type": "relative", "value": 10}
+    assert recovery["threshold"] == 1 and len(recovery["keys"]) == 1
+    for path in (primary, recovery):
+        assert not path["is_musig"] and path["hash_lock"] is None
+        sat = path["satisfaction"]
+        assert sat["feerate"] == 1
+        assert sat["fee"] == sat["input_vbytes"]
+    # The recovery path is more expensive to satisfy.
+    prim_sat, reco_sat = primary["satisfaction"], recovery["satisfaction"]
+    assert reco_sat["sat_weight"] > prim_sat["sat_weight"]
+
+    # The fees are computed at the given feerate.
+    res = lianad.rpc.getpolicy(10)
+    for path in res["paths"]:
+        sat = path["satisfaction"]
+        assert sat["fee"] == sat["input_vbytes"] * 10
+    with pytest.raises(RpcError, match="Invalid feerate"):
+        lianad.rpc.getpolicy(0)
+
+
+def test_lintdescriptor(lianad):
+    # The recovery path of the wallet becomes available after 10 blocks and the keys
+    # have no derivation path.
+    res = lianad.rpc.lintdescriptor()
+    kinds = [w["kind"] for w in res["warnings"]]
+    assert kinds == ["short_timelock"] + ["unusual_derivation_path"] * 2
+    short_tl = res["warnings"][0]
+    assert short_tl["timelock"] == {"type": "relative", "value": 10}
+    assert "less than a month" in short_tl["message"]
+
+    # We can also lint another descriptor. Use one with a longer timelock.
+    desc = lianad.rpc.getinfo()["descriptors"]["main"]["multi_desc"]
+    desc = re.sub(r"older\(10\)", "older(52560)", desc.split("#")[0])
+    res = lianad.rpc.lintdescriptor(desc)
+    kinds = [w["kind"] for w in res["warnings"]]
+    assert kinds == ["unusual_derivation_path"] * 2
+    with pytest.raises(RpcError, match="Invalid 'descriptor' parameter"):
+        lianad.rpc.lintdescriptor("not a descriptor")
+
+
 def test_getaddress(lianad):
     res = lianad.rpc.getnewaddress()
     assert "address" in res
@@ -450,6 +505,83 @@ def test_create_spend(lianad, bitcoind):
         lianad.rpc.createspend(destinations, [imma_coin["outpoint"]], 1)
 
 
+def test_estimatefee(lianad, bitcoind):
+    # The confirmation target must be sane.
+    for conf_target in (0, 1009):
+        with pytest.raises(RpcError, match="Invalid confirmation target"):
+            lianad.rpc.estimatefee(conf_target)
+
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    destinations = {bitcoind.rpc.getnewaddress(): 200_000}
+
+    # Either a feerate or a confirmation target must be given, but not both.
+    with pytest.raises(RpcError, match="Missing 'feerate' or 'conf_target'"):
+        lianad.rpc.createspend({"destinations": destinations, "outpoints": []})
+    with pytest.raises(RpcError, match="Only one of 'feerate' and 'conf_target'"):
+        lianad.rpc.createspend(destinations, [], 2, None, 6)
+
+    # The backend may not have enough data for an estimate on regtest. If it does, the
+    # estimate is used to create the spend.
+    try:
+        feerate = lianad.rpc.estimatefee(6)["feerate"]
+    except RpcError as e:
+        assert "Fee estimation unavailable" in str(e)
+        with pytest.raises(RpcError, match="Fee estimation unavailable"):
+            lianad.rpc.createspend(destinations, [], None, None, 6)
+    else:
+        assert feerate >= 1
+        res = lianad.rpc.createspend(
+            {"destinations": destinations, "outpoints": [], "conf_target": 6}
+        )
+        assert "psbt" in res
+
+
+def test_refreshcoins(lianad, bitcoind):
+    # Nothing to refresh without coins.
+    with pytest.raises(RpcError, match="No coin becomes spendable"):
+        lianad.rpc.refreshcoins(2)
+
+    # Receive two coins 4 blocks apart. The recovery path is available after 10 blocks.
+    for _ in range(2):
+        addr = lianad.rpc.getnewaddress()["address"]
+        txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+        bitcoind.generate_block(3)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 2)
+    wait_for(
+        lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+    coins = sorted(lianad.rpc.listcoins()["coins"], key=lambda c: c["block_height"])
+
+    # The first coin expires in 3 blocks, the second one in 7 blocks.
+    with pytest.raises(RpcError, match="No coin becomes spendable.* within 2 blocks"):
+        lianad.rpc.refreshcoins(2, 2)
+    res = lianad.rpc.refreshcoins(2, 3)
+    psbt = PSBT.from_base64(res["psbt"])
+    assert len(psbt.tx.vin) == 1 and len(psbt.tx.vout) == 1
+    prevout = psbt.tx.vin[0].prevout
+    assert f"{prevout.hash:064x}:{prevout.n}" == coins[0]["outpoint"]
+
+    # Both coins are refreshed into a single one with a larger threshold. The threshold
+    # can also be passed by name.
+    res = lianad.rpc.refreshcoins({"feerate": 2, "threshold": 7})
+    psbt = PSBT.from_base64(res["psbt"])
+    assert len(psbt.tx.vin) == 2 and len(psbt.tx.vout) == 1
+    txid = sign_and_broadcast(lianad, bitcoind, psbt)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+    coin = lianad.rpc.listcoins(["confirmed"])["coins"][0]
+    assert coin["is_change"]
+    assert coin["amount"] > 1_990_000
+
+    # The new coin's recovery path is 10 blocks away again.
+    with pytest.raises(RpcError, match="No coin becomes spendable"):
+        lianad.rpc.refreshcoins(2, 9)
+
+
 def test_list_spend(lianad, bitcoind):
     # Start by creating two conflicting Spend PSBTs. The first one will have a change
     # output but not the second one.
@@ -946,6 +1078,375 @@ def test_create_recovery(lianad, bitcoind):
     sign_and_broadcast(lianad, bitcoind, reco_psbt, recovery=True)
 
 
+def test_create_absolute_recovery(lianad, bitcoind):
+    """Test the sweep of coins through a recovery path behind an absolute timelock."""
+    # Configure a second wallet whose descriptor has, in addition to the usual relative timelock
+    # recovery path, a recovery path available at an absolute block height.
+    prim_hd, reco_hd, abs_hd = (
+        BIP32.from_seed(os.urandom(32), network="test") for _ in range(3)
+    )
+    prim_key, reco_key, abs_key = (
+        f"[{xpub_fingerprint(hd)}]{hd.get_xpub()}/<0;1>/*"
+        for hd in (prim_hd, reco_hd, abs_hd)
+    )
+    abs_timelock = bitcoind.rpc.getblockcount() + 20
+    if USE_TAPROOT:
+        desc = f"tr({prim_key},{{and_v(v:pk({reco_key}),older(1000)),and_v(v:pk({abs_key}),after({abs_timelock}))}})"
+    else:
+        desc = f"wsh(or_d(pk({prim_key}),or_i(and_v(v:pkh({reco_key}),older(1000)),and_v(v:pkh({abs_key}),after({abs_timelock})))))"
+    lianad.stop()
+    with open(lianad.conf_file, "a") as f:
+        f.write("\n[[wallets]]\n")
+        f.write('name = "heirs"\n')
+        f.write(f'descriptor = "{desc}"\n')
+    lianad.start()
+    heirs_rpc = lianad.rpc.for_wallet("heirs")
+
+    # Get a couple coins.
+    destinations = {
+        heirs_rpc.getnewaddress()["address"]: 0.1,
+        heirs_rpc.getnewaddress()["address"]: 0.2,
+    }
+    txid = bitcoind.rpc.sendmany("", destinations)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(heirs_rpc.listcoins(["confirmed"])["coins"]) == 2)
+
+    # The recovery path isn't available yet, and it must exist.
+    addr = bitcoind.rpc.getnewaddress()
+    with pytest.raises(
+        RpcError,
+        match="No coin currently spendable through this timelocked recovery path",
+    ):
+        heirs_rpc.createrecovery(addr, 2, None, abs_timelock)
+    with pytest.raises(
+        RpcError, match=f"No recovery path behind absolute timelock '{abs_timelock + 1}'."
+    ):
+        heirs_rpc.createrecovery(addr, 2, None, abs_timelock + 1)
+    with pytest.raises(
+        RpcError, match="Only one of 'timelock' and 'absolute_timelock' may be given."
+    ):
+        heirs_rpc.createrecovery(addr, 2, 1000, abs_timelock)
+
+    # Once the timelock height is reached, all the coins can be swept at once.
+    bitcoind.generate_block(abs_timelock - bitcoind.rpc.getblockcount())
+    wait_for(lambda: heirs_rpc.getinfo()["block_height"] == abs_timelock)
+    res = heirs_rpc.createrecovery(addr, 2, None, abs_timelock)
+    reco_psbt = PSBT.from_base64(res["psbt"])
+    assert reco_psbt.tx.nLockTime == abs_timelock
+    assert len(reco_psbt.tx.vin) == 2
+    assert len(reco_psbt.tx.vout) == 1
+    assert int(0.2999 * COIN) < int(reco_psbt.tx.vout[0].nValue) < int(0.3 * COIN)
+
+    # Sign it with the key of the absolute timelock recovery path and broadcast it.
+    if USE_TAPROOT:
+        signed_psbt = sign_psbt_taproot(reco_psbt, [abs_hd])
+    else:
+        signed_psbt = sign_psbt_wsh(reco_psbt, [abs_hd])
+    heirs_rpc.updatespend(signed_psbt.to_base64())
+    txid = signed_psbt.tx.txid().hex()
+    heirs_rpc.broadcastspend(txid)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(heirs_rpc.listcoins(["confirmed"])["coins"]) == 0)
+
+
+def test_hash_locked_recovery(lianad, bitcoind):
+    """Test spending through a recovery path which requires the preimage of a hash."""
+    # Configure a second wallet whose recovery path requires a secret held by a lawyer, in
+    # addition to the signature of the heir.
+    prim_hd, reco_hd = (
+        BIP32.from_seed(os.urandom(32), network="test") for _ in range(2)
+    )
+    prim_key, reco_key = (
+        f"[{xpub_fingerprint(hd)}]{hd.get_xpub()}/<0;1>/*"
+        for hd in (prim_hd, reco_hd)
+    )
+    preimage = os.urandom(32)
+    preimage_hash = hashlib.sha256(preimage).hexdigest()
+    if USE_TAPROOT:
+        desc = f"tr({prim_key},and_v(v:pk({reco_key}),and_v(v:sha256({preimage_hash}),older(10))))"
+    else:
+        desc = f"wsh(or_d(pk({prim_key}),and_v(v:pkh({reco_key}),and_v(v:sha256({preimage_hash}),older(10)))))"
+    lianad.stop()
+    with open(lianad.conf_file, "a") as f:
+        f.write("\n[[wallets]]\n")
+        f.write('name = "lawyer"\n')
+        f.write(f'descriptor = "{desc}"\n')
+    lianad.start()
+    lawyer_rpc = lianad.rpc.for_wallet("lawyer")
+
+    # Get a coin and make the recovery path available.
+    txid = bitcoind.rpc.sendtoaddress(lawyer_rpc.getnewaddress()["address"], 0.1)
+    bitcoind.generate_block(10, wait_for_mempool=txid)
+    wait_for(
+        lambda: lawyer_rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+
+    # Create a recovery transaction and sign it with the heir's key.
+    addr = bitcoind.rpc.getnewaddress()
+    res = lawyer_rpc.createrecovery(addr, 2, 10)
+    reco_psbt = PSBT.from_base64(res["psbt"])
+    if USE_TAPROOT:
+        signed_psbt = sign_psbt_taproot(reco_psbt, [reco_hd])
+    else:
+        signed_psbt = sign_psbt_wsh(reco_psbt, [reco_hd])
+    lawyer_rpc.updatespend(signed_psbt.to_base64())
+    txid = signed_psbt.tx.txid().hex()
+
+    # The signature isn't enough, the preimage must be revealed too.
+    with pytest.raises(RpcError, match="Failed to finalize the spend transaction PSBT"):
+        lawyer_rpc.broadcastspend(txid)
+    with pytest.raises(
+        RpcError,
+        match="Preimage doesn't unlock any of the hash locks of the descriptor.",
+    ):
+        lawyer_rpc.addspendpreimage(txid, os.urandom(32).hex())
+    lawyer_rpc.addspendpreimage(txid, preimage.hex())
+    stored_psbt = PSBT.from_base64(lawyer_rpc.listspendtxs()["spend_txs"][0]["psbt"])
+    assert all(
+        PSBT_IN_SHA256 in psbt_in.map and len(psbt_in.map[PSBT_IN_SHA256]) == 1
+        for psbt_in in stored_psbt.i
+    )
+
+    # Now it can be finalized and broadcast.
+    lawyer_rpc.broadcastspend(txid)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lawyer_rpc.listcoins(["confirmed"])["coins"]) == 0)
+
+
+def test_signspend(lianad, bitcoind):
+    """Test signing a stored Spend transaction with the hot keys stored in the datadir."""
+    # Configure a second wallet whose primary key is a hot key, and store its mnemonic.
+    mnemonic = " ".join(["abandon"] * 11 + ["about"])
+    seed = hashlib.pbkdf2_hmac("sha512", mnemonic.encode(), b"mnemonic", 2048)
+    hot_hd = BIP32.from_seed(seed, network="test")
+    reco_hd = BIP32.from_seed(os.urandom(32), network="test")
+    hot_fg, reco_fg = xpub_fingerprint(hot_hd), xpub_fingerprint(reco_hd)
+    desc = single_key_desc(
+        hot_fg, hot_hd.get_xpub(), reco_fg, reco_hd.get_xpub(), 10, USE_TAPROOT
+    )
+    mnemonics_dir = os.path.join(lianad.datadir, "regtest", "mnemonics")
+    os.makedirs(mnemonics_dir, exist_ok=True)
+    with open(os.path.join(mnemonics_dir, f"mnemonic-{hot_fg}.txt"), "w") as f:
+        f.write(mnemonic)
+    lianad.stop()
+    with open(lianad.conf_file, "a") as f:
+        f.write("\n[[wallets]]\n")
+        f.write('name = "hot"\n')
+        f.write(f'descriptor = "{desc}"\n')
+    lianad.start()
+    hot_rpc = lianad.rpc.for_wallet("hot")
+
+    # Get a coin in each wallet and create transactions spending them.
+    txids = [
+        bitcoind.rpc.sendtoaddress(rpc.getnewaddress()["address"], 0.1)
+        for rpc in (hot_rpc, lianad.rpc)
+    ]
+    bitcoind.generate_block(1, wait_for_mempool=txids)
+    destinations = {bitcoind.rpc.getnewaddress(): 50_000}
+    spend_txids = []
+    for rpc in (hot_rpc, lianad.rpc):
+        wait_for(lambda: len(rpc.listcoins(["confirmed"])["coins"]) == 1)
+        outpoint = rpc.listcoins()["coins"][0]["outpoint"]
+        res = rpc.createspend(destinations, [outpoint], 2)
+        rpc.updatespend(res["psbt"])
+        spend_txids.append(PSBT.from_base64(res["psbt"]).tx.txid().hex())
+    txid, main_txid = spend_txids
+
+    # Signing is disabled by default.
+    with pytest.raises(RpcError, match="Signing is disabled."):
+        hot_rpc.signspend(txid)
+    lianad.stop()
+    with open(lianad.conf_file, "r") as f:
+        conf = f.read()
+    with open(lianad.conf_file, "w") as f:
+        f.write("enable_signspend = true\n" + conf)
+    lianad.start()
+
+    # An unknown transaction can't be signed, nor one which doesn't use the hot key.
+    with pytest.raises(RpcError, match="Unknown spend transaction"):
+        hot_rpc.signspend("00" * 32)
+    with pytest.raises(RpcError, match="No hot key stored in the data directory"):
+        lianad.rpc.signspend(main_txid)
+
+    # The hot key signs the transaction, which is stored and can be broadcast.
+    res = hot_rpc.signspend(txid)
+    assert res["signers"] == [hot_fg]
+    assert hot_rpc.listspendtxs([txid])["spend_txs"][0]["psbt"] == res["psbt"]
+    hot_rpc.broadcastspend(txid)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(hot_rpc.listcoins(["spent"])["coins"]) == 1)
+
+
+@pytest.mark.skipif(not USE_TAPROOT, reason="MuSig2 is only available with Taproot.")
+def test_signspend_musig(lianad, bitcoind):
+    """Test signing a Spend transaction for a MuSig2 aggregate key between hot keys stored in the
+    datadir. Both rounds of the signing session are performed by a single call."""
+    mnemonics = [
+        " ".join(["abandon"] * 11 + ["about"]),
+        "legal winner thank year wave sausage worth useful legal winner thank yellow",
+    ]
+    hot_hds = [
+        BIP32.from_seed(
+            hashlib.pbkdf2_hmac("sha512", m.encode(), b"mnemonic", 2048), network="test"
+        )
+        for m in mnemonics
+    ]
+    hot_fgs = [xpub_fingerprint(hd) for hd in hot_hds]
+    mnemonics_dir = os.path.join(lianad.datadir, "regtest", "mnemonics")
+    os.makedirs(mnemonics_dir, exist_ok=True)
+    for fg, mnemonic in zip(hot_fgs, mnemonics):
+        with open(os.path.join(mnemonics_dir, f"mnemonic-{fg}.txt"), "w") as f:
+            f.write(mnemonic)
+    reco_hd = BIP32.from_seed(os.urandom(32), network="test")
+    participants = ",".join(
+        f"[{fg}]{hd.get_xpub()}" for fg, hd in zip(hot_fgs, hot_hds)
+    )
+    desc = f"tr(musig({participants})/<0;1>/*,and_v(v:pk([{xpub_fingerprint(reco_hd)}]{reco_hd.get_xpub()}/<0;1>/*),older(10)))"
+    lianad.stop()
+    with open(lianad.conf_file, "r") as f:
+        conf = f.read()
+    with open(lianad.conf_file, "w") as f:
+        f.write("enable_signspend = true\n")
+        f.write(conf)
+        f.write("\n[[wallets]]\n")
+        f.write('name = "musig"\n')
+        f.write(f'descriptor = "{desc}"\n')
+    lianad.start()
+    musig_rpc = lianad.rpc.for_wallet("musig")
+
+    # Get a coin and create a transaction spending it.
+    txid = bitcoind.rpc.sendtoaddress(musig_rpc.getnewaddress()["address"], 0.1)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(musig_rpc.listcoins(["confirmed"])["coins"]) == 1)
+    outpoint = musig_rpc.listcoins()["coins"][0]["outpoint"]
+    destinations = {bitcoind.rpc.getnewaddress(): 50_000}
+    res = musig_rpc.createspend(destinations, [outpoint], 2)
+    musig_rpc.updatespend(res["psbt"])
+    txid = PSBT.from_base64(res["psbt"]).tx.txid().hex()
+
+    # Both hot keys provide their nonce and their partial signature, which can be aggregated
+    # into a signature for the key path upon broadcast.
+    res = musig_rpc.signspend(txid)
+    assert sorted(res["signers"]) == sorted(hot_fgs)
+    musig_rpc.broadcastspend(txid)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(musig_rpc.listcoins(["spent"])["coins"]) == 1)
+
+
+EXTERNAL_SIGNER_SCRIPT = """#!{python}
+import json, sys
+sys.path.insert(0, "{tests_dir}")
+from bip32 import BIP32
+from test_framework.serializations import PSBT
+from test_framework.signer import sign_psbt_taproot, sign_psbt_wsh
+
+args = sys.argv[1:]
+signer_args = ["--fingerprint", "{fingerprint}", "--chain", "regtest"]
+if args == ["--chain", "regtest", "enumerate"]:
+    print(json.dumps([{{"fingerprint": "{fingerprint}", "name": "test signer"}}]))
+elif args[:-1] == signer_args + ["getxpub"]:
+    xpub = BIP32.from_xpriv("{xpriv}").get_xpub_from_path(args[-1])
+    print(json.dumps({{"xpub": xpub}}))
+elif args[:-1] == signer_args + ["displayaddress", "--desc"]:
+    # Display the address written next to this script, if the descriptor is for our key.
+    assert "[{fingerprint}]" in args[-1] and "*" not in args[-1]
+    with open(__file__ + ".address") as f:
+        print(json.dumps({{"address": f.read()}}))
+elif args == ["--stdin"] + signer_args:
+    command, psbt = sys.stdin.readline().split()
+    assert command == "signtx"
+    sign = sign_psbt_taproot if {taproot} else sign_psbt_wsh
+    psbt = sign(PSBT.from_base64(psbt), [BIP32.from_xpriv("{xpriv}")])
+    print(json.dumps({{"psbt": psbt.to_base64()}}))
+else:
+    print(json.dumps({{"error": "Unsupported command"}}))
+"""
+
+
+def test_signspend_external_signer(lianad, bitcoind):
+    """Test signing a stored Spend transaction through an external signer executable."""
+    ext_hd, reco_hd = (
+        BIP32.from_seed(os.urandom(32), network="test") for _ in range(2)
+    )
+    ext_fg, reco_fg = xpub_fingerprint(ext_hd), xpub_fingerprint(reco_hd)
+    desc = single_key_desc(
+        ext_fg, ext_hd.get_xpub(), reco_fg, reco_hd.get_xpub(), 10, USE_TAPROOT
+    )
+    signer_path = os.path.join(lianad.datadir, "external_signer.py")
+    with open(signer_path, "w") as f:
+        f.write(
+            EXTERNAL_SIGNER_SCRIPT.format(
+                python=sys.executable,
+                tests_dir=os.path.dirname(os.path.abspath(__file__)),
+                fingerprint=ext_fg,
+                taproot=USE_TAPROOT,
+                xpriv=ext_hd.get_xpriv(),
+            )
+        )
+    os.chmod(signer_path, 0o700)
+    with pytest.raises(RpcError, match="No external signer executable is set"):
+        lianad.rpc.listexternalsigners()
+    lianad.stop()
+    with open(lianad.conf_file, "r") as f:
+        conf = f.read()
+    with open(lianad.conf_file, "w") as f:
+        f.write(f"external_signer = '{signer_path}'\n")
+        f.write(conf)
+        f.write("\n[[wallets]]\n")
+        f.write('name = "external"\n')
+        f.write(f'descriptor = "{desc}"\n')
+    lianad.start()
+    ext_rpc = lianad.rpc.for_wallet("external")
+
+    # Get a coin and create a transaction spending it.
+    txid = bitcoind.rpc.sendtoaddress(ext_rpc.getnewaddress()["address"], 0.1)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(ext_rpc.listcoins(["confirmed"])["coins"]) == 1)
+    outpoint = ext_rpc.listcoins()["coins"][0]["outpoint"]
+    destinations = {bitcoind.rpc.getnewaddress(): 50_000}
+    res = ext_rpc.createspend(destinations, [outpoint], 2)
+    ext_rpc.updatespend(res["psbt"])
+    txid = PSBT.from_base64(res["psbt"]).tx.txid().hex()
+
+    # Signing must be enabled for the external signer to be used, too.
+    with pytest.raises(RpcError, match="Signing is disabled"):
+        ext_rpc.signspend(txid)
+    lianad.stop()
+    with open(lianad.conf_file, "r") as f:
+        conf = f.read()
+    with open(lianad.conf_file, "w") as f:
+        f.write("enable_signspend = true\n" + conf)
+    lianad.start()
+
+    # The external signer signs it, and it can be broadcast.
+    res = ext_rpc.signspend(txid)
+    assert res["signers"] == [ext_fg]
+    ext_rpc.broadcastspend(txid)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(ext_rpc.listcoins(["spent"])["coins"]) == 1)
+
+    # The signers of the executable can be listed, and their xpubs queried.
+    assert ext_rpc.listexternalsigners()["signers"] == [
+        {"fingerprint": ext_fg, "name": "test signer"}
+    ]
+    path = "m/48'/1'/0'/2'"
+    res = ext_rpc.getexternalxpub(ext_fg, path)
+    assert res["xpub"] == ext_hd.get_xpub_from_path(path)
+    with pytest.raises(RpcError, match="No external signer with fingerprint"):
+        ext_rpc.getexternalxpub(reco_fg, path)
+
+    # Our addresses can be displayed on the signer, which must report the same address.
+    addr = ext_rpc.getnewaddress()["address"]
+    with open(signer_path + ".address", "w") as f:
+        f.write(addr)
+    ext_rpc.displayaddress(ext_fg, addr)
+    other_addr = ext_rpc.getnewaddress()["address"]
+    with pytest.raises(RpcError, match=f"displayed address '{addr}' instead of"):
+        ext_rpc.displayaddress(ext_fg, other_addr)
+    with pytest.raises(RpcError, match="Unknown address"):
+        ext_rpc.displayaddress(ext_fg, bitcoind.rpc.getnewaddress())
+
+
 def test_labels(lianad, bitcoind):
     """Test the creation and updating of labels."""
     # We can set a label for an address.
@@ -1073,6 +1574,292 @@ def test_labels(lianad, bitcoind):
     assert res[random_address] == "this address is random"
 
 
+def test_labels_bip329(lianad, bitcoind):
+    """Test the export and import of labels in the BIP329 format."""
+    # Nothing to export on a fresh wallet.
+    assert lianad.rpc.exportlabels()["bip329"] == ""
+
+    # Label an address, a deposit transaction and the coin it created.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 1)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    coin = lianad.rpc.listcoins()["coins"][0]
+    inexistent_outpoint = "".join("1" for _ in range(64)) + ":42"
+    lianad.rpc.updatelabels(
+        {
+            addr: "an address",
+            txid: "a deposit",
+            coin["outpoint"]: "a coin",
+            inexistent_outpoint: "not our coin",
+        }
+    )
+
+    # All of them are exported. No coin is frozen, so all of them are spendable.
+    records = [
+        json.loads(line) for line in lianad.rpc.exportlabels()["bip329"].splitlines()
+    ]
+    assert len(records) == 4
+    assert {"type": "addr", "ref": addr, "label": "an address"} in records
+    assert {"type": "tx", "ref": txid, "label": "a deposit"} in records
+    assert {
+        "type": "output",
+        "ref": coin["outpoint"],
+        "label": "a coin",
+        "spendable": True,
+    } in records
+    assert {
+        "type": "output",
+        "ref": inexistent_outpoint,
+        "label": "not our coin",
+        "spendable": True,
+    } in records
+
+    # Importing labels overrides existing ones. Records we can't store are skipped.
+    sec_addr = lianad.rpc.getnewaddress()["address"]
+    bip329 = "\n".join(
+        json.dumps(rec)
+        for rec in [
+            {"type": "addr", "ref": addr, "label": "an imported address"},
+            {"type": "addr", "ref": sec_addr, "label": "another address"},
+            {"type": "output", "ref": coin["outpoint"], "spendable": "false"},
+            {"type": "input", "ref": f"{txid}:0", "label": "an input"},
+            {
+                "type": "xpub",
+                "ref": "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+                "label": "an xpub",
+            },
+        ]
+    )
+    res = lianad.rpc.importlabels(bip329)
+    assert res == {"imported": 2, "skipped": 3}
+    res = lianad.rpc.getlabels([addr, sec_addr, coin["outpoint"]])["labels"]
+    assert res == {
+        addr: "an imported address",
+        sec_addr: "another address",
+        coin["outpoint"]: "a coin",
+    }
+
+    # The exported labels can be imported back.
+    exported = lianad.rpc.exportlabels()["bip329"]
+    lianad.rpc.updatelabels({addr: None, txid: None})
+    assert lianad.rpc.importlabels(exported) == {"imported": 5, "skipped": 0}
+    assert sorted(lianad.rpc.exportlabels()["bip329"].splitlines()) == sorted(
+        exported.splitlines()
+    )
+
+    # If any of the records is invalid, nothing is imported.
+    with pytest.raises(RpcError, match="Invalid BIP329 record at line 2"):
+        lianad.rpc.importlabels(
+            json.dumps({"type": "tx", "ref": txid, "label": "a"}) + "\n{"
+        )
+    with pytest.raises(RpcError, match="Invalid label reference .* at line 2"):
+        lianad.rpc.importlabels(
+            "\n".join(
+                json.dumps(rec)
+                for rec in [
+                    {"type": "tx", "ref": txid, "label": "a"},
+                    {"type": "tx", "ref": coin["outpoint"], "label": "a"},
+                ]
+            )
+        )
+    with pytest.raises(
+        RpcError, match="at line 1 is too long: must be less or equal than 100"
+    ):
+        lianad.rpc.importlabels(
+            json.dumps({"type": "tx", "ref": txid, "label": "a" * 101})
+        )
+    assert lianad.rpc.getlabels([txid])["labels"] == {txid: "a deposit"}
+
+
+def test_backup(lianad, bitcoind):
+    """Test the creation and restoration of a wallet backup."""
+    # Receive a coin and create a Spend transaction for it.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 1)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    coin = lianad.rpc.listcoins()["coins"][0]
+    destinations = {bitcoind.rpc.getnewaddress(): 100_000}
+    psbt = lianad.rpc.createspend(destinations, [coin["outpoint"]], 2)["psbt"]
+    lianad.rpc.updatespend(psbt)
+    lianad.rpc.updatelabels({addr: "an address", txid: "a deposit"})
+
+    # The backup contains all the wallet data.
+    info = lianad.rpc.getinfo()
+    backup = lianad.rpc.createbackup()
+    assert backup["version"] == 1
+    assert backup["network"] == "regtest"
+    assert backup["descriptor"] == info["descriptors"]["main"]["multi_desc"]
+    assert backup["timestamp"] == info["timestamp"]
+    assert backup["receive_index"] == 1
+    assert backup["spend_txs"] == [{"psbt": psbt}]
+    assert len(backup["labels"]) == 2
+    assert {"type": "addr", "ref": addr, "label": "an address"} in backup["labels"]
+    assert {"type": "tx", "ref": txid, "label": "a deposit"} in backup["labels"]
+    assert backup["key_aliases"] == []
+    assert backup["hardware_wallets"] == []
+
+    # Remove the labels and the Spend, and restore them from the backup along with a
+    # higher derivation index.
+    lianad.rpc.updatelabels({addr: None, txid: None})
+    lianad.rpc.delspendtx(PSBT.from_base64(psbt).tx.txid().hex())
+    assert lianad.rpc.listspendtxs()["spend_txs"] == []
+    backup["receive_index"] = 42
+    lianad.rpc.restorebackup(backup)
+    assert lianad.rpc.getlabels([addr, txid])["labels"] == {
+        addr: "an address",
+        txid: "a deposit",
+    }
+    assert [s["psbt"] for s in lianad.rpc.listspendtxs()["spend_txs"]] == [psbt]
+    assert lianad.rpc.getnewaddress()["derivation_index"] == 42
+    # Derivation indexes are never decreased.
+    backup["receive_index"] = 1
+    lianad.rpc.restorebackup(backup)
+    assert lianad.rpc.getnewaddress()["derivation_index"] == 43
+
+    # A backup for an earlier wallet triggers a rescan from its creation date.
+    backup["timestamp"] = info["timestamp"] - 60 * 60
+    lianad.rpc.restorebackup(backup)
+    wait_for(lambda: lianad.rpc.getinfo()["rescan_progress"] is None)
+
+    # We can't restore a backup for another wallet, or from a future version.
+    with pytest.raises(RpcError, match="Backup is for a wallet with a different network"):
+        lianad.rpc.restorebackup({**backup, "network": "signet"})
+    other_desc = backup["descriptor"].replace("/<0;1>/*", "/<2;3>/*", 1).split("#")[0]
+    with pytest.raises(
+        RpcError, match="Backup is for a wallet with a different descriptor"
+    ):
+        lianad.rpc.restorebackup({**backup, "descriptor": other_desc})
+    with pytest.raises(RpcError, match="Unsupported backup version 2"):
+        lianad.rpc.restorebackup({**backup, "version": 2})
+    with pytest.raises(RpcError, match="Invalid 'backup' parameter"):
+        lianad.rpc.restorebackup({"version": 1})
+
+    # A backup with a Spend transaction for a coin which isn't ours is refused, and nothing
+    # from it is restored. Not even the Spend transactions before it, nor the derivation index.
+    lianad.rpc.updatelabels({addr: None})
+    foreign_psbt = PSBT.from_base64(psbt)
+    foreign_psbt.tx.vin[0].prevout.n = 42
+    foreign_psbt.g.map[0] = foreign_psbt.tx.serialize_without_witness()
+    lianad.rpc.delspendtx(PSBT.from_base64(psbt).tx.txid().hex())
+    with pytest.raises(RpcError, match="Unknown outpoint"):
+        lianad.rpc.restorebackup(
+            {
+                **backup,
+                "receive_index": 100,
+                "spend_txs": [{"psbt": psbt}, {"psbt": foreign_psbt.to_base64()}],
+            }
+        )
+    assert lianad.rpc.getlabels([addr])["labels"] == {}
+    assert lianad.rpc.listspendtxs()["spend_txs"] == []
+    assert lianad.rpc.getnewaddress()["derivation_index"] == 44
+
+
+def test_exporttransactions(lianad, bitcoind):
+    """Test the export of the transactions history as CSV."""
+
+    def wait_synced():
+        wait_for(
+            lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+        )
+
+    def blocktime():
+        return bitcoind.rpc.getblockheader(bitcoind.rpc.getbestblockhash())["time"]
+
+    def rows(*args):
+        lines = lianad.rpc.exporttransactions(*args)["csv"].split("\n")
+        assert lines[0] == "date,txid,direction,amount,fee,balance,block_height,labels"
+        return [line.split(",") for line in lines[1:]]
+
+    assert rows() == []
+
+    # Receive a first coin, then mine some blocks to force the blocktime to increase.
+    addr = lianad.rpc.getnewaddress()["address"]
+    deposit_a = bitcoind.rpc.sendtoaddress(addr, 0.1)
+    bitcoind.generate_block(1, wait_for_mempool=deposit_a)
+    height_a = bitcoind.rpc.getblockcount()
+    bitcoind.generate_block(12)
+    wait_synced()
+    second_timestamp = blocktime()
+
+    # Receive a second coin and spend it with a change output.
+    addr = lianad.rpc.getnewaddress()["address"]
+    deposit_b = bitcoind.rpc.sendtoaddress(addr, 0.2)
+    bitcoind.generate_block(1, wait_for_mempool=deposit_b)
+    wait_synced()
+    outpoint = next(
+        c["outpoint"]
+        for c in lianad.rpc.listcoins()["coins"]
+        if deposit_b in c["outpoint"]
+    )
+    destinations = {bitcoind.rpc.getnewaddress(): 100_000}
+    res = lianad.rpc.createspend(destinations, [outpoint], 2)
+    psbt = PSBT.from_base64(res["psbt"])
+    fee = int(0.2 * COIN) - sum(o.nValue for o in psbt.tx.vout)
+    spend_txid = sign_and_broadcast_psbt(lianad, psbt)
+    lianad.rpc.updatelabels({spend_txid: "rent, march"})
+    bitcoind.generate_block(1, wait_for_mempool=spend_txid)
+    spend_height = bitcoind.rpc.getblockcount()
+    wait_synced()
+
+    # All the transactions are exported in order, along with the running balance.
+    history = rows()
+    assert [r[1] for r in history] == [deposit_a, deposit_b, spend_txid]
+    assert history[0][2:] == [
+        "incoming",
+        "0.10000000",
+        "",
+        "0.10000000",
+        str(height_a),
+        "",
+    ]
+    assert history[1][2:5] == ["incoming", "0.20000000", ""]
+    assert history[1][5] == "0.30000000"
+    spend_row = ",".join(history[2])
+    assert spend_row.endswith(f',{spend_height},"rent, march"')
+    assert history[2][2] == "outgoing"
+    assert history[2][3] == f"-{(100_000 + fee) / COIN:.8f}"
+    assert history[2][4] == f"{fee / COIN:.8f}"
+    assert history[2][5] == f"{(int(0.3 * COIN) - 100_000 - fee) / COIN:.8f}"
+
+    # The date range only filters the exported transactions, the balance still accounts
+    # for the previous ones.
+    history = rows(second_timestamp)
+    assert [r[1] for r in history] == [deposit_b, spend_txid]
+    assert history[0][5] == "0.30000000"
+    assert rows(0, second_timestamp - 1)[0][1] == deposit_a
+    assert len(rows(0, second_timestamp - 1)) == 1
+    with pytest.raises(RpcError, match="Invalid value for 'start'"):
+        lianad.rpc.exporttransactions(-1)
+
+    # Receive a coin and spend it in the same block. The deposit is exported first, so the
+    # running balance never goes below the actual one.
+    balance = int(0.3 * COIN) - 100_000 - fee + int(0.4 * COIN)
+    addr = lianad.rpc.getnewaddress()["address"]
+    deposit_c = bitcoind.rpc.sendtoaddress(addr, 0.4)
+    wait_for(
+        lambda: any(
+            deposit_c in c["outpoint"] for c in lianad.rpc.listcoins()["coins"]
+        )
+    )
+    outpoint = next(
+        c["outpoint"]
+        for c in lianad.rpc.listcoins()["coins"]
+        if deposit_c in c["outpoint"]
+    )
+    destinations = {bitcoind.rpc.getnewaddress(): 100_000}
+    res = lianad.rpc.createspend(destinations, [outpoint], 2)
+    psbt = PSBT.from_base64(res["psbt"])
+    fee_c = int(0.4 * COIN) - sum(o.nValue for o in psbt.tx.vout)
+    spend_c = sign_and_broadcast_psbt(lianad, psbt)
+    bitcoind.generate_block(1, wait_for_mempool=[deposit_c, spend_c])
+    wait_synced()
+    history = rows()
+    assert [r[1] for r in history[3:]] == [deposit_c, spend_c]
+    assert history[3][6] == history[4][6]
+    assert history[3][5] == f"{balance / COIN:.8f}"
+    assert history[4][5] == f"{(balance - 100_000 - fee_c) / COIN:.8f}"
+
+
 def test_rbfpsbt_bump_fee(lianad, bitcoind):
     """Test the use of RBF to bump the fee of a transaction."""
 
@@ -1,10 +1,17 @@
+import hashlib
 import math
+import os
 
+from bip32 import BIP32
 from fixtures import *
+from http.server import BaseHTTPRequestHandler, HTTPServer
+from threading import Thread
 from test_framework.serializations import PSBT, uint256_from_str
 from test_framework.utils import (
     sign_and_broadcast_psbt,
     wait_for,
+    BitcoinBackendType,
+    BITCOIN_BACKEND_TYPE,
     COIN,
     RpcError,
     USE_TAPROOT,
@@ -534,6 +541,278 @@ def test_coin_selection_changeless(lianad, bitcoind):
     assert psbt.tx.vin[0].prevout.hash == txid_a
 
 
+def test_coin_selection_strategies(lianad, bitcoind):
+    """The coin selection strategy can be chosen when coins are selected automatically."""
+    # Get three coins, each confirmed in a different block.
+    txids = []
+    for amount in (0.001, 0.002, 0.0015):
+        txid = bitcoind.rpc.sendtoaddress(lianad.rpc.getnewaddress()["address"], amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+        txids.append(txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 3)
+    prevouts = [uint256_from_str(bytes.fromhex(txid)[::-1]) for txid in txids]
+
+    def spent_coins(strategy):
+        dest = {bitcoind.rpc.getnewaddress(): 120_000}
+        res = lianad.rpc.createspend(dest, [], 1, None, None, strategy)
+        psbt = PSBT.from_base64(res["psbt"])
+        return {prevouts.index(txin.prevout.hash) for txin in psbt.tx.vin}
+
+    # The oldest coins are spent first.
+    assert spent_coins("oldest_first") == {0, 1}
+    # The largest coin is enough.
+    assert spent_coins("largest_first") == {1}
+    # All coins are consolidated.
+    assert spent_coins("consolidation") == {0, 1, 2}
+
+    with pytest.raises(RpcError, match="Invalid 'coin_selection' parameter."):
+        lianad.rpc.createspend({}, [], 1, None, None, "random")
+
+
+@pytest.mark.skipif(
+    BITCOIN_BACKEND_TYPE is not BitcoinBackendType.Bitcoind,
+    reason="The payjoin receiver needs to test the mempool acceptance of the original.",
+)
+def test_payjoin(lianad, bitcoind):
+    """Pay to a second wallet using payjoin, this wallet being the sender."""
+    # Configure a second wallet, which will be the receiver.
+    receiver_signer = SingleSigner(is_taproot=USE_TAPROOT)
+    receiver_desc = single_key_desc(
+        xpub_fingerprint(receiver_signer.primary_hd),
+        receiver_signer.primary_hd.get_xpub(),
+        xpub_fingerprint(receiver_signer.recovery_hd),
+        receiver_signer.recovery_hd.get_xpub(),
+        12,
+        is_taproot=USE_TAPROOT,
+    )
+    lianad.stop()
+    with open(lianad.conf_file, "a") as f:
+        f.write("\n[[wallets]]\n")
+        f.write('name = "receiver"\n')
+        f.write(f'descriptor = "{receiver_desc}"\n')
+    lianad.start()
+    receiver_rpc = lianad.rpc.for_wallet("receiver")
+
+    # Both the sender and the receiver have a coin.
+    sender_addr = lianad.rpc.getnewaddress()["address"]
+    receiver_addr = receiver_rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendmany("", {sender_addr: 0.01, receiver_addr: 0.005})
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+    wait_for(lambda: len(receiver_rpc.listcoins(["confirmed"])["coins"]) == 1)
+    receiver_coin = receiver_rpc.listcoins()["coins"][0]
+
+    # The receiver can't contribute to a payjoin which doesn't pay to it, nor to one whose
+    # original transaction can't be broadcast.
+    def bitcoind_original():
+        res = bitcoind.rpc.walletcreatefundedpsbt(
+            [], [{bitcoind.rpc.getnewaddress(): 0.002}]
+        )
+        res = bitcoind.rpc.walletprocesspsbt(res["psbt"])
+        assert res["complete"]
+        return res["psbt"]
+
+    with pytest.raises(
+        RpcError, match="it doesn't pay to any of our receive addresses"
+    ):
+        receiver_rpc.createpayjoinproposal(bitcoind_original())
+    original = bitcoind_original()
+    bitcoind.rpc.sendrawtransaction(bitcoind.rpc.finalizepsbt(original)["hex"])
+    with pytest.raises(RpcError, match="original transaction can't be broadcast"):
+        receiver_rpc.createpayjoinproposal(original)
+
+    # The receiver's BIP78 endpoint. A real receiver would expose this through HTTPS.
+    class PayjoinHandler(BaseHTTPRequestHandler):
+        def do_POST(self):
+            original = self.rfile.read(int(self.headers["Content-Length"]))
+            res = receiver_rpc.createpayjoinproposal(original.decode())
+            proposal = receiver_signer.sign_psbt(PSBT.from_base64(res["psbt"]))
+            res = receiver_rpc.finalizepayjoinproposal(proposal.to_base64())
+            self.send_response(200)
+            self.send_header("Content-Type", "text/plain")
+            self.end_headers()
+            self.wfile.write(res["psbt"].encode())
+
+    server = HTTPServer(("127.0.0.1", 0), PayjoinHandler)
+    Thread(target=server.serve_forever, daemon=True).start()
+    endpoint = f"http://127.0.0.1:{server.server_port}/pj"
+
+    # Create the original transaction, paying to the URI. Nothing is stored nor sent to the
+    # receiver, the endpoint is only returned. Sign and store it.
+    uri = f"bitcoin:{receiver_addr}?amount=0.002&pj={endpoint}"
+    res = lianad.rpc.createspend({uri: 200_000}, [], 2)
+    assert res["payjoin_endpoint"] == endpoint
+    original_psbt = PSBT.from_base64(res["psbt"])
+    assert len(lianad.rpc.listspendtxs()["spend_txs"]) == 0
+    original_psbt = lianad.signer.sign_psbt(original_psbt)
+    lianad.rpc.updatespend(original_psbt.to_base64())
+    original_txid = original_psbt.tx.txid().hex()
+
+    # A payjoin can't be requested without an endpoint.
+    with pytest.raises(RpcError, match="The payment URI has no payjoin endpoint."):
+        lianad.rpc.requestpayjoin(original_txid, f"bitcoin:{receiver_addr}")
+
+    # Get the receiver's proposal, which spends its coin too.
+    res = lianad.rpc.requestpayjoin(original_txid, uri)
+    proposal_psbt = PSBT.from_base64(res["psbt"])
+    proposal_txid = proposal_psbt.tx.txid().hex()
+    assert proposal_txid != original_txid
+    assert len(proposal_psbt.tx.vin) == len(original_psbt.tx.vin) + 1
+    # It is stored apart from the Spend transactions.
+    spends = lianad.rpc.listspendtxs()["spend_txs"]
+    assert [original_txid] == [
+        PSBT.from_base64(s["psbt"]).tx.txid().hex() for s in spends
+    ]
+
+    # Sign it again and broadcast it. The receiver's coin gets spent.
+    proposal_psbt = lianad.signer.sign_psbt(proposal_psbt)
+    lianad.rpc.updatespend(proposal_psbt.to_base64())
+    lianad.rpc.broadcastspend(proposal_txid)
+    server.shutdown()
+    bitcoind.generate_block(1, wait_for_mempool=proposal_txid)
+    wait_for(
+        lambda: receiver_rpc.listcoins([], [receiver_coin["outpoint"]])["coins"][0][
+            "spend_info"
+        ]
+        is not None
+    )
+    # The receiver got both its coin and the payment back in a single output, minus the
+    # fee for its own input.
+    coins = receiver_rpc.listcoins(["confirmed"])["coins"]
+    assert len(coins) == 1
+    assert 690_000 < coins[0]["amount"] < 700_000
+
+
+@pytest.mark.skipif(
+    BITCOIN_BACKEND_TYPE is not BitcoinBackendType.Bitcoind,
+    reason="The payjoin receiver needs to test the mempool acceptance of the original.",
+)
+def test_payjoin_signspend(lianad, bitcoind):
+    """Perform a payjoin signing both the original and the proposal with the hot keys."""
+    # Configure a second wallet whose primary key is a hot key, which will be the sender.
+    mnemonic = " ".join(["abandon"] * 11 + ["about"])
+    seed = hashlib.pbkdf2_hmac("sha512", mnemonic.encode(), b"mnemonic", 2048)
+    hot_hd = BIP32.from_seed(seed, network="test")
+    reco_hd = BIP32.from_seed(os.urandom(32), network="test")
+    hot_fg = xpub_fingerprint(hot_hd)
+    desc = single_key_desc(
+        hot_fg,
+        hot_hd.get_xpub(),
+        xpub_fingerprint(reco_hd),
+        reco_hd.get_xpub(),
+        10,
+        is_taproot=USE_TAPROOT,
+    )
+    mnemonics_dir = os.path.join(lianad.datadir, "regtest", "mnemonics")
+    os.makedirs(mnemonics_dir, exist_ok=True)
+    with open(os.path.join(mnemonics_dir, f"mnemonic-{hot_fg}.txt"), "w") as f:
+        f.write(mnemonic)
+    lianad.stop()
+    with open(lianad.conf_file, "r") as f:
+        conf = f.read()
+    with open(lianad.conf_file, "w") as f:
+        f.write("enable_signspend = true\n" + conf)
+        f.write("\n[[wallets]]\n")
+        f.write('name = "hot"\n')
+        f.write(f'descriptor = "{desc}"\n')
+    lianad.start()
+    sender_rpc = lianad.rpc.for_wallet("hot")
+
+    # Both the sender and the receiver have a coin.
+    sender_addr = sender_rpc.getnewaddress()["address"]
+    receiver_addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendmany("", {sender_addr: 0.01, receiver_addr: 0.005})
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(sender_rpc.listcoins(["confirmed"])["coins"]) == 1)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+
+    # The receiver's BIP78 endpoint.
+    class PayjoinHandler(BaseHTTPRequestHandler):
+        def do_POST(self):
+            original = self.rfile.read(int(self.headers["Content-Length"]))
+            res = lianad.rpc.createpayjoinproposal(original.decode())
+            proposal = lianad.signer.sign_psbt(PSBT.from_base64(res["psbt"]))
+            res = lianad.rpc.finalizepayjoinproposal(proposal.to_base64())
+            self.send_response(200)
+            self.send_header("Content-Type", "text/plain")
+            self.end_headers()
+            self.wfile.write(res["psbt"].encode())
+
+    server = HTTPServer(("127.0.0.1", 0), PayjoinHandler)
+    Thread(target=server.serve_forever, daemon=True).start()
+    endpoint = f"http://127.0.0.1:{server.server_port}/pj"
+
+    # The original transaction is stored, signed and sent to the receiver. Its proposal is
+    # returned.
+    uri = f"bitcoin:{receiver_addr}?amount=0.002&pj={endpoint}"
+    res = sender_rpc.createspend({uri: None}, [], 2)
+    assert res["payjoin_endpoint"] == endpoint
+    sender_rpc.updatespend(res["psbt"])
+    original_txid = PSBT.from_base64(res["psbt"]).tx.txid().hex()
+    sender_rpc.signspend(original_txid)
+    res = sender_rpc.requestpayjoin(original_txid, uri)
+    proposal_psbt = PSBT.from_base64(res["psbt"])
+    proposal_txid = proposal_psbt.tx.txid().hex()
+    assert len(proposal_psbt.tx.vin) == 2
+    assert len(sender_rpc.listspendtxs()["spend_txs"]) == 1
+
+    # It can be signed again and broadcast.
+    sender_rpc.signspend(proposal_txid)
+    sender_rpc.broadcastspend(proposal_txid)
+    server.shutdown()
+    bitcoind.generate_block(1, wait_for_mempool=proposal_txid)
+    # The receiver's coin was spent, and it got both its value and the payment back.
+    wait_for(lambda: len(lianad.rpc.listcoins(["spent"])["coins"]) == 1)
+    coins = lianad.rpc.listcoins(["confirmed"])["coins"]
+    assert len(coins) == 1
+    assert 690_000 < coins[0]["amount"] < 700_000
+
+
+def test_spend_to_payment_uri(lianad, bitcoind):
+    """We can request and pay using BIP21 payment URIs."""
+    res = lianad.rpc.getnewaddress()
+    assert "uri" not in res
+    res = lianad.rpc.getnewaddress(1_000_000, "Savings")
+    assert res["uri"] == f"bitcoin:{res['address']}?amount=0.01&label=Savings"
+    txid = bitcoind.rpc.sendtoaddress(res["address"], 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+
+    # Pay to a URI with an amount, and to a bare address.
+    uri_addr = bitcoind.rpc.getnewaddress()
+    uri = f"bitcoin:{uri_addr}?amount=0.002&label=Coffee%20shop&message=Thanks"
+    addr = bitcoind.rpc.getnewaddress()
+    res = lianad.rpc.createspend({uri: None, addr: 100_000}, [], 2)
+    psbt = PSBT.from_base64(res["psbt"])
+    txid = psbt.tx.txid().hex()
+    vout = next(i for i, txo in enumerate(psbt.tx.vout) if txo.nValue == 200_000)
+
+    # The output paying to the URI is labelled with its label once the Spend is stored.
+    outpoint = f"{txid}:{vout}"
+    assert lianad.rpc.getlabels([outpoint])["labels"] == {}
+    lianad.rpc.updatespend(res["psbt"])
+    assert lianad.rpc.getlabels([outpoint])["labels"] == {outpoint: "Coffee shop"}
+
+    # A label too long to be set is ignored, with a warning.
+    long_uri = f"bitcoin:{uri_addr}?amount=0.002&label={'a' * 101}"
+    res = lianad.rpc.createspend({long_uri: None}, [], 2)
+    assert any("longer than 100 characters" in w for w in res["warnings"])
+    lianad.rpc.updatespend(res["psbt"])
+    psbt = PSBT.from_base64(res["psbt"])
+    txid = psbt.tx.txid().hex()
+    vout = next(i for i, txo in enumerate(psbt.tx.vout) if txo.nValue == 200_000)
+    outpoint = f"{txid}:{vout}"
+    assert lianad.rpc.getlabels([outpoint])["labels"] == {}
+
+    # The value must match the URI amount, which can't be absent from both.
+    with pytest.raises(RpcError, match="value doesn't match the payment URI amount"):
+        lianad.rpc.createspend({uri: 100_000}, [], 2)
+    with pytest.raises(RpcError, match=f"No amount to pay to '{uri_addr}'."):
+        lianad.rpc.createspend({f"bitcoin:{uri_addr}": None}, [], 2)
+    with pytest.raises(RpcError, match="Unknown required parameter"):
+        lianad.rpc.createspend({f"bitcoin:{uri_addr}?req-unknown=1": 100_000}, [], 2)
+
+
 def test_sweep(lianad, bitcoind):
     """
     Test we can leverage the change_address parameter to partially or completely sweep